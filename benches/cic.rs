@@ -0,0 +1,24 @@
+//! Decimating one read_sync buffer of 2.4 MS/s IQ by 10
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rtlsdr_rs::dsp::{self, CicDecimator};
+use rtlsdr_rs::DEFAULT_BUF_LENGTH;
+
+fn cic(c: &mut Criterion) {
+    let raw: Vec<u8> = (0..DEFAULT_BUF_LENGTH)
+        .map(|i| (i * 31 % 256) as u8)
+        .collect();
+    let samples_i16 = dsp::to_i16(&raw);
+    let samples_f32 = dsp::to_f32(&raw);
+
+    let mut cic_i16 = CicDecimator::new(10, 4, 2);
+    c.bench_function("cic_i16", |b| {
+        b.iter(|| cic_i16.process_i16(black_box(&samples_i16)))
+    });
+    let mut cic_f32 = CicDecimator::new(10, 4, 2);
+    c.bench_function("cic_f32", |b| {
+        b.iter(|| cic_f32.process_f32(black_box(&samples_f32)))
+    });
+}
+
+criterion_group!(benches, cic);
+criterion_main!(benches);
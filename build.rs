@@ -0,0 +1,12 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/rtlsdr.proto");
+        // Use the bundled protoc so the feature builds without one installed
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("Bundled protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::compile_protos("proto/rtlsdr.proto")
+            .expect("Compiling proto/rtlsdr.proto");
+    }
+}
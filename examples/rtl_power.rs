@@ -0,0 +1,72 @@
+//! Wideband power scan with hard-coded params that writes rtl_power
+//! compatible CSV to stdout, one sweep per integration interval until ctrl-c.
+//!
+//! Example command to scan the FM broadcast band into a file:
+//! cargo run --example rtl_power > fm.csv
+
+use rtlsdr_rs::bookmarks::Bookmarks;
+use rtlsdr_rs::scan::{write_csv, PowerScan, ScanConfig};
+use rtlsdr_rs::{error::Result, RtlSdr, TunerGain};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+// Scan range and resolution
+const START_FREQ: u32 = 88_000_000; // Hz
+const STOP_FREQ: u32 = 108_000_000; // Hz
+const BIN_WIDTH: u32 = 10_000; // Hz
+const INTEGRATION: Duration = Duration::from_secs(1);
+const CROP: f32 = 0.2; // Discard the hop edges where the filters roll off
+const SINGLE_SWEEP: bool = false;
+// Bookmarks file to print the level of each bookmark in range to stderr
+const BOOKMARKS_PATH: Option<&str> = None;
+// RTL Device Index
+const RTL_INDEX: usize = 0;
+
+fn main() -> Result<()> {
+    // Shutdown flag that is set true when ctrl-c signal caught
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    ctrlc::set_handler(|| {
+        SHUTDOWN.swap(true, Ordering::Relaxed);
+    })
+    .unwrap();
+
+    let mut config = ScanConfig::new(START_FREQ, STOP_FREQ, BIN_WIDTH);
+    config.integration = INTEGRATION;
+    config.crop = CROP;
+    let mut scan = PowerScan::new(config)?;
+    eprintln!(
+        "{} hops of {} bins, {:.1} Hz per bin",
+        scan.hops(),
+        scan.fft_size(),
+        scan.step()
+    );
+
+    let bookmarks = match BOOKMARKS_PATH {
+        Some(path) => Bookmarks::import(path)?,
+        None => Bookmarks::new(),
+    };
+
+    let mut sdr = RtlSdr::open(RTL_INDEX).expect("Unable to open SDR device!");
+    sdr.set_tuner_gain(TunerGain::Auto)?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    while !SHUTDOWN.load(Ordering::Relaxed) {
+        // Stamp every row of a sweep with its start time, like rtl_power
+        let time = SystemTime::now();
+        scan.sweep(&mut sdr, |hop| {
+            write_csv(&mut out, time, hop).expect("Failed to write CSV");
+            for bookmark in bookmarks.in_range(hop.low, hop.high) {
+                if let Some(power) = hop.power_at(bookmark.frequency) {
+                    eprintln!("{}: {:.1} dB", bookmark.name, power);
+                }
+            }
+        })?;
+        out.flush().expect("Failed to flush output");
+        if SINGLE_SWEEP {
+            break;
+        }
+    }
+    sdr.close()
+}
@@ -0,0 +1,68 @@
+//! Raw IQ recorder with hard-coded params, like the original rtl_sdr.
+//!
+//! Writes `capture.bin` until ctrl-c, which the simple_fm example can play
+//! back by setting its READ_FROM_FILE switch to true:
+//! cargo run --example rtl_sdr
+
+use rtlsdr_rs::bookmarks::Bookmarks;
+use rtlsdr_rs::demod::fm::optimal_settings;
+use rtlsdr_rs::record::RawRecorder;
+use rtlsdr_rs::{error::Result, RtlSdr, TunerGain, DEFAULT_BUF_LENGTH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// Same settings as simple_fm, so the capture can be demodulated by it
+const FREQUENCY: u32 = 94_900_000; // Hz
+const SAMPLE_RATE: u32 = 170_000; // Demodulation sample rate
+const RATE_RESAMPLE: u32 = 32_000; // Audio sample rate
+                                   // Bookmarks file and bookmark name to tune to instead of FREQUENCY, e.g.
+                                   // Some(("bookmarks.json", "WREK")). SDR# .xml and gqrx .csv files also work.
+const BOOKMARK: Option<(&str, &str)> = None;
+const OUTPUT_PATH: &str = "capture.bin";
+// Start a new numbered file at this size or age, None to write one file
+const MAX_SIZE: Option<u64> = None;
+const MAX_DURATION: Option<Duration> = None;
+// Write a .timestamps file with the host time of each block next to the data
+const TIMESTAMPS: bool = true;
+// RTL Device Index
+const RTL_INDEX: usize = 0;
+
+fn main() -> Result<()> {
+    // Shutdown flag that is set true when ctrl-c signal caught
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    ctrlc::set_handler(|| {
+        SHUTDOWN.swap(true, Ordering::Relaxed);
+    })
+    .unwrap();
+
+    let freq = match BOOKMARK {
+        Some((path, name)) => Bookmarks::import(path)?.resolve(name)?,
+        None => FREQUENCY,
+    };
+    // Capture frequency and rate for offset tuning to the station
+    let (radio, _) = optimal_settings(freq, SAMPLE_RATE, RATE_RESAMPLE);
+    let sdr = RtlSdr::open(RTL_INDEX).expect("Unable to open SDR device!");
+    sdr.set_tuner_gain(TunerGain::Auto)?;
+    sdr.set_center_freq(radio.capture_freq)?;
+    sdr.set_sample_rate(radio.capture_rate)?;
+    sdr.reset_buffer()?;
+
+    let mut recorder = RawRecorder::new(OUTPUT_PATH);
+    recorder.set_max_size(MAX_SIZE);
+    recorder.set_max_duration(MAX_DURATION);
+    recorder.set_timestamps(TIMESTAMPS);
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    while !SHUTDOWN.load(Ordering::Relaxed) {
+        let n = sdr.read_sync(&mut buf)?;
+        recorder.write(&buf[..n])?;
+        if n < buf.len() {
+            eprintln!("Short read, samples lost!");
+            recorder.log_drop(0)?;
+        }
+    }
+    recorder.close()?;
+    for file in recorder.files() {
+        eprintln!("Wrote {}", file.display());
+    }
+    sdr.close()
+}
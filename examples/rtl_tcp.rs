@@ -0,0 +1,31 @@
+//! rtl_tcp server with hard-coded params, compatible with SDR#, gqrx and
+//! other rtl_tcp clients. Serves until ctrl-c:
+//! cargo run --example rtl_tcp
+
+use rtlsdr_rs::error::Result;
+use rtlsdr_rs::net::rtl_tcp::Server;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// Listen on all interfaces on rtl_tcp's default port
+const ADDRESS: &str = "0.0.0.0:1234";
+// RTL Device Index
+const RTL_INDEX: usize = 0;
+
+fn main() -> Result<()> {
+    stderrlog::new().verbosity(log::Level::Info).init().unwrap();
+
+    // Shutdown flag that is set true when ctrl-c signal caught
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    ctrlc::set_handler(|| {
+        SHUTDOWN.swap(true, Ordering::Relaxed);
+    })
+    .unwrap();
+
+    let server = Server::start(ADDRESS, RTL_INDEX)?;
+    while !SHUTDOWN.load(Ordering::Relaxed) && server.is_running() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    server.stop()
+}
@@ -1,27 +1,57 @@
-use ctrlc;
+//! Checks for lost samples with the demodulator's test mode until ctrl-c,
+//! like rtl_test. Options:
+//! -t: find the frequency range the tuner PLL locks on, then exit
+//! -p [seconds]: estimate the sample clock error in PPM, reporting every
+//!    10 seconds or the given interval
+//!
+//! cargo run --example rtl_test -- -p
+
+use rtlsdr_rs::benchmark::{
+    tuner_range, CounterCheck, PpmMeter, RangeConfig, PPM_INTERVAL, PPM_SETTLE,
+};
 use rtlsdr_rs::{error::Result, RtlSdr};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 enum TestMode {
-    NO_BENCHMARK,
-    TUNER_BENCHMARK,
-    PPM_BENCHMARK,
+    Samples,
+    Tuner,
+    Ppm(Duration),
 }
-const DEFAULT_BUF_LENGTH: usize = (16 * 16384);
+const DEFAULT_BUF_LENGTH: usize = 16 * 16384;
 
 const DEVICE_INDEX: usize = 0;
 const SAMPLE_RATE: u32 = 2_048_000;
 
+fn parse_args() -> TestMode {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("-t") => TestMode::Tuner,
+        Some("-p") => {
+            let interval = args.next().map_or(PPM_INTERVAL, |s| {
+                Duration::from_secs(s.parse().expect("Invalid PPM interval"))
+            });
+            TestMode::Ppm(interval)
+        }
+        Some(arg) => panic!("Unknown option {}", arg),
+        None => TestMode::Samples,
+    }
+}
+
 fn main() -> Result<()> {
+    let mode = parse_args();
+
     // Create shutdown flag and set it when ctrl-c signal caught
-    static shutdown: AtomicBool = AtomicBool::new(false);
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
     ctrlc::set_handler(|| {
-        shutdown.swap(true, Ordering::Relaxed);
-    });
+        SHUTDOWN.swap(true, Ordering::Relaxed);
+    })
+    .expect("Error setting Ctrl-C handler");
 
     // Open device
     let mut sdr = RtlSdr::open(DEVICE_INDEX).expect("Unable to open SDR device!");
     // println!("{:#?}", sdr);
+    println!("Found {} tuner", sdr.get_tuner_info()?.name);
 
     let gains = sdr.get_tuner_gains()?;
     println!(
@@ -33,6 +63,19 @@ fn main() -> Result<()> {
             .collect::<Vec<_>>()
     );
 
+    if let TestMode::Tuner = mode {
+        println!("Benchmarking tuner range");
+        for band in tuner_range(&mut sdr, &RangeConfig::default())? {
+            println!(
+                "PLL locks from {:.1} to {:.1} MHz",
+                *band.start() as f64 / 1e6,
+                *band.end() as f64 / 1e6
+            );
+        }
+        sdr.close()?;
+        return Ok(());
+    }
+
     // Set sample rate
     sdr.set_sample_rate(SAMPLE_RATE)?;
     println!("Sampling at {} S/s", sdr.get_sample_rate());
@@ -46,23 +89,40 @@ fn main() -> Result<()> {
     sdr.reset_buffer()?;
 
     println!("Reading samples in sync mode...");
+    let mut ppm = match mode {
+        TestMode::Ppm(interval) => {
+            println!("Reporting PPM error every {} seconds", interval.as_secs());
+            Some(PpmMeter::with_interval(SAMPLE_RATE, interval, PPM_SETTLE))
+        }
+        _ => None,
+    };
+    let mut check = CounterCheck::new();
     let mut buf: [u8; DEFAULT_BUF_LENGTH] = [0; DEFAULT_BUF_LENGTH];
     loop {
-        if shutdown.load(Ordering::Relaxed) {
+        if SHUTDOWN.load(Ordering::Relaxed) {
             break;
         }
-        let n = sdr.read_sync(&mut buf);
-        if n.is_err() {
-            println!("Read error: {:#?}", n);
-        } else {
-            let n = n.unwrap();
-            if n < DEFAULT_BUF_LENGTH {
+        match sdr.read_sync(&mut buf) {
+            Ok(n) if n < DEFAULT_BUF_LENGTH => {
                 println!("Short read ({:#?}), samples lost, exiting!", n);
                 break;
             }
+            Ok(n) => {
+                let lost = check.process(&buf[..n]);
+                if lost > 0 {
+                    println!("Lost at least {} bytes", lost);
+                }
+                if let Some(report) = ppm.as_mut().and_then(|p| p.process(n)) {
+                    println!(
+                        "Real sample rate: {:.0} current PPM: {:.1} cumulative PPM: {:.1}",
+                        report.rate, report.ppm, report.cumulative_ppm
+                    );
+                }
+            }
+            Err(e) => println!("Read error: {:#?}", e),
         }
-        // println!("read {} samples!", n.unwrap());
     }
+    println!("Lost at least {} bytes in total", check.lost());
 
     println!("Close");
     sdr.close()?;
@@ -0,0 +1,60 @@
+use super::JitterBuffer;
+
+#[test]
+fn test_waits_for_target() {
+    let mut jitter = JitterBuffer::new(1, 4, 16);
+    let mut out = [1.0; 2];
+    jitter.push(&[0.1, 0.2, 0.3]);
+    jitter.pop(&mut out);
+    assert_eq!([0.0, 0.0], out);
+    assert_eq!(3, jitter.len());
+
+    jitter.push(&[0.4]);
+    jitter.pop(&mut out);
+    assert_eq!([0.1, 0.2], out);
+    // Keeps playing below the target once started
+    jitter.pop(&mut out);
+    assert_eq!([0.3, 0.4], out);
+    assert_eq!(0, jitter.underruns());
+}
+
+#[test]
+fn test_underrun_refills() {
+    let mut jitter = JitterBuffer::new(1, 2, 16);
+    let mut out = [1.0; 3];
+    jitter.push(&[0.1, 0.2]);
+    jitter.pop(&mut out);
+    assert_eq!([0.1, 0.2, 0.0], out);
+    assert_eq!(1, jitter.underruns());
+
+    // Silent until the target is reached again
+    jitter.push(&[0.3]);
+    jitter.pop(&mut out);
+    assert_eq!([0.0; 3], out);
+    jitter.push(&[0.4, 0.5, 0.6]);
+    jitter.pop(&mut out);
+    assert_eq!([0.3, 0.4, 0.5], out);
+}
+
+#[test]
+fn test_overrun_drops_oldest_frames() {
+    let mut jitter = JitterBuffer::new(2, 2, 4);
+    let samples: Vec<f32> = (0..10).map(|i| i as f32).collect();
+    jitter.push(&samples);
+    // Back to the 2 newest frames
+    assert_eq!(2, jitter.len());
+    assert_eq!(1, jitter.overruns());
+    let mut out = [0.0; 4];
+    jitter.pop(&mut out);
+    assert_eq!([6.0, 7.0, 8.0, 9.0], out);
+}
+
+#[test]
+fn test_push_i16() {
+    let mut jitter = JitterBuffer::new(1, 1, 4);
+    jitter.push_i16(&[i16::MIN, 0, 16384]);
+    let mut out = [0.0; 3];
+    jitter.pop(&mut out);
+    assert_eq!([-1.0, 0.0, 0.5], out);
+    assert!(jitter.is_empty());
+}
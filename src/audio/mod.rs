@@ -0,0 +1,118 @@
+//! Audio output for demodulated signals
+//!
+//! `JitterBuffer` smooths the bursty output of a demodulator, which arrives
+//! one USB buffer at a time, into the steady stream a sound card pulls. With
+//! the `audio` feature, `AudioSink` plays it through the system's audio
+//! output with cpal.
+
+#[cfg(feature = "audio")]
+mod sink;
+#[cfg(feature = "audio")]
+pub use sink::AudioSink;
+
+#[cfg(test)]
+mod audio_test;
+
+use std::collections::VecDeque;
+
+/// FIFO of interleaved samples between a producer and an audio callback.
+///
+/// Playback starts once `target` frames are buffered, and starts over from
+/// there whenever the buffer runs dry, so a late buffer costs one gap rather
+/// than crackling. The receiver's clock never quite matches the sound
+/// card's, so when more than `max` frames pile up the oldest are dropped
+/// back down to `target`, bounding the latency.
+pub struct JitterBuffer {
+    queue: VecDeque<f32>,
+    channels: usize,
+    target: usize, // Samples, a whole number of frames
+    max: usize,
+    playing: bool,
+    underruns: u64,
+    overruns: u64,
+}
+
+impl JitterBuffer {
+    pub fn new(channels: usize, target_frames: usize, max_frames: usize) -> JitterBuffer {
+        assert!(channels > 0, "Need at least one channel");
+        assert!(
+            max_frames >= target_frames,
+            "Maximum must be at least the target"
+        );
+        JitterBuffer {
+            queue: VecDeque::with_capacity(max_frames * channels),
+            channels,
+            target: target_frames * channels,
+            max: max_frames * channels,
+            playing: false,
+            underruns: 0,
+            overruns: 0,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Add samples scaled to +/-1.0
+    pub fn push(&mut self, samples: &[f32]) {
+        self.queue.extend(samples);
+        self.trim();
+    }
+
+    /// Add full scale i16 samples, as from `FmDemod`
+    pub fn push_i16(&mut self, samples: &[i16]) {
+        self.queue
+            .extend(samples.iter().map(|&s| s as f32 / 32768.0));
+        self.trim();
+    }
+
+    /// Fill `out` with the next samples, or silence while (re)filling
+    pub fn pop(&mut self, out: &mut [f32]) {
+        if !self.playing {
+            if self.queue.len() < self.target.max(1) {
+                out.fill(0.0);
+                return;
+            }
+            self.playing = true;
+        }
+        let n = out.len().min(self.queue.len());
+        for (o, s) in out.iter_mut().zip(self.queue.drain(..n)) {
+            *o = s;
+        }
+        if n < out.len() {
+            out[n..].fill(0.0);
+            self.underruns += 1;
+            self.playing = false;
+        }
+    }
+
+    /// Frames buffered
+    pub fn len(&self) -> usize {
+        self.queue.len() / self.channels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Times playback ran out of samples
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// Times samples were dropped to catch up
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    fn trim(&mut self) {
+        if self.queue.len() > self.max {
+            let excess = self.queue.len() - self.target;
+            // Keep frames aligned
+            let excess = excess - excess % self.channels;
+            self.queue.drain(..excess);
+            self.overruns += 1;
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use super::JitterBuffer;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig, SupportedStreamConfigRange};
+use log::{info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Buffering before playback starts, and the most kept before dropping
+const DEFAULT_LATENCY: Duration = Duration::from_millis(100);
+const MAX_LATENCY_FACTOR: u32 = 4;
+
+/// Plays demodulated audio on an output device.
+///
+/// The stream belongs to the thread that opened the sink, so open it on the
+/// thread that writes to it.
+///
+/// ```no_run
+/// use rtlsdr_rs::audio::AudioSink;
+/// use rtlsdr_rs::demod::fm::{optimal_settings, FmDemod};
+/// use rtlsdr_rs::RtlSdr;
+///
+/// let (radio, demod) = optimal_settings(94_900_000, 170_000, 32_000);
+/// let sdr = RtlSdr::open(0).unwrap();
+/// sdr.set_center_freq(radio.capture_freq).unwrap();
+/// sdr.set_sample_rate(radio.capture_rate).unwrap();
+/// let mut fm = FmDemod::new(demod);
+/// let audio = AudioSink::open(32_000, 1).unwrap();
+/// let mut buf = vec![0_u8; rtlsdr_rs::DEFAULT_BUF_LENGTH];
+/// loop {
+///     let n = sdr.read_sync(&mut buf).unwrap();
+///     audio.write_i16(&fm.demodulate(&mut buf[..n]));
+/// }
+/// ```
+pub struct AudioSink {
+    _stream: cpal::Stream,
+    buffer: Arc<Mutex<JitterBuffer>>,
+    sample_rate: u32,
+}
+
+impl AudioSink {
+    /// Play `channels` interleaved channels at `sample_rate` on the default
+    /// output device
+    pub fn open(sample_rate: u32, channels: u16) -> Result<AudioSink> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| RtlsdrErr("No audio output device".to_string()))?;
+        AudioSink::open_on(&device, sample_rate, channels)
+    }
+
+    /// Play on the first output device whose name contains `name`, see
+    /// `output_devices`
+    pub fn open_device(name: &str, sample_rate: u32, channels: u16) -> Result<AudioSink> {
+        let device = cpal::default_host()
+            .output_devices()
+            .map_err(audio_err)?
+            .find(|d| d.description().is_ok_and(|desc| desc.name().contains(name)))
+            .ok_or_else(|| RtlsdrErr(format!("No audio output device matching '{}'", name)))?;
+        AudioSink::open_on(&device, sample_rate, channels)
+    }
+
+    /// Names of the available output devices
+    pub fn output_devices() -> Result<Vec<String>> {
+        Ok(cpal::default_host()
+            .output_devices()
+            .map_err(audio_err)?
+            .filter_map(|d| d.description().ok().map(|desc| desc.name().to_string()))
+            .collect())
+    }
+
+    fn open_on(device: &cpal::Device, sample_rate: u32, channels: u16) -> Result<AudioSink> {
+        if channels == 0 {
+            return Err(RtlsdrErr("Need at least one audio channel".to_string()));
+        }
+        let supported = device
+            .supported_output_configs()
+            .map_err(audio_err)?
+            .collect();
+        let range = choose_config(supported, sample_rate, channels).ok_or_else(|| {
+            RtlsdrErr(format!(
+                "Audio output can't play {} channel(s) at {} Hz, resample with dsp::Resampler",
+                channels, sample_rate
+            ))
+        })?;
+        let config = StreamConfig {
+            channels: range.channels(),
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let frames = latency_frames(sample_rate, DEFAULT_LATENCY);
+        let buffer = Arc::new(Mutex::new(JitterBuffer::new(
+            channels as usize,
+            frames,
+            frames * MAX_LATENCY_FACTOR as usize,
+        )));
+        let stream = match range.sample_format() {
+            SampleFormat::F32 => build::<f32>(device, &config, &buffer),
+            SampleFormat::I16 => build::<i16>(device, &config, &buffer),
+            SampleFormat::U16 => build::<u16>(device, &config, &buffer),
+            SampleFormat::I32 => build::<i32>(device, &config, &buffer),
+            format => Err(RtlsdrErr(format!("Unsupported audio format {}", format))),
+        }?;
+        stream.play().map_err(audio_err)?;
+        info!(
+            "Audio output: {} Hz, {} channel(s) from {}",
+            sample_rate, config.channels, channels
+        );
+        Ok(AudioSink {
+            _stream: stream,
+            buffer,
+            sample_rate,
+        })
+    }
+
+    /// Queue interleaved full scale i16 samples
+    pub fn write_i16(&self, samples: &[i16]) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.push_i16(samples);
+        }
+    }
+
+    /// Queue interleaved samples scaled to +/-1.0
+    pub fn write_f32(&self, samples: &[f32]) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.push(samples);
+        }
+    }
+
+    /// Buffering before playback starts, and after running dry. Up to four
+    /// times as much is kept before catching up.
+    pub fn set_latency(&self, latency: Duration) {
+        let frames = latency_frames(self.sample_rate, latency);
+        if let Ok(mut buffer) = self.buffer.lock() {
+            let channels = buffer.channels();
+            *buffer = JitterBuffer::new(channels, frames, frames * MAX_LATENCY_FACTOR as usize);
+        }
+    }
+
+    /// Audio queued and not yet played
+    pub fn buffered(&self) -> Duration {
+        let frames = self.buffer.lock().map_or(0, |b| b.len());
+        Duration::from_secs_f64(frames as f64 / self.sample_rate as f64)
+    }
+
+    /// Times the output ran out of audio
+    pub fn underruns(&self) -> u64 {
+        self.buffer.lock().map_or(0, |b| b.underruns())
+    }
+}
+
+fn audio_err<E: std::fmt::Display>(e: E) -> crate::error::RtlsdrError {
+    RtlsdrErr(format!("Audio output: {}", e))
+}
+
+fn latency_frames(sample_rate: u32, latency: Duration) -> usize {
+    ((sample_rate as f64 * latency.as_secs_f64()) as usize).max(1)
+}
+
+/// A device configuration for `channels` at `sample_rate`, preferring the
+/// exact channel count, then mono copied to every output channel
+fn choose_config(
+    supported: Vec<SupportedStreamConfigRange>,
+    sample_rate: u32,
+    channels: u16,
+) -> Option<SupportedStreamConfigRange> {
+    let usable = |c: &&SupportedStreamConfigRange| {
+        (c.min_sample_rate()..=c.max_sample_rate()).contains(&sample_rate)
+            && matches!(
+                c.sample_format(),
+                SampleFormat::F32 | SampleFormat::I16 | SampleFormat::U16 | SampleFormat::I32
+            )
+    };
+    let exact = supported
+        .iter()
+        .filter(usable)
+        .filter(|c| c.channels() == channels)
+        .min_by_key(|c| c.sample_format() != SampleFormat::F32);
+    let upmix = || {
+        supported
+            .iter()
+            .filter(usable)
+            .filter(|_| channels == 1)
+            .min_by_key(|c| (c.channels(), c.sample_format() != SampleFormat::F32))
+    };
+    exact.or_else(upmix).cloned()
+}
+
+fn build<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    buffer: &Arc<Mutex<JitterBuffer>>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let buffer = buffer.clone();
+    let out_channels = config.channels as usize;
+    let mut samples = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                let frames = data.len() / out_channels;
+                let Ok(mut buffer) = buffer.lock() else {
+                    return;
+                };
+                let in_channels = buffer.channels();
+                samples.resize(frames * in_channels, 0.0);
+                buffer.pop(&mut samples);
+                drop(buffer);
+                for (frame, out) in data.chunks_exact_mut(out_channels).enumerate() {
+                    for (c, o) in out.iter_mut().enumerate() {
+                        // Mono goes to every channel
+                        let s = samples[frame * in_channels + c.min(in_channels - 1)];
+                        *o = T::from_sample(s);
+                    }
+                }
+            },
+            |e| warn!("Audio output error: {}", e),
+            None,
+        )
+        .map_err(audio_err)
+}
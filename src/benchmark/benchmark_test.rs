@@ -0,0 +1,87 @@
+use super::{find_lock_bands, CounterCheck, PpmMeter, RangeConfig};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_counter_check() {
+    let mut check = CounterCheck::new();
+    let buf: Vec<u8> = (250..=255).chain(0..10).collect();
+    assert_eq!(0, check.process(&buf));
+    // Continues across reads, and 5 bytes went missing in this one
+    assert_eq!(5, check.process(&[10, 11, 17, 18]));
+    assert_eq!(0, check.process(&[19]));
+    assert_eq!(5, check.lost());
+}
+
+#[test]
+fn test_ppm_meter() {
+    let start = Instant::now();
+    let second = Duration::from_secs(1);
+    let mut meter = PpmMeter::with_interval(1_000_000, 4 * second, 2 * second);
+    // Reads during the settling time are ignored
+    assert_eq!(None, meter.process_at(2_000_000, start));
+    assert_eq!(None, meter.process_at(2_000_000, start + second));
+    // This one starts the measurement, its samples arrived before it
+    assert_eq!(None, meter.process_at(2_000_000, start + 2 * second));
+
+    // 10 PPM fast
+    let bytes = 2 * 1_000_010;
+    for s in 3..6 {
+        assert_eq!(None, meter.process_at(bytes, start + s * second));
+    }
+    let report = meter.process_at(bytes, start + 6 * second).unwrap();
+    assert!((report.rate - 1_000_010.0).abs() < 1e-3);
+    assert!((report.ppm - 10.0).abs() < 1e-3);
+    assert!((report.cumulative_ppm - 10.0).abs() < 1e-3);
+    assert_eq!(4 * second, report.elapsed);
+
+    // Then 30 PPM slow
+    let bytes = 2 * 999_970;
+    for s in 7..10 {
+        assert_eq!(None, meter.process_at(bytes, start + s * second));
+    }
+    let report = meter.process_at(bytes, start + 10 * second).unwrap();
+    assert!((report.ppm + 30.0).abs() < 1e-3);
+    assert!((report.cumulative_ppm + 10.0).abs() < 1e-3);
+    assert_eq!(8 * second, report.elapsed);
+}
+
+#[test]
+fn test_find_lock_bands() {
+    // Like an E4000: locks from 52 to 2200 MHz, except for a gap around
+    // 1100 MHz
+    let config = RangeConfig {
+        start: 0,
+        stop: 2_400_000_000,
+        step: 10_000_000,
+        resolution: 100_000,
+    };
+    let mut tunes = 0;
+    let bands =
+        find_lock_bands(&config, |f| {
+            tunes += 1;
+            Ok((52_000_000..=1_099_950_000).contains(&f)
+                || (1_250_000_000..2_200_000_000).contains(&f))
+        })
+        .unwrap();
+    assert_eq!(2, bands.len());
+    let close = |a: u32, b: u32| (a as i64 - b as i64).abs() <= 100_000;
+    assert!(close(52_000_000, *bands[0].start()));
+    assert!(close(1_099_950_000, *bands[0].end()));
+    assert!(close(1_250_000_000, *bands[1].start()));
+    assert!(close(2_200_000_000, *bands[1].end()));
+    // Each edge is on the right side
+    assert!(*bands[0].start() >= 52_000_000);
+    assert!(*bands[0].end() <= 1_099_950_000);
+    assert!(*bands[1].end() < 2_200_000_000);
+    // Coarse steps plus bisecting 4 edges
+    assert!(tunes <= 241 + 4 * 8);
+
+    // Locked at both ends
+    let bands = find_lock_bands(&config, |_| Ok(true)).unwrap();
+    assert_eq!(vec![0..=2_400_000_000], bands);
+    let bands = find_lock_bands(&config, |_| Ok(false)).unwrap();
+    assert!(bands.is_empty());
+
+    let invalid = RangeConfig { step: 0, ..config };
+    assert!(find_lock_bands(&invalid, |_| Ok(true)).is_err());
+}
@@ -0,0 +1,254 @@
+//! The measurements behind `rtl_test`: counting samples lost between the
+//! device and the host, estimating the sample clock error in PPM, and
+//! finding the frequencies the tuner PLL can lock on.
+//!
+//! Lost samples are found with the demodulator's test mode
+//! (`RtlSdr::set_testmode`), where each sample byte is the next value of an
+//! 8-bit counter instead of ADC output.
+
+use crate::error::Result;
+use crate::error::RtlsdrError::{PllNotLocked, RtlsdrErr};
+use crate::RtlSdr;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod benchmark_test;
+
+/// How often `PpmMeter` reports, as rtl_test's `-p`
+pub const PPM_INTERVAL: Duration = Duration::from_secs(10);
+/// Time discarded at the start of a PPM measurement while USB transfers and
+/// the host settle
+pub const PPM_SETTLE: Duration = Duration::from_secs(5);
+
+/// Checks the test mode counter across reads
+#[derive(Debug, Default)]
+pub struct CounterCheck {
+    next: Option<u8>,
+    lost: u64,
+}
+
+impl CounterCheck {
+    pub fn new() -> CounterCheck {
+        CounterCheck::default()
+    }
+
+    /// Check a buffer read in test mode, returning the number of bytes
+    /// missing from it, at least. Gaps are only known modulo 256.
+    pub fn process(&mut self, buf: &[u8]) -> u64 {
+        let mut lost = 0;
+        for &b in buf {
+            let expected = *self.next.get_or_insert(b);
+            if b != expected {
+                lost += b.wrapping_sub(expected) as u64;
+            }
+            self.next = Some(b.wrapping_add(1));
+        }
+        self.lost += lost;
+        lost
+    }
+
+    /// Bytes lost since created
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+/// Sample clock error measured against the host clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PpmReport {
+    /// Samples per second over the last interval
+    pub rate: f64,
+    /// Error over the last interval
+    pub ppm: f64,
+    /// Error over the whole measurement, which converges as it runs
+    pub cumulative_ppm: f64,
+    /// Time measured, not counting the settling time
+    pub elapsed: Duration,
+}
+
+/// Estimates the sample clock error by counting the samples received over
+/// time. The host clock is usually far better than the dongle's crystal,
+/// but USB scheduling adds jitter, so this needs minutes to get within
+/// 1 PPM.
+#[derive(Debug)]
+pub struct PpmMeter {
+    sample_rate: u32,
+    interval: Duration,
+    settle: Duration,
+    created: Option<Instant>,
+    start: Option<Instant>,
+    samples: u64,
+    interval_start: Option<Instant>,
+    interval_samples: u64,
+}
+
+impl PpmMeter {
+    /// Measure against nominal `sample_rate`, reporting every `PPM_INTERVAL`
+    pub fn new(sample_rate: u32) -> PpmMeter {
+        PpmMeter::with_interval(sample_rate, PPM_INTERVAL, PPM_SETTLE)
+    }
+
+    /// Report every `interval` after discarding `settle`
+    pub fn with_interval(sample_rate: u32, interval: Duration, settle: Duration) -> PpmMeter {
+        PpmMeter {
+            sample_rate,
+            interval,
+            settle,
+            created: None,
+            start: None,
+            samples: 0,
+            interval_start: None,
+            interval_samples: 0,
+        }
+    }
+
+    /// Count a read of `bytes` raw bytes that just completed
+    pub fn process(&mut self, bytes: usize) -> Option<PpmReport> {
+        self.process_at(bytes, Instant::now())
+    }
+
+    /// Count a read of `bytes` raw bytes that completed at `now`, returning
+    /// a report at the end of each interval
+    pub fn process_at(&mut self, bytes: usize, now: Instant) -> Option<PpmReport> {
+        let created = *self.created.get_or_insert(now);
+        // Samples are counted from the end of the read that starts the
+        // measurement, as those before it arrived at an unknown time
+        let Some(start) = self.start else {
+            if now - created >= self.settle {
+                self.start = Some(now);
+                self.interval_start = Some(now);
+            }
+            return None;
+        };
+        let samples = bytes as u64 / 2;
+        self.samples += samples;
+        self.interval_samples += samples;
+        let interval_start = self.interval_start.unwrap_or(start);
+        let interval = now - interval_start;
+        if interval < self.interval {
+            return None;
+        }
+        let rate = self.interval_samples as f64 / interval.as_secs_f64();
+        let elapsed = now - start;
+        let cumulative = self.samples as f64 / elapsed.as_secs_f64();
+        self.interval_start = Some(now);
+        self.interval_samples = 0;
+        Some(PpmReport {
+            rate,
+            ppm: self.ppm(rate),
+            cumulative_ppm: self.ppm(cumulative),
+            elapsed,
+        })
+    }
+
+    fn ppm(&self, rate: f64) -> f64 {
+        (rate / self.sample_rate as f64 - 1.0) * 1e6
+    }
+}
+
+/// Frequencies to try when looking for the tuner's lock range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeConfig {
+    pub start: u32,
+    pub stop: u32,
+    /// Spacing of the coarse sweep. Bands or gaps narrower than this can be
+    /// missed.
+    pub step: u32,
+    /// Precision of the band edges
+    pub resolution: u32,
+}
+
+impl Default for RangeConfig {
+    fn default() -> RangeConfig {
+        RangeConfig {
+            start: 1_000_000,
+            stop: 2_400_000_000,
+            step: 5_000_000,
+            resolution: 100_000,
+        }
+    }
+}
+
+/// Find the bands where the tuner PLL locks, given `locks` to tune and
+/// report whether it locked. Sweeps from `config.start` to `config.stop`
+/// in coarse steps, then narrows each edge down to `config.resolution`.
+pub fn find_lock_bands<F: FnMut(u32) -> Result<bool>>(
+    config: &RangeConfig,
+    mut locks: F,
+) -> Result<Vec<RangeInclusive<u32>>> {
+    if config.start > config.stop || config.step == 0 || config.resolution == 0 {
+        return Err(RtlsdrErr(format!("Invalid range config {:?}", config)));
+    }
+    let mut bands = Vec::new();
+    // Start of the current band, and the last frequency tried
+    let mut band_start = None;
+    let mut prev: Option<(u32, bool)> = None;
+    let mut freq = config.start;
+    loop {
+        let locked = locks(freq)?;
+        match (prev, locked) {
+            (None, true) => band_start = Some(freq),
+            // Lower edge between an unlocked and a locked frequency
+            (Some((below, false)), true) => {
+                band_start = Some(find_edge(below, freq, false, config.resolution, &mut locks)?.1);
+            }
+            // Upper edge between a locked and an unlocked frequency
+            (Some((below, true)), false) => {
+                let (end, _) = find_edge(below, freq, true, config.resolution, &mut locks)?;
+                if let Some(start) = band_start.take() {
+                    bands.push(start..=end);
+                }
+            }
+            _ => {}
+        }
+        prev = Some((freq, locked));
+        if freq == config.stop {
+            break;
+        }
+        freq = freq.saturating_add(config.step).min(config.stop);
+    }
+    if let Some(start) = band_start {
+        bands.push(start..=config.stop);
+    }
+    Ok(bands)
+}
+
+/// Narrow the change in lock between `low` and `high` down to
+/// `resolution`, returning the last frequency on the `low` side and the
+/// first on the `high` side
+fn find_edge<F: FnMut(u32) -> Result<bool>>(
+    mut low: u32,
+    mut high: u32,
+    low_locked: bool,
+    resolution: u32,
+    locks: &mut F,
+) -> Result<(u32, u32)> {
+    while high - low > resolution {
+        let mid = low + (high - low) / 2;
+        if locks(mid)? == low_locked {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok((low, high))
+}
+
+/// Find the bands where the device's tuner PLL locks, then retune to the
+/// original frequency. Set direct sampling off (and not automatic) first,
+/// as the PLL isn't used while direct sampling.
+pub fn tuner_range(sdr: &mut RtlSdr, config: &RangeConfig) -> Result<Vec<RangeInclusive<u32>>> {
+    let freq = sdr.get_center_freq();
+    let bands = find_lock_bands(config, |f| match sdr.set_center_freq(f) {
+        Ok(()) => Ok(true),
+        // Frequencies the PLL can't be programmed for at all
+        Err(RtlsdrErr(_)) | Err(PllNotLocked(_)) => Ok(false),
+        Err(e) => Err(e),
+    });
+    // The original frequency may not have locked either
+    match sdr.set_center_freq(freq) {
+        Ok(()) | Err(PllNotLocked(_)) => bands,
+        Err(e) => Err(e),
+    }
+}
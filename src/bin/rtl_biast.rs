@@ -0,0 +1,12 @@
+//! Switch the bias tee, or any GPIO pin, on or off without starting a
+//! receive session, like the RTL-SDR Blog rtl_biast. Built with the `bins`
+//! feature, and also available as `rtlsdr biast`:
+//! cargo run --features bins --bin rtl_biast -- -b 1
+
+use clap::Parser;
+use rtlsdr_rs::cli::biast::{run, Args};
+use rtlsdr_rs::error::Result;
+
+fn main() -> Result<()> {
+    run(Args::parse())
+}
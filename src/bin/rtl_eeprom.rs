@@ -0,0 +1,12 @@
+//! Dump, edit, back up and restore the RTL2832 configuration EEPROM, like
+//! librtlsdr's rtl_eeprom. Built with the `bins` feature, and also available
+//! as `rtlsdr eeprom`:
+//! cargo run --features bins --bin rtl_eeprom -- -s 00000042
+
+use clap::Parser;
+use rtlsdr_rs::cli::eeprom::{run, Args};
+use rtlsdr_rs::error::Result;
+
+fn main() -> Result<()> {
+    run(Args::parse())
+}
@@ -0,0 +1,12 @@
+//! Narrow band FM, broadcast FM, AM and SSB receiver, like librtlsdr's
+//! rtl_fm. Built with the `bins` feature, and also available as
+//! `rtlsdr fm`:
+//! cargo run --features bins --bin rtl_fm -- -f 94.9M -M wbfm | play -r 32k -t raw -e s -b 16 -c 1 -V1 -
+
+use clap::Parser;
+use rtlsdr_rs::cli::fm::{run, Args};
+use rtlsdr_rs::error::Result;
+
+fn main() -> Result<()> {
+    run(Args::parse())
+}
@@ -0,0 +1,11 @@
+//! Raw u8 IQ capture to a file or stdout, like librtlsdr's rtl_sdr. Built
+//! with the `bins` feature, and also available as `rtlsdr record`:
+//! cargo run --features bins --bin rtl_sdr -- -f 94.9M -s 2.048M -n 20M capture.bin
+
+use clap::Parser;
+use rtlsdr_rs::cli::record::{run, Args};
+use rtlsdr_rs::error::Result;
+
+fn main() -> Result<()> {
+    run(Args::parse())
+}
@@ -0,0 +1,42 @@
+//! All the command line tools in one binary, sharing argument parsing and
+//! device selection. Built with the `bins` feature:
+//! cargo run --features bins --bin rtlsdr -- devices
+//! cargo run --features bins --bin rtlsdr -- fm -d 00000042 -f 94.9M -M wbfm
+
+use clap::{Parser, Subcommand};
+use rtlsdr_rs::cli::{biast, devices, doctor, eeprom, fm, power, record, tcp, test};
+use rtlsdr_rs::error::Result;
+
+#[derive(Parser)]
+#[command(about = "RTL-SDR receiver tools", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Devices(devices::Args),
+    Doctor(doctor::Args),
+    Test(test::Args),
+    Fm(fm::Args),
+    Power(power::Args),
+    Tcp(tcp::Args),
+    Eeprom(eeprom::Args),
+    Record(record::Args),
+    Biast(biast::Args),
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Devices(args) => devices::run(args),
+        Command::Doctor(args) => doctor::run(args),
+        Command::Test(args) => test::run(args),
+        Command::Fm(args) => fm::run(args),
+        Command::Power(args) => power::run(args),
+        Command::Tcp(args) => tcp::run(args),
+        Command::Eeprom(args) => eeprom::run(args),
+        Command::Record(args) => record::run(args),
+        Command::Biast(args) => biast::run(args),
+    }
+}
@@ -0,0 +1,152 @@
+use super::{Bookmark, Bookmarks, Mode};
+use std::fs;
+
+const SDRSHARP: &str = r#"<?xml version="1.0"?>
+<ArrayOfMemoryEntry xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <MemoryEntry>
+    <IsFavourite>true</IsFavourite>
+    <Name>WREK</Name>
+    <GroupName>Broadcast</GroupName>
+    <Frequency>91100000</Frequency>
+    <DetectorType>WFM</DetectorType>
+    <Shift>0</Shift>
+    <FilterBandwidth>180000</FilterBandwidth>
+  </MemoryEntry>
+  <MemoryEntry>
+    <IsFavourite>false</IsFavourite>
+    <Name>Tower &amp; Ground</Name>
+    <GroupName>Air</GroupName>
+    <Frequency>119100000</Frequency>
+    <DetectorType>AM</DetectorType>
+    <Shift>0</Shift>
+    <FilterBandwidth>8000</FilterBandwidth>
+  </MemoryEntry>
+</ArrayOfMemoryEntry>
+"#;
+
+const GQRX: &str = "# Tag name          ;  color
+Untagged            ; #c0c0c0
+Marine              ; #0000ff
+
+# Frequency ; Name                     ; Modulation          ;  Bandwidth; Tags
+   156800000; Channel 16               ; Narrow FM           ;      10000; Marine
+    14074000; FT8                      ; USB                 ;       2800; Untagged
+    94900000; WREK                     ; WFM (stereo)        ;     160000; Untagged
+";
+
+fn sample() -> Bookmarks {
+    Bookmarks::from_iter([
+        Bookmark::new("WREK", 91_100_000, Mode::Wfm, 200_000),
+        Bookmark::new("Tower", 119_100_000, Mode::Am, 8000),
+        Bookmark::new("FT8", 14_074_000, Mode::Usb, 2800),
+    ])
+}
+
+#[test]
+fn test_add_and_get() {
+    let mut bookmarks = sample();
+    let freqs: Vec<u32> = bookmarks.iter().map(|b| b.frequency).collect();
+    assert_eq!(vec![14_074_000, 91_100_000, 119_100_000], freqs);
+    assert_eq!(91_100_000, bookmarks.get("wrek").unwrap().frequency);
+
+    // Same name replaces, in its new frequency order
+    let old = bookmarks.add(Bookmark::new("wrek", 200_000_000, Mode::Wfm, 0));
+    assert_eq!(91_100_000, old.unwrap().frequency);
+    assert_eq!(3, bookmarks.len());
+    assert_eq!("wrek", bookmarks.iter().last().unwrap().name);
+
+    assert!(bookmarks.remove("Tower").is_some());
+    assert!(bookmarks.get("tower").is_none());
+}
+
+#[test]
+fn test_resolve() {
+    let bookmarks = sample();
+    assert_eq!(91_100_000, bookmarks.resolve("Wrek").unwrap());
+    assert_eq!(94_900_000, bookmarks.resolve("94.9M").unwrap());
+    assert_eq!(1_090_000_000, bookmarks.resolve("1.09G").unwrap());
+    assert_eq!(162_550, bookmarks.resolve("162.55k").unwrap());
+    assert_eq!(433_920_000, bookmarks.resolve("433920000").unwrap());
+    assert!(bookmarks.resolve("KISS").is_err());
+    assert!(bookmarks.resolve("-5M").is_err());
+}
+
+#[test]
+fn test_in_range() {
+    let bookmarks = sample();
+    let names: Vec<&str> = bookmarks
+        .in_range(88_000_000, 119_100_000)
+        .iter()
+        .map(|b| b.name.as_str())
+        .collect();
+    assert_eq!(vec!["WREK", "Tower"], names);
+    assert!(bookmarks.in_range(200_000_000, 100_000_000).is_empty());
+}
+
+#[test]
+fn test_json_round_trip() {
+    let bookmarks = sample();
+    assert_eq!(
+        bookmarks,
+        Bookmarks::from_json(&bookmarks.to_json()).unwrap()
+    );
+
+    let path = std::env::temp_dir().join(format!("rtlsdr-bookmarks-{}.json", std::process::id()));
+    bookmarks.save(&path).unwrap();
+    assert_eq!(bookmarks, Bookmarks::load(&path).unwrap());
+    assert_eq!(bookmarks, Bookmarks::import(&path).unwrap());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_json_errors() {
+    assert!(Bookmarks::from_json("{}").is_err());
+    assert!(Bookmarks::from_json(r#"[{"name": "A"}]"#).is_err());
+    assert!(Bookmarks::from_json(r#"[{"name": "A", "frequency": 1, "mode": "fax"}]"#).is_err());
+    // Mode and bandwidth are optional
+    let bookmarks = Bookmarks::from_json(r#"[{"name": "A", "frequency": 1}]"#).unwrap();
+    assert_eq!(
+        Bookmark::new("A", 1, Mode::Raw, 0),
+        bookmarks.iter().next().cloned().unwrap()
+    );
+}
+
+#[test]
+fn test_sdrsharp() {
+    let bookmarks = Bookmarks::from_sdrsharp(SDRSHARP).unwrap();
+    let expected = Bookmarks::from_iter([
+        Bookmark::new("WREK", 91_100_000, Mode::Wfm, 180_000),
+        Bookmark::new("Tower & Ground", 119_100_000, Mode::Am, 8000),
+    ]);
+    assert_eq!(expected, bookmarks);
+    assert_eq!(
+        bookmarks,
+        Bookmarks::from_sdrsharp(&bookmarks.to_sdrsharp()).unwrap()
+    );
+    assert!(Bookmarks::from_sdrsharp("<MemoryEntry><Name>A</Name></MemoryEntry>").is_err());
+}
+
+#[test]
+fn test_gqrx() {
+    let bookmarks = Bookmarks::from_gqrx(GQRX).unwrap();
+    let expected = Bookmarks::from_iter([
+        Bookmark::new("Channel 16", 156_800_000, Mode::Nfm, 10_000),
+        Bookmark::new("FT8", 14_074_000, Mode::Usb, 2800),
+        Bookmark::new("WREK", 94_900_000, Mode::Wfm, 160_000),
+    ]);
+    assert_eq!(expected, bookmarks);
+    assert_eq!(
+        bookmarks,
+        Bookmarks::from_gqrx(&bookmarks.to_gqrx()).unwrap()
+    );
+    assert!(Bookmarks::from_gqrx("abc; A; AM; 1000; Untagged").is_err());
+}
+
+#[test]
+fn test_merge() {
+    let mut bookmarks = sample();
+    bookmarks.merge(Bookmarks::from_gqrx(GQRX).unwrap());
+    assert_eq!(4, bookmarks.len());
+    // The imported FT8 and WREK replaced the existing ones
+    assert_eq!(94_900_000, bookmarks.resolve("WREK").unwrap());
+}
@@ -0,0 +1,159 @@
+//! Bookmark files of other SDR programs
+
+use super::{Bookmark, Mode};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+
+const GQRX_TAG: &str = "Untagged";
+
+/// Parse the `<MemoryEntry>` elements of an SDR# `frequencies.xml`
+pub(super) fn parse_sdrsharp(text: &str) -> Result<Vec<Bookmark>> {
+    let mut bookmarks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<MemoryEntry>") {
+        rest = &rest[start..];
+        let end = rest
+            .find("</MemoryEntry>")
+            .ok_or_else(|| RtlsdrErr("Unterminated MemoryEntry in SDR# file".to_string()))?;
+        let entry = &rest[..end];
+        rest = &rest[end..];
+
+        let frequency = element(entry, "Frequency")
+            .and_then(|f| f.trim().parse::<u32>().ok())
+            .ok_or_else(|| RtlsdrErr("SDR# entry has no valid Frequency".to_string()))?;
+        let name = element(entry, "Name")
+            .map(unescape)
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| frequency.to_string());
+        let mode = match element(entry, "DetectorType").map(str::trim) {
+            Some("AM") | Some("DSB") => Mode::Am,
+            Some("NFM") => Mode::Nfm,
+            Some("WFM") => Mode::Wfm,
+            Some("USB") => Mode::Usb,
+            Some("LSB") => Mode::Lsb,
+            Some("CW") => Mode::Cw,
+            _ => Mode::Raw,
+        };
+        let bandwidth = element(entry, "FilterBandwidth")
+            .and_then(|b| b.trim().parse().ok())
+            .unwrap_or(0);
+        bookmarks.push(Bookmark::new(name.trim(), frequency, mode, bandwidth));
+    }
+    Ok(bookmarks)
+}
+
+pub(super) fn write_sdrsharp(bookmarks: &[Bookmark]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\"?>\n<ArrayOfMemoryEntry \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n",
+    );
+    for b in bookmarks {
+        let detector = match b.mode {
+            Mode::Am => "AM",
+            Mode::Nfm => "NFM",
+            Mode::Wfm => "WFM",
+            Mode::Usb => "USB",
+            Mode::Lsb => "LSB",
+            Mode::Cw => "CW",
+            Mode::Raw => "RAW",
+        };
+        out.push_str(&format!(
+            "  <MemoryEntry>\n    <IsFavourite>false</IsFavourite>\n    \
+             <Name>{}</Name>\n    <GroupName>rtlsdr-rs</GroupName>\n    \
+             <Frequency>{}</Frequency>\n    <DetectorType>{}</DetectorType>\n    \
+             <Shift>0</Shift>\n    <FilterBandwidth>{}</FilterBandwidth>\n  </MemoryEntry>\n",
+            escape(&b.name),
+            b.frequency,
+            detector,
+            b.bandwidth
+        ));
+    }
+    out.push_str("</ArrayOfMemoryEntry>\n");
+    out
+}
+
+/// Parse the bookmark lines of a gqrx `bookmarks.csv`, skipping the tag list
+/// at the top
+pub(super) fn parse_gqrx(text: &str) -> Result<Vec<Bookmark>> {
+    let mut bookmarks = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        if fields.len() < 4 {
+            // A tag and its color
+            continue;
+        }
+        let frequency = fields[0]
+            .parse::<u32>()
+            .map_err(|_| RtlsdrErr(format!("Invalid frequency on gqrx line {}", i + 1)))?;
+        let mode = match fields[2] {
+            "AM" | "AM-Sync" => Mode::Am,
+            "Narrow FM" => Mode::Nfm,
+            m if m.starts_with("WFM") => Mode::Wfm,
+            "USB" => Mode::Usb,
+            "LSB" => Mode::Lsb,
+            "CW-L" | "CW-U" => Mode::Cw,
+            _ => Mode::Raw,
+        };
+        let bandwidth = fields[3].parse().unwrap_or(0);
+        let name = match fields[1] {
+            "" => frequency.to_string(),
+            name => name.to_string(),
+        };
+        bookmarks.push(Bookmark::new(&name, frequency, mode, bandwidth));
+    }
+    Ok(bookmarks)
+}
+
+pub(super) fn write_gqrx(bookmarks: &[Bookmark]) -> String {
+    let mut out = format!(
+        "# Tag name          ;  color\n{:<20}; #c0c0c0\n\n\
+         # Frequency ; Name                     ; Modulation          ;  Bandwidth; Tags\n",
+        GQRX_TAG
+    );
+    for b in bookmarks {
+        let modulation = match b.mode {
+            Mode::Am => "AM",
+            Mode::Nfm => "Narrow FM",
+            Mode::Wfm => "WFM (stereo)",
+            Mode::Usb => "USB",
+            Mode::Lsb => "LSB",
+            Mode::Cw => "CW-U",
+            Mode::Raw => "Raw I/Q",
+        };
+        // gqrx has no escaping for the separator
+        let name = b.name.replace(';', ",");
+        out.push_str(&format!(
+            "{:>12}; {:<25}; {:<20}; {:>10}; {}\n",
+            b.frequency, name, modulation, b.bandwidth, GQRX_TAG
+        ));
+    }
+    out
+}
+
+/// Text of the first `<tag>` element in `xml`
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..start + end])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
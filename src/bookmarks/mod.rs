@@ -0,0 +1,274 @@
+//! Named frequencies, so tools can tune to "WREK" instead of 91100000.
+//!
+//! Bookmarks are stored as JSON, and can be imported from the SDR# frequency
+//! manager (`frequencies.xml`) and gqrx (`bookmarks.csv`):
+//! ```no_run
+//! use rtlsdr_rs::bookmarks::{Bookmark, Bookmarks, Mode};
+//!
+//! let mut bookmarks = Bookmarks::import("frequencies.xml").unwrap();
+//! bookmarks.add(Bookmark::new("WREK", 91_100_000, Mode::Wfm, 200_000));
+//! bookmarks.save("bookmarks.json").unwrap();
+//! let freq = bookmarks.resolve("wrek").unwrap();
+//! ```
+mod formats;
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// Demodulation mode to listen with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))] // As in `name`
+pub enum Mode {
+    Am,
+    Nfm, // Narrowband FM
+    Wfm, // Broadcast FM
+    Usb,
+    Lsb,
+    Cw,
+    Raw, // Unknown or no demodulation
+}
+
+impl Mode {
+    /// Name as stored in the JSON file
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Am => "am",
+            Mode::Nfm => "nfm",
+            Mode::Wfm => "wfm",
+            Mode::Usb => "usb",
+            Mode::Lsb => "lsb",
+            Mode::Cw => "cw",
+            Mode::Raw => "raw",
+        }
+    }
+
+    /// Parse a name as returned by `name`, ignoring case
+    pub fn parse(name: &str) -> Option<Mode> {
+        match name.to_ascii_lowercase().as_str() {
+            "am" => Some(Mode::Am),
+            "nfm" => Some(Mode::Nfm),
+            "wfm" => Some(Mode::Wfm),
+            "usb" => Some(Mode::Usb),
+            "lsb" => Some(Mode::Lsb),
+            "cw" => Some(Mode::Cw),
+            "raw" => Some(Mode::Raw),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bookmark {
+    pub name: String,
+    pub frequency: u32, // Hz
+    pub mode: Mode,
+    pub bandwidth: u32, // Hz, 0 if unknown
+}
+
+impl Bookmark {
+    pub fn new(name: &str, frequency: u32, mode: Mode, bandwidth: u32) -> Bookmark {
+        Bookmark {
+            name: name.to_string(),
+            frequency,
+            mode,
+            bandwidth,
+        }
+    }
+}
+
+/// Bookmarks in frequency order, with unique names ignoring case
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bookmarks {
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Bookmarks {
+        Bookmarks::default()
+    }
+
+    /// Read a JSON file written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bookmarks> {
+        Bookmarks::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Read a file in any supported format, chosen by its extension: `.xml`
+    /// for SDR#, `.csv` for gqrx and otherwise JSON
+    pub fn import<P: AsRef<Path>>(path: P) -> Result<Bookmarks> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("xml") => Bookmarks::from_sdrsharp(&text),
+            Some("csv") => Bookmarks::from_gqrx(&text),
+            _ => Bookmarks::from_json(&text),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    pub fn from_json(text: &str) -> Result<Bookmarks> {
+        let json: Value = serde_json::from_str(text)
+            .map_err(|e| RtlsdrErr(format!("Invalid bookmarks: {}", e)))?;
+        let entries = json
+            .as_array()
+            .ok_or_else(|| RtlsdrErr("Bookmarks must be a JSON array".to_string()))?;
+        let mut bookmarks = Bookmarks::new();
+        for entry in entries {
+            let name = entry["name"]
+                .as_str()
+                .ok_or_else(|| RtlsdrErr(format!("Bookmark has no name: {}", entry)))?;
+            let frequency = entry["frequency"]
+                .as_u64()
+                .and_then(|f| u32::try_from(f).ok())
+                .ok_or_else(|| RtlsdrErr(format!("Bookmark {} has no frequency", name)))?;
+            let mode = match entry["mode"].as_str() {
+                Some(mode) => Mode::parse(mode)
+                    .ok_or_else(|| RtlsdrErr(format!("Unknown mode for {}: {}", name, mode)))?,
+                None => Mode::Raw,
+            };
+            let bandwidth = entry["bandwidth"].as_u64().unwrap_or(0) as u32;
+            bookmarks.add(Bookmark::new(name, frequency, mode, bandwidth));
+        }
+        Ok(bookmarks)
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: Vec<Value> = self
+            .bookmarks
+            .iter()
+            .map(|b| {
+                json!({
+                    "name": b.name,
+                    "frequency": b.frequency,
+                    "mode": b.mode.name(),
+                    "bandwidth": b.bandwidth,
+                })
+            })
+            .collect();
+        // Serializing a Value can't fail
+        serde_json::to_string_pretty(&entries).unwrap_or_default()
+    }
+
+    /// Parse an SDR# `frequencies.xml` file
+    pub fn from_sdrsharp(text: &str) -> Result<Bookmarks> {
+        Ok(Bookmarks::from_iter(formats::parse_sdrsharp(text)?))
+    }
+
+    pub fn to_sdrsharp(&self) -> String {
+        formats::write_sdrsharp(&self.bookmarks)
+    }
+
+    /// Parse a gqrx `bookmarks.csv` file
+    pub fn from_gqrx(text: &str) -> Result<Bookmarks> {
+        Ok(Bookmarks::from_iter(formats::parse_gqrx(text)?))
+    }
+
+    pub fn to_gqrx(&self) -> String {
+        formats::write_gqrx(&self.bookmarks)
+    }
+
+    /// Add a bookmark, returning any it replaced with the same name
+    pub fn add(&mut self, bookmark: Bookmark) -> Option<Bookmark> {
+        let replaced = self.remove(&bookmark.name);
+        let i = self
+            .bookmarks
+            .partition_point(|b| b.frequency <= bookmark.frequency);
+        self.bookmarks.insert(i, bookmark);
+        replaced
+    }
+
+    /// Add every bookmark in `other`, replacing those with the same names
+    pub fn merge(&mut self, other: Bookmarks) {
+        for bookmark in other.bookmarks {
+            self.add(bookmark);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Bookmark> {
+        let i = self
+            .bookmarks
+            .iter()
+            .position(|b| b.name.eq_ignore_ascii_case(name))?;
+        Some(self.bookmarks.remove(i))
+    }
+
+    /// Find a bookmark by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks
+            .iter()
+            .find(|b| b.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Frequency for a bookmark name, or a frequency in Hz with an optional
+    /// k, M or G suffix such as "94.9M"
+    pub fn resolve(&self, name: &str) -> Result<u32> {
+        if let Some(bookmark) = self.get(name) {
+            return Ok(bookmark.frequency);
+        }
+        parse_freq(name).ok_or_else(|| RtlsdrErr(format!("No bookmark named {}", name)))
+    }
+
+    /// Bookmarks from `low` to `high` Hz inclusive, e.g. within a scan range
+    pub fn in_range(&self, low: u32, high: u32) -> &[Bookmark] {
+        let start = self.bookmarks.partition_point(|b| b.frequency < low);
+        let end = self.bookmarks.partition_point(|b| b.frequency <= high);
+        &self.bookmarks[start..end.max(start)]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Bookmark> {
+        self.bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+}
+
+impl FromIterator<Bookmark> for Bookmarks {
+    fn from_iter<I: IntoIterator<Item = Bookmark>>(iter: I) -> Bookmarks {
+        let mut bookmarks = Bookmarks::new();
+        for bookmark in iter {
+            bookmarks.add(bookmark);
+        }
+        bookmarks
+    }
+}
+
+impl<'a> IntoIterator for &'a Bookmarks {
+    type Item = &'a Bookmark;
+    type IntoIter = std::slice::Iter<'a, Bookmark>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Frequency in Hz with an optional k, M or G suffix, such as `94.9M`
+pub fn parse_freq(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let (number, scale) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1e3),
+        'M' => (&text[..text.len() - 1], 1e6),
+        'G' | 'g' => (&text[..text.len() - 1], 1e9),
+        _ => (text, 1.0),
+    };
+    let hz = number.parse::<f64>().ok()? * scale;
+    (0.0..=u32::MAX as f64)
+        .contains(&hz)
+        .then(|| hz.round() as u32)
+}
+
+#[cfg(test)]
+mod bookmarks_test;
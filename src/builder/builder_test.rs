@@ -0,0 +1,35 @@
+use crate::units::Hertz;
+use crate::{DirectSampleMode, RtlSdr, Settings, TunerGain};
+
+#[test]
+fn test_merge() {
+    // As opened, with a calibration and the bias tee on from the EEPROM
+    let defaults = Settings {
+        center_freq: 0,
+        sample_rate: 0,
+        gain: TunerGain::Auto,
+        ppm: 2,
+        bandwidth: 0,
+        bias_tee: true,
+        direct_sampling: DirectSampleMode::Off,
+        agc: false,
+    };
+    assert_eq!(defaults, RtlSdr::builder().index(1).merge(defaults));
+
+    let builder = RtlSdr::builder()
+        .sample_rate(2_048_000)
+        .freq(Hertz::mhz(100.0))
+        .gain(TunerGain::Manual(496))
+        .agc(true);
+    assert_eq!(
+        Settings {
+            center_freq: 100_000_000,
+            sample_rate: 2_048_000,
+            gain: TunerGain::Manual(496),
+            agc: true,
+            ..defaults
+        },
+        builder.merge(defaults)
+    );
+    assert!(!builder.bias_tee(false).merge(defaults).bias_tee);
+}
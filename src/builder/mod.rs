@@ -0,0 +1,137 @@
+//! Opening a device with its settings in one expression
+//!
+//! ```no_run
+//! use rtlsdr_rs::{RtlSdr, TunerGain};
+//!
+//! let sdr = RtlSdr::builder()
+//!     .index(0)
+//!     .sample_rate(2_048_000)
+//!     .freq(100_000_000)
+//!     .gain(TunerGain::Auto)
+//!     .bias_tee(false)
+//!     .open()
+//!     .unwrap();
+//! ```
+
+use crate::error::Result;
+use crate::rtlsdr::OpenOptions;
+use crate::units::{Hertz, Ppm, SampleRate};
+use crate::{DirectSampleMode, RtlSdr, Settings, TunerGain};
+
+#[cfg(test)]
+mod builder_test;
+
+/// Settings to apply when opening a device. Those left unset keep the
+/// device's defaults, including any stored in its EEPROM.
+#[derive(Debug, Clone, Default)]
+pub struct RtlSdrBuilder {
+    index: usize,
+    center_freq: Option<u32>,
+    sample_rate: Option<u32>,
+    gain: Option<TunerGain>,
+    ppm: Option<i32>,
+    bandwidth: Option<u32>,
+    bias_tee: Option<bool>,
+    direct_sampling: Option<DirectSampleMode>,
+    agc: Option<bool>,
+    options: OpenOptions,
+}
+
+impl RtlSdrBuilder {
+    /// Device index, see `RtlSdr::list_devices`. Defaults to 0.
+    pub fn index(mut self, index: usize) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn freq(mut self, freq: impl Into<Hertz>) -> Self {
+        self.center_freq = Some(freq.into().0);
+        self
+    }
+
+    pub fn sample_rate(mut self, rate: impl Into<SampleRate>) -> Self {
+        self.sample_rate = Some(rate.into().0);
+        self
+    }
+
+    pub fn gain(mut self, gain: TunerGain) -> Self {
+        self.gain = Some(gain);
+        self
+    }
+
+    /// Overrides any calibration stored in the EEPROM
+    pub fn ppm(mut self, ppm: impl Into<Ppm>) -> Self {
+        self.ppm = Some(ppm.into().0);
+        self
+    }
+
+    /// Tuner bandwidth, or 0 to follow the sample rate
+    pub fn bandwidth(mut self, bw: impl Into<Hertz>) -> Self {
+        self.bandwidth = Some(bw.into().0);
+        self
+    }
+
+    pub fn bias_tee(mut self, on: bool) -> Self {
+        self.bias_tee = Some(on);
+        self
+    }
+
+    pub fn direct_sampling(mut self, mode: DirectSampleMode) -> Self {
+        self.direct_sampling = Some(mode);
+        self
+    }
+
+    /// RTL2832 digital AGC
+    pub fn agc(mut self, on: bool) -> Self {
+        self.agc = Some(on);
+        self
+    }
+
+    /// Fail to open if the EEPROM configuration can't be read, for
+    /// applications that depend on the bias tee or direct sampling it forces
+    /// on. By default a bad EEPROM only logs a warning.
+    pub fn require_eeprom(mut self, on: bool) -> Self {
+        self.options.require_eeprom = on;
+        self
+    }
+
+    /// Open boards whose tuner is dead or absent, with direct sampling on
+    /// (`DirectSampleMode::On`) instead of failing. Tuning then only moves
+    /// the digital downconverter, covering up to about 14 MHz.
+    pub fn allow_no_tuner(mut self, on: bool) -> Self {
+        self.options.allow_no_tuner = on;
+        self
+    }
+
+    /// Force the bias tee and direct sampling on when the EEPROM says to, like
+    /// librtlsdr from RTL-SDR Blog does. Off by default, as dongles programmed
+    /// by other software can have the flags set by accident; see
+    /// `RtlSdr::eeprom_overrides` for the flags read.
+    pub fn honor_eeprom_overrides(mut self, on: bool) -> Self {
+        self.options.honor_eeprom_overrides = on;
+        self
+    }
+
+    /// Open the device and apply the settings in one I2C repeater session,
+    /// returning it with the buffer reset, ready to read
+    pub fn open(self) -> Result<RtlSdr> {
+        let sdr = RtlSdr::open_device(self.index, self.options, None)?;
+        sdr.apply(&self.merge(sdr.settings()))?;
+        sdr.reset_buffer()?;
+        Ok(sdr)
+    }
+
+    /// `defaults` with the settings that were given replaced
+    fn merge(&self, defaults: Settings) -> Settings {
+        Settings {
+            center_freq: self.center_freq.unwrap_or(defaults.center_freq),
+            sample_rate: self.sample_rate.unwrap_or(defaults.sample_rate),
+            gain: self.gain.unwrap_or(defaults.gain),
+            ppm: self.ppm.unwrap_or(defaults.ppm),
+            bandwidth: self.bandwidth.unwrap_or(defaults.bandwidth),
+            bias_tee: self.bias_tee.unwrap_or(defaults.bias_tee),
+            direct_sampling: self.direct_sampling.unwrap_or(defaults.direct_sampling),
+            agc: self.agc.unwrap_or(defaults.agc),
+        }
+    }
+}
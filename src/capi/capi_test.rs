@@ -0,0 +1,75 @@
+use super::*;
+use std::ptr;
+
+#[test]
+fn test_code() {
+    assert_eq!(-6, code(&RtlsdrError::Usb(rusb::Error::Busy)));
+    assert_eq!(-99, code(&RtlsdrError::Usb(rusb::Error::Other)));
+    assert_eq!(-1, code(&RtlsdrError::RtlsdrErr("Failed".to_string())));
+}
+
+#[test]
+fn test_null_device() {
+    let dev = ptr::null_mut();
+    unsafe {
+        assert_eq!(-1, rtlsdr_close(dev));
+        assert_eq!(-1, rtlsdr_set_center_freq(dev, 100_000_000));
+        assert_eq!(0, rtlsdr_get_center_freq(dev));
+        assert_eq!(0, rtlsdr_get_sample_rate(dev));
+        assert_eq!(TUNER_UNKNOWN as c_int, rtlsdr_get_tuner_type(dev));
+        assert_eq!(-1, rtlsdr_get_tuner_gains(dev, ptr::null_mut()));
+        assert_eq!(-1, rtlsdr_cancel_async(dev));
+        assert_eq!(-1, rtlsdr_set_tuner_if_gain(dev, 0, 0));
+        assert_eq!(-1, rtlsdr_read_eeprom(dev, ptr::null_mut(), 0, 8));
+        assert_eq!(-1, rtlsdr_write_eeprom(dev, ptr::null_mut(), 0, 8));
+        assert_eq!(-1, rtlsdr_open(ptr::null_mut(), 0));
+    }
+}
+
+#[test]
+fn test_invalid_arguments() {
+    unsafe extern "C" fn cb(_buf: *mut c_uchar, _len: u32, _ctx: *mut c_void) {}
+    let dev = ptr::null_mut();
+    unsafe {
+        assert_eq!(-1, rtlsdr_read_async(dev, None, ptr::null_mut(), 0, 0));
+        // Not a whole number of USB packets
+        assert_eq!(
+            -1,
+            rtlsdr_read_async(dev, Some(cb), ptr::null_mut(), 0, 1000)
+        );
+        assert_eq!(
+            -1,
+            rtlsdr_read_sync(dev, ptr::null_mut(), 512, ptr::null_mut())
+        );
+        assert_eq!(-1, rtlsdr_set_direct_sampling(dev, 3));
+        assert_eq!(-1, rtlsdr_get_index_by_serial(ptr::null()));
+    }
+}
+
+#[test]
+fn test_eeprom_range() {
+    assert_eq!(Ok(0..EEPROM_SIZE), eeprom_range(0, EEPROM_SIZE as u16));
+    assert_eq!(Ok(250..256), eeprom_range(250, 6));
+    assert_eq!(Err(-2), eeprom_range(250, 7));
+    assert_eq!(Err(-2), eeprom_range(0, u16::MAX));
+}
+
+#[test]
+fn test_device_name_out_of_range() {
+    let name = unsafe { CStr::from_ptr(rtlsdr_get_device_name(u32::MAX)) };
+    assert!(name.to_bytes().is_empty());
+}
+
+#[test]
+fn test_copy_string() {
+    let mut buf = [1 as c_char; USB_STRING_LEN];
+    unsafe { copy_string("RTLSDRBlog", buf.as_mut_ptr()) };
+    let text = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    assert_eq!("RTLSDRBlog", text.to_str().unwrap());
+
+    // Truncated to fit with the terminator
+    let long = "x".repeat(2 * USB_STRING_LEN);
+    unsafe { copy_string(&long, buf.as_mut_ptr()) };
+    let text = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    assert_eq!(USB_STRING_LEN - 1, text.to_bytes().len());
+}
@@ -0,0 +1,567 @@
+//! librtlsdr compatible C API (feature `cdylib`), so existing C programs
+//! such as dump1090 and rtl_433 can run on this crate instead of librtlsdr.
+//!
+//! Build the shared library with
+//! ```text
+//! cargo rustc --release --features cdylib --crate-type cdylib
+//! ```
+//! and link against `librtlsdr_rs.so` in place of `librtlsdr.so`, using the
+//! usual `rtl-sdr.h` header.
+//!
+//! Functions follow librtlsdr's conventions: 0 on success and a negative
+//! value on error, with libusb error codes for USB errors. As in C, callers
+//! must pass valid pointers, and a device pointer must come from
+//! `rtlsdr_open` and not be used after `rtlsdr_close`. Calls on one device
+//! from several threads are serialized, but reads don't wait for control
+//! calls, so a control thread can retune while `rtlsdr_read_async` runs. Not
+//! supported: offset tuning, which R820T tuners don't have.
+#![allow(clippy::missing_safety_doc)]
+
+use crate::device::device_handle::{devices, usb_strings};
+use crate::device::KNOWN_DEVICES;
+use crate::error::RtlsdrError;
+use crate::net::rtl_tcp::{TUNER_R820T, TUNER_UNKNOWN};
+use crate::rtlsdr::OpenOptions;
+use crate::{DirectSampleMode, RtlSdr, TunerGain, DEFAULT_BUF_LENGTH, EEPROM_SIZE};
+use log::warn;
+use std::ffi::{c_char, c_int, c_uchar, c_void, CStr, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// Size of the string buffers passed to the USB string functions
+const USB_STRING_LEN: usize = 256;
+// Bulk transfers are in whole USB packets
+const USB_PACKET_LEN: u32 = 512;
+
+/// Callback for `rtlsdr_read_async` with each buffer of samples
+pub type ReadAsyncCallback = Option<unsafe extern "C" fn(*mut c_uchar, u32, *mut c_void)>;
+
+/// The `rtlsdr_dev_t` handle given to C
+pub struct Dev {
+    sdr: RtlSdr,
+    gain: Mutex<i32>, // Last manual gain set, 0 for auto
+    direct_sampling: Mutex<c_int>,
+    cancel: AtomicBool, // Set to stop `rtlsdr_read_async`
+}
+
+/// librtlsdr's return code for an error
+fn code(e: &RtlsdrError) -> c_int {
+    match e {
+        RtlsdrError::Usb(e) => match e {
+            rusb::Error::Io => -1,
+            rusb::Error::InvalidParam => -2,
+            rusb::Error::Access => -3,
+            rusb::Error::NoDevice => -4,
+            rusb::Error::NotFound => -5,
+            rusb::Error::Busy => -6,
+            rusb::Error::Timeout => -7,
+            rusb::Error::Overflow => -8,
+            rusb::Error::Pipe => -9,
+            rusb::Error::Interrupted => -10,
+            rusb::Error::NoMem => -11,
+            rusb::Error::NotSupported => -12,
+            _ => -99,
+        },
+        _ => -1,
+    }
+}
+
+/// Run `f` on the device, returning 0 or the error code
+unsafe fn with_dev<F>(dev: *mut Dev, f: F) -> c_int
+where
+    F: FnOnce(&Dev) -> Result<c_int, c_int>,
+{
+    match dev.as_ref() {
+        Some(dev) => f(dev).unwrap_or_else(|code| code),
+        None => -1,
+    }
+}
+
+/// Run `f` on the device's `RtlSdr`, returning 0 or the error code
+unsafe fn with_sdr<F>(dev: *mut Dev, f: F) -> c_int
+where
+    F: FnOnce(&RtlSdr) -> crate::error::Result<()>,
+{
+    with_dev(dev, |dev| {
+        f(&dev.sdr).map_err(|e| {
+            warn!("{}", e);
+            code(&e)
+        })?;
+        Ok(0)
+    })
+}
+
+/// Copy `text` into a C string buffer of `USB_STRING_LEN` bytes
+unsafe fn copy_string(text: &str, buf: *mut c_char) {
+    if buf.is_null() {
+        return;
+    }
+    let bytes = text.as_bytes();
+    let n = bytes.len().min(USB_STRING_LEN - 1);
+    std::ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buf, n);
+    *buf.add(n) = 0;
+}
+
+#[no_mangle]
+pub extern "C" fn rtlsdr_get_device_count() -> u32 {
+    devices().len() as u32
+}
+
+#[no_mangle]
+pub extern "C" fn rtlsdr_get_device_name(index: u32) -> *const c_char {
+    // Names live for the life of the program, as librtlsdr's do
+    static NAMES: OnceLock<Vec<CString>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        KNOWN_DEVICES
+            .iter()
+            .map(|d| CString::new(d.description).unwrap_or_default())
+            .collect()
+    });
+    let name = devices().get(index as usize).and_then(|(_, description)| {
+        KNOWN_DEVICES
+            .iter()
+            .position(|d| d.description == *description)
+    });
+    match name {
+        Some(i) => names[i].as_ptr(),
+        None => c"".as_ptr(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_device_usb_strings(
+    index: u32,
+    manufact: *mut c_char,
+    product: *mut c_char,
+    serial: *mut c_char,
+) -> c_int {
+    let Some((device, _)) = devices().into_iter().nth(index as usize) else {
+        return -2;
+    };
+    match usb_strings(&device) {
+        Ok((m, p, s)) => {
+            copy_string(&m, manufact);
+            copy_string(&p, product);
+            copy_string(&s, serial);
+            0
+        }
+        Err(e) => code(&e.into()),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_index_by_serial(serial: *const c_char) -> c_int {
+    if serial.is_null() {
+        return -1;
+    }
+    let serial = CStr::from_ptr(serial).to_string_lossy();
+    let devices = devices();
+    if devices.is_empty() {
+        return -2;
+    }
+    devices
+        .iter()
+        .position(|(device, _)| usb_strings(device).is_ok_and(|(_, _, s)| s == serial))
+        .map_or(-3, |i| i as c_int)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_open(dev: *mut *mut Dev, index: u32) -> c_int {
+    if dev.is_null() {
+        return -1;
+    }
+    // Match librtlsdr, which opens boards without a supported tuner in direct
+    // sampling mode and honors the EEPROM overrides
+    let options = OpenOptions {
+        allow_no_tuner: true,
+        honor_eeprom_overrides: true,
+        ..Default::default()
+    };
+    match RtlSdr::open_device(index as usize, options, None) {
+        Ok(sdr) => {
+            let direct_sampling = match sdr.settings().direct_sampling {
+                DirectSampleMode::On => 1,
+                DirectSampleMode::OnSwap => 2,
+                _ => 0,
+            };
+            *dev = Box::into_raw(Box::new(Dev {
+                sdr,
+                gain: Mutex::new(0),
+                direct_sampling: Mutex::new(direct_sampling),
+                cancel: AtomicBool::new(false),
+            }));
+            0
+        }
+        Err(e) => {
+            warn!("{}", e);
+            *dev = std::ptr::null_mut();
+            code(&e)
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_close(dev: *mut Dev) -> c_int {
+    if dev.is_null() {
+        return -1;
+    }
+    let dev = Box::from_raw(dev);
+    dev.sdr.close().map_or_else(|e| code(&e), |_| 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_xtal_freq(
+    dev: *mut Dev,
+    rtl_freq: u32,
+    tuner_freq: u32,
+) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_xtal_freq(rtl_freq, tuner_freq))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_xtal_freq(
+    dev: *mut Dev,
+    rtl_freq: *mut u32,
+    tuner_freq: *mut u32,
+) -> c_int {
+    with_dev(dev, |dev| {
+        let (rtl, tuner) = dev.sdr.get_xtal_freq();
+        if let Some(rtl_freq) = rtl_freq.as_mut() {
+            *rtl_freq = rtl;
+        }
+        if let Some(tuner_freq) = tuner_freq.as_mut() {
+            *tuner_freq = tuner;
+        }
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_usb_strings(
+    dev: *mut Dev,
+    manufact: *mut c_char,
+    product: *mut c_char,
+    serial: *mut c_char,
+) -> c_int {
+    with_dev(dev, |dev| {
+        let eeprom = dev.sdr.read_eeprom_config().map_err(|e| code(&e))?;
+        copy_string(&eeprom.manufacturer, manufact);
+        copy_string(&eeprom.product, product);
+        copy_string(&eeprom.serial, serial);
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_center_freq(dev: *mut Dev, freq: u32) -> c_int {
+    // librtlsdr only logs a failed lock
+    with_sdr(dev, |sdr| match sdr.set_center_freq(freq) {
+        Err(RtlsdrError::PllNotLocked(e)) => {
+            warn!("{}", e);
+            Ok(())
+        }
+        result => result,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_center_freq(dev: *mut Dev) -> u32 {
+    dev.as_ref().map_or(0, |dev| dev.sdr.get_center_freq())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_freq_correction(dev: *mut Dev, ppm: c_int) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_freq_correction(ppm))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_freq_correction(dev: *mut Dev) -> c_int {
+    with_dev(dev, |dev| Ok(dev.sdr.get_freq_correction()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_tuner_type(dev: *mut Dev) -> c_int {
+    let tuner_type = with_dev(dev, |dev| {
+        let info = dev.sdr.get_tuner_info().map_err(|e| code(&e))?;
+        Ok(match info.id {
+            crate::tuners::r820t::TUNER_ID => TUNER_R820T,
+            _ => TUNER_UNKNOWN,
+        } as c_int)
+    });
+    tuner_type.max(TUNER_UNKNOWN as c_int)
+}
+
+/// Fill `gains` (if not null) with the gain steps in tenths of a dB,
+/// returning how many there are
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_tuner_gains(dev: *mut Dev, gains: *mut c_int) -> c_int {
+    with_dev(dev, |dev| {
+        let steps = dev.sdr.get_tuner_gains().map_err(|e| code(&e))?;
+        if !gains.is_null() {
+            std::ptr::copy_nonoverlapping(steps.as_ptr(), gains, steps.len());
+        }
+        Ok(steps.len() as c_int)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_tuner_gain(dev: *mut Dev, gain: c_int) -> c_int {
+    with_dev(dev, |dev| {
+        dev.sdr
+            .set_tuner_gain(TunerGain::Manual(gain))
+            .map_err(|e| code(&e))?;
+        *dev.gain.lock().map_err(|_| -1)? = gain;
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_tuner_gain(dev: *mut Dev) -> c_int {
+    with_dev(dev, |dev| Ok(*dev.gain.lock().map_err(|_| -1)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_tuner_bandwidth(dev: *mut Dev, bw: u32) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_tuner_bandwidth(bw))
+}
+
+/// Set IF gain `stage` to `gain` in tenths of a dB, -2 for an out of range
+/// `stage`. The R820T has one stage, its VGA.
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_tuner_if_gain(
+    dev: *mut Dev,
+    stage: c_int,
+    gain: c_int,
+) -> c_int {
+    with_dev(dev, |dev| {
+        let info = dev.sdr.get_tuner_info().map_err(|e| code(&e))?;
+        if stage < 0 || stage >= info.caps.if_gain_stages as c_int {
+            return Err(-2);
+        }
+        dev.sdr
+            .set_tuner_if_gain(stage as u8, gain)
+            .map_err(|e| code(&e))?;
+        Ok(0)
+    })
+}
+
+/// Manual mode takes effect with the next `rtlsdr_set_tuner_gain`
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_tuner_gain_mode(dev: *mut Dev, manual: c_int) -> c_int {
+    if manual != 0 {
+        return with_dev(dev, |_| Ok(0));
+    }
+    with_dev(dev, |dev| {
+        dev.sdr
+            .set_tuner_gain(TunerGain::Auto)
+            .map_err(|e| code(&e))?;
+        *dev.gain.lock().map_err(|_| -1)? = 0;
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_sample_rate(dev: *mut Dev, rate: u32) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_sample_rate(rate))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_sample_rate(dev: *mut Dev) -> u32 {
+    dev.as_ref().map_or(0, |dev| dev.sdr.get_sample_rate())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_testmode(dev: *mut Dev, on: c_int) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_testmode(on != 0))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_agc_mode(dev: *mut Dev, on: c_int) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_agc_mode(on != 0))
+}
+
+/// 0 off, 1 I-ADC input, 2 Q-ADC input
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_direct_sampling(dev: *mut Dev, on: c_int) -> c_int {
+    let mode = match on {
+        0 => DirectSampleMode::Off,
+        1 => DirectSampleMode::On,
+        2 => DirectSampleMode::OnSwap,
+        _ => return -1,
+    };
+    with_dev(dev, |dev| {
+        dev.sdr.set_direct_sampling(mode).map_err(|e| code(&e))?;
+        *dev.direct_sampling.lock().map_err(|_| -1)? = on;
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_direct_sampling(dev: *mut Dev) -> c_int {
+    with_dev(dev, |dev| {
+        Ok(*dev.direct_sampling.lock().map_err(|_| -1)?)
+    })
+}
+
+/// Not supported, as librtlsdr reports for R820T tuners
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_offset_tuning(dev: *mut Dev, _on: c_int) -> c_int {
+    with_dev(dev, |_| Err(-2))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_get_offset_tuning(dev: *mut Dev) -> c_int {
+    with_dev(dev, |_| Ok(0))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_bias_tee(dev: *mut Dev, on: c_int) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_bias_tee(on != 0))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_set_bias_tee_gpio(dev: *mut Dev, gpio: c_int, on: c_int) -> c_int {
+    with_sdr(dev, |sdr| sdr.set_bias_tee_gpio(gpio as u8, on != 0))
+}
+
+/// Whether `len` bytes from `offset` fit in the EEPROM
+fn eeprom_range(offset: u8, len: u16) -> Result<std::ops::Range<usize>, c_int> {
+    let end = offset as usize + len as usize;
+    if end > EEPROM_SIZE {
+        return Err(-2);
+    }
+    Ok(offset as usize..end)
+}
+
+/// Read `len` bytes from `offset` into `data`, -2 if they don't fit in the
+/// EEPROM and -3 if it can't be read
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_read_eeprom(
+    dev: *mut Dev,
+    data: *mut u8,
+    offset: u8,
+    len: u16,
+) -> c_int {
+    with_dev(dev, |dev| {
+        let range = eeprom_range(offset, len)?;
+        if data.is_null() {
+            return Err(-2);
+        }
+        let image = dev.sdr.read_eeprom_image().map_err(|e| {
+            warn!("{}", e);
+            -3
+        })?;
+        std::ptr::copy_nonoverlapping(image[range].as_ptr(), data, len as usize);
+        Ok(0)
+    })
+}
+
+/// Write `len` bytes from `data` at `offset`, -2 if they don't fit in the
+/// EEPROM and -3 if it can't be written. The result must still have a valid
+/// header, as with `RtlSdr::write_eeprom_image`.
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_write_eeprom(
+    dev: *mut Dev,
+    data: *mut u8,
+    offset: u8,
+    len: u16,
+) -> c_int {
+    with_dev(dev, |dev| {
+        let range = eeprom_range(offset, len)?;
+        if data.is_null() {
+            return Err(-2);
+        }
+        let sdr = &dev.sdr;
+        let mut image = sdr.read_eeprom_image().map_err(|e| {
+            warn!("{}", e);
+            -3
+        })?;
+        image[range].copy_from_slice(std::slice::from_raw_parts(data, len as usize));
+        sdr.write_eeprom_image(&image).map_err(|e| {
+            warn!("{}", e);
+            -3
+        })?;
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_reset_buffer(dev: *mut Dev) -> c_int {
+    with_sdr(dev, |sdr| sdr.reset_buffer())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_read_sync(
+    dev: *mut Dev,
+    buf: *mut c_void,
+    len: c_int,
+    n_read: *mut c_int,
+) -> c_int {
+    if buf.is_null() || len < 0 {
+        return -1;
+    }
+    let buf = std::slice::from_raw_parts_mut(buf as *mut u8, len as usize);
+    with_dev(dev, |dev| {
+        let n = dev.sdr.read_sync(buf).map_err(|e| code(&e))?;
+        if let Some(n_read) = n_read.as_mut() {
+            *n_read = n as c_int;
+        }
+        Ok(0)
+    })
+}
+
+/// Read buffers of `buf_len` bytes (0 for the default), calling `cb` with
+/// each until `rtlsdr_cancel_async`. `buf_num` is accepted for
+/// compatibility; reads are synchronous so there are no transfer buffers
+/// to queue.
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_read_async(
+    dev: *mut Dev,
+    cb: ReadAsyncCallback,
+    ctx: *mut c_void,
+    _buf_num: u32,
+    buf_len: u32,
+) -> c_int {
+    let Some(cb) = cb else {
+        return -1;
+    };
+    let len = match buf_len {
+        0 => DEFAULT_BUF_LENGTH,
+        n if n % USB_PACKET_LEN == 0 => n as usize,
+        _ => return -1,
+    };
+    with_dev(dev, |dev| {
+        dev.cancel.store(false, Ordering::Relaxed);
+        let mut buf = vec![0_u8; len];
+        while !dev.cancel.load(Ordering::Relaxed) {
+            // Bulk reads don't lock the device, so the callback and other
+            // threads can retune
+            let n = dev.sdr.read_sync(&mut buf).map_err(|e| code(&e))?;
+            cb(buf.as_mut_ptr(), n as u32, ctx);
+        }
+        Ok(0)
+    })
+}
+
+/// `rtlsdr_read_async` with the default buffers
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_wait_async(
+    dev: *mut Dev,
+    cb: ReadAsyncCallback,
+    ctx: *mut c_void,
+) -> c_int {
+    rtlsdr_read_async(dev, cb, ctx, 0, 0)
+}
+
+/// Stop `rtlsdr_read_async` after the current buffer. Safe to call from the
+/// callback or another thread.
+#[no_mangle]
+pub unsafe extern "C" fn rtlsdr_cancel_async(dev: *mut Dev) -> c_int {
+    with_dev(dev, |dev| {
+        dev.cancel.store(true, Ordering::Relaxed);
+        Ok(0)
+    })
+}
+
+#[cfg(test)]
+mod capi_test;
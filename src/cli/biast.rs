@@ -0,0 +1,34 @@
+//! Switch the bias tee, or any GPIO pin, on or off without starting a
+//! receive session, like the RTL-SDR Blog rtl_biast
+//!
+//! The pin keeps its state after exiting, until the device is re-plugged or
+//! another program changes it. A bias tee forced on in the EEPROM can't be
+//! switched off.
+
+use super::DeviceArgs;
+use crate::error::Result;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(about = "Turn the bias tee or any GPIO pin on and off")]
+pub struct Args {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// 1 to turn the bias tee on, 0 to turn it off
+    #[arg(short, long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=1))]
+    pub bias_on: u8,
+    /// GPIO pin to switch; the bias tee is on pin 0
+    #[arg(short, long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=7))]
+    pub gpio: u8,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let sdr = args.device.open()?;
+    sdr.set_bias_tee_gpio(args.gpio, args.bias_on == 1)?;
+    eprintln!(
+        "GPIO {} {}",
+        args.gpio,
+        if args.bias_on == 1 { "on" } else { "off" }
+    );
+    sdr.close()
+}
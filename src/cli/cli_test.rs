@@ -0,0 +1,81 @@
+use super::fm::parse_frequencies;
+use super::power::parse_range;
+use super::{biast, devices, doctor, eeprom, fm, power, record, select_device, tcp, test};
+use crate::DeviceInfo;
+use clap::{CommandFactory, Parser};
+
+fn device(index: usize, serial: &str) -> DeviceInfo {
+    DeviceInfo {
+        index,
+        name: "Generic RTL2832U OEM",
+        manufacturer: "Realtek".to_string(),
+        product: "RTL2838UHIDIR".to_string(),
+        serial: serial.to_string(),
+    }
+}
+
+#[test]
+fn test_select_device() {
+    let devices = [device(0, "00000042"), device(1, "12345678"), device(2, "")];
+    assert_eq!(2, select_device("2", &devices).unwrap());
+    assert_eq!(1, select_device("12345678", &devices).unwrap());
+    // Out of range indexes are tried as serial numbers
+    assert_eq!(0, select_device("00000042", &devices).unwrap());
+    assert_eq!(1, select_device("1234", &devices).unwrap());
+    assert_eq!(1, select_device("678", &devices).unwrap());
+    assert!(select_device("999", &devices).is_err());
+    assert!(select_device("abc", &devices).is_err());
+    assert!(select_device("0", &[]).is_err());
+}
+
+#[test]
+fn test_parse_frequencies() {
+    assert_eq!(vec![94_900_000], parse_frequencies("94.9M").unwrap().0);
+    assert_eq!(
+        vec![144_000_000, 144_025_000, 144_050_000],
+        parse_frequencies("144M:144.05M:25k").unwrap().0
+    );
+    assert!(parse_frequencies("144M:143M:25k").is_err());
+    assert!(parse_frequencies("144M:145M").is_err());
+
+    let range = parse_range("88M:108M:10k").unwrap();
+    assert_eq!(
+        (88_000_000, 108_000_000, 10_000),
+        (range.start, range.stop, range.bin_width)
+    );
+    assert!(parse_range("88M:108M:0").is_err());
+    assert!(parse_range("108M:88M:10k").is_err());
+}
+
+#[test]
+fn test_args() {
+    // Catch conflicting option names in any tool
+    biast::Args::command().debug_assert();
+    devices::Args::command().debug_assert();
+    doctor::Args::command().debug_assert();
+    eeprom::Args::command().debug_assert();
+    fm::Args::command().debug_assert();
+    power::Args::command().debug_assert();
+    record::Args::command().debug_assert();
+    tcp::Args::command().debug_assert();
+    test::Args::command().debug_assert();
+
+    let args =
+        fm::Args::try_parse_from(["rtl_fm", "-f", "94.9M", "-d", "00000042", "-p", "-3"]).unwrap();
+    assert_eq!("00000042", args.device.device);
+    assert_eq!(-3, args.tuner.ppm);
+    assert_eq!(None, args.tuner.gain);
+
+    let args = test::Args::try_parse_from(["rtl_test", "-p"]).unwrap();
+    assert_eq!(Some(10), args.ppm);
+    let args = test::Args::try_parse_from(["rtl_test", "-p", "30"]).unwrap();
+    assert_eq!(Some(30), args.ppm);
+    assert_eq!("0", args.device.device);
+
+    let args = eeprom::Args::try_parse_from(["rtl_eeprom", "-b", "1", "-q", "0", "-y"]).unwrap();
+    assert_eq!(
+        (Some(true), Some(false), true),
+        (args.bias_tee, args.direct_sampling, args.yes)
+    );
+    assert!(eeprom::Args::try_parse_from(["rtl_eeprom", "-b", "2"]).is_err());
+}
@@ -0,0 +1,25 @@
+//! List the supported devices plugged in, with the index and serial number
+//! to select them by
+
+use crate::error::Result;
+use crate::RtlSdr;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(about = "List supported devices")]
+pub struct Args {}
+
+pub fn run(_args: Args) -> Result<()> {
+    let devices = RtlSdr::list_devices();
+    if devices.is_empty() {
+        eprintln!("No supported devices found");
+        return Ok(());
+    }
+    for device in devices {
+        println!(
+            "{}: {}, {}, SN: {} ({})",
+            device.index, device.manufacturer, device.product, device.serial, device.name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Check a device for the usual reasons it won't open or loses samples, and
+//! print what to do about them
+
+use super::DeviceArgs;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::RtlSdr;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(about = "Diagnose problems opening or reading a device")]
+pub struct Args {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    // Report a missing device as a failed check rather than an error
+    let index = match args.device.device.parse() {
+        Ok(index) => index,
+        Err(_) => args.device.index()?,
+    };
+    let report = RtlSdr::diagnose(index);
+    print!("{}", report);
+    if !report.passed() {
+        return Err(RtlsdrErr("Diagnostics found a problem".to_string()));
+    }
+    Ok(())
+}
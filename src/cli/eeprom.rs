@@ -0,0 +1,158 @@
+//! Dump, edit, back up and restore the RTL2832 configuration EEPROM, like
+//! librtlsdr's rtl_eeprom
+//!
+//! Changes take effect after the device is re-plugged.
+
+use super::DeviceArgs;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::Eeprom;
+use clap::Parser;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+#[derive(Parser, Debug)]
+#[command(about = "Show, edit, back up and restore the EEPROM")]
+pub struct Args {
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Set the manufacturer string
+    #[arg(short, long)]
+    pub manufacturer: Option<String>,
+    /// Set the product string
+    #[arg(short, long)]
+    pub product: Option<String>,
+    /// Set the serial number string
+    #[arg(short, long)]
+    pub serial: Option<String>,
+    /// Force the bias tee on at power up (RTL-SDR Blog convention)
+    #[arg(short, long, value_parser = parse_flag)]
+    pub bias_tee: Option<bool>,
+    /// Force direct sampling on at power up (RTL-SDR Blog convention)
+    #[arg(short = 'q', long, value_parser = parse_flag)]
+    pub direct_sampling: Option<bool>,
+    /// Back up the EEPROM to a file
+    #[arg(short = 'r', long)]
+    pub backup: Option<String>,
+    /// Restore the EEPROM from a backup file
+    #[arg(short = 'w', long)]
+    pub restore: Option<String>,
+    /// Write without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// 0 or 1, like rtl_eeprom's flags
+fn parse_flag(s: &str) -> std::result::Result<bool, String> {
+    match s {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(format!("Expected 0 or 1, got {}", s)),
+    }
+}
+
+fn print_config(eeprom: &Eeprom) {
+    println!("Vendor ID:\t\t0x{:04x}", eeprom.vendor_id);
+    println!("Product ID:\t\t0x{:04x}", eeprom.product_id);
+    println!("Manufacturer:\t\t{}", eeprom.manufacturer);
+    println!("Product:\t\t{}", eeprom.product);
+    println!("Serial number:\t\t{}", eeprom.serial);
+    println!("Serial number enabled:\t{}", yes_no(eeprom.have_serial));
+    println!("IR endpoint enabled:\t{}", yes_no(eeprom.enable_ir));
+    println!("Remote wakeup enabled:\t{}", yes_no(eeprom.remote_wakeup));
+    println!("Bias tee forced on:\t{}", yes_no(!eeprom.enable_ir));
+    println!("Direct sampling forced:\t{}", yes_no(eeprom.remote_wakeup));
+    if let Some(cal) = &eeprom.calibration {
+        println!(
+            "Calibration:\t\t{} ppm, {:+.1} dB gain offset, label \"{}\"",
+            cal.ppm,
+            cal.gain_offset as f32 / 10.0,
+            cal.label
+        );
+    }
+}
+
+fn yes_no(on: bool) -> &'static str {
+    if on {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn confirm() -> bool {
+    print!("Write new configuration to device [y/n]? ");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    let _ = io::stdin().lock().read_line(&mut answer);
+    answer.trim().eq_ignore_ascii_case("y")
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let sdr = args.device.open()?;
+
+    let image = sdr.read_eeprom_image()?;
+    println!("Current configuration:");
+    let current = match Eeprom::parse(&image) {
+        Ok(eeprom) => {
+            print_config(&eeprom);
+            Some(eeprom)
+        }
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    };
+
+    if let Some(path) = &args.backup {
+        fs::write(path, image)?;
+        println!("\nEEPROM backed up to {}", path);
+    }
+
+    if let Some(path) = &args.restore {
+        let backup = fs::read(path)?;
+        println!("\nConfiguration in {}:", path);
+        print_config(&Eeprom::parse(&backup)?);
+        if args.yes || confirm() {
+            sdr.write_eeprom_image(&backup)?;
+            println!("EEPROM restored. Please replug the device for changes to take effect.");
+        }
+        return Ok(());
+    }
+
+    let Some(mut eeprom) = current else {
+        // Nothing to edit without a valid header; a backup can be restored
+        return Ok(());
+    };
+    let before = eeprom.clone();
+    if let Some(manufacturer) = args.manufacturer {
+        eeprom.manufacturer = manufacturer;
+    }
+    if let Some(product) = args.product {
+        eeprom.product = product;
+    }
+    if let Some(serial) = args.serial {
+        eeprom.serial = serial;
+        eeprom.have_serial = true;
+    }
+    if let Some(on) = args.bias_tee {
+        eeprom.enable_ir = !on;
+    }
+    if let Some(on) = args.direct_sampling {
+        eeprom.remote_wakeup = on;
+    }
+    if eeprom == before {
+        return Ok(());
+    }
+
+    println!("\nNew configuration:");
+    print_config(&eeprom);
+    if args.yes || confirm() {
+        sdr.write_eeprom_config(&eeprom)?;
+        if sdr.read_eeprom_config()? != eeprom {
+            return Err(RtlsdrErr("EEPROM did not read back as written".to_string()));
+        }
+        println!("Configuration written. Please replug the device for changes to take effect.");
+    }
+    Ok(())
+}
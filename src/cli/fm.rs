@@ -0,0 +1,189 @@
+//! Narrow band FM, broadcast FM, AM and SSB receiver writing signed 16-bit
+//! mono audio to a file or stdout, like librtlsdr's rtl_fm
+//!
+//! With several frequencies (`-f` repeated, or a `start:stop:step` range)
+//! and a squelch level, it scans: it moves on to the next frequency once the
+//! squelch has been closed for `-t` buffers, and stays while it's open.
+//!
+//! Unlike rtl_fm, the squelch level is in dBFS, and there is no
+//! de-emphasis or DC blocking.
+
+use super::{interrupted, output, parse_hz, DeviceArgs, TunerArgs};
+use crate::demod::fm::{optimal_settings, FmDemod, Modulation, RadioConfig};
+use crate::dsp::{Squelch, SquelchMode};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::{RtlSdr, DEFAULT_BUF_LENGTH};
+use clap::{Parser, ValueEnum};
+use num_complex::Complex;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+
+// rtl_fm's default rates, and those of wbfm
+const DEFAULT_RATE: u32 = 24_000;
+const WBFM_RATE: u32 = 170_000;
+const WBFM_RESAMPLE_RATE: u32 = 32_000;
+// dB the level must drop below the squelch level to close it again
+const SQUELCH_HYSTERESIS: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Mode {
+    Fm,
+    Wbfm,
+    Am,
+    Usb,
+    Lsb,
+    Raw,
+}
+
+/// Frequencies from one `-f` argument
+#[derive(Clone, Debug)]
+pub struct Frequencies(pub Vec<u32>);
+
+#[derive(Parser, Debug)]
+#[command(about = "Demodulate FM, AM and SSB to raw 16-bit audio")]
+pub struct Args {
+    /// Frequency in Hz with an optional k, M or G suffix, or a range as
+    /// start:stop:step. Repeat to scan.
+    #[arg(short, long = "freq", required = true, value_parser = parse_frequencies)]
+    pub frequencies: Vec<Frequencies>,
+    #[arg(short = 'M', long, value_enum, default_value = "fm")]
+    pub mode: Mode,
+    /// Sample rate to demodulate at [default: 24k, 170k for wbfm]
+    #[arg(short, long, value_parser = parse_hz)]
+    pub sample_rate: Option<u32>,
+    /// Output sample rate [default: the sample rate, 32k for wbfm]
+    #[arg(short, long, value_parser = parse_hz)]
+    pub resample_rate: Option<u32>,
+    #[command(flatten)]
+    pub tuner: TunerArgs,
+    /// Squelch level in dBFS [default: off]
+    #[arg(short = 'l', long, allow_negative_numbers = true)]
+    pub squelch: Option<f32>,
+    /// Buffers with the squelch closed before scanning to the next frequency
+    #[arg(short = 't', long, default_value_t = 10)]
+    pub squelch_delay: u32,
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Output file, or - for stdout
+    #[arg(default_value = "-")]
+    pub output: String,
+}
+
+/// Parse a frequency or a start:stop:step range
+pub fn parse_frequencies(s: &str) -> std::result::Result<Frequencies, String> {
+    let parts: Vec<u32> = s
+        .split(':')
+        .map(parse_hz)
+        .collect::<std::result::Result<_, _>>()?;
+    match parts[..] {
+        [freq] => Ok(Frequencies(vec![freq])),
+        [start, stop, step] if step > 0 && start <= stop => {
+            Ok(Frequencies((start..=stop).step_by(step as usize).collect()))
+        }
+        _ => Err(format!(
+            "Expected a frequency or start:stop:step, got {}",
+            s
+        )),
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let frequencies: Vec<u32> = args.frequencies.iter().flat_map(|f| f.0.clone()).collect();
+    if frequencies.len() > 1 && args.squelch.is_none() {
+        return Err(RtlsdrErr(
+            "Scanning several frequencies needs a squelch level (-l)".to_string(),
+        ));
+    }
+    let shutdown = interrupted();
+
+    let (rate, resample_rate) = match args.mode {
+        Mode::Wbfm => (
+            args.sample_rate.unwrap_or(WBFM_RATE),
+            args.resample_rate.unwrap_or(WBFM_RESAMPLE_RATE),
+        ),
+        _ => {
+            let rate = args.sample_rate.unwrap_or(DEFAULT_RATE);
+            (rate, args.resample_rate.unwrap_or(rate))
+        }
+    };
+    let modulation = match args.mode {
+        Mode::Fm | Mode::Wbfm => Modulation::Fm,
+        Mode::Am => Modulation::Am,
+        Mode::Usb => Modulation::Usb,
+        Mode::Lsb => Modulation::Lsb,
+        Mode::Raw => Modulation::Raw,
+    };
+    let settings = |freq| optimal_settings(freq, rate, resample_rate);
+    let (radio, config) = settings(frequencies[0]);
+    let mut demod = FmDemod::new(config);
+    demod.set_modulation(modulation);
+    let full_scale = (128 * config.downsample) as f32;
+    let mut squelch = args
+        .squelch
+        .map(|level| Squelch::new(SquelchMode::Power, level, SQUELCH_HYSTERESIS, |_| {}));
+
+    let mut sdr = args.device.open()?;
+    args.tuner.apply(&mut sdr)?;
+    sdr.set_sample_rate(radio.capture_rate)?;
+    tune(&mut sdr, radio, frequencies[0])?;
+    sdr.reset_buffer()?;
+    eprintln!("Sampling at {} S/s", sdr.get_sample_rate());
+    eprintln!("Output at {} Hz", resample_rate);
+
+    let mut out = output(&args.output)?;
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    let mut current = 0;
+    let mut closed_for = 0;
+    // Samples read just after retuning can still be from the old frequency
+    let mut discard = false;
+    while !shutdown.load(Ordering::Relaxed) {
+        let n = sdr.read_sync(&mut buf)?;
+        if discard {
+            discard = false;
+            continue;
+        }
+        let mut channel = demod.channel(&mut buf[..n]);
+        if let Some(squelch) = squelch.as_mut() {
+            let samples: Vec<Complex<f32>> = channel
+                .iter()
+                .map(|s| Complex::new(s.re as f32, s.im as f32) / full_scale)
+                .collect();
+            if squelch.process(&samples) {
+                closed_for = 0;
+            } else {
+                channel.fill(Complex::new(0, 0));
+                closed_for += 1;
+            }
+        }
+        let audio = demod.demodulate_channel(channel);
+        let bytes: Vec<u8> = audio.iter().flat_map(|s| s.to_le_bytes()).collect();
+        if out.write_all(&bytes).and_then(|_| out.flush()).is_err() {
+            // The reader went away
+            break;
+        }
+        if frequencies.len() > 1 && closed_for > args.squelch_delay {
+            current = (current + 1) % frequencies.len();
+            tune(
+                &mut sdr,
+                settings(frequencies[current]).0,
+                frequencies[current],
+            )?;
+            closed_for = 0;
+            discard = true;
+        }
+        if n < buf.len() {
+            eprintln!("Short read ({}), samples lost, exiting!", n);
+            break;
+        }
+    }
+    sdr.close()?;
+    Ok(())
+}
+
+/// Tune the device for receiving `freq` with `radio`
+fn tune(sdr: &mut RtlSdr, radio: RadioConfig, freq: u32) -> Result<()> {
+    sdr.set_center_freq(radio.capture_freq)?;
+    eprintln!("Tuned to {} Hz", freq);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Command line tools, built with the `bins` feature. Each submodule has the
+//! arguments and entry point of one tool, shared by the `rtlsdr` binary's
+//! subcommands and the standalone `rtl_*` binaries:
+//!
+//! ```text
+//! rtlsdr devices
+//! rtlsdr doctor
+//! rtlsdr fm -f 94.9M -M wbfm | play -r 32k -t raw -e s -b 16 -c 1 -V1 -
+//! rtlsdr record -d 00000042 -f 1090M -n 20M adsb.bin
+//! ```
+//!
+//! Every tool selects its device with `-d`, taking an index or a serial
+//! number.
+
+pub mod biast;
+pub mod devices;
+pub mod doctor;
+pub mod eeprom;
+pub mod fm;
+pub mod power;
+pub mod record;
+pub mod tcp;
+pub mod test;
+
+#[cfg(test)]
+mod cli_test;
+
+use crate::bookmarks::parse_freq;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::{DeviceInfo, RtlSdr, TunerGain};
+use clap::Args;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Device selection shared by all tools
+#[derive(Args, Clone, Debug)]
+pub struct DeviceArgs {
+    /// Device index, or serial number or a prefix or suffix of one
+    #[arg(short, long, default_value = "0")]
+    pub device: String,
+}
+
+impl DeviceArgs {
+    /// Index of the selected device
+    pub fn index(&self) -> Result<usize> {
+        select_device(&self.device, &RtlSdr::list_devices())
+    }
+
+    pub fn open(&self) -> Result<RtlSdr> {
+        RtlSdr::open(self.index()?)
+    }
+}
+
+/// Tuner settings shared by the receiving tools
+#[derive(Args, Clone, Debug)]
+pub struct TunerArgs {
+    /// Tuner gain in dB [default: automatic]
+    #[arg(short, long)]
+    pub gain: Option<f32>,
+    /// Frequency correction in PPM
+    #[arg(short, long, default_value_t = 0, allow_negative_numbers = true)]
+    pub ppm: i32,
+}
+
+impl TunerArgs {
+    pub fn apply(&self, sdr: &mut RtlSdr) -> Result<()> {
+        match self.gain {
+            Some(db) => sdr.set_tuner_gain(TunerGain::Manual((db * 10.0).round() as i32))?,
+            None => sdr.set_tuner_gain(TunerGain::Auto)?,
+        }
+        if self.ppm != 0 {
+            sdr.set_freq_correction(self.ppm)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find `spec` in `devices` the way librtlsdr's tools do: as an index,
+/// then as an exact serial number, then as a serial prefix, then a suffix
+pub fn select_device(spec: &str, devices: &[DeviceInfo]) -> Result<usize> {
+    if devices.is_empty() {
+        return Err(RtlsdrErr("No supported devices found".to_string()));
+    }
+    if let Ok(index) = spec.parse::<usize>() {
+        if index < devices.len() {
+            return Ok(index);
+        }
+    }
+    let serials = || devices.iter().filter(|d| !d.serial.is_empty());
+    serials()
+        .find(|d| d.serial == spec)
+        .or_else(|| serials().find(|d| d.serial.starts_with(spec)))
+        .or_else(|| serials().find(|d| d.serial.ends_with(spec)))
+        .map(|d| d.index)
+        .ok_or_else(|| RtlsdrErr(format!("No device matching {}", spec)))
+}
+
+/// Parse a frequency or rate such as 94.9M, 24k or 1000
+pub fn parse_hz(s: &str) -> std::result::Result<u32, String> {
+    parse_freq(s).ok_or_else(|| format!("Invalid frequency: {}", s))
+}
+
+/// Flag set by ctrl-c. The handler is installed on first use.
+pub fn interrupted() -> &'static AtomicBool {
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    static HANDLER: Once = Once::new();
+    HANDLER.call_once(|| {
+        ctrlc::set_handler(|| SHUTDOWN.store(true, Ordering::Relaxed))
+            .expect("Error setting Ctrl-C handler");
+    });
+    &SHUTDOWN
+}
+
+/// Buffered output to the file at `path`, or stdout for -
+pub fn output(path: &str) -> Result<Box<dyn Write>> {
+    Ok(match path {
+        "-" => Box::new(io::stdout().lock()),
+        path => Box::new(BufWriter::new(File::create(path)?)),
+    })
+}
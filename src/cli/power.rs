@@ -0,0 +1,106 @@
+//! Wideband power scan writing rtl_power compatible CSV, one sweep per
+//! integration interval until ctrl-c or after a single sweep with `-1`
+
+use super::{interrupted, output, parse_hz, DeviceArgs, TunerArgs};
+use crate::bookmarks::Bookmarks;
+use crate::error::Result;
+use crate::scan::{write_csv, PowerScan, ScanConfig};
+use clap::Parser;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime};
+
+/// Scan range and bin width from `-f`
+#[derive(Clone, Copy, Debug)]
+pub struct Range {
+    pub start: u32,
+    pub stop: u32,
+    pub bin_width: u32,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Scan a frequency range and log the power per bin as CSV")]
+pub struct Args {
+    /// Range and bin width as start:stop:bin_width, e.g. 88M:108M:10k
+    #[arg(short, long = "freq", value_parser = parse_range)]
+    pub range: Range,
+    /// Integration time per hop in seconds
+    #[arg(short, long, default_value_t = 10.0)]
+    pub integration: f64,
+    /// Fraction of each hop discarded at the edges, where the filters roll
+    /// off, 0.0 to <1.0
+    #[arg(short, long, default_value_t = 0.0)]
+    pub crop: f32,
+    /// Stop after one sweep
+    #[arg(short = '1', long)]
+    pub single: bool,
+    /// Bookmarks file to print the level of each bookmark in range to stderr
+    #[arg(short, long)]
+    pub bookmarks: Option<String>,
+    #[command(flatten)]
+    pub tuner: TunerArgs,
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Output file, or - for stdout
+    #[arg(default_value = "-")]
+    pub output: String,
+}
+
+/// Parse a start:stop:bin_width range like rtl_power's `-f`
+pub fn parse_range(s: &str) -> std::result::Result<Range, String> {
+    let parts: Vec<u32> = s
+        .split(':')
+        .map(parse_hz)
+        .collect::<std::result::Result<_, _>>()?;
+    match parts[..] {
+        [start, stop, bin_width] if bin_width > 0 && start < stop => Ok(Range {
+            start,
+            stop,
+            bin_width,
+        }),
+        _ => Err(format!("Expected start:stop:bin_width, got {}", s)),
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let shutdown = interrupted();
+    let mut config = ScanConfig::new(args.range.start, args.range.stop, args.range.bin_width);
+    config.integration = Duration::from_secs_f64(args.integration);
+    config.crop = args.crop;
+    let mut scan = PowerScan::new(config)?;
+    eprintln!(
+        "{} hops of {} bins, {:.1} Hz per bin",
+        scan.hops(),
+        scan.fft_size(),
+        scan.step()
+    );
+    let bookmarks = match &args.bookmarks {
+        Some(path) => Bookmarks::import(path)?,
+        None => Bookmarks::new(),
+    };
+
+    let mut sdr = args.device.open()?;
+    args.tuner.apply(&mut sdr)?;
+    let mut out = output(&args.output)?;
+    let mut written = Ok(());
+    while !shutdown.load(Ordering::Relaxed) {
+        // Stamp every row of a sweep with its start time, like rtl_power
+        let time = SystemTime::now();
+        scan.sweep(&mut sdr, |hop| {
+            if written.is_ok() {
+                written = write_csv(&mut out, time, hop);
+            }
+            for bookmark in bookmarks.in_range(hop.low, hop.high) {
+                if let Some(power) = hop.power_at(bookmark.frequency) {
+                    eprintln!("{}: {:.1} dB", bookmark.name, power);
+                }
+            }
+        })?;
+        written = written.and_then(|_| out.flush());
+        if written.is_err() || args.single {
+            break;
+        }
+    }
+    sdr.close()?;
+    Ok(written?)
+}
@@ -0,0 +1,97 @@
+//! Raw u8 IQ capture to a file or stdout, like librtlsdr's rtl_sdr
+//!
+//! The device is read on its own thread, so a slow disk or pipe drops
+//! whole buffers instead of stalling USB transfers; the number of samples
+//! dropped is reported at exit.
+
+use super::{interrupted, output, parse_hz, DeviceArgs, TunerArgs};
+use crate::error::Result;
+use crate::stream::{Stream, DEFAULT_QUEUE_LEN};
+use crate::RtlSdr;
+use clap::Parser;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+// How often the capture loop checks for ctrl-c while waiting for samples
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Parser, Debug)]
+#[command(about = "Capture raw 8-bit IQ samples")]
+pub struct Args {
+    /// Frequency in Hz with an optional k, M or G suffix
+    #[arg(short, long = "freq", value_parser = parse_hz)]
+    pub frequency: u32,
+    /// Sample rate
+    #[arg(short, long, default_value = "2048000", value_parser = parse_hz)]
+    pub sample_rate: u32,
+    #[command(flatten)]
+    pub tuner: TunerArgs,
+    /// Number of samples to capture, with an optional k, M or G suffix
+    /// [default: until ctrl-c]
+    #[arg(short = 'n', long, value_parser = parse_hz)]
+    pub samples: Option<u32>,
+    #[command(flatten)]
+    pub device: DeviceArgs,
+    /// Output file, or - for stdout
+    pub output: String,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let shutdown = interrupted();
+    // Select the device here, so an unknown serial is reported before the
+    // stream thread starts
+    let index = args.device.index()?;
+    let (frequency, rate, tuner) = (args.frequency, args.sample_rate, args.tuner.clone());
+    let stream = Stream::start(
+        move || {
+            let mut sdr = RtlSdr::open(index)?;
+            tuner.apply(&mut sdr)?;
+            sdr.set_sample_rate(rate)?;
+            sdr.set_center_freq(frequency)?;
+            eprintln!("Tuned to {} Hz, sampling at {} S/s", frequency, rate);
+            sdr.reset_buffer()?;
+            Ok(sdr)
+        },
+        DEFAULT_QUEUE_LEN,
+    )?;
+
+    let mut out = output(&args.output)?;
+    let mut remaining = args.samples.map(|n| 2 * n as u64);
+    let mut written = 0_u64;
+    while !shutdown.load(Ordering::Relaxed) && remaining != Some(0) {
+        let buf = match stream.recv_timeout(POLL_INTERVAL) {
+            Ok(buf) => buf,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let len = remaining.map_or(buf.len(), |r| buf.len().min(r as usize));
+        if let Err(e) = out.write_all(&buf[..len]) {
+            eprintln!("Write failed: {}", e);
+            break;
+        }
+        written += len as u64;
+        remaining = remaining.map(|r| r - len as u64);
+    }
+    if shutdown.load(Ordering::Relaxed) {
+        eprintln!("User cancel, exiting...");
+    }
+    out.flush()?;
+
+    let stats = stream.stop()?;
+    eprintln!("Captured {} samples", written / 2);
+    if stats.dropped_samples > 0 {
+        eprintln!(
+            "Dropped {} samples because the output was too slow",
+            stats.dropped_samples
+        );
+    }
+    if stats.short_reads > 0 {
+        eprintln!(
+            "{} short reads from the device, samples may be missing",
+            stats.short_reads
+        );
+    }
+    Ok(())
+}
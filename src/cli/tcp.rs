@@ -0,0 +1,48 @@
+//! Serve a device to rtl_tcp clients such as SDR#, gqrx and SDR++ until
+//! ctrl-c or the device fails, like librtlsdr's rtl_tcp
+//!
+//! Clients set the frequency, sample rate and gain themselves.
+
+use super::{interrupted, DeviceArgs};
+use crate::error::Result;
+use crate::net::auth::Access;
+use crate::net::rtl_tcp::Server;
+use crate::RtlSdr;
+use clap::Parser;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(about = "Serve a device over the rtl_tcp protocol")]
+pub struct Args {
+    /// Address to listen on
+    #[arg(short, long, default_value = "0.0.0.0")]
+    pub address: String,
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 1234)]
+    pub port: u16,
+    /// Only serve clients that send this token first
+    #[arg(long)]
+    pub token: Option<String>,
+    #[command(flatten)]
+    pub device: DeviceArgs,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let shutdown = interrupted();
+    let index = args.device.index()?;
+    let access = match &args.token {
+        Some(token) => Access::open().with_token(token),
+        None => Access::open(),
+    };
+    let server =
+        Server::start_with_access((args.address.as_str(), args.port), access, move || {
+            RtlSdr::open(index)
+        })?;
+    eprintln!("Listening on {}", server.local_addr());
+    while !shutdown.load(Ordering::Relaxed) && server.is_running() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    server.stop()
+}
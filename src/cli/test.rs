@@ -0,0 +1,88 @@
+//! Check for lost samples with the demodulator's test mode until ctrl-c,
+//! like librtlsdr's rtl_test, or benchmark the tuner range or the sample
+//! clock error
+
+use super::{interrupted, parse_hz, DeviceArgs};
+use crate::benchmark::{tuner_range, CounterCheck, PpmMeter, RangeConfig, PPM_SETTLE};
+use crate::error::Result;
+use crate::DEFAULT_BUF_LENGTH;
+use clap::Parser;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(about = "Check for lost samples and benchmark the device")]
+pub struct Args {
+    /// Sample rate
+    #[arg(short, long, default_value = "2048000", value_parser = parse_hz)]
+    pub sample_rate: u32,
+    /// Find the frequency range the tuner PLL locks on, then exit
+    #[arg(short, long)]
+    pub tuner_benchmark: bool,
+    /// Estimate the sample clock error in PPM, reporting every 10 seconds or
+    /// the given number of seconds
+    #[arg(short, long, num_args = 0..=1, default_missing_value = "10")]
+    pub ppm: Option<u64>,
+    #[command(flatten)]
+    pub device: DeviceArgs,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let shutdown = interrupted();
+    let mut sdr = args.device.open()?;
+    println!("Found {} tuner", sdr.get_tuner_info()?.name);
+    let gains = sdr.get_tuner_gains()?;
+    println!(
+        "Supported gain values ({}): {:?}",
+        gains.len(),
+        gains.iter().map(|g| *g as f32 / 10.0).collect::<Vec<_>>()
+    );
+
+    if args.tuner_benchmark {
+        println!("Benchmarking tuner range");
+        for band in tuner_range(&mut sdr, &RangeConfig::default())? {
+            println!(
+                "PLL locks from {:.1} to {:.1} MHz",
+                *band.start() as f64 / 1e6,
+                *band.end() as f64 / 1e6
+            );
+        }
+        return sdr.close();
+    }
+
+    sdr.set_sample_rate(args.sample_rate)?;
+    println!("Sampling at {} S/s", sdr.get_sample_rate());
+    sdr.set_testmode(true)?;
+    sdr.reset_buffer()?;
+
+    println!("Reading samples in sync mode...");
+    let mut ppm = args.ppm.map(|secs| {
+        println!("Reporting PPM error every {} seconds", secs);
+        PpmMeter::with_interval(args.sample_rate, Duration::from_secs(secs), PPM_SETTLE)
+    });
+    let mut check = CounterCheck::new();
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    while !shutdown.load(Ordering::Relaxed) {
+        match sdr.read_sync(&mut buf) {
+            Ok(n) if n < DEFAULT_BUF_LENGTH => {
+                println!("Short read ({}), samples lost, exiting!", n);
+                break;
+            }
+            Ok(n) => {
+                let lost = check.process(&buf[..n]);
+                if lost > 0 {
+                    println!("Lost at least {} bytes", lost);
+                }
+                if let Some(report) = ppm.as_mut().and_then(|p| p.process(n)) {
+                    println!(
+                        "Real sample rate: {:.0} current PPM: {:.1} cumulative PPM: {:.1}",
+                        report.rate, report.ppm, report.cumulative_ppm
+                    );
+                }
+            }
+            Err(e) => println!("Read error: {}", e),
+        }
+    }
+    println!("Lost at least {} bytes in total", check.lost());
+    sdr.close()
+}
@@ -0,0 +1,229 @@
+//! ADS-B (1090 MHz Extended Squitter) demodulation and Mode S framing
+//!
+//! Works on raw IQ sampled at 2 MS/s, two samples per 1 us Mode S bit. The
+//! magnitude signal is searched for the 8 us preamble, the pulse position
+//! modulated bits that follow are sliced, and frames with valid parity are
+//! passed to a callback as raw bytes for downstream decoders.
+//!
+//! ```no_run
+//! use rtlsdr_rs::demod::adsb::{AdsbDemod, ADSB_FREQ, ADSB_RATE};
+//! use rtlsdr_rs::{RtlSdr, DEFAULT_BUF_LENGTH};
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! sdr.set_center_freq(ADSB_FREQ).unwrap();
+//! sdr.set_sample_rate(ADSB_RATE).unwrap();
+//! sdr.reset_buffer().unwrap();
+//! let mut demod = AdsbDemod::new(|frame| println!("{}", frame));
+//! let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+//! loop {
+//!     let n = sdr.read_sync(&mut buf).unwrap();
+//!     demod.process(&buf[..n]);
+//! }
+//! ```
+
+use crate::dsp::IQ_OFFSET;
+use std::fmt;
+
+/// Mode S downlink frequency in Hz
+pub const ADSB_FREQ: u32 = 1_090_000_000;
+/// Sample rate the demodulator expects, in Hz
+pub const ADSB_RATE: u32 = 2_000_000;
+
+const PREAMBLE_LEN: usize = 16; // Samples
+const SHORT_BITS: usize = 56;
+const LONG_BITS: usize = 112;
+// Samples from the preamble start to the end of a long frame
+const FRAME_LEN: usize = PREAMBLE_LEN + 2 * LONG_BITS;
+// Preamble pulses must be this many times the level of the quiet samples
+const PREAMBLE_SNR: u32 = 2;
+// Mode S parity generator polynomial, x^24 + x^23 + ... + x^10 + x^3 + 1
+const CRC_POLY: u32 = 0x1ff_f409;
+
+/// A Mode S frame with its parity bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeSFrame {
+    pub data: Vec<u8>, // 7 bytes for short frames, 14 for long
+    pub sample: u64,   // Index of the first preamble sample in the stream
+    pub signal: u16,   // Mean magnitude of the preamble pulses
+}
+
+impl ModeSFrame {
+    /// Downlink format
+    pub fn df(&self) -> u8 {
+        self.data[0] >> 3
+    }
+
+    /// Parity field XOR the parity computed over the rest of the frame.
+    /// Zero for a valid extended squitter; for most other formats it is the
+    /// ICAO address of the aircraft.
+    pub fn residual(&self) -> u32 {
+        let n = self.data.len();
+        let parity = u32::from_be_bytes([0, self.data[n - 3], self.data[n - 2], self.data[n - 1]]);
+        crc(&self.data[..n - 3]) ^ parity
+    }
+
+    /// Whether the parity can be checked without knowing the address and
+    /// is correct: DF17/18 extended squitter, or DF11 all-call reply with an
+    /// interrogator code in the low 7 bits
+    pub fn crc_ok(&self) -> bool {
+        match self.df() {
+            17 | 18 => self.residual() == 0,
+            11 => self.residual() & !0x7f == 0,
+            _ => false,
+        }
+    }
+
+    /// ICAO address of the aircraft, from the address field or, for formats
+    /// that overlay it on the parity, from the residual
+    pub fn icao(&self) -> u32 {
+        match self.df() {
+            11 | 17 | 18 => u32::from_be_bytes([0, self.data[1], self.data[2], self.data[3]]),
+            _ => self.residual(),
+        }
+    }
+}
+
+/// Hex in the `*...;` format of rtl_adsb and dump1090 --raw
+impl fmt::Display for ModeSFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "*")?;
+        for b in &self.data {
+            write!(f, "{:02x}", b)?;
+        }
+        write!(f, ";")
+    }
+}
+
+/// Mode S CRC-24 of `data`
+pub fn crc(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= CRC_POLY;
+            }
+        }
+    }
+    crc & 0xff_ffff
+}
+
+/// Magnitude of raw IQ samples, scaled by 256
+pub fn magnitude(src: &[u8], dst: &mut [u16]) {
+    assert_eq!(src.len(), 2 * dst.len());
+    for (d, iq) in dst.iter_mut().zip(src.chunks_exact(2)) {
+        let i = iq[0] as f32 - IQ_OFFSET as f32;
+        let q = iq[1] as f32 - IQ_OFFSET as f32;
+        *d = ((i * i + q * q).sqrt() * 256.0) as u16;
+    }
+}
+
+/// Searches the sample stream for Mode S frames
+pub struct AdsbDemod<F: FnMut(&ModeSFrame)> {
+    callback: F,
+    mag: Vec<u16>, // Unsearched magnitude samples, carried over between calls
+    sample: u64,   // Stream index of mag[0]
+    unchecked: bool,
+}
+
+impl<F: FnMut(&ModeSFrame)> AdsbDemod<F> {
+    /// `callback` is called with each frame that passes its parity check
+    pub fn new(callback: F) -> Self {
+        AdsbDemod {
+            callback,
+            mag: Vec::new(),
+            sample: 0,
+            unchecked: false,
+        }
+    }
+
+    /// Also pass frames whose parity can't be checked without knowing the
+    /// aircraft address (surveillance replies and Comm-B). Without a list of
+    /// known addresses many of these will be noise.
+    pub fn set_unchecked(&mut self, on: bool) {
+        self.unchecked = on;
+    }
+
+    /// Demodulate raw IQ samples at `ADSB_RATE`, returning the number of
+    /// frames passed to the callback. Frames may span calls.
+    pub fn process(&mut self, buf: &[u8]) -> usize {
+        let start = self.mag.len();
+        self.mag.resize(start + buf.len() / 2, 0);
+        magnitude(&buf[..buf.len() / 2 * 2], &mut self.mag[start..]);
+
+        let mut frames = 0;
+        let mut pos = 0;
+        while pos + FRAME_LEN <= self.mag.len() {
+            match self.detect(pos) {
+                Some(frame) => {
+                    let bits = frame.data.len() * 8;
+                    (self.callback)(&frame);
+                    frames += 1;
+                    pos += PREAMBLE_LEN + 2 * bits;
+                }
+                None => pos += 1,
+            }
+        }
+        self.mag.drain(..pos);
+        self.sample += pos as u64;
+        frames
+    }
+
+    /// Look for a frame starting at `pos`
+    fn detect(&self, pos: usize) -> Option<ModeSFrame> {
+        let m = &self.mag[pos..pos + FRAME_LEN];
+        // Pulses at 0, 1, 3.5 and 4.5 us
+        if !(m[0] > m[1]
+            && m[1] < m[2]
+            && m[2] > m[3]
+            && m[3] < m[0]
+            && m[4] < m[0]
+            && m[5] < m[0]
+            && m[6] < m[0]
+            && m[7] > m[8]
+            && m[8] < m[9]
+            && m[9] > m[6])
+        {
+            return None;
+        }
+        let high = (m[0] as u32 + m[2] as u32 + m[7] as u32 + m[9] as u32) / 4;
+        // Correlate against the quiet parts of the preamble
+        let quiet = [1, 3, 4, 5, 6, 8, 10, 11, 12, 13, 14, 15];
+        let low = quiet.iter().map(|i| m[*i] as u32).sum::<u32>() / quiet.len() as u32;
+        if high < PREAMBLE_SNR * low.max(1) {
+            return None;
+        }
+
+        let mut data = [0_u8; LONG_BITS / 8];
+        let mut bits = LONG_BITS;
+        let mut i = 0;
+        while i < bits {
+            let a = m[PREAMBLE_LEN + 2 * i];
+            let b = m[PREAMBLE_LEN + 2 * i + 1];
+            if a > b {
+                data[i / 8] |= 0x80 >> (i % 8);
+            }
+            // The first format bit selects the frame length
+            if i == 0 && a <= b {
+                bits = SHORT_BITS;
+            }
+            i += 1;
+        }
+        let frame = ModeSFrame {
+            data: data[..bits / 8].to_vec(),
+            sample: self.sample + pos as u64,
+            signal: high as u16,
+        };
+        if frame.crc_ok() || (self.unchecked && known_format(frame.df())) {
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+/// Downlink formats defined for civil Mode S
+fn known_format(df: u8) -> bool {
+    matches!(df, 0 | 4 | 5 | 11 | 16 | 17 | 18 | 19 | 20 | 21 | 24..=31)
+}
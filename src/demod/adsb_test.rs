@@ -0,0 +1,140 @@
+use super::adsb::{crc, magnitude, AdsbDemod, ModeSFrame};
+
+// DF17 airborne identification from "The 1090 MHz Riddle", ICAO 4840d6
+const DF17: &str = "8d4840d6202cc371c32ce0576098";
+
+/// Frame bytes with parity XORed with `overlay`, as hex
+fn with_parity(data: &[u8], overlay: u32) -> String {
+    let mut data = data.to_vec();
+    let parity = crc(&data) ^ overlay;
+    data.extend(&parity.to_be_bytes()[1..]);
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// DF11 all-call reply from 4840d6 with interrogator code 0
+fn df11() -> String {
+    with_parity(&[0x5d, 0x48, 0x40, 0xd6], 0)
+}
+
+fn bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Raw 2 MS/s IQ of a frame with its preamble, at the given pulse amplitude
+/// over a low level of deterministic noise
+fn signal(hex: &str, amplitude: f32, lead: usize) -> Vec<u8> {
+    let mut levels = vec![false; lead];
+    let mut preamble = [false; 16];
+    for i in [0, 2, 7, 9] {
+        preamble[i] = true;
+    }
+    levels.extend(preamble);
+    for b in bytes(hex) {
+        for bit in 0..8 {
+            let one = b & (0x80 >> bit) != 0;
+            levels.extend([one, !one]);
+        }
+    }
+    levels.extend([false; 300]);
+    levels
+        .iter()
+        .enumerate()
+        .flat_map(|(n, high)| {
+            let noise = ((n * 7919) % 13) as f32 - 6.0;
+            let a = if *high { amplitude } else { 0.0 };
+            let phase = n as f32 * 0.7;
+            [
+                (127.0 + a * phase.cos() + noise).round() as u8,
+                (127.0 + a * phase.sin() - noise).round() as u8,
+            ]
+        })
+        .collect()
+}
+
+fn frame(hex: &str) -> ModeSFrame {
+    ModeSFrame {
+        data: bytes(hex),
+        sample: 0,
+        signal: 0,
+    }
+}
+
+#[test]
+fn test_crc() {
+    let data = bytes(DF17);
+    assert_eq!(0x576098, crc(&data[..11]));
+    assert_eq!(0, frame(DF17).residual());
+    assert!(frame(DF17).crc_ok());
+    assert!(frame(&df11()).crc_ok());
+    assert_eq!(0x4840d6, frame(&df11()).icao());
+
+    let mut corrupt = frame(DF17);
+    corrupt.data[5] ^= 0x10;
+    assert!(!corrupt.crc_ok());
+}
+
+#[test]
+fn test_frame_fields() {
+    let frame = frame(DF17);
+    assert_eq!(17, frame.df());
+    assert_eq!(0x4840d6, frame.icao());
+    assert_eq!("*8d4840d6202cc371c32ce0576098;", frame.to_string());
+}
+
+#[test]
+fn test_magnitude() {
+    let mut out = [0_u16; 3];
+    magnitude(&[127, 127, 130, 131, 0, 127], &mut out);
+    assert_eq!([0, 5 * 256, 127 * 256], out);
+}
+
+#[test]
+fn test_demodulate_split_buffers() {
+    let mut buf = signal(DF17, 60.0, 1000);
+    buf.extend(signal(&df11(), 40.0, 50));
+    let mut frames = Vec::new();
+    {
+        let mut demod = AdsbDemod::new(|f: &ModeSFrame| frames.push(f.clone()));
+        // Buffer boundaries fall inside the frames
+        let mut count = 0;
+        for c in buf.chunks(1100) {
+            count += demod.process(c);
+        }
+        assert_eq!(2, count);
+    }
+    assert_eq!(bytes(DF17), frames[0].data);
+    assert_eq!(1000, frames[0].sample);
+    assert!(frames[0].signal > 50 * 256);
+    assert_eq!(bytes(&df11()), frames[1].data);
+    assert_eq!(1000 + 16 + 224 + 300 + 50, frames[1].sample);
+}
+
+#[test]
+fn test_rejects_noise_and_bad_parity() {
+    let mut corrupt = bytes(DF17);
+    corrupt[4] ^= 0x01;
+    let hex: String = corrupt.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut count = 0;
+    let mut demod = AdsbDemod::new(|_: &ModeSFrame| count += 1);
+    assert_eq!(0, demod.process(&signal(&hex, 60.0, 100)));
+    assert_eq!(0, demod.process(&signal("", 0.0, 10000)));
+    drop(demod);
+    assert_eq!(0, count);
+}
+
+#[test]
+fn test_unchecked_frames() {
+    // DF4 surveillance altitude reply, address overlaid on parity
+    let hex = with_parity(&[0x20, 0x00, 0x12, 0x34], 0xabcdef);
+    let mut frames = Vec::new();
+    let mut demod = AdsbDemod::new(|f: &ModeSFrame| frames.push(f.clone()));
+    assert_eq!(0, demod.process(&signal(&hex, 60.0, 100)));
+    demod.set_unchecked(true);
+    assert_eq!(1, demod.process(&signal(&hex, 60.0, 100)));
+    drop(demod);
+    assert_eq!(4, frames[0].df());
+    assert_eq!(0xabcdef, frames[0].icao());
+}
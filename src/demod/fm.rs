@@ -0,0 +1,266 @@
+//! FM demodulation, ported from rtl_fm, along with rtl_fm's other modes
+//! (`Modulation`)
+//!
+//! ```no_run
+//! use rtlsdr_rs::{demod::fm, RtlSdr, DEFAULT_BUF_LENGTH};
+//!
+//! // 94.9 MHz, demodulating at 170 kHz with 32 kHz audio output
+//! let (radio, config) = fm::optimal_settings(94_900_000, 170_000, 32_000);
+//! let sdr = RtlSdr::open(0).unwrap();
+//! sdr.set_center_freq(radio.capture_freq).unwrap();
+//! sdr.set_sample_rate(radio.capture_rate).unwrap();
+//! sdr.reset_buffer().unwrap();
+//!
+//! let mut demod = fm::FmDemod::new(config);
+//! let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+//! let n = sdr.read_sync(&mut buf).unwrap();
+//! // Signed 16-bit mono audio at 32 kHz
+//! let audio = demod.demodulate(&mut buf[..n]);
+//! ```
+
+use crate::dsp;
+use log::info;
+use num_complex::Complex;
+use std::f64::consts::PI;
+
+/// Radio configuration produced by `optimal_settings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RadioConfig {
+    pub capture_freq: u32, // Hz, offset from the station for offset tuning
+    pub capture_rate: u32, // Hz
+}
+
+/// Demodulation configuration produced by `optimal_settings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemodConfig {
+    pub rate_in: u32,       // Rate in Hz
+    pub rate_out: u32,      // Rate in Hz
+    pub rate_resample: u32, // Rate in Hz
+    pub downsample: u32,
+    pub output_scale: u32,
+}
+
+/// Determine the radio and demodulation configurations for receiving `freq`,
+/// demodulating at `rate` and producing audio at `rate_resample` (all in Hz).
+/// The device is tuned a quarter of the capture rate above `freq` to keep
+/// the station away from the DC spike.
+pub fn optimal_settings(freq: u32, rate: u32, rate_resample: u32) -> (RadioConfig, DemodConfig) {
+    let downsample = (1_000_000 / rate) + 1;
+    info!("downsample: {}", downsample);
+    let capture_rate = downsample * rate;
+    info!("rate_in: {} capture_rate: {}", rate, capture_rate);
+    // Use offset-tuning
+    let capture_freq = freq + capture_rate / 4;
+    info!("capture_freq: {}", capture_freq);
+    let output_scale = ((1 << 15) / (128 * downsample)).max(1);
+    (
+        RadioConfig {
+            capture_freq,
+            capture_rate,
+        },
+        DemodConfig {
+            rate_in: rate,
+            rate_out: rate,
+            rate_resample,
+            downsample,
+            output_scale,
+        },
+    )
+}
+
+/// Demodulation modes of rtl_fm. All but `Fm` scale their output by
+/// `DemodConfig::output_scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Modulation {
+    #[default]
+    Fm,
+    Am,
+    Usb, // Upper sideband
+    Lsb, // Lower sideband
+    Raw, // Filtered IQ, interleaved and not resampled
+}
+
+/// State data for demodulation, carried over between buffers
+pub struct FmDemod {
+    config: DemodConfig,
+    modulation: Modulation,
+    prev_index: usize,
+    now_lpr: i32,
+    prev_lpr_index: i32,
+    lp_now: Complex<i32>,
+    demod_pre: Complex<i32>,
+}
+
+impl FmDemod {
+    pub fn new(config: DemodConfig) -> Self {
+        FmDemod {
+            config,
+            modulation: Modulation::Fm,
+            prev_index: 0,
+            now_lpr: 0,
+            prev_lpr_index: 0,
+            lp_now: Complex::new(0, 0),
+            demod_pre: Complex::new(0, 0),
+        }
+    }
+
+    pub fn config(&self) -> &DemodConfig {
+        &self.config
+    }
+
+    /// Demodulate with `modulation` instead of FM
+    pub fn set_modulation(&mut self, modulation: Modulation) {
+        self.modulation = modulation;
+    }
+
+    pub fn modulation(&self) -> Modulation {
+        self.modulation
+    }
+
+    /// Performs the entire demodulation process on raw received bytes
+    /// captured with the `RadioConfig` settings, returning signed 16-bit
+    /// audio. The buffer is rotated in place as part of offset tuning.
+    pub fn demodulate(&mut self, buf: &mut [u8]) -> Vec<i16> {
+        let channel = self.channel(buf);
+        self.demodulate_channel(channel)
+    }
+
+    /// The first half of `demodulate`: shift the station to the center and
+    /// filter and decimate to `rate_in`, where it can be measured (e.g. for
+    /// squelch) before `demodulate_channel`. Full scale is
+    /// `128 * downsample`.
+    pub fn channel(&mut self, buf: &mut [u8]) -> Vec<Complex<i32>> {
+        dsp::rotate_90(buf);
+        let complex = buf_to_complex(&dsp::to_i16(buf));
+        self.low_pass_complex(complex)
+    }
+
+    /// The second half of `demodulate`: demodulate the channel samples from
+    /// `channel` and resample to `rate_resample`
+    pub fn demodulate_channel(&mut self, channel: Vec<Complex<i32>>) -> Vec<i16> {
+        let scale = self.config.output_scale as i32;
+        let scaled = |v: i32| (v * scale).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let demodulated = match self.modulation {
+            Modulation::Fm => self.fm_demod(channel),
+            Modulation::Am => channel
+                .iter()
+                .map(|s| scaled((s.re as f64).hypot(s.im as f64) as i32))
+                .collect(),
+            Modulation::Usb => channel.iter().map(|s| scaled(s.re + s.im)).collect(),
+            Modulation::Lsb => channel.iter().map(|s| scaled(s.re - s.im)).collect(),
+            Modulation::Raw => {
+                return channel
+                    .iter()
+                    .flat_map(|s| [scaled(s.re), scaled(s.im)])
+                    .collect()
+            }
+        };
+        self.low_pass_real(demodulated)
+    }
+
+    /// Demodulate to the FM multiplex signal (mono audio, stereo pilot and
+    /// subcarrier, RDS) at `rate_out`, before audio filtering. Pass the result
+    /// to `low_pass_real` to get the same audio as `demodulate`.
+    pub fn mpx(&mut self, buf: &mut [u8]) -> Vec<i16> {
+        // low-pass filter to downsample to our desired sample rate
+        let lowpassed = self.channel(buf);
+
+        // Demodulate FM signal
+        self.fm_demod(lowpassed)
+    }
+
+    /// Applies a low-pass filter on a vector of complex values, decimating by
+    /// `downsample`
+    pub fn low_pass_complex(&mut self, buf: Vec<Complex<i32>>) -> Vec<Complex<i32>> {
+        let mut res = vec![];
+        for sample in buf {
+            self.lp_now += sample;
+
+            self.prev_index += 1;
+            if self.prev_index < self.config.downsample as usize {
+                continue;
+            }
+
+            res.push(self.lp_now);
+            self.lp_now = Complex::new(0, 0);
+            self.prev_index = 0;
+        }
+        res
+    }
+
+    /// Performs FM demodulation on a vector of complex input data
+    pub fn fm_demod(&mut self, buf: Vec<Complex<i32>>) -> Vec<i16> {
+        let first = match buf.first() {
+            Some(s) => *s,
+            None => return vec![],
+        };
+        let mut result = Vec::with_capacity(buf.len());
+        result.push(polar_discriminant(first, self.demod_pre) as i16);
+        for w in buf.windows(2) {
+            result.push(polar_discriminant_fast(w[1], w[0]) as i16);
+        }
+        self.demod_pre = buf[buf.len() - 1];
+        result
+    }
+
+    /// Applies a low-pass filter to a vector of real-valued data, resampling
+    /// from `rate_out` to `rate_resample`
+    pub fn low_pass_real(&mut self, buf: Vec<i16>) -> Vec<i16> {
+        let mut result = vec![];
+        // Simple square-window FIR
+        let slow = self.config.rate_resample;
+        let fast = self.config.rate_out;
+        for sample in buf {
+            self.now_lpr += sample as i32;
+            self.prev_lpr_index += slow as i32;
+            if self.prev_lpr_index < fast as i32 {
+                continue;
+            }
+            result.push((self.now_lpr / ((fast / slow) as i32)) as i16);
+            self.prev_lpr_index -= fast as i32;
+            self.now_lpr = 0;
+        }
+        result
+    }
+}
+
+/// Find the polar discriminant for a pair of complex values using real atan2 function
+fn polar_discriminant(a: Complex<i32>, b: Complex<i32>) -> i32 {
+    let c = a * b.conj();
+    let angle = f64::atan2(c.im as f64, c.re as f64);
+    (angle / PI * (1 << 14) as f64) as i32
+}
+
+/// Find the polar discriminant for a pair of complex values using a fast atan2 approximation
+fn polar_discriminant_fast(a: Complex<i32>, b: Complex<i32>) -> i32 {
+    let c = a * b.conj();
+    fast_atan2(c.im, c.re)
+}
+
+/// Fast atan2 approximation, scaled so that pi = 1 << 14
+pub fn fast_atan2(y: i32, x: i32) -> i32 {
+    let pi4 = 1 << 12;
+    let pi34 = 3 * (1 << 12);
+    if x == 0 && y == 0 {
+        return 0;
+    }
+    let yabs = y.abs();
+    let angle = if x >= 0 {
+        pi4 - (pi4 as i64 * (x - yabs) as i64) as i32 / (x + yabs)
+    } else {
+        pi34 - (pi4 as i64 * (x + yabs) as i64) as i32 / (yabs - x)
+    };
+    if y < 0 {
+        return -angle;
+    }
+    angle
+}
+
+/// Convert interleaved i16 components (real and imaginary) to i32 Complex values
+pub(super) fn buf_to_complex(buf: &[i16]) -> Vec<Complex<i32>> {
+    buf.chunks_exact(2)
+        .map(|w| Complex::new(w[0] as i32, w[1] as i32))
+        .collect()
+}
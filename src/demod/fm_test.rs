@@ -0,0 +1,174 @@
+use super::fm::{buf_to_complex, fast_atan2, optimal_settings, FmDemod, Modulation};
+use num_complex::Complex;
+
+// Tests for the major demodulation functions, using input/output data extracted
+// from the original rtl_fm program
+const FREQUENCY: u32 = 94_900_000;
+const SAMPLE_RATE: u32 = 170_000;
+const RATE_RESAMPLE: u32 = 32_000;
+
+#[test]
+fn test_lowpass() {
+    // Based on data from rtl_fm
+    // rtl_fm -f 92.5M -M fm -s 170k -A fast -r 32k -l 0
+    let lowpass = vec![
+        108, -34, 52, 18, 8, -2, 9, 107, -20, -14, -12, 19, -68, 42, -49, -62, 12, -48, -7, -13,
+        30, -10, -60, 58, 119, 71, 28, -12, -50, -84, 6, -25, -47, -44, 6, 62, -15, 4, -2, 33, 29,
+        -17, 0, 224, -3, 37, -57, -32, -25, 6, -32, 47, -52, -50, -49, -48, -63, -88, -6, -29, 41,
+        -104, 53, -33, -10, -30, -69, 104, -46, 98, -42, 28, -50, 26, 28, -8, 57, -23, -146, -40,
+        5, -10, 81, 124,
+    ];
+    let lp_complex = buf_to_complex(&lowpass);
+
+    let (_, demod_config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    let mut demod = FmDemod::new(demod_config);
+
+    let buf_signed = vec![
+        71, -33, -7, -29, 19, 9, 6, -1, 24, 11, -5, 9, 12, 5, -5, -28, 33, 24, -5, 17, 8, 6, 9, -6,
+        0, -2, 8, 10, 14, 3, 10, 5, -6, 7, -18, -25, -16, -21, 16, -4, 9, 78, -24, 22, 7, 18, 17,
+        14, -7, 3, -7, 9, 12, -13, 3, -8, -22, 1, 1, -6, -8, -8, -5, 1, 2, 18, 3, -22, -4, 11, 0,
+        19, -27, 2, 7, 2, -20, 4, -14, 27, -10, -9, -4, 16, -12, -27, -12, 7, -8, 16, 6, 45, -32,
+        -35, 9, -68, 42, -37, 3, 14, -25, 6, -10, -9, 9, -10, -7, -12, -3, 10, 9, 10, -30, -5, 29,
+        -15, -19, -3, 7, -10, 13, -21, 15, 18, 3, -10, 12, 2, -12, -7, -1, 8, -19, -3, 12, 13, -20,
+        -5, 6, 18, -11, 13, -28, 22, -6, -17, 23, -15, 86, 44, 42, 44, -31, 7, 5, 8, -3, -12, 17,
+        5, 7, -26, 19, 6, -39, 26, 27, -11, -48, -18, 17, -7, 2, -18, -18, -11, 9, -6, -12, -24,
+        11, 23, -23, -23, 0, -21, 7, 6, 5, -12, 6, 2, 12, -7, -13, -2, 21, -15, 22, -7, -17, -3,
+        -72, -10, -19, -20, 0, 16, 2, 9, 24, 22, -9, 11, 8, 24, -35, -6, -1, -9, 13, -2, 7, -3,
+        -31, 10, 32, 14, -19, 10, 2, 26, -12, 7, 4, -16, 10, 11, 13, -5, -5, 9, -10, 19, 10, -21,
+        -11, -7, 56, -28, -11, 11, -12, -2, 2, 22, 1, 51, -9, 60, 26, 35, -8, 58, 34, 0, 15, -9,
+        -14, -4, -5, 14, -6, 1, -27, 35, 12, -37, -23, 2, 22, -16, -28, 35, 4, -15, -44, -1, 30,
+        -38, 6, 18, -25, 4, -7, 8, -12, -7, -17, 21, 17, -3, 2, 19, -10, -5, 17, -8, -13, 16, -45,
+        28, -40, -41, -15, 20, 3, 17, -30, -25, 42, 2, -12, -23, 28, -16, -12, -17, 0, -14, -35,
+        21, -10, -8, -20, -14, -8, -24, -18, -32, 3, -9, -16, 6, -16, -1, -8, -28, 4, -12, 1, 1,
+        -10, -24, 21, -5, -6, 15, -16, -4, 31, 20, -27, 8, -23, -85, 67, -50, -10, -13, 3, 16, 3,
+        7, 44, -25, -34, -1, 17, -32, 22, -2, 1, 20, 21, -3, 0, 15, -9, -25, 3, -1, -5, -5, -20,
+        -11, -33, 20, -7, 49, 5, 47, -24, 21, -1, -9, -9, -24, 30, 16, -22, 4, -28, 21, -23, -14,
+        8, 15, -11, 56, -63, 26, 9, -7, -15, 12, -2, 1, -16, -12, 45, 8, -2, 2, -3, 18, -24, -1,
+        -17, -18, -2, -7, -2, 32, -3, 10, -3, 28, -8, -23, -1, -43, 34, -9, 9, 29, -23, 5, 4, -8,
+        -11, -2, 37, -2, 31, 11, 19, -27, -50, -6, -16, 5, -47, -18, -37, -46, 11, 13, -7, 12, 1,
+        -17, -17, 2, -1, 10, -2, -16, 4, -1, 20, 12, 15, 27, -15, 5, 8, 28, 29, 42, 24, 8, 20, 14,
+        11, 18, 16, -17, -6, -3, 14, -5,
+    ];
+    let complex = buf_to_complex(&buf_signed);
+    let lowpassed = demod.low_pass_complex(complex);
+    assert_eq!(lp_complex, lowpassed);
+}
+
+#[test]
+fn test_demod() {
+    // Based on data from rtl_fm
+    // rtl_fm -f 92.5M -M fm -s 170k -A fast -r 32k -l 0
+    let lowpass = vec![
+        108, -34, 52, 18, 8, -2, 9, 107, -20, -14, -12, 19, -68, 42, -49, -62, 12, -48, -7, -13,
+        30, -10, -60, 58, 119, 71, 28, -12, -50, -84, 6, -25, -47, -44, 6, 62, -15, 4, -2, 33, 29,
+        -17, 0, 224, -3, 37, -57, -32, -25, 6, -32, 47, -52, -50, -49, -48, -63, -88, -6, -29, 41,
+        -104, 53, -33, -10, -30, -69, 104, -46, 98, -42, 28, -50, 26, 28, -8, 57, -23, -146, -40,
+        5, -10, 81, 124,
+    ];
+    let lp_complex = buf_to_complex(&lowpass);
+    let demod_expected = vec![
+        0, 3489, -3236, 9337, 11916, -8564, 2688, 7340, 4624, -3906, 9406, 13730, -9938, -4746,
+        -9153, 4043, -5222, -12548, 7028, -6147, -11481, 11220, 615, 10771, -3940, -3900, 9381, 76,
+        1228, 2517, 3241, 3490, -6608, -11786, -1057, 3088, 805, -14996, -783, -12842, 9551, 11213,
+    ];
+
+    let (_, demod_config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    let mut demod = FmDemod::new(demod_config);
+
+    let demodulated = demod.fm_demod(lp_complex);
+    assert_eq!(demod_expected, demodulated);
+}
+
+#[test]
+fn test_lowpass_real() {
+    let demodulated = vec![
+        0, 3489, -3236, 9337, 11916, -8564, 2688, 7340, 4624, -3906, 9406, 13730, -9938, -4746,
+        -9153, 4043, -5222, -12548, 7028, -6147, -11481, 11220, 615, 10771, -3940, -3900, 9381, 76,
+        1228, 2517, 3241, 3490, -6608, -11786, -1057, 3088, 805, -14996, -783, -12842, 9551, 11213,
+    ];
+    let result = vec![2588, 4030, -1212, -3430, 2585, 2110, -6110];
+
+    let (_, demod_config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    let mut demod = FmDemod::new(demod_config);
+
+    let output = demod.low_pass_real(demodulated);
+    assert_eq!(result, output);
+}
+
+#[test]
+fn test_optimal_settings() {
+    let (radio, config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    assert_eq!(6, config.downsample);
+    assert_eq!(1_020_000, radio.capture_rate);
+    assert_eq!(FREQUENCY + 255_000, radio.capture_freq);
+    assert_eq!(42, config.output_scale);
+}
+
+#[test]
+fn test_fast_atan2() {
+    assert_eq!(0, fast_atan2(0, 0));
+    assert_eq!(0, fast_atan2(0, 100));
+    assert_eq!(1 << 13, fast_atan2(100, 0));
+    assert_eq!(-(1 << 13), fast_atan2(-100, 0));
+    assert_eq!(1 << 14, fast_atan2(0, -100));
+}
+
+#[test]
+fn test_demodulate_empty() {
+    let (_, config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    let mut demod = FmDemod::new(config);
+    assert!(demod.demodulate(&mut []).is_empty());
+}
+
+#[test]
+fn test_modulations() {
+    // No resampling, so one output sample per channel sample
+    let (_, config) = optimal_settings(FREQUENCY, 24_000, 24_000);
+    let scale = config.output_scale as i16;
+    let channel = vec![Complex::new(30, 40), Complex::new(-5, 2)];
+    let mut demod = FmDemod::new(config);
+    assert_eq!(Modulation::Fm, demod.modulation());
+
+    demod.set_modulation(Modulation::Am);
+    assert_eq!(
+        vec![50 * scale, 5 * scale],
+        demod.demodulate_channel(channel.clone())
+    );
+    demod.set_modulation(Modulation::Usb);
+    assert_eq!(
+        vec![70 * scale, -3 * scale],
+        demod.demodulate_channel(channel.clone())
+    );
+    demod.set_modulation(Modulation::Lsb);
+    assert_eq!(
+        vec![-10 * scale, -7 * scale],
+        demod.demodulate_channel(channel.clone())
+    );
+    demod.set_modulation(Modulation::Raw);
+    assert_eq!(
+        vec![30 * scale, 40 * scale, -5 * scale, 2 * scale],
+        demod.demodulate_channel(channel)
+    );
+
+    // Full scale saturates instead of wrapping
+    let full = 128 * config.downsample as i32;
+    demod.set_modulation(Modulation::Usb);
+    assert_eq!(
+        vec![i16::MAX],
+        demod.demodulate_channel(vec![Complex::new(full, full)])
+    );
+}
+
+#[test]
+fn test_channel_then_demodulate() {
+    let (_, config) = optimal_settings(FREQUENCY, SAMPLE_RATE, RATE_RESAMPLE);
+    let input: Vec<u8> = (0..12_000).map(|i| (i * 37 % 256) as u8).collect();
+    let mut whole = FmDemod::new(config);
+    let mut split = FmDemod::new(config);
+    let channel = split.channel(&mut input.clone());
+    assert_eq!(input.len() / 2 / 6, channel.len());
+    assert_eq!(
+        whole.demodulate(&mut input.clone()),
+        split.demodulate_channel(channel)
+    );
+}
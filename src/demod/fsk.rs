@@ -0,0 +1,102 @@
+//! Symbol-rate FSK and OOK demodulation with clock recovery, producing a
+//! bitstream for protocol decoders (433/868 MHz ISM sensors, remotes, ...)
+//!
+//! The soft symbol value is the instantaneous frequency for FSK and the
+//! magnitude for OOK. It is averaged over half a symbol, sliced at the
+//! midpoint of its tracked high and low levels, and sampled once per symbol
+//! by a clock that is pulled towards the middle between transitions.
+
+use num_complex::Complex;
+
+// Portion of the timing error corrected at each transition
+const CLOCK_GAIN: f32 = 0.3;
+// Symbols over which the tracked levels relax towards the signal
+const LEVEL_DECAY_SYMBOLS: f32 = 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modulation {
+    Fsk, // 1 is the higher frequency
+    Ook, // 1 is carrier on
+}
+
+pub struct FskDemod<F: FnMut(bool)> {
+    modulation: Modulation,
+    step: f32,          // Clock phase advance per sample, baud / rate
+    decay: f32,         // Level tracker decay per sample
+    prev: Complex<f32>, // Previous sample, for the FSK discriminator
+    window: Vec<f32>,   // Soft values in the averaging window
+    pos: usize,         // Next slot in `window`
+    sum: f32,           // Sum of `window`
+    high: f32,
+    low: f32,
+    phase: f32, // Symbol clock, bits are sampled as it wraps past 1.0
+    bit: bool,  // Current sliced level
+    callback: F,
+}
+
+impl<F: FnMut(bool)> FskDemod<F> {
+    /// Demodulate `baud` symbols per second from complex samples at `rate`.
+    /// The signal should be shifted near 0 Hz and filtered to its bandwidth.
+    pub fn new(modulation: Modulation, rate: u32, baud: u32, callback: F) -> Self {
+        let sps = rate as f32 / baud as f32;
+        assert!(sps >= 2.0, "Need at least two samples per symbol");
+        FskDemod {
+            modulation,
+            step: 1.0 / sps,
+            decay: 1.0 / (LEVEL_DECAY_SYMBOLS * sps),
+            prev: Complex::new(0.0, 0.0),
+            window: vec![0.0; (sps / 2.0).round().max(1.0) as usize],
+            pos: 0,
+            sum: 0.0,
+            high: 0.0,
+            low: 0.0,
+            phase: 0.0,
+            bit: false,
+            callback,
+        }
+    }
+
+    /// Demodulate a buffer, returning the number of bits passed to the
+    /// callback. State carries over between calls.
+    pub fn process(&mut self, buf: &[Complex<f32>]) -> usize {
+        let mut bits = 0;
+        for s in buf {
+            let value = match self.modulation {
+                Modulation::Fsk => (s * self.prev.conj()).arg(),
+                Modulation::Ook => s.norm(),
+            };
+            self.prev = *s;
+
+            self.sum += value - self.window[self.pos];
+            self.window[self.pos] = value;
+            self.pos = (self.pos + 1) % self.window.len();
+            let value = self.sum / self.window.len() as f32;
+
+            // Levels jump out to new extremes and slowly relax inwards
+            self.high = if value > self.high {
+                value
+            } else {
+                self.high - (self.high - value) * self.decay
+            };
+            self.low = if value < self.low {
+                value
+            } else {
+                self.low + (value - self.low) * self.decay
+            };
+
+            let bit = value > (self.high + self.low) / 2.0;
+            if bit != self.bit {
+                // Transitions belong halfway between sampling instants
+                self.phase += CLOCK_GAIN * (0.5 - self.phase);
+                self.bit = bit;
+            }
+            self.phase += self.step;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+                (self.callback)(self.bit);
+                bits += 1;
+            }
+        }
+        bits
+    }
+}
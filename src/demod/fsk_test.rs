@@ -0,0 +1,95 @@
+use super::fsk::{FskDemod, Modulation};
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+const RATE: u32 = 250_000;
+const BAUD: u32 = 10_000;
+
+/// Preamble followed by pseudo-random data bits
+fn message() -> Vec<bool> {
+    let mut bits: Vec<bool> = (0..16).map(|i| i % 2 == 0).collect();
+    let mut lfsr: u16 = 0xace1;
+    for _ in 0..200 {
+        let bit = (lfsr ^ (lfsr >> 2) ^ (lfsr >> 3) ^ (lfsr >> 5)) & 1;
+        lfsr = (lfsr >> 1) | (bit << 15);
+        bits.push(bit != 0);
+    }
+    bits
+}
+
+/// Modulate with a transmitter clock `drift` ppm off, with some noise
+fn modulate(bits: &[bool], modulation: Modulation, offset: f32, drift: f32) -> Vec<Complex<f32>> {
+    let sps = RATE as f32 / BAUD as f32 * (1.0 + drift * 1e-6);
+    let len = (bits.len() as f32 * sps) as usize;
+    let mut phase = 0.0_f32;
+    (0..len)
+        .map(|n| {
+            let bit = bits[(n as f32 / sps) as usize];
+            let noise = Complex::new(
+                ((n * 7919) % 101) as f32 / 101.0 - 0.5,
+                ((n * 104_729) % 97) as f32 / 97.0 - 0.5,
+            ) * 0.1;
+            match modulation {
+                Modulation::Fsk => {
+                    let dev = if bit { 20_000.0 } else { -20_000.0 };
+                    phase += 2.0 * PI * (dev + offset) / RATE as f32;
+                    Complex::from_polar(1.0, phase) + noise
+                }
+                Modulation::Ook => {
+                    phase += 2.0 * PI * offset / RATE as f32;
+                    let amplitude = if bit { 1.0 } else { 0.0 };
+                    Complex::from_polar(amplitude, phase) + noise
+                }
+            }
+        })
+        .collect()
+}
+
+fn demodulate(modulation: Modulation, samples: &[Complex<f32>]) -> Vec<bool> {
+    let mut bits = Vec::new();
+    let mut demod = FskDemod::new(modulation, RATE, BAUD, |b| bits.push(b));
+    for c in samples.chunks(333) {
+        demod.process(c);
+    }
+    drop(demod);
+    bits
+}
+
+/// Whether the data after the preamble appears in `received`
+fn contains_data(received: &[bool], sent: &[bool]) -> bool {
+    let data = &sent[16..];
+    received.windows(data.len()).any(|w| w == data)
+}
+
+#[test]
+fn test_fsk() {
+    let sent = message();
+    let received = demodulate(
+        Modulation::Fsk,
+        &modulate(&sent, Modulation::Fsk, 3_000.0, 0.0),
+    );
+    assert!((received.len() as i32 - sent.len() as i32).abs() <= 1);
+    assert!(contains_data(&received, &sent));
+}
+
+#[test]
+fn test_fsk_clock_drift() {
+    // 0.5% faster transmitter clock slips a full bit over 200 bits without
+    // clock recovery
+    let sent = message();
+    let received = demodulate(
+        Modulation::Fsk,
+        &modulate(&sent, Modulation::Fsk, -2_000.0, -5_000.0),
+    );
+    assert!(contains_data(&received, &sent));
+}
+
+#[test]
+fn test_ook() {
+    let sent = message();
+    let received = demodulate(
+        Modulation::Ook,
+        &modulate(&sent, Modulation::Ook, 1_000.0, 3_000.0),
+    );
+    assert!(contains_data(&received, &sent));
+}
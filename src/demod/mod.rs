@@ -0,0 +1,14 @@
+//! Demodulators for the IQ stream returned by `read_sync`
+pub mod adsb;
+pub mod fm;
+pub mod fsk;
+pub mod rds;
+
+#[cfg(test)]
+mod adsb_test;
+#[cfg(test)]
+mod fm_test;
+#[cfg(test)]
+mod fsk_test;
+#[cfg(test)]
+mod rds_test;
@@ -0,0 +1,457 @@
+//! RDS (Radio Data System) decoding from the FM multiplex signal
+//!
+//! ```no_run
+//! use rtlsdr_rs::demod::{fm, rds::RdsDecoder};
+//! # let (_, config) = fm::optimal_settings(94_900_000, 170_000, 32_000);
+//! # let mut demod = fm::FmDemod::new(config);
+//! # let mut buf = vec![0_u8; 16384];
+//! let mut rds = RdsDecoder::new(config.rate_out, |event| println!("{:?}", event));
+//! let mpx = demod.mpx(&mut buf);
+//! rds.process(&mpx);
+//! let audio = demod.low_pass_real(mpx);
+//! ```
+
+use num_complex::Complex;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+// Demodulation
+const SUBCARRIER: f32 = 57_000.0; // Hz
+const SYMBOL_RATE: u32 = 19_000; // Hz, 16 samples per 1187.5 bps bit
+const SAMPLES_PER_BIT: usize = 16;
+const LPF_CUTOFF: f32 = 2_400.0; // Hz
+const LPF_TAPS: usize = 127;
+const COSTAS_ALPHA: f32 = 0.02;
+const COSTAS_BETA: f32 = 0.0002;
+const CLOCK_DECAY: f32 = 0.99;
+
+// Block sync, see IEC 62106 annex B
+const BLOCK_BITS: u32 = 26;
+const POLY: u32 = 0x5b9; // x^10 + x^8 + x^7 + x^5 + x^4 + x^3 + 1
+const OFFSETS: [u16; 5] = [0x0fc, 0x198, 0x168, 0x350, 0x1b4]; // A, B, C, C', D
+const OFFSET_POS: [usize; 5] = [0, 1, 2, 2, 3]; // Block position of each offset word
+const MAX_BURST: u32 = 5; // Longest correctable burst error
+const MAX_BAD_BLOCKS: u32 = 20; // Consecutive uncorrectable blocks before sync is lost
+
+const PS_LEN: usize = 8;
+const RT_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RdsEvent {
+    Pi(u16),                // Program identification code
+    ProgramService(String), // Station name
+    RadioText(String),
+}
+
+/// Decodes RDS from the FM multiplex signal, invoking a callback for each
+/// change of station identification, name or radiotext
+pub struct RdsDecoder<F: FnMut(RdsEvent)> {
+    callback: F,
+    demod: Demodulator,
+    sync: BlockSync,
+    pi: Option<u16>,
+    ps: [u8; PS_LEN],
+    ps_mask: u8,
+    ps_last: String,
+    rt: [u8; RT_LEN],
+    rt_mask: u16,
+    rt_ab: Option<bool>,
+    rt_last: String,
+}
+
+impl<F: FnMut(RdsEvent)> RdsDecoder<F> {
+    /// `rate` is the multiplex sample rate in Hz, which must be high enough
+    /// to carry the 57 kHz subcarrier
+    pub fn new(rate: u32, callback: F) -> Self {
+        RdsDecoder {
+            callback,
+            demod: Demodulator::new(rate),
+            sync: BlockSync::new(),
+            pi: None,
+            ps: [b' '; PS_LEN],
+            ps_mask: 0,
+            ps_last: String::new(),
+            rt: [b' '; RT_LEN],
+            rt_mask: 0,
+            rt_ab: None,
+            rt_last: String::new(),
+        }
+    }
+
+    /// Process multiplex samples from `FmDemod::mpx`
+    pub fn process(&mut self, mpx: &[i16]) {
+        for &x in mpx {
+            if let Some(bit) = self.demod.push(x) {
+                self.push_bit(bit);
+            }
+        }
+    }
+
+    /// Whether the block sync is locked to the bit stream
+    pub fn synced(&self) -> bool {
+        self.sync.synced
+    }
+
+    pub(super) fn push_bit(&mut self, bit: bool) {
+        if let Some(group) = self.sync.push(bit) {
+            self.handle_group(group);
+        }
+    }
+
+    fn handle_group(&mut self, group: [Option<u16>; 4]) {
+        if let Some(pi) = group[0] {
+            self.set_pi(pi);
+        }
+        let b = match group[1] {
+            Some(b) => b,
+            None => return,
+        };
+        let version_b = b & 0x0800 != 0;
+        match b >> 12 {
+            // Basic tuning and switching information
+            0 => {
+                if let Some(d) = group[3] {
+                    let addr = (b & 0x03) as usize;
+                    self.ps[addr * 2..addr * 2 + 2].copy_from_slice(&d.to_be_bytes());
+                    self.ps_mask |= 1 << addr;
+                    if self.ps_mask == 0x0f {
+                        let ps = decode_text(&self.ps);
+                        if ps != self.ps_last {
+                            self.ps_last = ps.clone();
+                            (self.callback)(RdsEvent::ProgramService(ps));
+                        }
+                    }
+                }
+            }
+            // Radiotext
+            2 => {
+                let ab = b & 0x0010 != 0;
+                if self.rt_ab != Some(ab) {
+                    // Text A/B flag toggles when a new message starts
+                    self.rt = [b' '; RT_LEN];
+                    self.rt_mask = 0;
+                    self.rt_ab = Some(ab);
+                }
+                let addr = (b & 0x0f) as usize;
+                let seg_len = if version_b { 2 } else { 4 };
+                let chars = match (version_b, group[2], group[3]) {
+                    (false, Some(c), Some(d)) => [c.to_be_bytes(), d.to_be_bytes()].concat(),
+                    (true, _, Some(d)) => d.to_be_bytes().to_vec(),
+                    _ => return,
+                };
+                self.rt[addr * seg_len..(addr + 1) * seg_len].copy_from_slice(&chars);
+                self.rt_mask |= 1 << addr;
+                self.check_radiotext(seg_len);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_radiotext(&mut self, seg_len: usize) {
+        let max = 16 * seg_len;
+        // The text ends early at a carriage return, if it has been received
+        let end = (0..max)
+            .find(|&i| self.rt[i] == b'\r' && self.rt_mask & (1 << (i / seg_len)) != 0)
+            .unwrap_or(max);
+        let segments = end.div_ceil(seg_len);
+        let needed = ((1_u32 << segments) - 1) as u16;
+        if self.rt_mask & needed != needed {
+            return;
+        }
+        let rt = decode_text(&self.rt[..end]);
+        if rt != self.rt_last {
+            self.rt_last = rt.clone();
+            (self.callback)(RdsEvent::RadioText(rt));
+        }
+    }
+
+    fn set_pi(&mut self, pi: u16) {
+        if self.pi != Some(pi) {
+            self.pi = Some(pi);
+            (self.callback)(RdsEvent::Pi(pi));
+        }
+    }
+}
+
+/// Map RDS characters to a string, dropping trailing padding. Characters
+/// outside the ASCII range of the RDS character set become '?'.
+fn decode_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&c| match c {
+            0x20..=0x7e => c as char,
+            _ => '?',
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Recovers the RDS bit stream: mixes the 57 kHz subcarrier to baseband,
+/// filters and resamples to 16 samples per bit, tracks the carrier phase
+/// with a Costas loop and picks the biphase symbol timing with the most
+/// energy.
+struct Demodulator {
+    rate: u32,
+    nco_phase: f32,
+    nco_step: f32,
+    taps: [f32; LPF_TAPS],
+    history: [Complex<f32>; LPF_TAPS],
+    hist_pos: usize,
+    resample_acc: u32,
+    costas_phase: f32,
+    costas_freq: f32,
+    window: [f32; SAMPLES_PER_BIT],
+    win_pos: usize,
+    energy: [f32; SAMPLES_PER_BIT],
+    since_bit: usize, // Samples since the last bit
+    prev_symbol: f32,
+}
+
+impl Demodulator {
+    fn new(rate: u32) -> Demodulator {
+        assert!(
+            rate as f32 > 2.0 * (SUBCARRIER + LPF_CUTOFF),
+            "Sample rate too low for RDS: {} Hz",
+            rate
+        );
+        // Hamming windowed sinc low-pass
+        let mut taps = [0.0; LPF_TAPS];
+        let fc = LPF_CUTOFF / rate as f32;
+        let mid = (LPF_TAPS / 2) as f32;
+        for (i, tap) in taps.iter_mut().enumerate() {
+            let n = i as f32 - mid;
+            let sinc = if n == 0.0 {
+                2.0 * fc
+            } else {
+                (2.0 * PI * fc * n).sin() / (PI * n)
+            };
+            let window = 0.54 - 0.46 * (2.0 * PI * i as f32 / (LPF_TAPS - 1) as f32).cos();
+            *tap = sinc * window;
+        }
+        Demodulator {
+            rate,
+            nco_phase: 0.0,
+            nco_step: 2.0 * PI * SUBCARRIER / rate as f32,
+            taps,
+            history: [Complex::new(0.0, 0.0); LPF_TAPS],
+            hist_pos: 0,
+            resample_acc: 0,
+            costas_phase: 0.0,
+            costas_freq: 0.0,
+            window: [0.0; SAMPLES_PER_BIT],
+            win_pos: 0,
+            energy: [0.0; SAMPLES_PER_BIT],
+            since_bit: 0,
+            prev_symbol: 0.0,
+        }
+    }
+
+    /// Add a multiplex sample, returning a bit when one is complete
+    fn push(&mut self, x: i16) -> Option<bool> {
+        let (sin, cos) = self.nco_phase.sin_cos();
+        let x = x as f32;
+        self.history[self.hist_pos] = Complex::new(x * cos, -x * sin);
+        self.hist_pos = (self.hist_pos + 1) % LPF_TAPS;
+        self.nco_phase = (self.nco_phase + self.nco_step) % (2.0 * PI);
+
+        self.resample_acc += SYMBOL_RATE;
+        if self.resample_acc < self.rate {
+            return None;
+        }
+        self.resample_acc -= self.rate;
+        // Oldest sample first
+        let (new, old) = self.history.split_at(self.hist_pos);
+        let y: Complex<f32> = self
+            .taps
+            .iter()
+            .zip(old.iter().chain(new))
+            .map(|(t, h)| h * t)
+            .sum();
+        self.symbol_sample(y)
+    }
+
+    fn symbol_sample(&mut self, y: Complex<f32>) -> Option<bool> {
+        // BPSK Costas loop
+        let s = y * Complex::from_polar(1.0, -self.costas_phase);
+        let err = s.re * s.im / (s.norm_sqr() + f32::EPSILON);
+        self.costas_freq += COSTAS_BETA * err;
+        self.costas_phase =
+            (self.costas_phase + self.costas_freq + COSTAS_ALPHA * err) % (2.0 * PI);
+
+        // Biphase matched filter over the last bit period: the first half of
+        // each symbol is the inverse of the second
+        let slot = self.win_pos;
+        self.window[slot] = s.re;
+        self.win_pos = (slot + 1) % SAMPLES_PER_BIT;
+        let half = SAMPLES_PER_BIT / 2;
+        let v: f32 = (0..SAMPLES_PER_BIT)
+            .map(|i| {
+                let x = self.window[(self.win_pos + i) % SAMPLES_PER_BIT];
+                if i < half {
+                    x
+                } else {
+                    -x
+                }
+            })
+            .sum();
+
+        // Symbol clock: sample at the offset with the most biphase energy
+        self.energy[slot] = self.energy[slot] * CLOCK_DECAY + v.abs();
+        let best = (0..SAMPLES_PER_BIT)
+            .max_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+            .unwrap_or(0);
+        self.since_bit += 1;
+        // Don't sample the same bit twice when the best offset moves later,
+        // or skip one when it moves to an offset that has just passed
+        let due = slot == best && self.since_bit >= SAMPLES_PER_BIT / 2;
+        if !due && self.since_bit < SAMPLES_PER_BIT * 5 / 4 {
+            return None;
+        }
+        self.since_bit = 0;
+        // Differential decoding removes the Costas loop phase ambiguity
+        let bit = (v > 0.0) != (self.prev_symbol > 0.0);
+        self.prev_symbol = v;
+        Some(bit)
+    }
+}
+
+/// Finds block boundaries in the bit stream and corrects burst errors
+struct BlockSync {
+    reg: u32,
+    synced: bool,
+    bit_index: u64,
+    last_valid: Option<(u64, usize)>, // Bit index and position of the last valid block seen while searching
+    bit_count: u32,
+    block_pos: usize,
+    bad_blocks: u32,
+    group: [Option<u16>; 4],
+    bursts: HashMap<u16, u32>, // Syndrome to error pattern
+}
+
+impl BlockSync {
+    fn new() -> BlockSync {
+        let mut bursts = HashMap::new();
+        for len in 1..=MAX_BURST {
+            // Bursts start and end with an error bit
+            let inner = len.saturating_sub(2);
+            for middle in 0..1_u32 << inner {
+                let pattern = if len == 1 {
+                    1
+                } else {
+                    (1 << (len - 1)) | (middle << 1) | 1
+                };
+                for shift in 0..=BLOCK_BITS - len {
+                    let e = pattern << shift;
+                    bursts.entry(syndrome(e)).or_insert(e);
+                }
+            }
+        }
+        BlockSync {
+            reg: 0,
+            synced: false,
+            bit_index: 0,
+            last_valid: None,
+            bit_count: 0,
+            block_pos: 0,
+            bad_blocks: 0,
+            group: [None; 4],
+            bursts,
+        }
+    }
+
+    /// Add a bit, returning a group once its last block has been received
+    fn push(&mut self, bit: bool) -> Option<[Option<u16>; 4]> {
+        self.reg = ((self.reg << 1) | bit as u32) & ((1 << BLOCK_BITS) - 1);
+        self.bit_index += 1;
+        if !self.synced {
+            return self.search();
+        }
+        self.bit_count += 1;
+        if self.bit_count < BLOCK_BITS {
+            return None;
+        }
+        self.bit_count = 0;
+        self.block_pos = (self.block_pos + 1) % 4;
+        let data = self.correct(self.block_pos);
+        self.group[self.block_pos] = data;
+        if data.is_some() {
+            self.bad_blocks = 0;
+        } else {
+            self.bad_blocks += 1;
+            if self.bad_blocks >= MAX_BAD_BLOCKS {
+                self.synced = false;
+                self.last_valid = None;
+                self.group = [None; 4];
+                return None;
+            }
+        }
+        self.take_group()
+    }
+
+    /// Look for two consecutive blocks with valid offset words
+    fn search(&mut self) -> Option<[Option<u16>; 4]> {
+        if self.bit_index < BLOCK_BITS as u64 {
+            return None;
+        }
+        let s = syndrome(self.reg);
+        let k = OFFSETS.iter().position(|&o| o == s)?;
+        let pos = OFFSET_POS[k];
+        if let Some((index, prev)) = self.last_valid {
+            if self.bit_index - index == BLOCK_BITS as u64 && pos == (prev + 1) % 4 {
+                self.synced = true;
+                self.bit_count = 0;
+                self.bad_blocks = 0;
+                self.block_pos = pos;
+                self.group = [None; 4];
+                self.group[pos] = Some((self.reg >> 10) as u16);
+                self.last_valid = None;
+                return self.take_group();
+            }
+        }
+        self.last_valid = Some((self.bit_index, pos));
+        None
+    }
+
+    fn take_group(&mut self) -> Option<[Option<u16>; 4]> {
+        if self.block_pos != 3 {
+            return None;
+        }
+        Some(std::mem::take(&mut self.group))
+    }
+
+    /// Data of the block in the register if it is valid for `pos`, after
+    /// correcting any burst error
+    fn correct(&self, pos: usize) -> Option<u16> {
+        let s = syndrome(self.reg);
+        OFFSETS
+            .iter()
+            .zip(OFFSET_POS)
+            .filter(|&(_, p)| p == pos)
+            .find_map(|(&offset, _)| match s ^ offset {
+                0 => Some(self.reg),
+                e => self.bursts.get(&e).map(|e| self.reg ^ e),
+            })
+            .map(|block| (block >> 10) as u16)
+    }
+}
+
+/// Remainder of a 26-bit block divided by the generator polynomial. For a
+/// valid block this is its offset word.
+pub(super) fn syndrome(block: u32) -> u16 {
+    let mut r = block;
+    for i in (10..BLOCK_BITS).rev() {
+        if r & (1 << i) != 0 {
+            r ^= POLY << (i - 10);
+        }
+    }
+    r as u16
+}
+
+/// Encode 16 data bits into a block with the offset word for `offset`
+/// (index into A, B, C, C', D)
+#[cfg(test)]
+pub(super) fn encode_block(data: u16, offset: usize) -> u32 {
+    let check = syndrome((data as u32) << 10);
+    ((data as u32) << 10) | (check ^ OFFSETS[offset]) as u32
+}
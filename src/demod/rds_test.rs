@@ -0,0 +1,135 @@
+use super::rds::{encode_block, syndrome, RdsDecoder, RdsEvent};
+use std::f64::consts::PI;
+
+const PI_CODE: u16 = 0x54a8;
+
+/// Blocks of the 0A groups carrying a station name
+fn ps_groups(name: &str) -> Vec<[u16; 4]> {
+    let name = name.as_bytes();
+    (0..4)
+        .map(|addr| {
+            let d = u16::from_be_bytes([name[2 * addr], name[2 * addr + 1]]);
+            [PI_CODE, addr as u16, 0, d]
+        })
+        .collect()
+}
+
+/// Blocks of the 2A groups carrying radiotext, terminated by a carriage return
+fn rt_groups(text: &str) -> Vec<[u16; 4]> {
+    let mut text = text.as_bytes().to_vec();
+    text.push(b'\r');
+    text.resize(text.len().div_ceil(4) * 4, b' ');
+    text.chunks(4)
+        .enumerate()
+        .map(|(addr, c)| {
+            [
+                PI_CODE,
+                0x2000 | addr as u16,
+                u16::from_be_bytes([c[0], c[1]]),
+                u16::from_be_bytes([c[2], c[3]]),
+            ]
+        })
+        .collect()
+}
+
+fn group_bits(groups: &[[u16; 4]]) -> Vec<bool> {
+    let mut bits = Vec::new();
+    for group in groups {
+        for (pos, &data) in group.iter().enumerate() {
+            let offset = [0, 1, 2, 4][pos];
+            let block = encode_block(data, offset);
+            bits.extend((0..26).rev().map(|i| block & (1 << i) != 0));
+        }
+    }
+    bits
+}
+
+fn decode_bits(bits: &[bool]) -> Vec<RdsEvent> {
+    let mut events = Vec::new();
+    {
+        let mut rds = RdsDecoder::new(171_000, |e| events.push(e));
+        for &bit in bits {
+            rds.push_bit(bit);
+        }
+    }
+    events
+}
+
+#[test]
+fn test_syndrome_of_valid_block() {
+    for (k, offset) in [0x0fc, 0x198, 0x168, 0x350, 0x1b4].iter().enumerate() {
+        assert_eq!(*offset, syndrome(encode_block(0xbeef, k)));
+    }
+}
+
+#[test]
+fn test_decode_groups() {
+    let mut groups = [ps_groups("TEST FM "), ps_groups("TEST FM ")].concat();
+    groups.extend(rt_groups("HELLO WORLD"));
+    // Start mid-block so sync has to be found, losing the first group
+    let bits = group_bits(&groups);
+    let events = decode_bits(&bits[7..]);
+    assert_eq!(
+        vec![
+            RdsEvent::Pi(PI_CODE),
+            RdsEvent::ProgramService("TEST FM".to_string()),
+            RdsEvent::RadioText("HELLO WORLD".to_string()),
+        ],
+        events
+    );
+}
+
+#[test]
+fn test_decode_corrects_burst_errors() {
+    let groups = [ps_groups("RUST FM "), ps_groups("RUST FM ")].concat();
+    let mut bits = group_bits(&groups);
+    // Burst in the last block of the second group
+    for bit in &mut bits[26 * 7 + 3..26 * 7 + 7] {
+        *bit = !*bit;
+    }
+    let events = decode_bits(&bits);
+    assert!(events.contains(&RdsEvent::ProgramService("RUST FM".to_string())));
+}
+
+#[test]
+fn test_decode_mpx() {
+    let groups = [ps_groups("TEST FM "), rt_groups("RDS FROM RUST")].concat();
+    let bits: Vec<bool> = (0..4).flat_map(|_| group_bits(&groups)).collect();
+
+    // Differentially encoded biphase symbols on a 57 kHz subcarrier, plus
+    // the 19 kHz stereo pilot and a mono audio tone. The transmitter clock
+    // runs 25 ppm fast relative to the receiver.
+    let rate = 171_000.0;
+    let clock = 1.000_025;
+    let mut symbols = Vec::with_capacity(bits.len());
+    let mut level = false;
+    for &bit in &bits {
+        level ^= bit;
+        symbols.push(if level { 1.0 } else { -1.0 });
+    }
+    let len = (bits.len() as f64 * rate / (1187.5 * clock)) as usize;
+    let mpx: Vec<i16> = (0..len)
+        .map(|n| {
+            let t = n as f64 / rate * clock;
+            let pos = t * 1187.5;
+            let symbol: f64 = symbols[(pos as usize).min(symbols.len() - 1)];
+            let shaped = if pos.fract() < 0.5 { symbol } else { -symbol };
+            let audio = 3000.0 * (2.0 * PI * 1000.0 * t).sin();
+            let pilot = 1000.0 * (2.0 * PI * 19_000.0 * t).cos();
+            let rds = 300.0 * shaped * (2.0 * PI * 57_000.0 * t + 1.0).cos();
+            (audio + pilot + rds) as i16
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    {
+        let mut rds = RdsDecoder::new(rate as u32, |e| events.push(e));
+        for chunk in mpx.chunks(16384) {
+            rds.process(chunk);
+        }
+        assert!(rds.synced());
+    }
+    assert!(events.contains(&RdsEvent::Pi(PI_CODE)));
+    assert!(events.contains(&RdsEvent::ProgramService("TEST FM".to_string())));
+    assert!(events.contains(&RdsEvent::RadioText("RDS FROM RUST".to_string())));
+}
@@ -7,7 +7,7 @@ pub struct UsbDeviceSignature {
     pub pid: u16,
     pub description: &'static str,
 }
-pub const KNOWN_DEVICES: &'static [UsbDeviceSignature; 42] = &[
+pub const KNOWN_DEVICES: &[UsbDeviceSignature; 42] = &[
     UsbDeviceSignature {
         vid: 0x0bda,
         pid: 0x2832,
@@ -222,6 +222,9 @@ pub const KNOWN_DEVICES: &'static [UsbDeviceSignature; 42] = &[
 
 pub const EEPROM_ADDR: u16 = 0xa0;
 pub const EEPROM_SIZE: usize = 256;
+pub const EEPROM_READ_CHUNK: usize = 8;
+pub const EEPROM_PAGE_SIZE: usize = 8;
+pub const EEPROM_WRITE_DELAY: Duration = Duration::from_millis(5);
 
 // Blocks
 pub const BLOCK_DEMOD: u16 = 0;
@@ -257,6 +260,34 @@ pub const USB_EPA_MAXPKT: u16 = 0x2158;
 pub const USB_EPA_MAXPKT_2: u16 = 0x215a;
 pub const USB_EPA_FIFO_CFG: u16 = 0x2160;
 
+// IR Registers
+pub const IR_RX_BUF: u16 = 0xfc00;
+pub const IR_RX_IE: u16 = 0xfd00;
+pub const IR_RX_IF: u16 = 0xfd01;
+pub const IR_RX_CTRL: u16 = 0xfd02;
+pub const IR_RX_CFG: u16 = 0xfd03;
+pub const IR_MAX_DURATION0: u16 = 0xfd04;
+pub const IR_MAX_DURATION1: u16 = 0xfd05;
+pub const IR_IDLE_LEN0: u16 = 0xfd06;
+pub const IR_IDLE_LEN1: u16 = 0xfd07;
+pub const IR_GLITCH_LEN: u16 = 0xfd08;
+pub const IR_RX_BUF_CTRL: u16 = 0xfd09;
+pub const IR_RX_BUF_DATA: u16 = 0xfd0a;
+pub const IR_RX_BC: u16 = 0xfd0b;
+pub const IR_RX_CLK: u16 = 0xfd0c;
+pub const IR_RX_C_COUNT_L: u16 = 0xfd0d;
+pub const IR_RX_C_COUNT_H: u16 = 0xfd0e;
+pub const IR_SUSPEND_CTRL: u16 = 0xfd10;
+pub const IR_ERR_TOL_CTRL: u16 = 0xfd11;
+pub const IR_UNIT_LEN: u16 = 0xfd12;
+pub const IR_ERR_TOL_LEN: u16 = 0xfd13;
+pub const IR_MAX_H_TOL_LEN: u16 = 0xfd14;
+pub const IR_MAX_L_TOL_LEN: u16 = 0xfd15;
+pub const IR_MASK_CTRL: u16 = 0xfd16;
+pub const IR_MASK_DATA: u16 = 0xfd17;
+pub const IR_RES_MASK_ADDR: u16 = 0xfd18;
+pub const IR_RES_MASK_T_LEN: u16 = 0xfd19;
+
 pub const CTRL_IN: u8 =
     rusb::constants::LIBUSB_ENDPOINT_IN | rusb::constants::LIBUSB_REQUEST_TYPE_VENDOR;
 pub const CTRL_OUT: u8 =
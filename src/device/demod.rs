@@ -0,0 +1,127 @@
+//! RTL2832 demodulator register map and helpers for the register sequences
+//! used to configure it.
+
+use super::{Device, RegWidth};
+use crate::error::Result;
+
+/// A demodulator register, addressed by page and offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemodReg {
+    pub page: u16,
+    pub addr: u16,
+}
+
+const fn reg(page: u16, addr: u16) -> DemodReg {
+    DemodReg { page, addr }
+}
+
+// Page 0
+pub const OPT_ADC_IQ: DemodReg = reg(0, 0x06); // ADC datapath, bit 4 swaps I and Q
+pub const ADC_EN: DemodReg = reg(0, 0x08); // ADC enables, bit 6 = I, bit 7 = Q
+pub const CLK_OUT: DemodReg = reg(0, 0x0d); // TP_CK0 clock output, bit 0 disables
+pub const SDR_MODE: DemodReg = reg(0, 0x19); // SDR mode, DAGC and test mode
+pub const PID_FILTER: DemodReg = reg(0, 0x61);
+// Page 1
+pub const SOFT_RST: DemodReg = reg(1, 0x01); // Soft reset (bit 2) and I2C repeater (bit 3)
+pub const AGC_LOOP: DemodReg = reg(1, 0x04); // RF and IF AGC loop
+pub const EN_DAGC: DemodReg = reg(1, 0x11);
+pub const SPEC_INV: DemodReg = reg(1, 0x15);
+pub const ADJ_CHAN_REJ: DemodReg = reg(1, 0x16); // DDC shift, start of the 5 DDC registers
+pub const DDC_IF_FREQ: DemodReg = reg(1, 0x19); // 22-bit IF frequency, 0x19-0x1b
+pub const FIR_COEFF: DemodReg = reg(1, 0x1c); // 20 bytes of packed FIR coefficients
+pub const SAMPLE_FREQ_CORR: DemodReg = reg(1, 0x3e); // 14-bit sample rate offset, 0x3e-0x3f
+pub const FSM_STATE: DemodReg = reg(1, 0x93); // FSM state-holding registers, 0x93-0x94
+pub const RSAMP_RATIO: DemodReg = reg(1, 0x9f); // 32-bit resampler ratio, 0x9f-0xa2
+pub const ZERO_IF: DemodReg = reg(1, 0xb1); // Zero-IF, DC cancellation and IQ compensation
+
+// DDC shift and IF frequency registers cleared at init, 0x16-0x1b
+pub const DDC_LEN: u16 = 6;
+
+impl Device {
+    pub fn demod_write(&self, reg: DemodReg, val: u16, width: RegWidth) -> Result<usize> {
+        self.demod_write_reg(reg.page, reg.addr, val, width)
+    }
+
+    pub fn set_i2c_repeater(&self, enable: bool) -> Result<()> {
+        let val = if enable { 0x18 } else { 0x10 };
+        self.demod_write(SOFT_RST, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// Pulse the demod soft reset (bit 2)
+    pub fn reset_demod(&self) -> Result<()> {
+        self.demod_write(SOFT_RST, 0x14, RegWidth::Byte)?;
+        self.demod_write(SOFT_RST, 0x10, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// Zero-IF mode also enables DC cancellation and IQ estimation/compensation
+    pub fn set_zero_if(&self, enable: bool) -> Result<()> {
+        let val = if enable { 0x1b } else { 0x1a };
+        self.demod_write(ZERO_IF, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    pub fn set_spectrum_inversion(&self, enable: bool) -> Result<()> {
+        self.demod_write(SPEC_INV, enable as u16, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// Swap the I and Q ADC datapaths (opt_adc_iq)
+    pub fn set_iq_swap(&self, swap: bool) -> Result<()> {
+        let val = if swap { 0x90 } else { 0x80 };
+        self.demod_write(OPT_ADC_IQ, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// 4.096 MHz clock output on pin TP_CK0
+    pub fn set_clock_output(&self, enable: bool) -> Result<()> {
+        let val = if enable { 0x82 } else { 0x83 };
+        self.demod_write(CLK_OUT, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// SDR mode with DAGC disabled, optionally replacing samples with a counter
+    pub fn set_test_mode(&self, enable: bool) -> Result<()> {
+        let val = if enable { 0x03 } else { 0x05 };
+        self.demod_write(SDR_MODE, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// SDR mode with the RTL2832 digital AGC on or off. Shares a register
+    /// with test mode, so either turns the other off.
+    pub fn set_agc_mode(&self, enable: bool) -> Result<()> {
+        let val = if enable { 0x25 } else { 0x05 };
+        self.demod_write(SDR_MODE, val, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    /// Write the 22-bit DDC IF frequency register value
+    pub fn set_ddc_if_freq(&self, if_freq: i32) -> Result<()> {
+        let DemodReg { page, addr } = DDC_IF_FREQ;
+        self.demod_write_reg(page, addr, ((if_freq >> 16) as u16) & 0x3f, RegWidth::Byte)?;
+        self.demod_write_reg(
+            page,
+            addr + 1,
+            ((if_freq >> 8) as u16) & 0xff,
+            RegWidth::Byte,
+        )?;
+        self.demod_write_reg(page, addr + 2, if_freq as u16 & 0xff, RegWidth::Byte)?;
+        Ok(())
+    }
+
+    pub fn set_resample_ratio(&self, ratio: u32) -> Result<()> {
+        let DemodReg { page, addr } = RSAMP_RATIO;
+        self.demod_write_reg(page, addr, (ratio >> 16) as u16, RegWidth::Word)?;
+        self.demod_write_reg(page, addr + 2, (ratio & 0xffff) as u16, RegWidth::Word)?;
+        Ok(())
+    }
+
+    /// Write the 14-bit sample frequency offset
+    pub fn set_sample_freq_corr(&self, offs: i16) -> Result<()> {
+        let DemodReg { page, addr } = SAMPLE_FREQ_CORR;
+        self.demod_write_reg(page, addr + 1, (offs & 0xff) as u16, RegWidth::Byte)?;
+        self.demod_write_reg(page, addr, ((offs >> 8) & 0x3f) as u16, RegWidth::Byte)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use mockall::predicate::{self, eq};
+use mockall::Sequence;
+
+use crate::device::mock_device_handle::MockDeviceHandle;
+use crate::device::{Device, WriteVerify};
+
+use super::{CTRL_IN, CTRL_OUT, CTRL_TIMEOUT};
+
+/// Expect a demod register write followed by the status read that
+/// `demod_write_reg` always performs
+fn expect_demod_write(
+    mock_handle: &mut MockDeviceHandle,
+    seq: &mut Sequence,
+    page: u16,
+    addr: u16,
+    data_expected: &'static [u8],
+) {
+    mock_handle
+        .expect_write_control()
+        .times(1)
+        .in_sequence(seq)
+        .with(
+            eq(CTRL_OUT),
+            eq(0),
+            eq((addr << 8) | 0x20),
+            eq(0x10 | page),
+            predicate::always(),
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(move |_, _, _, _, data, _| {
+            assert_eq!(data, data_expected);
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_read_control()
+        .times(1)
+        .in_sequence(seq)
+        .with(
+            eq(CTRL_IN),
+            eq(0),
+            eq((0x01 << 8) | 0x20),
+            eq(0x0a),
+            predicate::always(),
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(|_, _, _, _, _, _| Ok(1));
+}
+
+#[test]
+fn test_set_zero_if() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0xb1, &[0x1b]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0xb1, &[0x1a]);
+    let device = Device::from_handle(mock_handle);
+    device.set_zero_if(true).unwrap();
+    device.set_zero_if(false).unwrap();
+}
+
+#[test]
+fn test_reset_demod() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x01, &[0x14]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x01, &[0x10]);
+    let device = Device::from_handle(mock_handle);
+    device.reset_demod().unwrap();
+}
+
+#[test]
+fn test_set_ddc_if_freq() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    // Upper 6 bits are masked off
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x19, &[0x34]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x1a, &[0x56]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x1b, &[0x78]);
+    let device = Device::from_handle(mock_handle);
+    device.set_ddc_if_freq(0x0f34_5678).unwrap();
+}
+
+#[test]
+fn test_set_resample_ratio() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x9f, &[0x01, 0xc2]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0xa1, &[0x00, 0x00]);
+    let device = Device::from_handle(mock_handle);
+    device.set_resample_ratio(0x01c2_0000).unwrap();
+}
+
+#[test]
+fn test_set_sample_freq_corr() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    // -1 ppm = -16 (0xfff0), written low byte first then 6 high bits
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x3f, &[0xf0]);
+    expect_demod_write(&mut mock_handle, &mut seq, 1, 0x3e, &[0x3f]);
+    let device = Device::from_handle(mock_handle);
+    device.set_sample_freq_corr(-16).unwrap();
+}
+
+#[test]
+fn test_demod_write_array() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    expect_demod_write(
+        &mut mock_handle,
+        &mut seq,
+        1,
+        0x1c,
+        &[0xca, 0xdc, 0xd7, 0xd8],
+    );
+    let device = Device::from_handle(mock_handle);
+    let result = device
+        .demod_write_array(1, 0x1c, &[0xca, 0xdc, 0xd7, 0xd8])
+        .unwrap();
+    assert_eq!(4, result);
+}
+
+#[test]
+fn test_deferred_write_verify() {
+    let mut seq = Sequence::new();
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_write_control()
+        .times(2)
+        .in_sequence(&mut seq)
+        .returning(|_, _, _, _, data, _| Ok(data.len()));
+    // A single status read for both writes once verification is restored
+    mock_handle
+        .expect_read_control()
+        .times(1)
+        .in_sequence(&mut seq)
+        .with(
+            eq(CTRL_IN),
+            eq(0),
+            eq((0x01 << 8) | 0x20),
+            eq(0x0a),
+            predicate::always(),
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(|_, _, _, _, _, _| Ok(1));
+    let device = Device::from_handle(mock_handle);
+    device.set_write_verify(WriteVerify::Deferred).unwrap();
+    device.set_zero_if(true).unwrap();
+    device.set_spectrum_inversion(true).unwrap();
+    device.set_write_verify(WriteVerify::EveryWrite).unwrap();
+    // Nothing left to flush
+    device.flush_write_verify().unwrap();
+}
@@ -1,20 +1,80 @@
+use std::path::Path;
 use std::time::Duration;
 
 use crate::error::Result;
 use crate::error::RtlsdrError::RtlsdrErr;
 use rusb::{Context, UsbContext};
-use log::{error, info};
+use log::info;
 
+use super::transcript::{Recorder, Transaction};
 use super::KNOWN_DEVICES;
+
+const STRING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Devices of a known type, in the order `RtlSdr::open` counts them
+pub fn devices() -> Vec<(rusb::Device<Context>, &'static str)> {
+    let Ok(devices) = Context::new().and_then(|c| c.devices()) else {
+        return Vec::new();
+    };
+    devices
+        .iter()
+        .filter_map(|device| {
+            let desc = device.device_descriptor().ok()?;
+            let known = KNOWN_DEVICES
+                .iter()
+                .find(|d| d.vid == desc.vendor_id() && d.pid == desc.product_id())?;
+            Some((device, known.description))
+        })
+        .collect()
+}
+
+/// Manufacturer, product and serial strings of an unopened device
+pub fn usb_strings(device: &rusb::Device<Context>) -> rusb::Result<(String, String, String)> {
+    let handle = device.open()?;
+    let language = *handle
+        .read_languages(STRING_TIMEOUT)?
+        .first()
+        .ok_or(rusb::Error::NotFound)?;
+    let desc = device.device_descriptor()?;
+    let manufacturer = handle
+        .read_manufacturer_string(language, &desc, STRING_TIMEOUT)
+        .unwrap_or_default();
+    let product = handle
+        .read_product_string(language, &desc, STRING_TIMEOUT)
+        .unwrap_or_default();
+    let serial = handle
+        .read_serial_number_string(language, &desc, STRING_TIMEOUT)
+        .unwrap_or_default();
+    Ok((manufacturer, product, serial))
+}
+
 #[derive(Debug)]
 pub struct DeviceHandle {
     handle: rusb::DeviceHandle<Context>,
+    recorder: Option<Recorder>,
 }
 impl DeviceHandle {
     pub fn open(index: usize) -> Result<Self> {
         let mut context = Context::new()?;
         let handle = DeviceHandle::open_device(&mut context, index)?;
-        Ok(DeviceHandle { handle: handle })
+        Ok(DeviceHandle {
+            handle,
+            recorder: None,
+        })
+    }
+
+    /// Log every following transaction to a transcript file at `path`,
+    /// appending if it exists
+    pub fn record_to(&mut self, path: &Path) -> Result<()> {
+        self.recorder = Some(Recorder::append(path)?);
+        Ok(())
+    }
+
+    fn record(&self, transaction: Transaction) -> Result<()> {
+        match &self.recorder {
+            Some(recorder) => recorder.record(&transaction),
+            None => Ok(()),
+        }
     }
     pub fn open_device<T: UsbContext>(
         context: &mut T,
@@ -67,10 +127,10 @@ impl DeviceHandle {
         )))
     }
     
-    pub fn claim_interface(&mut self, iface: u8) -> Result<()> {
+    pub fn claim_interface(&self, iface: u8) -> Result<()> {
         Ok(self.handle.claim_interface(iface)?)
     }
-    pub fn reset(&mut self) -> Result<()> {
+    pub fn reset(&self) -> Result<()> {
         Ok(self.handle.reset()?)
     }
 
@@ -83,9 +143,17 @@ impl DeviceHandle {
         buf: &mut [u8],
         timeout: Duration,
     ) -> Result<usize> {
-        Ok(self
+        let len = self
             .handle
-            .read_control(request_type, request, value, index, buf, timeout)?)
+            .read_control(request_type, request, value, index, buf, timeout)?;
+        self.record(Transaction::ControlIn {
+            request_type,
+            request,
+            value,
+            index,
+            data: buf[..len].to_vec(),
+        })?;
+        Ok(len)
     }
 
     pub fn write_control(
@@ -97,12 +165,23 @@ impl DeviceHandle {
         buf: &[u8],
         timeout: Duration,
     ) -> Result<usize> {
-        Ok(self
+        let len = self
             .handle
-            .write_control(request_type, request, value, index, buf, timeout)?)
+            .write_control(request_type, request, value, index, buf, timeout)?;
+        self.record(Transaction::ControlOut {
+            request_type,
+            request,
+            value,
+            index,
+            data: buf.to_vec(),
+            len,
+        })?;
+        Ok(len)
     }
 
     pub fn read_bulk(&self, endpoint: u8, buf: &mut [u8], timeout: Duration) -> Result<usize> {
-        Ok(self.handle.read_bulk(endpoint, buf, timeout)?)
+        let len = self.handle.read_bulk(endpoint, buf, timeout)?;
+        self.record(Transaction::BulkIn { endpoint, len })?;
+        Ok(len)
     }
 }
@@ -1,9 +1,20 @@
 use mockall::predicate::{self, eq};
 
 use crate::device::mock_device_handle::MockDeviceHandle;
-use crate::device::{Device, EEPROM_SIZE};
+use crate::device::{dump_line, Device, RegWidth, EEPROM_READ_CHUNK, EEPROM_SIZE};
+use crate::eeprom::{FLAGS_OFFSET, FLAG_ENABLE_IR, FLAG_REMOTE_WAKEUP};
+use crate::error::{EepromError, RtlsdrError};
+use crate::rtlsdr::{OpenOptions, RtlSdr};
+use crate::tuners::r820t::{R820T, TUNER_INFO};
+use crate::tuners::Tuner;
+use crate::{DirectSampleMode, NotchFilter, Settings, TrackingFilter, TunerGain};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 
-use super::{BLOCK_IIC, BLOCK_SYS, CTRL_IN, CTRL_OUT, CTRL_TIMEOUT, EEPROM_ADDR, GPO};
+use super::{
+    BLOCK_IIC, BLOCK_IRB, BLOCK_SYS, BLOCK_USB, CTRL_IN, CTRL_OUT, CTRL_TIMEOUT, DEMOD_CTL,
+    EEPROM_ADDR, GPO, IR_RX_BC, IR_RX_BUF, IR_RX_BUF_CTRL, IR_RX_IF, USB_SYSCTL,
+};
 
 #[test]
 fn test_read_reg_u8() {
@@ -29,10 +40,8 @@ fn test_read_reg_u8() {
             data[0] = data_expected as u8;
             Ok(1)
         });
-    let device = Device {
-        handle: mock_handle,
-    };
-    let result = device.read_reg(block, addr, 1).unwrap();
+    let device = Device::from_handle(mock_handle);
+    let result = device.read_reg(block, addr, RegWidth::Byte).unwrap();
     assert_eq!(data_expected, result);
 }
 
@@ -61,10 +70,8 @@ fn test_read_reg_u16() {
             data[1] = data_expected[1];
             Ok(2)
         });
-    let device = Device {
-        handle: mock_handle,
-    };
-    let result = device.read_reg(block, addr, 2).unwrap();
+    let device = Device::from_handle(mock_handle);
+    let result = device.read_reg(block, addr, RegWidth::Word).unwrap();
     assert_eq!(u16::from_le_bytes(data_expected), result);
 }
 
@@ -92,10 +99,10 @@ fn test_write_reg_u8() {
             assert_eq!(data[0], data_expected as u8);
             Ok(1)
         });
-    let device = Device {
-        handle: mock_handle,
-    };
-    let result = device.write_reg(block, addr, data_expected, 1).unwrap();
+    let device = Device::from_handle(mock_handle);
+    let result = device
+        .write_reg(block, addr, data_expected, RegWidth::Byte)
+        .unwrap();
     assert_eq!(1, result);
 }
 
@@ -123,10 +130,10 @@ fn test_write_reg_u16() {
             assert_eq!(data, data_expected.to_be_bytes());
             Ok(1)
         });
-    let device = Device {
-        handle: mock_handle,
-    };
-    let result = device.write_reg(block, addr, data_expected, 2).unwrap();
+    let device = Device::from_handle(mock_handle);
+    let result = device
+        .write_reg(block, addr, data_expected, RegWidth::Word)
+        .unwrap();
     assert_eq!(1, result);
 }
 
@@ -152,20 +159,36 @@ fn test_demod_read_reg() {
             data[0] = value;
             Ok(2)
         });
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let result = device.demod_read_reg(page, addr).unwrap();
     assert_eq!(value as u16, result);
 }
 
+#[test]
+fn test_demod_errors() {
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .returning(|_, _, _, _, _, _| Err(RtlsdrError::Usb(rusb::Error::NoDevice)));
+    mock_handle
+        .expect_write_control()
+        .returning(|_, _, _, _, _, _| Err(RtlsdrError::Usb(rusb::Error::NoDevice)));
+    let device = Device::from_handle(mock_handle);
+    assert!(matches!(
+        device.demod_read_reg(0x0a, 0x01),
+        Err(RtlsdrError::Usb(rusb::Error::NoDevice))
+    ));
+    assert!(matches!(
+        device.demod_write_reg(1, 0x01, 0x14, RegWidth::Byte),
+        Err(RtlsdrError::Usb(rusb::Error::NoDevice))
+    ));
+}
+
 #[test]
 #[should_panic]
 fn test_read_eeprom_out_of_range() {
     let mock_handle = MockDeviceHandle::new();
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let mut data = [0; 5];
     // Try to read more than eeprom size - should panic
     device.read_eeprom(&mut data, 0, EEPROM_SIZE).unwrap();
@@ -189,22 +212,17 @@ fn test_read_eeprom_reads_expected_data() {
         )
         .returning(|_, _, _, _, _, _| Ok(1)); // Return success
 
-    // Expect the read_control call for reading the data
+    // Expect a single read_control call for the whole chunk
     let expected_data = [0x12, 0x34, 0x56, 0x78, 0x9A];
     mock_handle
         .expect_read_control()
-        .times(expected_data.len()) // This will be called len times
+        .times(1)
         .returning(move |_, _, _, _, buf, _| {
-            static mut CALL_COUNT: usize = 0;
-            let call_count = unsafe { CALL_COUNT };
-            buf[0] = expected_data[call_count];
-            unsafe { CALL_COUNT += 1 };
-            Ok(1) // Return success
+            buf.copy_from_slice(&expected_data);
+            Ok(buf.len())
         });
 
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let mut data = [0; 5];
     let data_len = data.len();
     device.read_eeprom(&mut data, 0, data_len).unwrap();
@@ -233,24 +251,73 @@ fn test_read_eeprom_partial_read() {
     let expected_data = [0xAB, 0xCD];
     mock_handle
         .expect_read_control()
-        .times(expected_data.len())  // Expecting 2 calls, one for each byte
+        .times(1)  // Both bytes fit in a single chunk
         .returning(move |_, _, _, _, buf, _| {
-            static mut CALL_COUNT: usize = 0;
-            let call_count = unsafe { CALL_COUNT };
-            buf[0] = expected_data[call_count];
-            unsafe { CALL_COUNT += 1 };
-            Ok(1)
+            buf.copy_from_slice(&expected_data);
+            Ok(buf.len())
         });
 
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let mut data = [0; 2];
     let data_len = data.len();
     device.read_eeprom(&mut data, 0, data_len).unwrap();
     assert_eq!(data, expected_data);
 }
 
+#[test]
+fn test_read_eeprom_chunked() {
+    let mut mock_handle = MockDeviceHandle::new();
+
+    mock_handle
+        .expect_write_control()
+        .times(1)
+        .with(
+            eq(CTRL_OUT),
+            eq(0),
+            eq(EEPROM_ADDR),
+            eq((BLOCK_IIC << 8) | 0x10),
+            eq([0x10]), // Setting the offset to 0x10
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(|_, _, _, _, _, _| Ok(1));
+
+    // One full chunk followed by the remainder
+    let expected_data: Vec<u8> = (0..EEPROM_READ_CHUNK as u8 + 4).collect();
+    let mut pos = 0;
+    let source = expected_data.clone();
+    mock_handle
+        .expect_read_control()
+        .times(2)
+        .returning(move |_, _, _, _, buf, _| {
+            buf.copy_from_slice(&source[pos..pos + buf.len()]);
+            pos += buf.len();
+            Ok(buf.len())
+        });
+
+    let device = Device::from_handle(mock_handle);
+    let mut data = vec![0; expected_data.len()];
+    let data_len = data.len();
+    device.read_eeprom(&mut data, 0x10, data_len).unwrap();
+    assert_eq!(data, expected_data);
+}
+
+#[test]
+fn test_read_eeprom_short_read() {
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_write_control()
+        .times(1)
+        .returning(|_, _, _, _, _, _| Ok(1));
+    mock_handle
+        .expect_read_control()
+        .times(1)
+        .returning(|_, _, _, _, _, _| Ok(1));
+
+    let device = Device::from_handle(mock_handle);
+    let mut data = [0; 4];
+    assert!(device.read_eeprom(&mut data, 0, 4).is_err());
+}
+
 #[test]
 fn test_read_eeprom_larger_buffer() {
     let mut mock_handle = MockDeviceHandle::new();
@@ -273,18 +340,14 @@ fn test_read_eeprom_larger_buffer() {
     let expected_data = [0xDE, 0xAD];
     mock_handle
         .expect_read_control()
-        .times(expected_data.len())  // Expecting 2 calls, one for each byte
+        .times(1)
         .returning(move |_, _, _, _, buf, _| {
-            static mut CALL_COUNT: usize = 0;
-            let call_count = unsafe { CALL_COUNT };
-            buf[0] = expected_data[call_count];
-            unsafe { CALL_COUNT += 1 };
-            Ok(1)
+            assert_eq!(buf.len(), expected_data.len());
+            buf.copy_from_slice(&expected_data);
+            Ok(buf.len())
         });
 
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let mut data = [0xFF; 4];
     device.read_eeprom(&mut data, 0, 2).unwrap();  // Reading only 2 bytes
     assert_eq!(data[..2], expected_data);  // Verify the first 2 bytes
@@ -295,11 +358,798 @@ fn test_read_eeprom_larger_buffer() {
 #[should_panic]
 fn test_read_eeprom_invalid_offset() {
     let mock_handle = MockDeviceHandle::new();
-    let device = Device {
-        handle: mock_handle,
-    };
+    let device = Device::from_handle(mock_handle);
     let mut data = [0; 5];
     let data_len = data.len();
     // This should panic because the offset + length exceeds EEPROM_SIZE
     device.read_eeprom(&mut data, EEPROM_SIZE as u8, data_len).unwrap();
 }
+
+/// Back the mock with a simulated EEPROM, returning a counter of data writes
+fn fake_eeprom(
+    mock_handle: &mut MockDeviceHandle,
+    mem: Arc<Mutex<[u8; EEPROM_SIZE]>>,
+    write_protected: bool,
+) -> Arc<Mutex<usize>> {
+    let ptr = Arc::new(Mutex::new(0_usize));
+    let writes = Arc::new(Mutex::new(0_usize));
+    let (wptr, wmem, wcount) = (ptr.clone(), mem.clone(), writes.clone());
+    mock_handle
+        .expect_write_control()
+        .with(
+            eq(CTRL_OUT),
+            eq(0),
+            eq(EEPROM_ADDR),
+            eq((BLOCK_IIC << 8) | 0x10),
+            predicate::always(),
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(move |_, _, _, _, data, _| {
+            *wptr.lock().unwrap() = data[0] as usize;
+            if data.len() == 2 {
+                *wcount.lock().unwrap() += 1;
+                if !write_protected {
+                    wmem.lock().unwrap()[data[0] as usize] = data[1];
+                }
+            }
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, _, _, buf, _| {
+            let mut p = ptr.lock().unwrap();
+            buf.copy_from_slice(&mem.lock().unwrap()[*p..*p + buf.len()]);
+            *p += buf.len();
+            Ok(buf.len())
+        });
+    writes
+}
+
+#[test]
+fn test_write_eeprom_skips_unchanged_bytes() {
+    let mut mock_handle = MockDeviceHandle::new();
+    let mem = Arc::new(Mutex::new([0_u8; EEPROM_SIZE]));
+    mem.lock().unwrap()[..2].copy_from_slice(&[0x28, 0x00]);
+    let writes = fake_eeprom(&mut mock_handle, mem.clone(), false);
+
+    let device = Device::from_handle(mock_handle);
+    assert_eq!(2, device.write_eeprom(&[0x28, 0x32], 0).unwrap());
+    assert_eq!(1, *writes.lock().unwrap());
+    assert_eq!([0x28, 0x32], mem.lock().unwrap()[..2]);
+}
+
+#[test]
+fn test_write_eeprom_across_pages() {
+    let mut mock_handle = MockDeviceHandle::new();
+    let mem = Arc::new(Mutex::new([0_u8; EEPROM_SIZE]));
+    let writes = fake_eeprom(&mut mock_handle, mem.clone(), false);
+
+    let data: Vec<u8> = (1..=12).collect();
+    let device = Device::from_handle(mock_handle);
+    device.write_eeprom(&data, 5).unwrap();
+    assert_eq!(12, *writes.lock().unwrap());
+    assert_eq!(data[..], mem.lock().unwrap()[5..17]);
+}
+
+#[test]
+fn test_write_eeprom_write_protected() {
+    let mut mock_handle = MockDeviceHandle::new();
+    let mem = Arc::new(Mutex::new([0_u8; EEPROM_SIZE]));
+    fake_eeprom(&mut mock_handle, mem, true);
+
+    let device = Device::from_handle(mock_handle);
+    let err = device.write_eeprom(&[0x28, 0x32], 0).unwrap_err();
+    assert!(matches!(
+        err,
+        RtlsdrError::Eeprom(EepromError::WriteProtected)
+    ));
+}
+
+#[test]
+fn test_write_eeprom_flags_only_touches_flags() {
+    let mut mock_handle = MockDeviceHandle::new();
+    let mem = Arc::new(Mutex::new([0xff_u8; EEPROM_SIZE]));
+    // A valid header followed by strings that don't parse
+    mem.lock().unwrap()[..10]
+        .copy_from_slice(&[0x28, 0x32, 0xda, 0x0b, 0x38, 0x28, 0xa5, 0x02, 0x03, 0x7f]);
+    let before = *mem.lock().unwrap();
+    let writes = fake_eeprom(&mut mock_handle, mem.clone(), false);
+
+    let sdr = RtlSdr::new(Device::from_handle(mock_handle));
+    sdr.write_eeprom_flags(FLAG_ENABLE_IR | FLAG_REMOTE_WAKEUP, FLAG_REMOTE_WAKEUP)
+        .unwrap();
+    assert_eq!(1, *writes.lock().unwrap());
+    let mut expected = before;
+    expected[FLAGS_OFFSET] = FLAG_REMOTE_WAKEUP;
+    assert_eq!(expected, *mem.lock().unwrap());
+
+    // Images without a header are left alone
+    mem.lock().unwrap()[..2].fill(0xff);
+    assert!(sdr.write_eeprom_flags(FLAG_REMOTE_WAKEUP, 0).is_err());
+    assert_eq!(1, *writes.lock().unwrap());
+}
+
+#[test]
+fn test_write_reg_mask() {
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .times(1)
+        .returning(|_, _, _, _, data, _| {
+            data[0] = 0b1010_0101;
+            Ok(1)
+        });
+    mock_handle
+        .expect_write_control()
+        .times(1)
+        .with(
+            eq(CTRL_OUT),
+            eq(0),
+            eq(GPO),
+            eq((BLOCK_SYS << 8) | 0x10),
+            eq([0b1010_1001_u8]),
+            eq(CTRL_TIMEOUT),
+        )
+        .returning(|_, _, _, _, _, _| Ok(1));
+    let device = Device::from_handle(mock_handle);
+    device.write_reg_mask(BLOCK_SYS, GPO, 0b0000_1000, 0b0000_1100).unwrap();
+}
+
+#[test]
+fn test_invalid_lengths() {
+    // Nothing reaches the handle, which has no expectations
+    let device = Device::from_handle(MockDeviceHandle::new());
+    assert!(device.write_array(BLOCK_SYS, GPO, &[0; 2], 3).is_err());
+    let mut buf = [0_u8; 16];
+    assert!(device.read_eeprom(&mut buf, 0, 32).is_err());
+    assert!(device
+        .read_eeprom(&mut buf, (EEPROM_SIZE - 8) as u8, 16)
+        .is_err());
+    assert!(device.write_eeprom(&buf, (EEPROM_SIZE - 8) as u8).is_err());
+}
+
+#[test]
+fn test_paranoid() {
+    // Every register reads back 0x09, and the reads are kept
+    let reads = Arc::new(Mutex::new(Vec::new()));
+    let mut mock_handle = MockDeviceHandle::new();
+    let kept = reads.clone();
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, value, index, data, _| {
+            kept.lock().unwrap().push((value, index));
+            data.fill(0x09);
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_write_control()
+        .returning(|_, _, _, _, data, _| Ok(data.len()));
+    let device = Device::from_handle(mock_handle);
+    device
+        .write_reg(BLOCK_SYS, DEMOD_CTL, 0xe8, RegWidth::Byte)
+        .unwrap();
+    assert!(reads.lock().unwrap().is_empty());
+
+    device.set_paranoid(true);
+    device
+        .write_reg(BLOCK_USB, USB_SYSCTL, 0x09, RegWidth::Byte)
+        .unwrap();
+    assert_eq!(0, device.write_mismatches());
+    device
+        .write_reg(BLOCK_SYS, DEMOD_CTL, 0xe8, RegWidth::Byte)
+        .unwrap();
+    assert_eq!(1, device.write_mismatches());
+    device
+        .demod_write_reg(1, 0x15, 0x01, RegWidth::Byte)
+        .unwrap();
+    assert_eq!(2, device.write_mismatches());
+    // Read backs use the write's value and index without the write flag,
+    // and the demod status read still follows the demod write
+    assert_eq!(
+        vec![
+            (USB_SYSCTL, BLOCK_USB << 8),
+            (DEMOD_CTL, BLOCK_SYS << 8),
+            ((0x15 << 8) | 0x20, 1),
+            ((0x01 << 8) | 0x20, 0x0a),
+        ],
+        *reads.lock().unwrap()
+    );
+}
+
+#[test]
+fn test_dump_line() {
+    assert_eq!(
+        "c0 00 3001 0200 1: 12",
+        dump_line(CTRL_IN, GPO, BLOCK_SYS << 8, 1, &[0x12], &Ok(1))
+    );
+    assert_eq!(
+        "40 00 0120 0110 2: 00 09",
+        dump_line(CTRL_OUT, 0x0120, 0x0110, 2, &[0x00, 0x09], &Ok(2))
+    );
+    let failed = Err(RtlsdrError::Usb(rusb::Error::Timeout));
+    assert_eq!(
+        "c0 00 0120 000a 2: failed: Operation timed out",
+        dump_line(CTRL_IN, 0x0120, 0x000a, 2, &[], &failed)
+    );
+}
+
+#[test]
+fn test_settings() {
+    // Accepts every transfer, reading zeros, and keeps the writes to the
+    // soft reset and I2C repeater register
+    let soft_rst = Arc::new(Mutex::new(Vec::new()));
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .returning(|_, _, _, _, data, _| Ok(data.len()));
+    let writes = soft_rst.clone();
+    mock_handle
+        .expect_write_control()
+        .returning(move |_, _, value, index, data, _| {
+            if (value, index) == ((0x01 << 8) | 0x20, 0x11) {
+                writes.lock().unwrap().push(data[0]);
+            }
+            Ok(data.len())
+        });
+    let mut sdr = RtlSdr::new(Device::from_handle(mock_handle));
+    let settings = Settings {
+        center_freq: 7_100_000,
+        sample_rate: 2_048_000,
+        gain: TunerGain::Manual(280),
+        ppm: -3,
+        bandwidth: 0,
+        bias_tee: true,
+        direct_sampling: DirectSampleMode::AutoBelow(24_000_000),
+        agc: true,
+    };
+    sdr.apply_settings(&settings).unwrap();
+    let applied = sdr.settings();
+    // The sample rate is the closest the resampler can make
+    assert!(applied.sample_rate.abs_diff(settings.sample_rate) <= 1);
+    assert_eq!(
+        settings,
+        Settings {
+            sample_rate: settings.sample_rate,
+            ..applied
+        }
+    );
+    // The repeater is switched on once, and back on after the demod reset
+    assert_eq!(
+        vec![0x18, 0x14, 0x10, 0x18, 0x10],
+        *soft_rst.lock().unwrap()
+    );
+
+    sdr.set_testmode(true).unwrap();
+    assert!(!sdr.settings().agc);
+    sdr.set_bias_tee_gpio(5, false).unwrap();
+    assert!(sdr.settings().bias_tee);
+}
+
+/// An EEPROM as written by rtl_eeprom but with empty strings. IR is enabled
+/// and remote wakeup off, so neither the bias tee nor direct sampling is forced.
+fn blank_eeprom() -> [u8; EEPROM_SIZE] {
+    let mut eeprom = [0xff; EEPROM_SIZE];
+    eeprom[..15].copy_from_slice(&[
+        0x28, 0x32, 0xda, 0x0b, 0x38, 0x28, 0xa5, 0x02, 0x02, 0x02, 0x03, 0x02, 0x03, 0x02, 0x03,
+    ]);
+    eeprom
+}
+
+/// Control writes made to a `logged_device`, as (value, index, data)
+type Writes = Arc<Mutex<Vec<(u16, u16, Vec<u8>)>>>;
+
+/// A device with an R820T, if `r820t`, whose registers all read as the chip
+/// ID, so the tuner is found but its PLL lock bit is never set. Other
+/// registers read zero. The EEPROM is blank, or reads fail if `eeprom_fails`.
+fn mock_device(r820t: bool, eeprom_fails: bool) -> Device {
+    logged_device(r820t, eeprom_fails).0
+}
+
+/// A `mock_device` that also logs every control write
+fn logged_device(r820t: bool, eeprom_fails: bool) -> (Device, Writes) {
+    eeprom_device(r820t, (!eeprom_fails).then(blank_eeprom))
+}
+
+/// A `logged_device` serving the given EEPROM image, or failing EEPROM reads
+/// if there is none
+fn eeprom_device(r820t: bool, eeprom: Option<[u8; EEPROM_SIZE]>) -> (Device, Writes) {
+    let writes = Writes::default();
+    let log = writes.clone();
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle.expect_claim_interface().returning(|_| Ok(()));
+    let ptr = Arc::new(Mutex::new(0_usize));
+    let wptr = ptr.clone();
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, value, index, data, _| {
+            let tuner = (TUNER_INFO.i2c_addr as u16, BLOCK_IIC << 8);
+            if (value, index) == (EEPROM_ADDR, BLOCK_IIC << 8) {
+                let Some(eeprom) = eeprom else {
+                    return Err(RtlsdrError::Usb(rusb::Error::Pipe));
+                };
+                let mut p = ptr.lock().unwrap();
+                data.copy_from_slice(&eeprom[*p..*p + data.len()]);
+                *p += data.len();
+                return Ok(data.len());
+            }
+            let chip_id = r820t && (value, index) == tuner;
+            data.fill(if chip_id { 0x69 } else { 0 });
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_write_control()
+        .returning(move |_, _, value, index, data, _| {
+            if value == EEPROM_ADDR {
+                *wptr.lock().unwrap() = data[0] as usize;
+            }
+            log.lock().unwrap().push((value, index, data.to_vec()));
+            Ok(data.len())
+        });
+    (Device::from_handle(mock_handle), writes)
+}
+
+/// The last value written to R820T register `reg`
+fn tuner_reg(writes: &Writes, reg: u8) -> Option<u8> {
+    let tuner = (TUNER_INFO.i2c_addr as u16, (BLOCK_IIC << 8) | 0x10);
+    writes
+        .lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .filter(|(value, index, data)| (*value, *index) == tuner && data.len() > 1)
+        .find_map(|(_, _, data)| {
+            let offset = reg.checked_sub(data[0])? as usize + 1;
+            data.get(offset).copied()
+        })
+}
+
+#[test]
+fn test_init_eeprom_failure() {
+    let mut sdr = RtlSdr::new(mock_device(true, true));
+    sdr.init().unwrap();
+
+    let mut sdr = RtlSdr::new(mock_device(true, true));
+    sdr.set_open_options(OpenOptions {
+        require_eeprom: true,
+        ..Default::default()
+    });
+    assert!(matches!(
+        sdr.init(),
+        Err(RtlsdrError::Usb(rusb::Error::Pipe))
+    ));
+}
+
+#[test]
+fn test_init_no_tuner() {
+    let mut sdr = RtlSdr::new(mock_device(false, false));
+    assert!(sdr.init().is_err());
+
+    let (device, writes) = logged_device(false, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_open_options(OpenOptions {
+        allow_no_tuner: true,
+        ..Default::default()
+    });
+    sdr.init().unwrap();
+    assert_eq!(DirectSampleMode::On, sdr.settings().direct_sampling);
+    sdr.set_center_freq(7_100_000).unwrap();
+    // Only the probe for the chip ID reaches the tuner address
+    let tuner = TUNER_INFO.i2c_addr as u16;
+    assert!(writes
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|(value, _, data)| *value != tuner || *data == [TUNER_INFO.check_addr]));
+}
+
+#[test]
+fn test_pll_not_locked() {
+    let mut sdr = RtlSdr::new(mock_device(true, false));
+    sdr.init().unwrap();
+    // Retuning to the unset frequency doesn't fail the rate change
+    sdr.set_sample_rate(2_048_000).unwrap();
+
+    match sdr.set_center_freq(100_000_000) {
+        Err(RtlsdrError::PllNotLocked(e)) => assert_eq!(100_000_000, e.freq),
+        result => panic!("Expected PllNotLocked, got {:?}", result),
+    }
+    assert!(!sdr.pll_locked().unwrap());
+    // Settings that retune still apply
+    sdr.set_freq_correction(5).unwrap();
+}
+
+#[test]
+fn test_tune_past_u32() {
+    let mut sdr = RtlSdr::new(mock_device(true, false));
+    sdr.init().unwrap();
+    // The LO, above the IF, doesn't fit in a u32
+    assert!(matches!(
+        sdr.set_center_freq(u32::MAX),
+        Err(RtlsdrError::RtlsdrErr(_))
+    ));
+}
+
+#[test]
+fn test_notch_and_tracking_filter() {
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    // The mock PLL never locks, but the tuner is still programmed
+    assert!(sdr.set_center_freq(100_000_000).is_err());
+    // The 100 MHz band defaults: notch off, tracking filter in circuit
+    assert_eq!(Some(0x00), tuner_reg(&writes, 0x17).map(|r| r & 0x08));
+    assert_eq!(Some(0x00), tuner_reg(&writes, 0x1a).map(|r| r & 0xc0));
+    assert_eq!(Some(0x34), tuner_reg(&writes, 0x1b));
+
+    sdr.set_notch_filter(NotchFilter::On).unwrap();
+    assert_eq!(Some(0x08), tuner_reg(&writes, 0x17).map(|r| r & 0x08));
+    sdr.set_notch_filter(NotchFilter::Off).unwrap();
+    assert_eq!(Some(0x00), tuner_reg(&writes, 0x17).map(|r| r & 0x08));
+
+    sdr.set_tracking_filter(TrackingFilter::Bypass).unwrap();
+    assert_eq!(Some(0x40), tuner_reg(&writes, 0x1a).map(|r| r & 0xc0));
+    sdr.set_tracking_filter(TrackingFilter::Manual(0x42))
+        .unwrap();
+    assert_eq!(Some(0x00), tuner_reg(&writes, 0x1a).map(|r| r & 0xc0));
+    assert_eq!(Some(0x42), tuner_reg(&writes, 0x1b));
+    sdr.set_tracking_filter(TrackingFilter::Auto).unwrap();
+    assert_eq!(Some(0x34), tuner_reg(&writes, 0x1b));
+}
+
+/// The frequency the R820T VCO is programmed for, decoded from the PLL
+/// integer (R20) and sigma-delta (R21, R22) registers
+fn tuner_vco_freq(writes: &Writes, xtal: u32) -> f64 {
+    let reg = |r| tuner_reg(writes, r).unwrap() as u32;
+    let nint = 4 * (reg(0x14) & 0x3f) + (reg(0x14) >> 6) + 13;
+    let sdm = (reg(0x16) << 8) | reg(0x15);
+    2.0 * xtal as f64 * (nint as f64 + sdm as f64 / 65536.0)
+}
+
+#[test]
+fn test_harmonic_mode() {
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    sdr.set_sample_rate(2_048_000).unwrap();
+    let xtal = sdr.get_tuner_xtal_freq();
+    let lo = 2_400_000_000.0 + sdr.get_if_frequency().unwrap() as f64;
+
+    // The fundamental can't reach 2.4 GHz
+    assert!(matches!(
+        sdr.set_center_freq(2_400_000_000),
+        Err(RtlsdrError::RtlsdrErr(_))
+    ));
+
+    // With the 3rd harmonic the PLL runs at a third of the LO, with the VCO
+    // divided by 4. The mock PLL never locks.
+    sdr.set_harmonic_mode(3).unwrap();
+    assert!(matches!(
+        sdr.set_center_freq(2_400_000_000),
+        Err(RtlsdrError::PllNotLocked(_))
+    ));
+    let pll = tuner_vco_freq(&writes, xtal) / 4.0;
+    assert!((pll - lo / 3.0).abs() < 1_000.0, "{}", pll);
+    let actual = sdr.get_actual_center_freq().unwrap();
+    assert!((actual - 2_400_000_000.0).abs() < 1_000.0, "{}", actual);
+
+    // Changing the harmonic retunes
+    sdr.set_harmonic_mode(5).unwrap();
+    let pll = tuner_vco_freq(&writes, xtal) / 4.0;
+    assert!((pll - lo / 5.0).abs() < 1_000.0, "{}", pll);
+    let actual = sdr.get_actual_center_freq().unwrap();
+    assert!((actual - 2_400_000_000.0).abs() < 1_000.0, "{}", actual);
+}
+
+#[test]
+fn test_harmonic_mode_range() {
+    let mut sdr = RtlSdr::new(mock_device(true, false));
+    sdr.init().unwrap();
+    let caps = sdr.get_tuner_info().unwrap().caps;
+    assert_eq!((24_000_000, 1_766_000_000), (caps.min_freq, caps.max_freq));
+    sdr.set_harmonic_mode(3).unwrap();
+    let caps = sdr.get_tuner_info().unwrap().caps;
+    assert_eq!((72_000_000, u32::MAX), (caps.min_freq, caps.max_freq));
+}
+
+#[test]
+fn test_set_tuner_if_gain() {
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    assert_eq!(1, sdr.get_tuner_info().unwrap().caps.if_gain_stages);
+    assert!(sdr.set_tuner_if_gain(1, 0).is_err());
+
+    // The closest VGA step to 16 dB is 16.3 dB
+    sdr.set_tuner_if_gain(0, 160).unwrap();
+    assert_eq!(Some(0x08), tuner_reg(&writes, 0x0c).map(|r| r & 0x9f));
+    sdr.set_tuner_if_gain(0, -100).unwrap();
+    assert_eq!(Some(0x00), tuner_reg(&writes, 0x0c).map(|r| r & 0x9f));
+    sdr.set_tuner_if_gain(0, 1000).unwrap();
+    assert_eq!(Some(0x0f), tuner_reg(&writes, 0x0c).map(|r| r & 0x9f));
+
+    // Setting the tuner gain keeps the IF gain
+    sdr.set_tuner_gain(TunerGain::Auto).unwrap();
+    assert_eq!(Some(0x0f), tuner_reg(&writes, 0x0c).map(|r| r & 0x9f));
+    sdr.set_tuner_gain(TunerGain::Manual(200)).unwrap();
+    assert_eq!(Some(0x0f), tuner_reg(&writes, 0x0c).map(|r| r & 0x9f));
+}
+
+#[test]
+fn test_tuner_bandwidths() {
+    const LOW_PASS: [u32; 16] = [
+        0, 0, 0, 0, 0, 0, 350_000, 450_000, 550_000, 700_000, 900_000, 1_200_000, 1_450_000,
+        1_550_000, 1_600_000, 1_700_000,
+    ];
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    for &bw in sdr.get_tuner_info().unwrap().caps.bandwidths {
+        sdr.set_tuner_bandwidth(bw).unwrap();
+        let reg_0b = tuner_reg(&writes, 0x0b).unwrap();
+        // Decode the filter setting: the low-pass corner plus any high-pass
+        // corners left enabled, or one of the fixed wide settings
+        let actual = match reg_0b & 0xef {
+            0x0b => 8_000_000,
+            0x2a => 7_000_000,
+            0x6b => 6_000_000,
+            _ => {
+                let hp1 = if reg_0b & 0x40 == 0 { 350_000 } else { 0 };
+                let hp2 = if reg_0b & 0x20 == 0 { 380_000 } else { 0 };
+                LOW_PASS[(reg_0b & 0x0f) as usize] + hp1 + hp2
+            }
+        };
+        assert_eq!(bw, actual);
+    }
+}
+
+#[test]
+fn test_filter_calibration_keeps_pll_state() {
+    // Tuner reads report a PLL lock only while `locked` is set. Reads are bit
+    // reversed, so 0x02 arrives as the 0x40 lock bit.
+    let locked = Arc::new(AtomicBool::new(false));
+    let lock = locked.clone();
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, _, _, data, _| {
+            data.fill(if lock.load(Ordering::Relaxed) {
+                0x02
+            } else {
+                0
+            });
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_write_control()
+        .returning(|_, _, _, _, data, _| Ok(data.len()));
+    let device = Device::from_handle(mock_handle);
+    let mut tuner = R820T::new(&device);
+    tuner.set_xtal_freq(28_800_000).unwrap();
+    tuner.set_freq(&device, 100_000_000).unwrap();
+    let lo = tuner.get_lo_freq().unwrap();
+    assert!(!tuner.get_pll_locked().unwrap());
+
+    // The calibration locks the PLL at 56 MHz, then hands it back
+    locked.store(true, Ordering::Relaxed);
+    tuner.recalibrate_filter(&device, 1).unwrap();
+    assert_eq!(lo, tuner.get_lo_freq().unwrap());
+    assert!(!tuner.get_pll_locked().unwrap());
+}
+
+#[test]
+fn test_read_rssi() {
+    // R3 reads back the gain the tuner AGC picked. Reads are bit reversed, so
+    // the raw byte is reversed here to report `gain` as the LNA step.
+    let gain = Arc::new(AtomicU8::new(0));
+    let level = gain.clone();
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle.expect_claim_interface().returning(|_| Ok(()));
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, value, index, data, _| {
+            if (value, index) != (TUNER_INFO.i2c_addr as u16, BLOCK_IIC << 8) {
+                data.fill(0);
+            } else if data.len() == 4 {
+                data.fill(0);
+                data[3] = level.load(Ordering::Relaxed).reverse_bits();
+            } else {
+                data.fill(0x69);
+            }
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_write_control()
+        .returning(|_, _, _, _, data, _| Ok(data.len()));
+    let mut sdr = RtlSdr::new(Device::from_handle(mock_handle));
+    sdr.init().unwrap();
+
+    // A stronger signal makes the AGC back the gain off
+    gain.store(12, Ordering::Relaxed);
+    assert_eq!(-24, sdr.read_rssi().unwrap());
+    gain.store(3, Ordering::Relaxed);
+    assert_eq!(-6, sdr.read_rssi().unwrap());
+
+    // A manual gain doesn't follow the signal
+    sdr.set_tuner_gain(TunerGain::Manual(280)).unwrap();
+    assert!(sdr.read_rssi().is_err());
+}
+
+#[test]
+fn test_auto_direct_sampling() {
+    let mut sdr = RtlSdr::new(mock_device(true, false));
+    sdr.init().unwrap();
+    // Nothing is tuned until there's a frequency. The mock PLL never locks,
+    // so only direct sampling reports a lock.
+    sdr.set_direct_sampling(DirectSampleMode::AutoBelow(24_000_000))
+        .unwrap();
+    assert!(!sdr.pll_locked().unwrap());
+
+    sdr.set_center_freq(7_100_000).unwrap();
+    assert!(sdr.pll_locked().unwrap());
+    assert!(sdr.set_center_freq(24_000_000).is_err());
+    assert!(!sdr.pll_locked().unwrap());
+    sdr.set_center_freq(23_999_999).unwrap();
+
+    // Leaving auto mode retunes in the new mode
+    sdr.set_direct_sampling(DirectSampleMode::Off).unwrap();
+    assert!(!sdr.pll_locked().unwrap());
+    assert_eq!(23_999_999, sdr.get_center_freq());
+}
+
+#[test]
+fn test_auto_direct_sampling_forced() {
+    // The remote wakeup bit forces direct sampling
+    let mut eeprom = blank_eeprom();
+    eeprom[7] |= 0x01;
+    let (device, writes) = eeprom_device(true, Some(eeprom));
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_open_options(OpenOptions {
+        honor_eeprom_overrides: true,
+        ..Default::default()
+    });
+    sdr.init().unwrap();
+    sdr.set_direct_sampling(DirectSampleMode::AutoBelow(24_000_000))
+        .unwrap();
+    sdr.set_center_freq(7_100_000).unwrap();
+
+    // Tuning above the threshold stays in direct sampling without touching
+    // the tuner
+    let tuned = writes.lock().unwrap().len();
+    sdr.set_center_freq(100_000_000).unwrap();
+    sdr.set_center_freq(101_000_000).unwrap();
+    assert!(sdr.pll_locked().unwrap());
+    let tuner = TUNER_INFO.i2c_addr as u16;
+    assert!(writes.lock().unwrap()[tuned..]
+        .iter()
+        .all(|(value, _, _)| *value != tuner));
+}
+
+#[test]
+fn test_init_forced_direct_sampling() {
+    let mut eeprom = blank_eeprom();
+    eeprom[7] |= 0x01;
+    let (device, writes) = eeprom_device(true, Some(eeprom));
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_open_options(OpenOptions {
+        honor_eeprom_overrides: true,
+        ..Default::default()
+    });
+    sdr.init().unwrap();
+    assert_eq!(DirectSampleMode::OnSwap, sdr.settings().direct_sampling);
+    assert_eq!("", sdr.get_tuner_info().unwrap().id);
+
+    // The tuner is probed but never initialized or tuned
+    sdr.set_center_freq(100_000_000).unwrap();
+    let tuner = TUNER_INFO.i2c_addr as u16;
+    assert!(writes
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|(value, _, data)| *value != tuner || *data == [TUNER_INFO.check_addr]));
+}
+
+#[test]
+fn test_freq_correction_before_tuning() {
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    // Nothing to retune yet, so the tuner isn't touched
+    let before = writes.lock().unwrap().len();
+    sdr.set_freq_correction(5).unwrap();
+    let tuner = TUNER_INFO.i2c_addr as u16;
+    assert!(writes.lock().unwrap()[before..]
+        .iter()
+        .all(|(value, _, _)| *value != tuner));
+    assert_eq!(0, sdr.get_center_freq());
+}
+
+#[test]
+fn test_set_xtal_freq() {
+    let mut sdr = RtlSdr::new(mock_device(true, false));
+    sdr.init().unwrap();
+    assert!(sdr.set_xtal_freq(28_801_001, 0).is_err());
+    assert!(sdr.set_xtal_freq(28_800_500, 1_000_000).is_err());
+    assert!(sdr.set_xtal_freq(0, 48_000_000).is_err());
+    // A rejected tuner clock leaves the RTL2832 clock alone too
+    assert_eq!(28_800_000, sdr.get_xtal_freq());
+
+    // The R828D on a Blog V4 runs from a 16 MHz crystal
+    sdr.set_xtal_freq(28_800_500, 16_000_000).unwrap();
+    assert_eq!(28_800_500, sdr.get_xtal_freq());
+    assert_eq!(16_000_000, sdr.get_tuner_xtal_freq());
+    sdr.set_xtal_freq(0, 0).unwrap();
+    assert_eq!(28_800_500, sdr.get_tuner_xtal_freq());
+}
+
+#[test]
+fn test_get_ir_truncates() {
+    // A 6 byte code is waiting in the IR buffer
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .returning(|_, _, value, index, data, _| {
+            match (value, index) {
+                (IR_RX_IF, i) if i == BLOCK_IRB << 8 => data[0] = 0x83,
+                (IR_RX_BC, i) if i == BLOCK_IRB << 8 => data[0] = 6,
+                (IR_RX_BUF, i) if i == BLOCK_IRB << 8 => {
+                    data.copy_from_slice(&[0x81, 0x02, 0x83, 0x04, 0x85, 0x06][..data.len()])
+                }
+                _ => data.fill(0),
+            }
+            Ok(data.len())
+        });
+    let refreshed = Arc::new(AtomicBool::new(false));
+    let refresh = refreshed.clone();
+    mock_handle
+        .expect_write_control()
+        .returning(move |_, _, value, index, data, _| {
+            if (value, index) == (IR_RX_BUF_CTRL, (BLOCK_IRB << 8) | 0x10) {
+                refresh.store(true, Ordering::Relaxed);
+            }
+            Ok(data.len())
+        });
+    let mut sdr = RtlSdr::new(Device::from_handle(mock_handle));
+
+    let mut buf = [0; 4];
+    assert_eq!(4, sdr.get_ir(&mut buf).unwrap());
+    assert_eq!([0x81, 0x02, 0x83, 0x04], buf);
+    // The buffer is released for the next code
+    assert!(refreshed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_set_gpio_pattern() {
+    let gpo = |writes: &Writes| {
+        let writes = writes.lock().unwrap();
+        let gpo = writes
+            .iter()
+            .rev()
+            .find(|(value, index, _)| (*value, *index) == (GPO, (BLOCK_SYS << 8) | 0x10));
+        gpo.map(|(_, _, data)| data[0])
+    };
+    let (device, writes) = logged_device(true, false);
+    let mut sdr = RtlSdr::new(device);
+    sdr.init().unwrap();
+    sdr.set_gpio_pattern(0b011, 0b010).unwrap();
+    assert_eq!(Some(0b010), gpo(&writes));
+    // GPIO 0 is the bias tee
+    sdr.set_gpio_pattern(0b001, 0b001).unwrap();
+    assert!(sdr.settings().bias_tee);
+    sdr.set_gpio_pattern(0b110, 0b000).unwrap();
+    assert!(sdr.settings().bias_tee);
+    sdr.set_gpio_pattern(0b001, 0b000).unwrap();
+    assert!(!sdr.settings().bias_tee);
+
+    // A cleared IR bit forces the bias tee on GPIO 0 to stay high
+    let mut eeprom = blank_eeprom();
+    eeprom[7] &= !0x02;
+    let (device, writes) = eeprom_device(true, Some(eeprom));
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_open_options(OpenOptions {
+        honor_eeprom_overrides: true,
+        ..Default::default()
+    });
+    sdr.init().unwrap();
+    sdr.set_gpio_pattern(0b011, 0b010).unwrap();
+    assert_eq!(Some(0b011), gpo(&writes));
+}
@@ -2,14 +2,16 @@
 use crate::error::Result;
 use mockall::mock;
 
+use std::path::Path;
 use std::time::Duration;
 
 mock! {
     #[derive(Debug)]
     pub DeviceHandle {
         pub fn open(index: usize) -> Result<Self>;
-        pub fn claim_interface(&mut self, iface: u8) -> Result<()>;
-        pub fn reset(&mut self) -> Result<()>;
+        pub fn record_to(&mut self, path: &Path) -> Result<()>;
+        pub fn claim_interface(&self, iface: u8) -> Result<()>;
+        pub fn reset(&self) -> Result<()>;
         pub fn read_control(
             &self,
             request_type: u8,
@@ -1,42 +1,164 @@
 pub mod constants;
 pub use constants::*;
+pub mod demod;
+// The real handle is swapped for the mock in unit tests
+#[cfg_attr(test, allow(dead_code))]
 pub mod device_handle;
 #[cfg(test)]
-mod mock_device_handle;
+pub(crate) mod mock_device_handle;
+#[cfg(test)]
+mod replay;
+pub mod transcript;
 
 #[cfg(not(test))]
 use device_handle::DeviceHandle;
 #[cfg(test)]
 use mock_device_handle::MockDeviceHandle as DeviceHandle;
 
-use crate::error::Result;
-use byteorder::{ByteOrder, LittleEndian};
+use crate::error::{EepromError, Result};
+use crate::error::RtlsdrError::RtlsdrErr;
 /// Low-level io functions for interfacing with rusb(libusb)
-use log::{error, info};
+use log::{info, warn};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 
+#[cfg(test)]
+mod demod_test;
 #[cfg(test)]
 mod device_test;
+#[cfg(test)]
+mod transcript_test;
+
+/// Width of a register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegWidth {
+    Byte,
+    Word,
+}
+
+impl RegWidth {
+    fn len(self) -> usize {
+        match self {
+            RegWidth::Byte => 1,
+            RegWidth::Word => 2,
+        }
+    }
+
+    /// Registers are read little endian
+    fn decode(self, data: [u8; 2]) -> u16 {
+        match self {
+            RegWidth::Byte => data[0] as u16,
+            RegWidth::Word => u16::from_le_bytes(data),
+        }
+    }
+
+    /// Bytes to write from big endian `data`, since registers are written
+    /// big endian. A byte write takes the low byte.
+    fn encode(self, data: &[u8; 2]) -> &[u8] {
+        match self {
+            RegWidth::Byte => &data[1..],
+            RegWidth::Word => data,
+        }
+    }
+}
+
+/// When to read back the demod status register after a demod write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteVerify {
+    EveryWrite, // Read the status after each write (librtlsdr behavior)
+    Deferred,   // Skip per-write reads and do a single read on flush
+}
 
 #[derive(Debug)]
 pub struct Device {
     handle: DeviceHandle,
+    defer_verify: AtomicBool, // WriteVerify::Deferred
+    verify_pending: AtomicBool,
+    dump: AtomicBool,
+    paranoid: AtomicBool,
+    write_mismatches: AtomicU64,
 }
 
 impl Device {
     pub fn new(index: usize) -> Result<Device> {
-        Ok(Device {
-            handle: DeviceHandle::open(index)?,
-        })
+        Ok(Device::opened(DeviceHandle::open(index)?))
+    }
+
+    /// Open the device, logging all USB transactions (including init) to a
+    /// transcript file at `path`, appending if it exists
+    pub fn open_recording(index: usize, path: &Path) -> Result<Device> {
+        let mut handle = DeviceHandle::open(index)?;
+        handle.record_to(path)?;
+        Ok(Device::opened(handle))
     }
 
-    pub fn claim_interface(&mut self, iface: u8) -> Result<()> {
-        Ok(self.handle.claim_interface(iface)?)
+    /// A newly opened device, dumping control transfers from the start if
+    /// `RTLSDR_USB_DUMP` is set and verifying writes if `RTLSDR_PARANOID` is,
+    /// so both cover init
+    fn opened(handle: DeviceHandle) -> Device {
+        let device = Device::from_handle(handle);
+        device.set_usb_dump(std::env::var_os("RTLSDR_USB_DUMP").is_some());
+        device.set_paranoid(std::env::var_os("RTLSDR_PARANOID").is_some());
+        device
     }
 
-    pub fn test_write(&mut self) -> Result<()> {
+    pub(crate) fn from_handle(handle: DeviceHandle) -> Device {
+        Device {
+            handle,
+            defer_verify: AtomicBool::new(false),
+            verify_pending: AtomicBool::new(false),
+            dump: AtomicBool::new(false),
+            paranoid: AtomicBool::new(false),
+            write_mismatches: AtomicU64::new(0),
+        }
+    }
+
+    /// Log every control transfer at info level, one line each in the
+    /// order libusb_control_transfer takes its arguments, so a session can
+    /// be diffed against librtlsdr with the same call logged
+    pub fn set_usb_dump(&self, on: bool) {
+        self.dump.store(on, Ordering::Relaxed);
+    }
+
+    /// Read back every register write, logging a warning for each one that
+    /// reads back differently. Doubles the control transfers; for tracking
+    /// down hubs and dongles that drop transfers silently.
+    pub fn set_paranoid(&self, on: bool) {
+        self.paranoid.store(on, Ordering::Relaxed);
+    }
+
+    /// Register writes that read back differently while paranoid
+    pub fn write_mismatches(&self) -> u64 {
+        self.write_mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Set when demod writes are followed by a status read. Switching back to
+    /// `EveryWrite` flushes any deferred read.
+    pub fn set_write_verify(&self, verify: WriteVerify) -> Result<()> {
+        self.defer_verify
+            .store(verify == WriteVerify::Deferred, Ordering::Relaxed);
+        if verify == WriteVerify::EveryWrite {
+            self.flush_write_verify()?;
+        }
+        Ok(())
+    }
+
+    /// Perform the status read for any writes made since the last one
+    pub fn flush_write_verify(&self) -> Result<()> {
+        if self.verify_pending.swap(false, Ordering::Relaxed) {
+            self.demod_read_reg(0x0a, 0x1)?;
+        }
+        Ok(())
+    }
+
+    pub fn claim_interface(&self, iface: u8) -> Result<()> {
+        self.handle.claim_interface(iface)
+    }
+
+    pub fn test_write(&self) -> Result<()> {
         // try a dummy write and reset device if it fails
-        let len: usize = self.write_reg(BLOCK_USB, USB_SYSCTL, 0x09, 1)?;
+        let len: usize = self.write_reg(BLOCK_USB, USB_SYSCTL, 0x09, RegWidth::Byte)?;
         if len == 0 {
             info!("Resetting device...");
             self.handle.reset()?;
@@ -44,104 +166,137 @@ impl Device {
         Ok(())
     }
 
-    pub fn reset_demod(&self) -> Result<()> {
-        self.demod_write_reg(1, 0x01, 0x14, 1)?;
-        self.demod_write_reg(1, 0x01, 0x10, 1)?;
-        Ok(())
-    }
-
-    /// TODO: This only supports len of 1 or 2, maybe use an enum or make this generic?
-    pub fn read_reg(&self, block: u16, addr: u16, len: usize) -> Result<u16> {
-        assert!(len == 1 || len == 2);
+    pub fn read_reg(&self, block: u16, addr: u16, width: RegWidth) -> Result<u16> {
         let mut data: [u8; 2] = [0, 0];
         let index: u16 = block << 8;
-        self.handle
-            .read_control(CTRL_IN, 0, addr, index, &mut data[..len], CTRL_TIMEOUT)?;
-        // Read registers as little endian, but write as big; not sure why
-        Ok(LittleEndian::read_u16(&data))
+        let len = width.len();
+        self.control_in(addr, index, &mut data[..len])?;
+        Ok(width.decode(data))
     }
 
-    pub fn write_reg(&self, block: u16, addr: u16, val: u16, len: usize) -> Result<usize> {
-        assert!(len == 1 || len == 2);
-        // Read registers as little endian, but write as big; not sure why
-        let data: [u8; 2] = val.to_be_bytes();
-        let data_slice = if len == 1 { &data[1..2] } else { &data };
+    pub fn write_reg(&self, block: u16, addr: u16, val: u16, width: RegWidth) -> Result<usize> {
+        let data = val.to_be_bytes();
         let index = (block << 8) | 0x10;
-        // info!("write_reg addr: {:x} index: {:x} data: {:x?} data slice: {}", addr, index, data, data_slice.len());
-        Ok(self
-            .handle
-            .write_control(CTRL_OUT, 0, addr, index, data_slice, CTRL_TIMEOUT)?)
+        let n = self.control_out(addr, index, width.encode(&data))?;
+        self.verify_write(addr, index, width.encode(&data))?;
+        Ok(n)
+    }
+
+    /// Update only the bits of an 8-bit register selected by `mask`
+    pub fn write_reg_mask(&self, block: u16, addr: u16, val: u8, mask: u8) -> Result<()> {
+        let cur = self.read_reg(block, addr, RegWidth::Byte)? as u8;
+        let val = (val & mask) | (cur & !mask);
+        if val != cur {
+            self.write_reg(block, addr, val as u16, RegWidth::Byte)?;
+        }
+        Ok(())
     }
 
     /// Only supports u8 reads
     pub fn demod_read_reg(&self, page: u16, addr: u16) -> Result<u16> {
         let mut data = [0_u8];
         let index = page;
-        let _bytes = match self.handle.read_control(
-            CTRL_IN,
-            0,
-            (addr << 8) | 0x20,
-            index,
-            &mut data,
-            CTRL_TIMEOUT,
-        ) {
-            Ok(n) => {
-                // info!("demod_read_reg got {} bytes: [{:#02x}, {:#02x}] value: {:x}", n, data[0], data[1], BigEndian::read_u16(&data));
-                Ok(n)
-            }
-            Err(e) => {
-                error!(
-                    "demod_read_reg failed: {} page: {:#02x} addr: {:#02x}",
-                    e, page, addr
-                );
-                Err(e)
-            }
-        };
+        self.control_in((addr << 8) | 0x20, index, &mut data)?;
         let reg: u16 = data[0] as u16;
         Ok(reg)
     }
 
-    /// TODO: only supports len of 1 or 2, maybe use enum or make this generic
-    pub fn demod_write_reg(&self, page: u16, mut addr: u16, val: u16, len: usize) -> Result<usize> {
-        assert!(len == 1 || len == 2);
+    pub fn demod_write_reg(
+        &self,
+        page: u16,
+        addr: u16,
+        val: u16,
+        width: RegWidth,
+    ) -> Result<usize> {
+        let data = val.to_be_bytes();
+        self.demod_write_array(page, addr, width.encode(&data))
+    }
+
+    /// Write consecutive demod registers starting at `addr` in a single transfer
+    pub fn demod_write_array(&self, page: u16, addr: u16, data: &[u8]) -> Result<usize> {
         let index = 0x10 | page;
-        addr = (addr << 8) | 0x20;
-        let data: [u8; 2] = val.to_be_bytes();
-        let data_slice = if len == 1 { &data[1..2] } else { &data };
-
-        let bytes =
-            match self
-                .handle
-                .write_control(CTRL_OUT, 0, addr, index, data_slice, CTRL_TIMEOUT)
-            {
-                Ok(n) => n,
-                Err(e) => {
-                    error!(
-                        "demod_write_reg failed: {} page: {:#02x} addr: {:#02x} val: {:#02x}",
-                        e, page, addr, val
-                    );
-                    0
-                }
-            };
+        let value = (addr << 8) | 0x20;
 
-        self.demod_read_reg(0x0a, 0x1)?;
+        let bytes = self.control_out(value, index, data)?;
+        self.verify_write(value, index, data)?;
+
+        if self.defer_verify.load(Ordering::Relaxed) {
+            self.verify_pending.store(true, Ordering::Relaxed);
+        } else {
+            self.demod_read_reg(0x0a, 0x1)?;
+        }
 
         Ok(bytes)
     }
 
     pub fn bulk_transfer(&self, buf: &mut [u8]) -> Result<usize> {
-        Ok(self.handle.read_bulk(0x81, buf, Duration::ZERO)?)
+        self.handle.read_bulk(0x81, buf, Duration::ZERO)
     }
 
     pub fn read_eeprom(&self, data: &mut [u8], offset: u8, len: usize) -> Result<usize> {
-        assert!((len + offset as usize) <= EEPROM_SIZE);
+        check_eeprom_range(offset, len)?;
+        if len > data.len() {
+            return Err(RtlsdrErr(format!(
+                "EEPROM read of {} bytes into a {} byte buffer",
+                len,
+                data.len()
+            )));
+        }
         self.write_array(BLOCK_IIC, EEPROM_ADDR, &[offset], 1)?;
-        for i in 0..len {
-            self.read_array(BLOCK_IIC, EEPROM_ADDR, &mut data[i..i+1], 1)?;
+        // The EEPROM address auto-increments, so read sequentially in chunks
+        for chunk in data[..len].chunks_mut(EEPROM_READ_CHUNK) {
+            let n = self.read_array(BLOCK_IIC, EEPROM_ADDR, chunk, chunk.len() as u8)?;
+            if n != chunk.len() {
+                return Err(RtlsdrErr(format!(
+                    "Short EEPROM read: {} of {} bytes",
+                    n,
+                    chunk.len()
+                )));
+            }
         }
         Ok(len)
     }
 
+    /// Write `data` to the EEPROM at `offset`, skipping bytes that already match
+    /// and verifying each page after writing it. The first page written doubles
+    /// as a write-protect probe: if none of its bytes change the write stops
+    /// with `EepromError::WriteProtected`.
+    pub fn write_eeprom(&self, data: &[u8], offset: u8) -> Result<usize> {
+        check_eeprom_range(offset, data.len())?;
+        let mut written = false;
+        let mut pos = 0;
+        while pos < data.len() {
+            let addr = offset as usize + pos;
+            // Don't cross a page boundary
+            let len = (EEPROM_PAGE_SIZE - addr % EEPROM_PAGE_SIZE).min(data.len() - pos);
+            let want = &data[pos..pos + len];
+            let mut before = [0_u8; EEPROM_PAGE_SIZE];
+            self.read_eeprom(&mut before, addr as u8, len)?;
+            if before[..len] != *want {
+                for (i, val) in want.iter().enumerate() {
+                    if before[i] == *val {
+                        continue;
+                    }
+                    self.write_array(BLOCK_IIC, EEPROM_ADDR, &[(addr + i) as u8, *val], 2)?;
+                    // The EEPROM needs some time to complete the write cycle
+                    std::thread::sleep(EEPROM_WRITE_DELAY);
+                }
+                let mut after = [0_u8; EEPROM_PAGE_SIZE];
+                self.read_eeprom(&mut after, addr as u8, len)?;
+                if after[..len] != *want {
+                    if !written && after == before {
+                        return Err(EepromError::WriteProtected.into());
+                    }
+                    let i = (0..len).find(|&i| after[i] != want[i]).unwrap_or(0);
+                    return Err(EepromError::VerifyFailed { offset: addr + i }.into());
+                }
+                written = true;
+            }
+            pos += len;
+        }
+        Ok(data.len())
+    }
+
     pub fn i2c_read_reg(&self, i2c_addr: u8, reg: u8) -> Result<u8> {
         let addr: u16 = i2c_addr.into();
         let reg: [u8; 1] = [reg];
@@ -157,7 +312,7 @@ impl Device {
     }
 
     pub fn i2c_write(&self, i2c_addr: u16, buffer: &[u8]) -> Result<usize> {
-        Ok(self.write_array(BLOCK_IIC, i2c_addr, buffer, buffer.len())?)
+        self.write_array(BLOCK_IIC, i2c_addr, buffer, buffer.len())
     }
 
     pub fn i2c_read(&self, i2c_addr: u16, buffer: &mut [u8], len: u8) -> Result<usize> {
@@ -166,15 +321,97 @@ impl Device {
 
     pub fn read_array(&self, block: u16, addr: u16, arr: &mut [u8], _len: u8) -> Result<usize> {
         let index: u16 = block << 8;
-        Ok(self
-            .handle
-            .read_control(CTRL_IN, 0, addr, index, arr, CTRL_TIMEOUT)?)
+        self.control_in(addr, index, arr)
     }
 
     pub fn write_array(&self, block: u16, addr: u16, arr: &[u8], len: usize) -> Result<usize> {
         let index: u16 = (block << 8) | 0x10;
-        Ok(self
+        let data = arr
+            .get(..len)
+            .ok_or_else(|| RtlsdrErr(format!("Write of {} bytes from {} bytes", len, arr.len())))?;
+        self.control_out(addr, index, data)
+    }
+
+    /// Read back a register write made with `value` and `index`, whose read
+    /// has the same value and the index without the write flag
+    fn verify_write(&self, value: u16, index: u16, data: &[u8]) -> Result<()> {
+        if !self.paranoid.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mut read = vec![0_u8; data.len()];
+        let n = self.control_in(value, index & !0x10, &mut read)?;
+        if read[..n] != *data {
+            self.write_mismatches.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Register write {:04x} {:04x} read back {:02x?}, wrote {:02x?}",
+                value,
+                index,
+                &read[..n],
+                data
+            );
+        }
+        Ok(())
+    }
+
+    fn control_in(&self, value: u16, index: u16, buf: &mut [u8]) -> Result<usize> {
+        let res = self
+            .handle
+            .read_control(CTRL_IN, 0, value, index, buf, CTRL_TIMEOUT);
+        if self.dump.load(Ordering::Relaxed) {
+            let data = res.as_ref().map_or(&[][..], |&n| &buf[..n]);
+            info!(
+                "{}",
+                dump_line(CTRL_IN, value, index, buf.len(), data, &res)
+            );
+        }
+        res
+    }
+
+    fn control_out(&self, value: u16, index: u16, data: &[u8]) -> Result<usize> {
+        let res = self
             .handle
-            .write_control(CTRL_OUT, 0, addr, index, &arr[..len], CTRL_TIMEOUT)?)
+            .write_control(CTRL_OUT, 0, value, index, data, CTRL_TIMEOUT);
+        if self.dump.load(Ordering::Relaxed) {
+            info!(
+                "{}",
+                dump_line(CTRL_OUT, value, index, data.len(), data, &res)
+            );
+        }
+        res
+    }
+}
+
+/// A control transfer as `type request value index length: data`, in hex
+/// apart from the length, followed by the error if it failed. Reads show the
+/// bytes returned, writes the bytes sent.
+fn dump_line(
+    request_type: u8,
+    value: u16,
+    index: u16,
+    len: usize,
+    data: &[u8],
+    res: &Result<usize>,
+) -> String {
+    let mut line = format!(
+        "{:02x} 00 {:04x} {:04x} {}:",
+        request_type, value, index, len
+    );
+    for b in data {
+        line.push_str(&format!(" {:02x}", b));
+    }
+    if let Err(e) = res {
+        line.push_str(&format!(" failed: {}", e));
+    }
+    line
+}
+
+fn check_eeprom_range(offset: u8, len: usize) -> Result<()> {
+    if offset as usize + len <= EEPROM_SIZE {
+        Ok(())
+    } else {
+        Err(RtlsdrErr(format!(
+            "EEPROM access of {} bytes at {:#04x} is past the end",
+            len, offset
+        )))
     }
 }
@@ -0,0 +1,104 @@
+//! Mock handle that plays back a recorded transcript, checking that the
+//! driver makes exactly the same transactions in the same order
+use super::mock_device_handle::MockDeviceHandle;
+use super::transcript::Transaction;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+pub struct Replay {
+    pending: Arc<Mutex<VecDeque<Transaction>>>,
+}
+
+impl Replay {
+    pub fn new(transcript: Vec<Transaction>) -> Replay {
+        Replay {
+            pending: Arc::new(Mutex::new(transcript.into())),
+        }
+    }
+
+    pub fn handle(&self) -> MockDeviceHandle {
+        let mut handle = MockDeviceHandle::new();
+        handle.expect_claim_interface().returning(|_| Ok(()));
+        handle.expect_reset().returning(|| Ok(()));
+
+        let pending = self.pending.clone();
+        handle.expect_read_control().returning(
+            move |request_type, request, value, index, buf, _| match next(&pending) {
+                Transaction::ControlIn {
+                    request_type: t,
+                    request: r,
+                    value: v,
+                    index: i,
+                    data,
+                } if (t, r, v, i) == (request_type, request, value, index)
+                    && data.len() <= buf.len() =>
+                {
+                    buf[..data.len()].copy_from_slice(&data);
+                    Ok(data.len())
+                }
+                expected => panic!(
+                    "Expected {}, got ctrl_in {:02x} {:02x} {:04x} {:04x} [{} bytes]",
+                    expected,
+                    request_type,
+                    request,
+                    value,
+                    index,
+                    buf.len()
+                ),
+            },
+        );
+
+        let pending = self.pending.clone();
+        handle.expect_write_control().returning(
+            move |request_type, request, value, index, buf, _| match next(&pending) {
+                Transaction::ControlOut {
+                    request_type: t,
+                    request: r,
+                    value: v,
+                    index: i,
+                    data,
+                    len,
+                } if (t, r, v, i) == (request_type, request, value, index) && data == buf => {
+                    Ok(len)
+                }
+                expected => panic!(
+                    "Expected {}, got ctrl_out {:02x} {:02x} {:04x} {:04x} {:02x?}",
+                    expected, request_type, request, value, index, buf
+                ),
+            },
+        );
+
+        let pending = self.pending.clone();
+        handle
+            .expect_read_bulk()
+            .returning(move |endpoint, buf, _| match next(&pending) {
+                Transaction::BulkIn { endpoint: e, len } if e == endpoint && len <= buf.len() => {
+                    buf[..len].fill(0);
+                    Ok(len)
+                }
+                expected => panic!(
+                    "Expected {}, got bulk_in {:02x} [{} bytes]",
+                    expected,
+                    endpoint,
+                    buf.len()
+                ),
+            });
+        handle
+    }
+
+    /// Panic if any recorded transactions were not replayed
+    pub fn assert_done(&self) {
+        let pending = self.pending.lock().unwrap();
+        if let Some(t) = pending.front() {
+            panic!("{} transactions not replayed, next: {}", pending.len(), t);
+        }
+    }
+}
+
+fn next(pending: &Mutex<VecDeque<Transaction>>) -> Transaction {
+    pending
+        .lock()
+        .unwrap()
+        .pop_front()
+        .expect("Transaction after end of transcript")
+}
@@ -0,0 +1,171 @@
+//! Text transcripts of the USB transactions made with a device, so a real
+//! init or tune sequence can be recorded once and replayed in tests
+//!
+//! Each line holds one transaction, fields in hex:
+//! ```text
+//! # kind    type req value index data
+//! ctrl_in   c0   00  0120  0000  3c
+//! ctrl_out  40   00  3000  0110  09    1   <- bytes the device accepted
+//! bulk_in   81   40000                     <- endpoint, bytes read
+//! ```
+//! Empty data is written as `-`. Only successful transactions are recorded.
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    ControlIn {
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: Vec<u8>, // Bytes returned by the device
+    },
+    ControlOut {
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: Vec<u8>,
+        len: usize, // Bytes the device accepted
+    },
+    // Sample data isn't kept, only how much was read
+    BulkIn {
+        endpoint: u8,
+        len: usize,
+    },
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transaction::ControlIn {
+                request_type,
+                request,
+                value,
+                index,
+                data,
+            } => write!(
+                f,
+                "ctrl_in {:02x} {:02x} {:04x} {:04x} {}",
+                request_type,
+                request,
+                value,
+                index,
+                hex(data)
+            ),
+            Transaction::ControlOut {
+                request_type,
+                request,
+                value,
+                index,
+                data,
+                len,
+            } => write!(
+                f,
+                "ctrl_out {:02x} {:02x} {:04x} {:04x} {} {:x}",
+                request_type,
+                request,
+                value,
+                index,
+                hex(data),
+                len
+            ),
+            Transaction::BulkIn { endpoint, len } => {
+                write!(f, "bulk_in {:02x} {:x}", endpoint, len)
+            }
+        }
+    }
+}
+
+impl FromStr for Transaction {
+    type Err = crate::error::RtlsdrError;
+
+    fn from_str(line: &str) -> Result<Transaction> {
+        let bad = || RtlsdrErr(format!("Invalid transaction: {}", line));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let num = |i: usize| -> Result<u64> {
+            let field = fields.get(i).ok_or_else(bad)?;
+            u64::from_str_radix(field, 16).map_err(|_| bad())
+        };
+        let data = |i: usize| -> Result<Vec<u8>> {
+            match *fields.get(i).ok_or_else(bad)? {
+                "-" => Ok(Vec::new()),
+                s if s.len().is_multiple_of(2) => (0..s.len())
+                    .step_by(2)
+                    .map(|j| u8::from_str_radix(&s[j..j + 2], 16).map_err(|_| bad()))
+                    .collect(),
+                _ => Err(bad()),
+            }
+        };
+        let transaction = match (fields.first().copied(), fields.len()) {
+            (Some("ctrl_in"), 6) => Transaction::ControlIn {
+                request_type: num(1)? as u8,
+                request: num(2)? as u8,
+                value: num(3)? as u16,
+                index: num(4)? as u16,
+                data: data(5)?,
+            },
+            (Some("ctrl_out"), 7) => Transaction::ControlOut {
+                request_type: num(1)? as u8,
+                request: num(2)? as u8,
+                value: num(3)? as u16,
+                index: num(4)? as u16,
+                data: data(5)?,
+                len: num(6)? as usize,
+            },
+            (Some("bulk_in"), 3) => Transaction::BulkIn {
+                endpoint: num(1)? as u8,
+                len: num(2)? as usize,
+            },
+            _ => return Err(bad()),
+        };
+        Ok(transaction)
+    }
+}
+
+/// Parse a transcript, skipping blank lines and `#` comments
+// Transcripts are only replayed in unit tests
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn parse_transcript(text: &str) -> Result<Vec<Transaction>> {
+    text.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Appends transactions to a transcript file
+#[derive(Debug)]
+pub struct Recorder {
+    out: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Append to the transcript at `path`, creating it if needed
+    pub fn append(path: &Path) -> Result<Recorder> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Recorder {
+            out: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn record(&self, transaction: &Transaction) -> Result<()> {
+        let mut out = self.out.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(writeln!(out, "{}", transaction)?)
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "-".to_string();
+    }
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
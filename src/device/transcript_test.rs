@@ -0,0 +1,140 @@
+use super::replay::Replay;
+use super::transcript::{parse_transcript, Recorder, Transaction};
+use super::{Device, RegWidth, BLOCK_SYS, GPO};
+use crate::rtlsdr::{OpenOptions, RtlSdr};
+use crate::DirectSampleMode;
+
+fn replay_device(text: &str) -> (Replay, Device) {
+    let replay = Replay::new(parse_transcript(text).unwrap());
+    let device = Device::from_handle(replay.handle());
+    (replay, device)
+}
+
+#[test]
+fn test_transaction_roundtrip() {
+    let transactions = [
+        Transaction::ControlIn {
+            request_type: 0xc0,
+            request: 0,
+            value: 0x0120,
+            index: 0x000a,
+            data: vec![0x3c],
+        },
+        Transaction::ControlOut {
+            request_type: 0x40,
+            request: 0,
+            value: 0x3000,
+            index: 0x0110,
+            data: vec![0x00, 0x09],
+            len: 2,
+        },
+        Transaction::ControlOut {
+            request_type: 0x40,
+            request: 0,
+            value: 0x3000,
+            index: 0x0110,
+            data: vec![],
+            len: 0,
+        },
+        Transaction::BulkIn {
+            endpoint: 0x81,
+            len: 0x40000,
+        },
+    ];
+    let text: String = transactions.iter().map(|t| format!("{}\n", t)).collect();
+    assert_eq!(
+        "ctrl_out 40 00 3000 0110 0009 2",
+        text.lines().nth(1).unwrap()
+    );
+    assert_eq!(transactions.to_vec(), parse_transcript(&text).unwrap());
+}
+
+#[test]
+fn test_parse_transcript() {
+    let text = "# comment\n\nbulk_in 81 100 # trailing comment\n";
+    assert_eq!(
+        vec![Transaction::BulkIn {
+            endpoint: 0x81,
+            len: 0x100
+        }],
+        parse_transcript(text).unwrap()
+    );
+    assert!(parse_transcript("ctrl_in c0 00 0120").is_err());
+    assert!(parse_transcript("ctrl_in c0 00 0120 000a 3").is_err());
+    assert!(parse_transcript("ctrl_in c0 00 0120 000a zz").is_err());
+    assert!(parse_transcript("interrupt 81 1").is_err());
+}
+
+#[test]
+fn test_recorder_appends() {
+    let path = std::env::temp_dir().join(format!("rtlsdr-transcript-{}", std::process::id()));
+    std::fs::write(&path, "# first session\n").unwrap();
+    let transaction = Transaction::BulkIn {
+        endpoint: 0x81,
+        len: 0x100,
+    };
+    Recorder::append(&path)
+        .unwrap()
+        .record(&transaction)
+        .unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!("# first session\nbulk_in 81 100\n", text);
+}
+
+#[test]
+fn test_replay_registers() {
+    let (replay, device) = replay_device(
+        "ctrl_in c0 00 3001 0200 12
+         ctrl_out 40 00 3001 0210 34 1",
+    );
+    assert_eq!(
+        0x12,
+        device.read_reg(BLOCK_SYS, GPO, RegWidth::Byte).unwrap()
+    );
+    assert_eq!(
+        1,
+        device
+            .write_reg(BLOCK_SYS, GPO, 0x34, RegWidth::Byte)
+            .unwrap()
+    );
+    replay.assert_done();
+}
+
+#[test]
+#[should_panic(expected = "Expected ctrl_out")]
+fn test_replay_mismatch() {
+    let (_replay, device) = replay_device("ctrl_out 40 00 3001 0210 34 1");
+    device
+        .write_reg(BLOCK_SYS, GPO, 0x35, RegWidth::Byte)
+        .unwrap();
+}
+
+#[test]
+#[should_panic(expected = "not replayed")]
+fn test_replay_incomplete() {
+    let (replay, _device) = replay_device("ctrl_out 40 00 3001 0210 34 1");
+    replay.assert_done();
+}
+
+#[test]
+fn test_replay_set_sample_rate() {
+    let (replay, device) = replay_device(include_str!("fixtures/set_sample_rate.txt"));
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_sample_rate(2_048_000).unwrap();
+    assert_eq!(2_048_000, sdr.get_sample_rate());
+    replay.assert_done();
+}
+
+#[test]
+fn test_replay_init_no_tuner() {
+    let (replay, device) = replay_device(include_str!("fixtures/init_no_tuner.txt"));
+    let mut sdr = RtlSdr::new(device);
+    sdr.set_open_options(OpenOptions {
+        allow_no_tuner: true,
+        ..Default::default()
+    });
+    sdr.init().unwrap();
+    assert_eq!(DirectSampleMode::On, sdr.settings().direct_sampling);
+    replay.assert_done();
+}
@@ -0,0 +1,111 @@
+use super::*;
+use crate::error::Result;
+use crate::Eeprom;
+
+/// Test mode counter, skipping `skip` bytes after `gap_at` buffers
+struct Counter {
+    next: u8,
+    buffers: usize,
+    gap_at: Option<usize>,
+    skip: u8,
+    short: bool,
+}
+
+impl SampleSource for Counter {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if Some(self.buffers) == self.gap_at {
+            self.next = self.next.wrapping_add(self.skip);
+        }
+        self.buffers += 1;
+        for b in buf.iter_mut() {
+            *b = self.next;
+            self.next = self.next.wrapping_add(1);
+        }
+        Ok(if self.short { buf.len() / 2 } else { buf.len() })
+    }
+
+    fn sample_rate(&self) -> u32 {
+        CONTINUITY_RATE
+    }
+
+    fn center_freq(&self) -> u32 {
+        0
+    }
+}
+
+fn counter(gap_at: Option<usize>, skip: u8, short: bool) -> Counter {
+    Counter {
+        next: 0,
+        buffers: 0,
+        gap_at,
+        skip,
+        short,
+    }
+}
+
+#[test]
+fn test_continuity() {
+    let check = continuity_check(&mut counter(None, 0, false), 4);
+    assert_eq!(Status::Pass, check.status);
+
+    let check = continuity_check(&mut counter(Some(2), 10, false), 4);
+    assert_eq!(Status::Fail, check.status);
+    assert!(
+        check.detail.contains("at least 10 bytes"),
+        "{}",
+        check.detail
+    );
+
+    let check = continuity_check(&mut counter(None, 0, true), 4);
+    assert_eq!(Status::Fail, check.status);
+    assert!(check.detail.contains("Short read"));
+}
+
+#[test]
+fn test_usb_checks() {
+    assert_eq!(Status::Pass, speed_check(Speed::High).status);
+    assert_eq!(Status::Fail, speed_check(Speed::Full).status);
+    assert_eq!(Status::Warning, speed_check(Speed::Unknown).status);
+
+    assert!(open_check(rusb::Error::Access).detail.contains("udev"));
+
+    assert_eq!(Status::Pass, kernel_driver_check(Ok(false)).status);
+    let check = kernel_driver_check(Ok(true));
+    assert_eq!(Status::Fail, check.status);
+    assert!(check.detail.contains("dvb_usb_rtl28xxu"));
+    assert_eq!(
+        Status::Pass,
+        kernel_driver_check(Err(rusb::Error::NotSupported)).status
+    );
+
+    assert_eq!(Status::Pass, claim_check(Ok(())).status);
+    let check = claim_check(Err(rusb::Error::Busy));
+    assert_eq!(Status::Fail, check.status);
+    assert!(check.detail.contains("another program"));
+}
+
+#[test]
+fn test_eeprom_check() {
+    let check = eeprom_check(Eeprom::parse(&[0xff; 256]));
+    assert_eq!(Status::Warning, check.status);
+    assert!(check.detail.contains("header"));
+    let check = eeprom_check(Err(RtlsdrError::Usb(rusb::Error::Pipe)));
+    assert_eq!(Status::Fail, check.status);
+}
+
+#[test]
+fn test_report() {
+    let mut report = Report {
+        index: 0,
+        checks: Vec::new(),
+    };
+    assert!(report.push(Check::new("Device", Status::Pass, "Generic RTL2832U")));
+    assert!(report.push(speed_check(Speed::Unknown)));
+    assert!(report.passed());
+    assert!(!report.push(claim_check(Err(rusb::Error::Busy))));
+    assert!(!report.passed());
+    let text = report.to_string();
+    assert_eq!(3, text.lines().count());
+    assert!(text.starts_with("[PASS] Device: Generic RTL2832U\n[WARN] USB speed"));
+    assert!(text.contains("[FAIL] Interface: In use"));
+}
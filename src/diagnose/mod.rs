@@ -0,0 +1,307 @@
+//! Checks for the usual reasons a device won't open or loses samples: the
+//! DVB-T kernel driver claiming it, missing udev permissions, another
+//! program using it, a full speed USB port, an unreadable EEPROM and
+//! samples dropped between the device and the host.
+//!
+//! ```no_run
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let report = RtlSdr::diagnose(0);
+//! print!("{}", report);
+//! if !report.passed() {
+//!     std::process::exit(1);
+//! }
+//! ```
+
+use crate::benchmark::CounterCheck;
+use crate::device::device_handle::devices;
+use crate::error::RtlsdrError;
+use crate::source::SampleSource;
+use crate::{RtlSdr, DEFAULT_BUF_LENGTH};
+use rusb::Speed;
+use std::fmt;
+
+#[cfg(test)]
+mod diagnose_test;
+
+// The interface librtlsdr and `RtlSdr::open` claim
+const INTERFACE: u8 = 0;
+/// Buffers read in test mode, about 2.5 s at `CONTINUITY_RATE`
+pub const CONTINUITY_BUFFERS: usize = 20;
+/// Sample rate of the continuity check, the highest that usually streams
+/// without drops on a USB 2.0 port
+pub const CONTINUITY_RATE: u32 = 2_400_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Status {
+    Pass,
+    /// The device works, but may not work well
+    Warning,
+    Fail,
+}
+
+/// Result of one check
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    /// What was found, and how to fix it if it failed
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Check {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Checks in the order they ran. Checks stop at the first failure that
+/// prevents the later ones from running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    pub index: usize,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether no check failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+
+    /// Add `check`, returning whether the later checks can run
+    fn push(&mut self, check: Check) -> bool {
+        let ok = check.status != Status::Fail;
+        self.checks.push(check);
+        ok
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Pass => "PASS",
+                Status::Warning => "WARN",
+                Status::Fail => "FAIL",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run all checks on device `index`
+pub fn diagnose(index: usize) -> Report {
+    let mut report = Report {
+        index,
+        checks: Vec::new(),
+    };
+    let found = devices();
+    let Some((device, name)) = found.get(index) else {
+        report.push(Check::new(
+            "Device",
+            Status::Fail,
+            format!(
+                "No supported device at index {} ({} found)",
+                index,
+                found.len()
+            ),
+        ));
+        return report;
+    };
+    report.push(Check::new("Device", Status::Pass, *name));
+    if !report.push(speed_check(device.speed())) {
+        return report;
+    }
+
+    let handle = match device.open() {
+        Ok(handle) => handle,
+        Err(e) => {
+            report.push(open_check(e));
+            return report;
+        }
+    };
+    report.push(Check::new(
+        "Permissions",
+        Status::Pass,
+        "Device can be opened",
+    ));
+    if !report.push(kernel_driver_check(handle.kernel_driver_active(INTERFACE))) {
+        return report;
+    }
+    let claimed = handle.claim_interface(INTERFACE);
+    if claimed.is_ok() {
+        let _ = handle.release_interface(INTERFACE);
+    }
+    drop(handle);
+    if !report.push(claim_check(claimed)) {
+        return report;
+    }
+
+    let mut sdr = match RtlSdr::open(index) {
+        Ok(sdr) => sdr,
+        Err(e) => {
+            report.push(Check::new(
+                "Initialization",
+                Status::Fail,
+                format!("Opening the device failed: {}", e),
+            ));
+            return report;
+        }
+    };
+    report.push(eeprom_check(sdr.read_eeprom_config()));
+    let started = sdr
+        .set_sample_rate(CONTINUITY_RATE)
+        .and_then(|_| sdr.set_testmode(true))
+        .and_then(|_| sdr.reset_buffer());
+    match started {
+        Ok(()) => report.push(continuity_check(&mut sdr, CONTINUITY_BUFFERS)),
+        Err(e) => report.push(Check::new(
+            "Sample continuity",
+            Status::Fail,
+            format!("Starting test mode failed: {}", e),
+        )),
+    };
+    let _ = sdr.set_testmode(false);
+    let _ = sdr.close();
+    report
+}
+
+fn speed_check(speed: Speed) -> Check {
+    let name = "USB speed";
+    match speed {
+        Speed::High | Speed::Super | Speed::SuperPlus => {
+            Check::new(name, Status::Pass, format!("{:?} speed", speed))
+        }
+        Speed::Low | Speed::Full => Check::new(
+            name,
+            Status::Fail,
+            format!(
+                "{:?} speed; the device needs a USB 2.0 high speed port, try another port or hub",
+                speed
+            ),
+        ),
+        _ => Check::new(name, Status::Warning, "Unknown, the OS didn't report it"),
+    }
+}
+
+fn open_check(e: rusb::Error) -> Check {
+    let name = "Permissions";
+    match e {
+        rusb::Error::Access => Check::new(
+            name,
+            Status::Fail,
+            "No permission to open the device; install the rtl-sdr udev rules, or run as root",
+        ),
+        e => Check::new(
+            name,
+            Status::Fail,
+            format!("Opening the device failed: {}", e),
+        ),
+    }
+}
+
+fn kernel_driver_check(active: rusb::Result<bool>) -> Check {
+    let name = "Kernel driver";
+    match active {
+        Ok(false) => Check::new(name, Status::Pass, "Not claimed by a kernel driver"),
+        Ok(true) => Check::new(
+            name,
+            Status::Fail,
+            "Claimed by the DVB-T kernel driver; unload it with `sudo rmmod dvb_usb_rtl28xxu` \
+             and blacklist it in /etc/modprobe.d",
+        ),
+        Err(rusb::Error::NotSupported) => {
+            Check::new(name, Status::Pass, "Not applicable on this platform")
+        }
+        Err(e) => Check::new(name, Status::Warning, format!("Couldn't check: {}", e)),
+    }
+}
+
+fn claim_check(claimed: rusb::Result<()>) -> Check {
+    let name = "Interface";
+    match claimed {
+        Ok(()) => Check::new(name, Status::Pass, "Not in use"),
+        Err(rusb::Error::Busy) => Check::new(
+            name,
+            Status::Fail,
+            "In use by another program, e.g. rtl_tcp, a decoder or SDR software",
+        ),
+        Err(e) => Check::new(name, Status::Fail, format!("Claiming failed: {}", e)),
+    }
+}
+
+fn eeprom_check(eeprom: crate::error::Result<crate::Eeprom>) -> Check {
+    let name = "EEPROM";
+    match eeprom {
+        Ok(eeprom) if eeprom.have_serial => Check::new(
+            name,
+            Status::Pass,
+            format!(
+                "{} {}, serial {}",
+                eeprom.manufacturer, eeprom.product, eeprom.serial
+            ),
+        ),
+        Ok(eeprom) => Check::new(
+            name,
+            Status::Pass,
+            format!(
+                "{} {}, no serial number",
+                eeprom.manufacturer, eeprom.product
+            ),
+        ),
+        // A blank or corrupt EEPROM doesn't stop the device from working
+        Err(RtlsdrError::RtlsdrErr(e)) => Check::new(
+            name,
+            Status::Warning,
+            format!("Not valid ({}); rtl_eeprom can restore a backup", e),
+        ),
+        Err(e) => Check::new(name, Status::Fail, format!("Reading failed: {}", e)),
+    }
+}
+
+/// Read `buffers` buffers from `source` in test mode, checking the counter
+/// for lost samples
+fn continuity_check<S: SampleSource>(source: &mut S, buffers: usize) -> Check {
+    let name = "Sample continuity";
+    let mut check = CounterCheck::new();
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    for _ in 0..buffers {
+        match source.read_sync(&mut buf) {
+            Ok(n) if n < buf.len() => {
+                return Check::new(
+                    name,
+                    Status::Fail,
+                    format!("Short read ({} bytes), samples were lost", n),
+                );
+            }
+            Ok(n) => {
+                check.process(&buf[..n]);
+            }
+            Err(e) => return Check::new(name, Status::Fail, format!("Reading failed: {}", e)),
+        }
+    }
+    match check.lost() {
+        0 => Check::new(
+            name,
+            Status::Pass,
+            format!("{} buffers without lost samples", buffers),
+        ),
+        lost => Check::new(
+            name,
+            Status::Fail,
+            format!(
+                "Lost at least {} bytes; try another USB port or cable, or a lower sample rate",
+                lost
+            ),
+        ),
+    }
+}
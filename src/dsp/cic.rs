@@ -0,0 +1,159 @@
+//! CIC decimator with a droop compensation FIR
+//!
+//! A cascaded integrator-comb filter decimates by any integer ratio using
+//! only additions, which makes it cheap enough to bring the full capture
+//! rate down to a channel rate. Its passband droops like sinc^N, so the
+//! output goes through a short FIR that flattens the passband and cuts off
+//! what the CIC lets alias near the output Nyquist frequency.
+//!
+//! The filter runs in fixed point for both the `i16` and `f32` paths; the
+//! integrators wrap, which the combs undo exactly.
+
+use std::f64::consts::PI;
+
+const COMP_TAPS: usize = 31;
+const COMP_PASS: f64 = 0.35; // Passband edge in cycles per output sample
+const COMP_SHIFT: u32 = 15; // Fixed point fraction bits of the FIR coefficients
+const F32_SHIFT: u32 = 20; // Fixed point fraction bits for f32 samples
+
+/// Decimates one or more interleaved channels (2 for IQ) by `ratio`
+pub struct CicDecimator {
+    stages: usize,
+    ratio: usize,
+    channels: usize,
+    gain: i64, // ratio^stages
+    integrators: Vec<i64>,
+    combs: Vec<i64>,
+    count: usize, // Frames of channels since the last output
+    channel: usize,
+    comp: [i32; COMP_TAPS],
+    history: Vec<i64>, // Last COMP_TAPS outputs per channel
+    hist_pos: usize,
+}
+
+impl CicDecimator {
+    pub fn new(ratio: usize, stages: usize, channels: usize) -> CicDecimator {
+        assert!(
+            ratio >= 1 && channels >= 1,
+            "Invalid CIC decimator parameters"
+        );
+        assert!(
+            (1..=6).contains(&stages),
+            "CIC decimator supports 1 to 6 stages"
+        );
+        CicDecimator {
+            stages,
+            ratio,
+            channels,
+            gain: (ratio as i64).pow(stages as u32),
+            integrators: vec![0; stages * channels],
+            combs: vec![0; stages * channels],
+            count: 0,
+            channel: 0,
+            comp: compensation(ratio, stages),
+            history: vec![0; COMP_TAPS * channels],
+            hist_pos: 0,
+        }
+    }
+
+    pub fn ratio(&self) -> usize {
+        self.ratio
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn process_i16(&mut self, input: &[i16]) -> Vec<i16> {
+        let mut out = Vec::with_capacity(input.len() / self.ratio + self.channels);
+        for &x in input {
+            if let Some(y) = self.push(x as i64) {
+                out.push(y.clamp(i16::MIN as i64, i16::MAX as i64) as i16);
+            }
+        }
+        out
+    }
+
+    /// Samples are expected to be roughly within +/-1.0, as from `dsp::to_f32`
+    pub fn process_f32(&mut self, input: &[f32]) -> Vec<f32> {
+        let scale = (1 << F32_SHIFT) as f32;
+        let mut out = Vec::with_capacity(input.len() / self.ratio + self.channels);
+        for &x in input {
+            if let Some(y) = self.push((x * scale) as i64) {
+                out.push(y as f32 / scale);
+            }
+        }
+        out
+    }
+
+    fn push(&mut self, x: i64) -> Option<i64> {
+        let ch = self.channel;
+        self.channel = (ch + 1) % self.channels;
+        let integrators = &mut self.integrators[ch * self.stages..(ch + 1) * self.stages];
+        let mut acc = x;
+        for i in integrators.iter_mut() {
+            *i = i.wrapping_add(acc);
+            acc = *i;
+        }
+        // The last frame of each decimation period is output
+        let output = self.count == self.ratio - 1;
+        if self.channel == 0 {
+            self.count = (self.count + 1) % self.ratio;
+        }
+        if !output {
+            return None;
+        }
+        let combs = &mut self.combs[ch * self.stages..(ch + 1) * self.stages];
+        for c in combs.iter_mut() {
+            let prev = *c;
+            *c = acc;
+            acc = acc.wrapping_sub(prev);
+        }
+        Some(self.compensate(ch, acc / self.gain))
+    }
+
+    fn compensate(&mut self, ch: usize, x: i64) -> i64 {
+        let history = &mut self.history[ch * COMP_TAPS..(ch + 1) * COMP_TAPS];
+        history[self.hist_pos] = x;
+        let pos = self.hist_pos;
+        if ch == self.channels - 1 {
+            self.hist_pos = (self.hist_pos + 1) % COMP_TAPS;
+        }
+        // Oldest sample first
+        let (new, old) = history.split_at(pos + 1);
+        let acc: i64 = old
+            .iter()
+            .chain(new)
+            .zip(&self.comp)
+            .map(|(x, c)| x * *c as i64)
+            .sum();
+        acc >> COMP_SHIFT
+    }
+}
+
+/// Design the compensation FIR by sampling the inverse CIC response up to
+/// the passband edge and windowing the result
+fn compensation(ratio: usize, stages: usize) -> [i32; COMP_TAPS] {
+    const POINTS: usize = 512;
+    let r = ratio as f64;
+    let mid = (COMP_TAPS / 2) as f64;
+    let mut taps = [0.0_f64; COMP_TAPS];
+    for (n, tap) in taps.iter_mut().enumerate() {
+        let mut sum = 0.0;
+        for k in 0..POINTS {
+            let f = (k as f64 + 0.5) / POINTS as f64 * COMP_PASS;
+            // CIC response at f cycles per output sample
+            let cic = if ratio == 1 {
+                1.0
+            } else {
+                ((PI * f).sin() / (r * (PI * f / r).sin())).powi(stages as i32)
+            };
+            sum += (2.0 * PI * f * (n as f64 - mid)).cos() / cic;
+        }
+        let window = 0.54 - 0.46 * (2.0 * PI * n as f64 / (COMP_TAPS - 1) as f64).cos();
+        *tap = 2.0 * sum * COMP_PASS / POINTS as f64 * window;
+    }
+    // Unity gain at DC
+    let dc: f64 = taps.iter().sum();
+    taps.map(|t| (t / dc * (1 << COMP_SHIFT) as f64).round() as i32)
+}
@@ -0,0 +1,64 @@
+use super::CicDecimator;
+use std::f32::consts::PI;
+
+fn tone(freq: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| 0.5 * (2.0 * PI * freq * n as f32).sin())
+        .collect()
+}
+
+fn rms(buf: &[f32]) -> f32 {
+    (buf.iter().map(|x| x * x).sum::<f32>() / buf.len() as f32).sqrt()
+}
+
+#[test]
+fn test_cic_dc_gain() {
+    let mut cic = CicDecimator::new(10, 4, 1);
+    let out = cic.process_i16(&[1000; 2000]);
+    assert_eq!(200, out.len());
+    assert!(out[50..].iter().all(|&y| (y - 1000).abs() <= 1));
+}
+
+#[test]
+fn test_cic_flat_passband() {
+    // 0.3 cycles per output sample is well into the CIC droop
+    for freq in [0.05, 0.2, 0.3] {
+        let mut cic = CicDecimator::new(8, 4, 1);
+        let out = cic.process_f32(&tone(freq / 8.0, 80_000));
+        let gain = rms(&out[100..]) / rms(&tone(freq / 8.0, 80_000));
+        assert!((gain - 1.0).abs() < 0.05, "{} {}", freq, gain);
+    }
+}
+
+#[test]
+fn test_cic_rejects_alias() {
+    // Would alias to 0.1 cycles per output sample
+    let mut cic = CicDecimator::new(8, 4, 1);
+    let out = cic.process_f32(&tone(0.9 / 8.0, 80_000));
+    assert!(rms(&out[100..]) < 0.01);
+}
+
+#[test]
+fn test_cic_interleaved_channels() {
+    // I constant, Q alternating sign per frame (at Nyquist, so removed)
+    let input: Vec<i16> = (0..4000)
+        .flat_map(|n| [500, if n % 2 == 0 { 800 } else { -800 }])
+        .collect();
+    let mut cic = CicDecimator::new(4, 3, 2);
+    let out = cic.process_i16(&input);
+    assert_eq!(2000, out.len());
+    for frame in out[100..].chunks_exact(2) {
+        assert!((frame[0] - 500).abs() <= 1);
+        assert!(frame[1].abs() <= 1);
+    }
+}
+
+#[test]
+fn test_cic_chunked() {
+    let input = tone(0.01, 10_000);
+    let mut cic = CicDecimator::new(5, 3, 1);
+    let whole = cic.process_f32(&input);
+    let mut cic = CicDecimator::new(5, 3, 1);
+    let chunked: Vec<f32> = input.chunks(37).flat_map(|c| cic.process_f32(c)).collect();
+    assert_eq!(whole, chunked);
+}
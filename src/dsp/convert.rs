@@ -0,0 +1,220 @@
+//! Conversion of raw interleaved u8 IQ samples into signed and floating point
+//! buffers. The ADC output is offset binary centered on 127.
+//!
+//! The `_into` variants write into a caller supplied buffer, which must be the
+//! same length as the input (or half of it for complex output).
+//!
+//! `SampleFormat` generalizes this to the formats samples are stored or sent
+//! in, so recorders and sinks can take the output format as a parameter.
+
+use num_complex::Complex;
+
+/// DC level of the unsigned ADC samples
+pub const IQ_OFFSET: u8 = 127;
+// Scale for float output, giving a range of about -1.0 to 1.0
+const F32_SCALE: f32 = 1.0 / 128.0;
+
+pub fn to_i16_into(src: &[u8], dst: &mut [i16]) {
+    assert_eq!(src.len(), dst.len());
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = *s as i16 - IQ_OFFSET as i16;
+    }
+}
+
+pub fn to_i16(src: &[u8]) -> Vec<i16> {
+    let mut dst = vec![0; src.len()];
+    to_i16_into(src, &mut dst);
+    dst
+}
+
+pub fn to_f32_into(src: &[u8], dst: &mut [f32]) {
+    convert_into(src, dst);
+}
+
+pub fn to_f32(src: &[u8]) -> Vec<f32> {
+    let mut dst = vec![0.0; src.len()];
+    to_f32_into(src, &mut dst);
+    dst
+}
+
+/// Convert I/Q byte pairs into complex samples. A trailing odd byte is ignored.
+pub fn to_complex_into(src: &[u8], dst: &mut [Complex<f32>]) {
+    convert_into(&src[..src.len() / 2 * 2], dst);
+}
+
+pub fn to_complex(src: &[u8]) -> Vec<Complex<f32>> {
+    let mut dst = vec![Complex::new(0.0, 0.0); src.len() / 2];
+    to_complex_into(src, &mut dst);
+    dst
+}
+
+/// A sample format convertible to and from the raw u8 device samples, and
+/// serialized little endian as in SigMF files and rtl_tcp style streams
+pub trait SampleFormat: Copy + Default {
+    /// Raw bytes making up one sample: 1, or 2 for complex formats
+    const RAW_LEN: usize;
+    /// Bytes of one serialized sample
+    const SIZE: usize;
+    /// SigMF `core:datatype` of a recording in this format
+    const SIGMF_DATATYPE: &'static str;
+
+    /// Convert `RAW_LEN` raw bytes
+    fn from_raw(raw: &[u8]) -> Self;
+    /// Convert back into `RAW_LEN` raw bytes, rounding and clamping
+    fn to_raw(self, raw: &mut [u8]);
+    fn read_le(buf: &[u8]) -> Self;
+    fn write_le(self, buf: &mut [u8]);
+}
+
+/// Offset binary as produced by the ADC
+impl SampleFormat for u8 {
+    const RAW_LEN: usize = 1;
+    const SIZE: usize = 1;
+    const SIGMF_DATATYPE: &'static str = "cu8";
+
+    fn from_raw(raw: &[u8]) -> u8 {
+        raw[0]
+    }
+    fn to_raw(self, raw: &mut [u8]) {
+        raw[0] = self;
+    }
+    fn read_le(buf: &[u8]) -> u8 {
+        buf[0]
+    }
+    fn write_le(self, buf: &mut [u8]) {
+        buf[0] = self;
+    }
+}
+
+/// Signed, centered on the ADC midpoint. The raw value 255 clips to 127.
+impl SampleFormat for i8 {
+    const RAW_LEN: usize = 1;
+    const SIZE: usize = 1;
+    const SIGMF_DATATYPE: &'static str = "ci8";
+
+    fn from_raw(raw: &[u8]) -> i8 {
+        (raw[0] as i16 - IQ_OFFSET as i16).min(i8::MAX as i16) as i8
+    }
+    fn to_raw(self, raw: &mut [u8]) {
+        raw[0] = (self as i16 + IQ_OFFSET as i16).max(0) as u8;
+    }
+    fn read_le(buf: &[u8]) -> i8 {
+        buf[0] as i8
+    }
+    fn write_le(self, buf: &mut [u8]) {
+        buf[0] = self as u8;
+    }
+}
+
+/// Signed 16-bit scaled to full range, unlike `to_i16`. The raw value 255
+/// clips to `i16::MAX`.
+impl SampleFormat for i16 {
+    const RAW_LEN: usize = 1;
+    const SIZE: usize = 2;
+    const SIGMF_DATATYPE: &'static str = "ci16_le";
+
+    fn from_raw(raw: &[u8]) -> i16 {
+        ((raw[0] as i32 - IQ_OFFSET as i32) << 8).min(i16::MAX as i32) as i16
+    }
+    fn to_raw(self, raw: &mut [u8]) {
+        raw[0] = (self as f32 / 256.0 + IQ_OFFSET as f32)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    fn read_le(buf: &[u8]) -> i16 {
+        i16::from_le_bytes([buf[0], buf[1]])
+    }
+    fn write_le(self, buf: &mut [u8]) {
+        buf[..2].copy_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// Float scaled to about -1.0 to 1.0, as `to_f32`
+impl SampleFormat for f32 {
+    const RAW_LEN: usize = 1;
+    const SIZE: usize = 4;
+    const SIGMF_DATATYPE: &'static str = "cf32_le";
+
+    fn from_raw(raw: &[u8]) -> f32 {
+        (raw[0] as f32 - IQ_OFFSET as f32) * F32_SCALE
+    }
+    fn to_raw(self, raw: &mut [u8]) {
+        raw[0] = (self / F32_SCALE + IQ_OFFSET as f32)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    fn read_le(buf: &[u8]) -> f32 {
+        f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+    }
+    fn write_le(self, buf: &mut [u8]) {
+        buf[..4].copy_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// I/Q pairs of floats, as `to_complex`
+impl SampleFormat for Complex<f32> {
+    const RAW_LEN: usize = 2;
+    const SIZE: usize = 8;
+    const SIGMF_DATATYPE: &'static str = "cf32_le";
+
+    fn from_raw(raw: &[u8]) -> Complex<f32> {
+        Complex::new(f32::from_raw(&raw[..1]), f32::from_raw(&raw[1..2]))
+    }
+    fn to_raw(self, raw: &mut [u8]) {
+        self.re.to_raw(&mut raw[..1]);
+        self.im.to_raw(&mut raw[1..2]);
+    }
+    fn read_le(buf: &[u8]) -> Complex<f32> {
+        Complex::new(f32::read_le(&buf[..4]), f32::read_le(&buf[4..8]))
+    }
+    fn write_le(self, buf: &mut [u8]) {
+        self.re.write_le(&mut buf[..4]);
+        self.im.write_le(&mut buf[4..8]);
+    }
+}
+
+/// Convert raw samples into `dst`, which must hold `src.len() / T::RAW_LEN`
+pub fn convert_into<T: SampleFormat>(src: &[u8], dst: &mut [T]) {
+    assert_eq!(src.len(), dst.len() * T::RAW_LEN);
+    for (d, s) in dst.iter_mut().zip(src.chunks_exact(T::RAW_LEN)) {
+        *d = T::from_raw(s);
+    }
+}
+
+/// Convert raw samples, ignoring a trailing partial sample
+pub fn convert<T: SampleFormat>(src: &[u8]) -> Vec<T> {
+    src.chunks_exact(T::RAW_LEN).map(T::from_raw).collect()
+}
+
+/// Convert samples back into raw bytes in `dst`, which must hold
+/// `src.len() * T::RAW_LEN`
+pub fn to_raw_into<T: SampleFormat>(src: &[T], dst: &mut [u8]) {
+    assert_eq!(src.len() * T::RAW_LEN, dst.len());
+    for (s, d) in src.iter().zip(dst.chunks_exact_mut(T::RAW_LEN)) {
+        s.to_raw(d);
+    }
+}
+
+/// Append raw samples to `out` serialized as `T`
+pub fn encode<T: SampleFormat>(src: &[u8], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.resize(start + src.len() / T::RAW_LEN * T::SIZE, 0);
+    for (s, d) in src
+        .chunks_exact(T::RAW_LEN)
+        .zip(out[start..].chunks_exact_mut(T::SIZE))
+    {
+        T::from_raw(s).write_le(d);
+    }
+}
+
+/// Convert samples serialized as `T` into raw bytes in `dst`, which must
+/// hold `src.len() / T::SIZE * T::RAW_LEN`
+pub fn decode_into<T: SampleFormat>(src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len() / T::SIZE * T::RAW_LEN, dst.len());
+    for (s, d) in src
+        .chunks_exact(T::SIZE)
+        .zip(dst.chunks_exact_mut(T::RAW_LEN))
+    {
+        T::read_le(s).to_raw(d);
+    }
+}
@@ -0,0 +1,95 @@
+use super::{
+    convert, decode_into, encode, to_complex, to_complex_into, to_f32, to_i16, to_i16_into,
+    to_raw_into, SampleFormat,
+};
+use num_complex::Complex;
+
+#[test]
+fn test_to_i16() {
+    assert_eq!(vec![-127, 0, 128, 1], to_i16(&[0, 127, 255, 128]));
+}
+
+#[test]
+fn test_to_i16_into() {
+    let mut dst = [0_i16; 3];
+    to_i16_into(&[10, 127, 200], &mut dst);
+    assert_eq!([-117, 0, 73], dst);
+}
+
+#[test]
+#[should_panic]
+fn test_to_i16_into_length_mismatch() {
+    to_i16_into(&[0, 1], &mut [0; 3]);
+}
+
+#[test]
+fn test_to_f32() {
+    assert_eq!(vec![-127.0 / 128.0, 0.0, 1.0], to_f32(&[0, 127, 255]));
+}
+
+#[test]
+fn test_to_complex() {
+    let out = to_complex(&[255, 127, 63, 191, 1]);
+    assert_eq!(vec![Complex::new(1.0, 0.0), Complex::new(-0.5, 0.5)], out);
+}
+
+#[test]
+fn test_to_complex_into() {
+    let mut dst = [Complex::new(0.0, 0.0); 1];
+    to_complex_into(&[127, 255], &mut dst);
+    assert_eq!(Complex::new(0.0, 1.0), dst[0]);
+}
+
+#[test]
+fn test_sample_format_roundtrip() {
+    fn roundtrip<T: SampleFormat>() -> Vec<u8> {
+        let raw: Vec<u8> = (0..=255).collect();
+        let mut back = vec![0; raw.len()];
+        to_raw_into(&convert::<T>(&raw), &mut back);
+        back
+    }
+    let raw: Vec<u8> = (0..=255).collect();
+    assert_eq!(raw, roundtrip::<u8>());
+    assert_eq!(raw, roundtrip::<i16>());
+    assert_eq!(raw, roundtrip::<f32>());
+    assert_eq!(raw, roundtrip::<Complex<f32>>());
+    // i8 clips the single value above the midpoint that doesn't fit
+    assert_eq!(raw[..255], roundtrip::<i8>()[..255]);
+    assert_eq!(254, roundtrip::<i8>()[255]);
+}
+
+#[test]
+fn test_sample_format_scale() {
+    assert_eq!(vec![-127 << 8, 0, i16::MAX], convert::<i16>(&[0, 127, 255]));
+    assert_eq!(convert::<f32>(&[0, 127, 255]), to_f32(&[0, 127, 255]));
+    assert_eq!(
+        vec![Complex::new(1.0, -0.5)],
+        convert::<Complex<f32>>(&[255, 63, 1])
+    );
+}
+
+#[test]
+fn test_encode_decode() {
+    let mut bytes = vec![0xaa];
+    encode::<i16>(&[127, 255], &mut bytes);
+    assert_eq!(vec![0xaa, 0x00, 0x00, 0xff, 0x7f], bytes);
+    let mut raw = [0_u8; 2];
+    decode_into::<i16>(&bytes[1..], &mut raw);
+    assert_eq!([127, 255], raw);
+
+    let mut bytes = Vec::new();
+    encode::<Complex<f32>>(&[255, 127, 0], &mut bytes);
+    assert_eq!(8, bytes.len());
+    assert_eq!(1.0_f32.to_le_bytes(), bytes[..4]);
+    let mut raw = [0_u8; 2];
+    decode_into::<Complex<f32>>(&bytes, &mut raw);
+    assert_eq!([255, 127], raw);
+
+    // Out of range values clamp
+    let mut raw = [0_u8; 2];
+    decode_into::<f32>(
+        &[2.0_f32.to_le_bytes(), (-2.0_f32).to_le_bytes()].concat(),
+        &mut raw,
+    );
+    assert_eq!([255, 0], raw);
+}
@@ -0,0 +1,44 @@
+//! Streaming DC offset removal. Zero-IF tuners leave a spike at the center
+//! of the spectrum; this tracks the mean of I and Q with a single-pole IIR
+//! filter and subtracts it.
+
+use num_complex::Complex;
+
+pub struct DcBlocker {
+    alpha: f32,       // Filter coefficient, larger tracks the DC level faster
+    dc: Complex<f32>, // Current estimate of the DC offset
+}
+
+impl DcBlocker {
+    /// `alpha` is in (0, 1]. The cutoff is roughly `alpha * rate / 2pi` Hz,
+    /// so 1e-4 at 2.4 MS/s removes everything below about 40 Hz.
+    pub fn new(alpha: f32) -> DcBlocker {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "DC blocker alpha must be in (0, 1]"
+        );
+        DcBlocker {
+            alpha,
+            dc: Complex::new(0.0, 0.0),
+        }
+    }
+
+    /// Remove the DC offset from `buf` in place. State carries over between
+    /// calls, so buffers from a stream can be passed one after another.
+    pub fn process(&mut self, buf: &mut [Complex<f32>]) {
+        for s in buf.iter_mut() {
+            self.dc += (*s - self.dc) * self.alpha;
+            *s -= self.dc;
+        }
+    }
+
+    /// Current estimate of the DC offset
+    pub fn offset(&self) -> Complex<f32> {
+        self.dc
+    }
+
+    /// Forget the tracked offset, e.g. after retuning
+    pub fn reset(&mut self) {
+        self.dc = Complex::new(0.0, 0.0);
+    }
+}
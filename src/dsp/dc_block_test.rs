@@ -0,0 +1,47 @@
+use super::DcBlocker;
+use num_complex::Complex;
+
+#[test]
+fn test_dc_blocker_removes_offset() {
+    let offset = Complex::new(0.3, -0.2);
+    let mut dc = DcBlocker::new(0.01);
+    // Feed the stream in several buffers to check state is carried over
+    let mut buf = vec![offset; 1000];
+    for _ in 0..2 {
+        buf.fill(offset);
+        dc.process(&mut buf);
+    }
+    assert!((dc.offset() - offset).norm() < 1e-4);
+    assert!(buf.last().unwrap().norm() < 1e-4);
+}
+
+#[test]
+fn test_dc_blocker_passes_signal() {
+    let mut dc = DcBlocker::new(0.001);
+    // Tone at a quarter of the sample rate on top of a DC offset
+    let tone = [
+        Complex::new(1.0, 0.0),
+        Complex::new(0.0, 1.0),
+        Complex::new(-1.0, 0.0),
+        Complex::new(0.0, -1.0),
+    ];
+    let offset = Complex::new(0.5, 0.5);
+    let mut buf: Vec<Complex<f32>> = tone
+        .iter()
+        .cycle()
+        .take(40_000)
+        .map(|t| t + offset)
+        .collect();
+    dc.process(&mut buf);
+    for (out, t) in buf[buf.len() - 4..].iter().zip(tone) {
+        assert!((out - t).norm() < 0.01);
+    }
+}
+
+#[test]
+fn test_dc_blocker_reset() {
+    let mut dc = DcBlocker::new(1.0);
+    dc.process(&mut [Complex::new(1.0, 1.0)]);
+    dc.reset();
+    assert_eq!(Complex::new(0.0, 0.0), dc.offset());
+}
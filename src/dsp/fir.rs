@@ -0,0 +1,136 @@
+//! Windowed-sinc FIR filter design
+//!
+//! Frequencies are normalized to the sample rate (cycles per sample, 0 to
+//! 0.5). `rtl_fir` quantizes a design for the RTL2832 decimation filter set
+//! with `RtlSdr::set_fir`.
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::{Fir, FIR_LEN};
+use std::f64::consts::PI;
+
+// DC gain of the 32 hardware taps in the librtlsdr default filter
+const RTL_FIR_GAIN: f64 = 4238.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hamming,
+    Blackman,
+    Kaiser(f64), // Shape parameter beta, see `kaiser_beta`
+}
+
+impl Window {
+    /// Window value for tap `n` of `len`
+    pub fn value(self, n: usize, len: usize) -> f64 {
+        if len <= 1 {
+            return 1.0;
+        }
+        let x = n as f64 / (len - 1) as f64;
+        match self {
+            Window::Rectangular => 1.0,
+            Window::Hamming => 0.54 - 0.46 * (2.0 * PI * x).cos(),
+            Window::Blackman => 0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos(),
+            Window::Kaiser(beta) => {
+                let r = 2.0 * x - 1.0;
+                bessel_i0(beta * (1.0 - r * r).sqrt()) / bessel_i0(beta)
+            }
+        }
+    }
+}
+
+/// Kaiser beta giving about `attenuation` dB of stopband rejection
+pub fn kaiser_beta(attenuation: f64) -> f64 {
+    if attenuation > 50.0 {
+        0.1102 * (attenuation - 8.7)
+    } else if attenuation >= 21.0 {
+        0.5842 * (attenuation - 21.0).powf(0.4) + 0.07886 * (attenuation - 21.0)
+    } else {
+        0.0
+    }
+}
+
+/// Lowpass taps with unity gain at DC
+pub fn lowpass(len: usize, cutoff: f64, window: Window) -> Vec<f32> {
+    let taps = sinc(len, cutoff, window);
+    let gain: f64 = taps.iter().sum();
+    taps.iter().map(|t| (t / gain) as f32).collect()
+}
+
+/// Bandpass taps with unity gain at the center of the band
+pub fn bandpass(len: usize, low: f64, high: f64, window: Window) -> Vec<f32> {
+    assert!(low < high, "Bandpass low edge must be below the high edge");
+    let taps: Vec<f64> = sinc(len, high, window)
+        .iter()
+        .zip(sinc(len, low, window))
+        .map(|(h, l)| h - l)
+        .collect();
+    let gain = response(&taps, (low + high) / 2.0);
+    taps.iter().map(|t| (t / gain) as f32).collect()
+}
+
+/// Design the RTL2832 decimation filter: a 32 tap lowpass, of which the
+/// hardware takes the first half, scaled to the default filter's gain
+pub fn rtl_fir(cutoff: f64, window: Window) -> Result<Fir> {
+    let taps = lowpass(2 * FIR_LEN, cutoff, window);
+    let mut coeffs = [0_i32; FIR_LEN];
+    for (i, (c, t)) in coeffs.iter_mut().zip(&taps).enumerate() {
+        *c = (*t as f64 * RTL_FIR_GAIN).round() as i32;
+        // First 8 coefficients are 8 bits, the rest 12 bits
+        let max = if i < 8 { 127 } else { 2047 };
+        if !(-max - 1..=max).contains(c) {
+            return Err(RtlsdrErr(format!(
+                "FIR coefficient {} out of range for the hardware: {}",
+                i, c
+            )));
+        }
+    }
+    Ok(Fir::Custom(coeffs))
+}
+
+/// Unnormalized windowed sinc lowpass
+fn sinc(len: usize, cutoff: f64, window: Window) -> Vec<f64> {
+    assert!(len > 0, "Filter must have at least one tap");
+    assert!(
+        (0.0..=0.5).contains(&cutoff),
+        "Cutoff must be between 0 and 0.5 cycles per sample"
+    );
+    let mid = (len - 1) as f64 / 2.0;
+    (0..len)
+        .map(|n| {
+            let t = n as f64 - mid;
+            let s = if t == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * PI * cutoff * t).sin() / (PI * t)
+            };
+            s * window.value(n, len)
+        })
+        .collect()
+}
+
+/// Magnitude of the frequency response at `freq`
+fn response(taps: &[f64], freq: f64) -> f64 {
+    let (re, im) = taps
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, t)| {
+            let w = 2.0 * PI * freq * n as f64;
+            (re + t * w.cos(), im - t * w.sin())
+        });
+    (re * re + im * im).sqrt()
+}
+
+/// Zeroth order modified Bessel function of the first kind
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..50 {
+        term *= (x / (2.0 * k as f64)).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
@@ -0,0 +1,55 @@
+use super::fir::{bandpass, kaiser_beta, lowpass, rtl_fir, Window};
+use crate::Fir;
+use std::f32::consts::PI;
+
+/// Magnitude of the frequency response at `freq` cycles per sample
+fn gain(taps: &[f32], freq: f32) -> f32 {
+    let (re, im) = taps
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, t)| {
+            let w = 2.0 * PI * freq * n as f32;
+            (re + t * w.cos(), im - t * w.sin())
+        });
+    (re * re + im * im).sqrt()
+}
+
+#[test]
+fn test_lowpass() {
+    let taps = lowpass(63, 0.1, Window::Hamming);
+    assert_eq!(63, taps.len());
+    assert!((gain(&taps, 0.0) - 1.0).abs() < 1e-6);
+    assert!((gain(&taps, 0.05) - 1.0).abs() < 0.01);
+    assert!(gain(&taps, 0.2) < 0.01);
+    // Linear phase
+    assert!(taps.iter().zip(taps.iter().rev()).all(|(a, b)| a == b));
+}
+
+#[test]
+fn test_kaiser_attenuation() {
+    let taps = lowpass(101, 0.1, Window::Kaiser(kaiser_beta(80.0)));
+    let stop = (150..500)
+        .map(|f| gain(&taps, f as f32 / 1000.0))
+        .fold(0.0, f32::max);
+    assert!(20.0 * stop.log10() < -75.0);
+}
+
+#[test]
+fn test_bandpass() {
+    let taps = bandpass(101, 0.1, 0.2, Window::Blackman);
+    assert!((gain(&taps, 0.15) - 1.0).abs() < 1e-4);
+    assert!(gain(&taps, 0.0) < 1e-3);
+    assert!(gain(&taps, 0.35) < 1e-3);
+}
+
+#[test]
+fn test_rtl_fir() {
+    let fir = rtl_fir(0.2, Window::Kaiser(kaiser_beta(40.0))).unwrap();
+    let coeffs = match fir {
+        Fir::Custom(c) => c,
+        _ => unreachable!(),
+    };
+    assert!((2 * coeffs.iter().sum::<i32>() - 4238).abs() < 16);
+    // Wide enough to need a coefficient outside the 8 bit range
+    assert!(rtl_fir(0.5, Window::Rectangular).is_err());
+}
@@ -0,0 +1,73 @@
+//! Adaptive IQ gain and phase imbalance correction. Mismatched I and Q
+//! paths produce a mirror image of every signal on the other side of the
+//! center frequency; zero-IF tuners like the E4000 are the worst offenders.
+//!
+//! The imbalance is estimated blindly from running averages of I², Q² and
+//! I·Q, which assumes the signal is not itself asymmetric in the long run.
+
+use num_complex::Complex;
+
+pub struct IqBalancer {
+    rate: f32, // Averaging coefficient, larger adapts faster
+    ii: f32,   // Running mean of I²
+    qq: f32,   // Running mean of Q²
+    iq: f32,   // Running mean of I·Q
+}
+
+impl IqBalancer {
+    /// `rate` is in (0, 1]; around 1e-5 gives a stable estimate at typical
+    /// sample rates while still tracking temperature drift
+    pub fn new(rate: f32) -> IqBalancer {
+        assert!(
+            rate > 0.0 && rate <= 1.0,
+            "IQ balance rate must be in (0, 1]"
+        );
+        IqBalancer {
+            rate,
+            ii: 0.0,
+            qq: 0.0,
+            iq: 0.0,
+        }
+    }
+
+    /// Update the estimate from `buf` and correct it in place
+    pub fn process(&mut self, buf: &mut [Complex<f32>]) {
+        for s in buf.iter() {
+            self.ii += (s.re * s.re - self.ii) * self.rate;
+            self.qq += (s.im * s.im - self.qq) * self.rate;
+            self.iq += (s.re * s.im - self.iq) * self.rate;
+        }
+        let (gain, phase) = match (self.gain(), self.phase()) {
+            (Some(g), Some(p)) => (g, p),
+            _ => return,
+        };
+        // Q = g * (sin(wt) * cos(p) + I * sin(p)), solve for sin(wt)
+        let (sin, cos) = phase.sin_cos();
+        for s in buf.iter_mut() {
+            s.im = (s.im / gain - s.re * sin) / cos;
+        }
+    }
+
+    /// Estimated Q/I amplitude ratio, once there is enough signal
+    pub fn gain(&self) -> Option<f32> {
+        if self.ii <= f32::EPSILON || self.qq <= f32::EPSILON {
+            return None;
+        }
+        Some((self.qq / self.ii).sqrt())
+    }
+
+    /// Estimated phase error of Q relative to I in radians
+    pub fn phase(&self) -> Option<f32> {
+        if self.ii <= f32::EPSILON || self.qq <= f32::EPSILON {
+            return None;
+        }
+        let sin = (self.iq / (self.ii * self.qq).sqrt()).clamp(-0.99, 0.99);
+        Some(sin.asin())
+    }
+
+    pub fn reset(&mut self) {
+        self.ii = 0.0;
+        self.qq = 0.0;
+        self.iq = 0.0;
+    }
+}
@@ -0,0 +1,57 @@
+use super::IqBalancer;
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+const GAIN: f32 = 1.2;
+const PHASE: f32 = 0.1;
+
+/// Tone at 1/10 of the sample rate with a Q path gain and phase error
+fn imbalanced(len: usize, start: usize) -> Vec<Complex<f32>> {
+    (start..start + len)
+        .map(|n| {
+            let w = 2.0 * PI * n as f32 / 10.0;
+            Complex::new(w.cos(), GAIN * (w + PHASE).sin())
+        })
+        .collect()
+}
+
+/// Power of the mirror image relative to the tone, in dB
+fn image_rejection(buf: &[Complex<f32>], start: usize) -> f32 {
+    let mut tone = Complex::new(0.0, 0.0);
+    let mut image = Complex::new(0.0, 0.0);
+    for (i, s) in buf.iter().enumerate() {
+        let w = 2.0 * PI * (start + i) as f32 / 10.0;
+        tone += s * Complex::from_polar(1.0, -w);
+        image += s * Complex::from_polar(1.0, w);
+    }
+    10.0 * (tone.norm_sqr() / image.norm_sqr()).log10()
+}
+
+#[test]
+fn test_iq_balance_estimate() {
+    let mut bal = IqBalancer::new(1e-3);
+    bal.process(&mut imbalanced(20_000, 0));
+    assert!((bal.gain().unwrap() - GAIN).abs() < 0.01);
+    assert!((bal.phase().unwrap() - PHASE).abs() < 0.01);
+}
+
+#[test]
+fn test_iq_balance_improves_image_rejection() {
+    let mut bal = IqBalancer::new(1e-3);
+    bal.process(&mut imbalanced(20_000, 0));
+
+    let before = imbalanced(1000, 20_000);
+    let mut after = before.clone();
+    bal.process(&mut after);
+    assert!(image_rejection(&before, 20_000) < 25.0);
+    assert!(image_rejection(&after, 20_000) > 40.0);
+}
+
+#[test]
+fn test_iq_balance_no_signal() {
+    let mut bal = IqBalancer::new(0.5);
+    let mut buf = [Complex::new(0.0, 0.0); 16];
+    bal.process(&mut buf);
+    assert_eq!(None, bal.gain());
+    assert!(buf.iter().all(|s| s.norm() == 0.0));
+}
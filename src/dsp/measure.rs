@@ -0,0 +1,124 @@
+//! Input level measurement. Levels are in dBFS, where 0 dB is the power of a
+//! full scale complex tone (magnitude 1.0 after `to_complex`), so a full
+//! scale square wave on both I and Q can reach +3 dB.
+
+use super::convert::IQ_OFFSET;
+use super::spectrum::to_db;
+use num_complex::Complex;
+
+// Scale matching `to_complex`
+const SCALE: f32 = 1.0 / 128.0;
+/// Approximate input power in dBm that reaches ADC full scale with 0 dB of
+/// tuner gain, including the fixed VGA gain. Varies by a few dB between
+/// dongles and across frequency; store a `Calibration::gain_offset` to
+/// correct it.
+pub const FULL_SCALE_DBM: f32 = -10.0;
+
+/// IQ sample buffers whose level can be measured
+pub trait IqSamples {
+    /// Mean power of the samples, 0.0 for an empty buffer
+    fn mean_power(&self) -> f32;
+    /// Number of I or Q values at the ADC limits. Only raw samples can show
+    /// clipping, so this is 0 for converted buffers.
+    fn clipped(&self) -> usize;
+}
+
+impl IqSamples for [u8] {
+    fn mean_power(&self) -> f32 {
+        let pairs = self.len() / 2;
+        if pairs == 0 {
+            return 0.0;
+        }
+        let sum: u64 = self[..2 * pairs]
+            .iter()
+            .map(|s| {
+                let v = *s as i64 - IQ_OFFSET as i64;
+                (v * v) as u64
+            })
+            .sum();
+        sum as f32 * SCALE * SCALE / pairs as f32
+    }
+
+    fn clipped(&self) -> usize {
+        self.iter().filter(|s| **s == 0 || **s == 255).count()
+    }
+}
+
+impl IqSamples for [Complex<f32>] {
+    fn mean_power(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.iter().map(|s| s.norm_sqr()).sum::<f32>() / self.len() as f32
+    }
+
+    fn clipped(&self) -> usize {
+        0
+    }
+}
+
+/// Mean power of a buffer in dBFS
+pub fn power_dbfs<S: IqSamples + ?Sized>(buf: &S) -> f32 {
+    to_db(buf.mean_power())
+}
+
+/// Convert a level in dBFS to approximate antenna input power in dBm, given
+/// the tuner gain in tenths of a dB
+pub fn dbfs_to_dbm(dbfs: f32, gain: i32) -> f32 {
+    dbfs + FULL_SCALE_DBM - gain as f32 / 10.0
+}
+
+/// Tracks the input level over a stream of buffers
+pub struct LevelMeter {
+    alpha: f32,         // Weight of each new buffer, 1.0 for no averaging
+    power: Option<f32>, // Averaged linear power, None until the first buffer
+    clipped: usize,     // Clipped values in the last buffer
+}
+
+impl LevelMeter {
+    /// `alpha` is in (0, 1]. Each buffer's mean power is blended into the
+    /// level with this weight, so the time constant is about `1 / alpha`
+    /// buffers.
+    pub fn new(alpha: f32) -> LevelMeter {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "Level meter alpha must be in (0, 1]"
+        );
+        LevelMeter {
+            alpha,
+            power: None,
+            clipped: 0,
+        }
+    }
+
+    /// Measure a buffer and return the averaged level in dBFS
+    pub fn process<S: IqSamples + ?Sized>(&mut self, buf: &S) -> f32 {
+        let power = buf.mean_power();
+        self.power = Some(match self.power {
+            Some(p) => p + (power - p) * self.alpha,
+            None => power,
+        });
+        self.clipped = buf.clipped();
+        self.level()
+    }
+
+    /// Averaged level in dBFS, -inf before any buffer has been measured
+    pub fn level(&self) -> f32 {
+        self.power.map_or(f32::NEG_INFINITY, to_db)
+    }
+
+    /// Number of clipped I or Q values in the last buffer
+    pub fn clipped(&self) -> usize {
+        self.clipped
+    }
+
+    /// Whether the last buffer hit the ADC limits, suggesting lower gain
+    pub fn clipping(&self) -> bool {
+        self.clipped > 0
+    }
+
+    pub fn reset(&mut self) {
+        self.power = None;
+        self.clipped = 0;
+    }
+}
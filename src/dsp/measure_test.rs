@@ -0,0 +1,72 @@
+use super::measure::{dbfs_to_dbm, power_dbfs, IqSamples, LevelMeter, FULL_SCALE_DBM};
+use super::to_complex;
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+/// Raw samples of a complex tone with the given peak amplitude in ADC steps
+fn raw_tone(amplitude: f32, len: usize) -> Vec<u8> {
+    (0..len)
+        .flat_map(|n| {
+            let p = 2.0 * PI * n as f32 / 16.0;
+            [
+                (127.0 + amplitude * p.cos()).round().clamp(0.0, 255.0) as u8,
+                (127.0 + amplitude * p.sin()).round().clamp(0.0, 255.0) as u8,
+            ]
+        })
+        .collect()
+}
+
+#[test]
+fn test_power_dbfs_raw_and_complex_agree() {
+    let raw = raw_tone(64.0, 1024);
+    let level = power_dbfs(raw.as_slice());
+    assert!((level - -6.02).abs() < 0.1);
+    assert!((power_dbfs(to_complex(&raw).as_slice()) - level).abs() < 1e-3);
+}
+
+#[test]
+fn test_power_dbfs_silence() {
+    assert_eq!(0.0, [127_u8; 16].mean_power());
+    assert!(power_dbfs([127_u8; 16].as_slice()) < -150.0);
+    let empty: &[Complex<f32>] = &[];
+    assert_eq!(0.0, empty.mean_power());
+}
+
+#[test]
+fn test_clipping() {
+    let raw = raw_tone(200.0, 64);
+    assert!(raw.clipped() > 0);
+    assert_eq!(0, raw_tone(100.0, 64).clipped());
+
+    let mut meter = LevelMeter::new(1.0);
+    meter.process(raw.as_slice());
+    assert!(meter.clipping());
+    meter.process(raw_tone(100.0, 64).as_slice());
+    assert!(!meter.clipping());
+}
+
+#[test]
+fn test_level_meter_averaging() {
+    let mut meter = LevelMeter::new(0.1);
+    assert_eq!(f32::NEG_INFINITY, meter.level());
+    let loud = vec![Complex::new(1.0, 0.0); 100];
+    let quiet = vec![Complex::new(0.1, 0.0); 100];
+    // First buffer sets the level directly
+    assert!(meter.process(loud.as_slice()).abs() < 1e-5);
+    // Then moves towards the new level a fraction at a time
+    let level = meter.process(quiet.as_slice());
+    assert!(level < 0.0 && level > -1.0);
+    for _ in 0..200 {
+        meter.process(quiet.as_slice());
+    }
+    assert!((meter.level() - -20.0).abs() < 0.01);
+    meter.reset();
+    assert_eq!(f32::NEG_INFINITY, meter.level());
+}
+
+#[test]
+fn test_dbfs_to_dbm() {
+    assert_eq!(FULL_SCALE_DBM, dbfs_to_dbm(0.0, 0));
+    // More gain means the same level came from a weaker input
+    assert!((dbfs_to_dbm(-20.0, 496) - (FULL_SCALE_DBM - 69.6)).abs() < 1e-4);
+}
@@ -0,0 +1,50 @@
+//! Signal processing helpers for the raw IQ samples returned by `read_sync`
+pub mod cic;
+pub use cic::CicDecimator;
+pub mod convert;
+pub use convert::*;
+pub mod dc_block;
+pub use dc_block::DcBlocker;
+pub mod fir;
+pub use fir::Window;
+pub mod iq_balance;
+pub use iq_balance::IqBalancer;
+pub mod measure;
+pub use measure::{dbfs_to_dbm, power_dbfs, LevelMeter};
+pub mod nco;
+pub use nco::Nco;
+pub mod resample;
+pub use resample::{ResampleQuality, Resampler};
+pub mod rotate;
+pub use rotate::{rotate_90, rotate_90_scalar};
+pub mod spectrum;
+pub use spectrum::{Spectrum, Trace, TraceMode};
+pub mod squelch;
+pub use squelch::{Squelch, SquelchMode};
+pub mod waterfall;
+pub use waterfall::{Detector, Waterfall};
+
+#[cfg(test)]
+mod cic_test;
+#[cfg(test)]
+mod convert_test;
+#[cfg(test)]
+mod dc_block_test;
+#[cfg(test)]
+mod fir_test;
+#[cfg(test)]
+mod iq_balance_test;
+#[cfg(test)]
+mod measure_test;
+#[cfg(test)]
+mod nco_test;
+#[cfg(test)]
+mod resample_test;
+#[cfg(test)]
+mod rotate_test;
+#[cfg(test)]
+mod spectrum_test;
+#[cfg(test)]
+mod squelch_test;
+#[cfg(test)]
+mod waterfall_test;
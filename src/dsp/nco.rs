@@ -0,0 +1,68 @@
+//! Frequency translation with a complex NCO, for offset tuning in software.
+//! Tuning the hardware away from a signal and shifting it back here moves
+//! the DC spike out of the channel, and channels off-center in a wideband
+//! capture can be brought to 0 Hz before filtering and decimation.
+
+use num_complex::Complex;
+
+// Samples between renormalizations of the oscillator phasor
+const RENORM_INTERVAL: usize = 1024;
+
+pub struct Nco {
+    rate: f64,
+    freq: f64,            // Hz
+    phasor: Complex<f64>, // Current oscillator output
+    step: Complex<f64>,   // Rotation per sample
+}
+
+impl Nco {
+    /// Shift by `freq` Hz at `rate` samples per second. Positive values move
+    /// the spectrum up, so a channel at +100 kHz is brought to 0 Hz with
+    /// -100 kHz.
+    pub fn new(freq: f64, rate: u32) -> Nco {
+        let mut nco = Nco {
+            rate: rate as f64,
+            freq: 0.0,
+            phasor: Complex::new(1.0, 0.0),
+            step: Complex::new(1.0, 0.0),
+        };
+        nco.set_frequency(freq);
+        nco
+    }
+
+    /// Change the shift without a phase discontinuity
+    pub fn set_frequency(&mut self, freq: f64) {
+        self.freq = freq;
+        self.step = Complex::from_polar(1.0, 2.0 * std::f64::consts::PI * freq / self.rate);
+    }
+
+    pub fn frequency(&self) -> f64 {
+        self.freq
+    }
+
+    /// Shift `buf` in place. Phase carries over between calls, so buffers
+    /// from a stream can be passed one after another.
+    pub fn mix(&mut self, buf: &mut [Complex<f32>]) {
+        for chunk in buf.chunks_mut(RENORM_INTERVAL) {
+            for s in chunk.iter_mut() {
+                let out = Complex::new(s.re as f64, s.im as f64) * self.phasor;
+                *s = Complex::new(out.re as f32, out.im as f32);
+                self.phasor *= self.step;
+            }
+            // Keep rounding errors from growing or shrinking the amplitude
+            self.phasor /= self.phasor.norm();
+        }
+    }
+
+    /// Shift `src` into `dst`, which must be the same length
+    pub fn mix_into(&mut self, src: &[Complex<f32>], dst: &mut [Complex<f32>]) {
+        assert_eq!(src.len(), dst.len(), "Output must match input length");
+        dst.copy_from_slice(src);
+        self.mix(dst);
+    }
+
+    /// Restart the oscillator at zero phase
+    pub fn reset(&mut self) {
+        self.phasor = Complex::new(1.0, 0.0);
+    }
+}
@@ -0,0 +1,58 @@
+use super::nco::Nco;
+use num_complex::Complex;
+use std::f64::consts::PI;
+
+fn tone(freq: f64, rate: f64, len: usize) -> Vec<Complex<f32>> {
+    (0..len)
+        .map(|n| {
+            let p = 2.0 * PI * freq * n as f64 / rate;
+            Complex::new(p.cos() as f32, p.sin() as f32)
+        })
+        .collect()
+}
+
+#[test]
+fn test_shift_to_dc() {
+    let mut buf = tone(100_000.0, 2_400_000.0, 10_000);
+    let mut nco = Nco::new(-100_000.0, 2_400_000);
+    // Split mid-stream to check the phase carries over
+    let (a, b) = buf.split_at_mut(3333);
+    nco.mix(a);
+    nco.mix(b);
+    for s in &buf {
+        assert!((s - Complex::new(1.0, 0.0)).norm() < 1e-4);
+    }
+}
+
+#[test]
+fn test_shift_up() {
+    let src = tone(0.0, 1.0, 1000);
+    let mut dst = vec![Complex::new(0.0, 0.0); 1000];
+    let mut nco = Nco::new(25_000.0, 250_000);
+    nco.mix_into(&src, &mut dst);
+    for (d, e) in dst.iter().zip(tone(25_000.0, 250_000.0, 1000)) {
+        assert!((d - e).norm() < 1e-4);
+    }
+}
+
+#[test]
+fn test_amplitude_stable() {
+    let mut buf = vec![Complex::new(1.0, 0.0); 1_000_000];
+    let mut nco = Nco::new(12_345.678, 2_048_000);
+    nco.mix(&mut buf);
+    assert!((buf.last().unwrap().norm() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_retune_continuous_phase() {
+    let mut buf = vec![Complex::new(1.0, 0.0); 200];
+    let mut nco = Nco::new(1_000.0, 8_000);
+    nco.mix(&mut buf[..100]);
+    nco.set_frequency(2_000.0);
+    assert_eq!(2_000.0, nco.frequency());
+    nco.mix(&mut buf[100..]);
+    // 100 samples at 1/8 cycle each leave the phase at half a cycle, then
+    // it advances a quarter cycle per sample
+    assert!((buf[100] - Complex::new(-1.0, 0.0)).norm() < 1e-5);
+    assert!((buf[102] - Complex::new(1.0, 0.0)).norm() < 1e-5);
+}
@@ -0,0 +1,118 @@
+//! Arbitrary-ratio polyphase resampler
+//!
+//! The anti-aliasing filter is a windowed sinc stored as a bank of
+//! fractional-delay phases. Output samples between two phases interpolate
+//! their coefficients, so any pair of rates can be converted, e.g. 170 kHz
+//! FM audio to 48 kHz for a sound card.
+
+use std::f64::consts::PI;
+use std::ops::{Add, Mul};
+
+const PHASES: usize = 64;
+const ROLLOFF: f64 = 0.9; // Cutoff as a fraction of the lower Nyquist frequency
+
+/// Filter length. When decimating the filters are longer by the decimation
+/// ratio, keeping the transition band width the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResampleQuality {
+    Fast,   // 8 taps per phase
+    Medium, // 16 taps per phase
+    High,   // 32 taps per phase
+}
+
+impl ResampleQuality {
+    fn taps(self) -> usize {
+        match self {
+            ResampleQuality::Fast => 8,
+            ResampleQuality::Medium => 16,
+            ResampleQuality::High => 32,
+        }
+    }
+}
+
+/// Resampler for a stream of real (`f32`) or complex (`Complex<f32>`) samples
+pub struct Resampler<T> {
+    step: f64, // Input samples per output sample
+    pos: f64,  // Position of the next output in `history`
+    taps: usize,
+    bank: Vec<f32>, // (PHASES + 1) filters of `taps` coefficients
+    history: Vec<T>,
+}
+
+impl<T> Resampler<T>
+where
+    T: Copy + Default + Add<Output = T> + Mul<f32, Output = T>,
+{
+    pub fn new(rate_in: u32, rate_out: u32, quality: ResampleQuality) -> Self {
+        assert!(
+            rate_in > 0 && rate_out > 0,
+            "Resampler rates must be non-zero"
+        );
+        let step = rate_in as f64 / rate_out as f64;
+        let taps = quality.taps() * step.ceil().max(1.0) as usize;
+        // Cutoff in cycles per input sample
+        let fc = 0.5 * ROLLOFF * (1.0 / step).min(1.0);
+        let mut bank = Vec::with_capacity((PHASES + 1) * taps);
+        for p in 0..=PHASES {
+            let frac = p as f64 / PHASES as f64;
+            let phase: Vec<f64> = (0..taps)
+                .map(|k| {
+                    // Distance from the output point to input sample k
+                    let t = k as f64 - (taps / 2 - 1) as f64 - frac;
+                    let sinc = if t == 0.0 {
+                        2.0 * fc
+                    } else {
+                        (2.0 * PI * fc * t).sin() / (PI * t)
+                    };
+                    // Blackman window over the span of the filter
+                    let u = (t + taps as f64 / 2.0) / taps as f64;
+                    let window = 0.42 - 0.5 * (2.0 * PI * u).cos() + 0.08 * (4.0 * PI * u).cos();
+                    sinc * window
+                })
+                .collect();
+            // Unity gain at DC for every phase
+            let sum: f64 = phase.iter().sum();
+            bank.extend(phase.iter().map(|c| (c / sum) as f32));
+        }
+        Resampler {
+            step,
+            pos: 0.0,
+            taps,
+            bank,
+            history: vec![T::default(); taps - 1],
+        }
+    }
+
+    /// Resample `input`, appending the output to `out`. State carries over
+    /// between calls so a stream can be fed in buffers of any size.
+    pub fn process_into(&mut self, input: &[T], out: &mut Vec<T>) {
+        self.history.extend_from_slice(input);
+        let mut coefs = vec![0.0_f32; self.taps];
+        while (self.pos as usize) + self.taps <= self.history.len() {
+            let i = self.pos as usize;
+            let p = (self.pos - i as f64) * PHASES as f64;
+            let (pi, pf) = (p as usize, (p - p.floor()) as f32);
+            let a = &self.bank[pi * self.taps..(pi + 1) * self.taps];
+            let b = &self.bank[(pi + 1) * self.taps..(pi + 2) * self.taps];
+            for (c, (a, b)) in coefs.iter_mut().zip(a.iter().zip(b)) {
+                *c = a + (b - a) * pf;
+            }
+            let y = self.history[i..i + self.taps]
+                .iter()
+                .zip(&coefs)
+                .fold(T::default(), |acc, (x, c)| acc + *x * *c);
+            out.push(y);
+            self.pos += self.step;
+        }
+        // Keep the samples still needed for the next output
+        let keep_from = (self.pos as usize).min(self.history.len());
+        self.history.drain(..keep_from);
+        self.pos -= keep_from as f64;
+    }
+
+    pub fn process(&mut self, input: &[T]) -> Vec<T> {
+        let mut out = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        self.process_into(input, &mut out);
+        out
+    }
+}
@@ -0,0 +1,78 @@
+use super::{ResampleQuality, Resampler};
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+fn tone(freq: f32, rate: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| (2.0 * PI * freq * n as f32 / rate).sin())
+        .collect()
+}
+
+fn rms(buf: &[f32]) -> f32 {
+    (buf.iter().map(|x| x * x).sum::<f32>() / buf.len() as f32).sqrt()
+}
+
+#[test]
+fn test_resample_length() {
+    let mut rs = Resampler::<f32>::new(170_000, 48_000, ResampleQuality::Medium);
+    let out = rs.process(&vec![0.0; 170_000]);
+    assert!((out.len() as i32 - 48_000).abs() <= 16);
+}
+
+#[test]
+fn test_resample_dc_gain() {
+    let mut rs = Resampler::<f32>::new(44_100, 48_000, ResampleQuality::Fast);
+    let out = rs.process(&vec![1.0; 4410]);
+    assert!(out[100..].iter().all(|y| (y - 1.0).abs() < 1e-3));
+}
+
+#[test]
+fn test_resample_passband_and_alias() {
+    for quality in [
+        ResampleQuality::Fast,
+        ResampleQuality::Medium,
+        ResampleQuality::High,
+    ] {
+        let mut rs = Resampler::<f32>::new(170_000, 48_000, quality);
+        let out = rs.process(&tone(1_000.0, 170_000.0, 34_000));
+        let level = rms(&out[100..]);
+        assert!(
+            (level - 0.5_f32.sqrt()).abs() < 0.02,
+            "{:?} {}",
+            quality,
+            level
+        );
+
+        // Above the output Nyquist frequency, so it must not alias through
+        let mut rs = Resampler::<f32>::new(170_000, 48_000, quality);
+        let out = rs.process(&tone(40_000.0, 170_000.0, 34_000));
+        let level = rms(&out[100..]);
+        assert!(level < 0.05, "{:?} {}", quality, level);
+    }
+}
+
+#[test]
+fn test_resample_chunked() {
+    let input = tone(3_000.0, 32_000.0, 5000);
+    let mut rs = Resampler::<f32>::new(32_000, 48_000, ResampleQuality::High);
+    let whole = rs.process(&input);
+
+    let mut rs = Resampler::<f32>::new(32_000, 48_000, ResampleQuality::High);
+    let mut chunked = Vec::new();
+    for chunk in input.chunks(333) {
+        rs.process_into(chunk, &mut chunked);
+    }
+    assert_eq!(whole.len(), chunked.len());
+    assert!(whole
+        .iter()
+        .zip(&chunked)
+        .all(|(a, b)| (a - b).abs() < 1e-5));
+}
+
+#[test]
+fn test_resample_complex() {
+    let mut rs = Resampler::<Complex<f32>>::new(2_400_000, 240_000, ResampleQuality::Medium);
+    let out = rs.process(&vec![Complex::new(0.5, -0.5); 24_000]);
+    assert_eq!(2400, out.len());
+    assert!((out[2000] - Complex::new(0.5, -0.5)).norm() < 1e-3);
+}
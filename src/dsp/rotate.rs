@@ -0,0 +1,119 @@
+//! 90 degree rotation of IQ samples, shifting the spectrum by a quarter of
+//! the sample rate. Used with offset tuning to move the signal of interest
+//! away from the DC spike and back to the center.
+//!
+//! SIMD versions are picked at runtime; build with the `disable-simd` feature
+//! to always use the scalar code.
+
+// Each group of 4 samples is multiplied by 1, j, -1, -j. In bytes that swaps
+// I and Q of every odd sample and inverts (255 - x) the components that
+// become negative.
+#[cfg(not(feature = "disable-simd"))]
+const SHUFFLE: [u8; 16] = [0, 1, 3, 2, 4, 5, 7, 6, 8, 9, 11, 10, 12, 13, 15, 14];
+#[cfg(not(feature = "disable-simd"))]
+const INVERT: [u8; 16] = [
+    0, 0, 0xff, 0, 0xff, 0xff, 0, 0xff, 0, 0, 0xff, 0, 0xff, 0xff, 0, 0xff,
+];
+
+/// Rotate interleaved u8 IQ samples in place, in groups of 8 bytes (4
+/// samples). Trailing bytes that don't make up a whole group are left
+/// untouched; buffers filled by `read_sync` never have any.
+pub fn rotate_90(buf: &mut [u8]) {
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "disable-simd")
+    ))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { rotate_90_avx2(buf) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { rotate_90_ssse3(buf) };
+        }
+    }
+    #[cfg(all(target_arch = "aarch64", not(feature = "disable-simd")))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { rotate_90_neon(buf) };
+        }
+    }
+    rotate_90_scalar(buf)
+}
+
+/// Reference implementation, also used for the tail of the SIMD versions.
+/// Like `rotate_90`, leaves a partial group at the end untouched.
+pub fn rotate_90_scalar(buf: &mut [u8]) {
+    for c in buf.chunks_exact_mut(8) {
+        let tmp = 255 - c[3];
+        c[3] = c[2];
+        c[2] = tmp;
+
+        c[4] = 255 - c[4];
+        c[5] = 255 - c[5];
+
+        let tmp = 255 - c[6];
+        c[6] = c[7];
+        c[7] = tmp;
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(feature = "disable-simd")
+))]
+#[target_feature(enable = "ssse3")]
+pub(super) unsafe fn rotate_90_ssse3(buf: &mut [u8]) {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    let shuffle = _mm_loadu_si128(SHUFFLE.as_ptr() as *const __m128i);
+    let invert = _mm_loadu_si128(INVERT.as_ptr() as *const __m128i);
+    let mut chunks = buf.chunks_exact_mut(16);
+    for c in &mut chunks {
+        let ptr = c.as_mut_ptr() as *mut __m128i;
+        let v = _mm_shuffle_epi8(_mm_loadu_si128(ptr), shuffle);
+        _mm_storeu_si128(ptr, _mm_xor_si128(v, invert));
+    }
+    rotate_90_scalar(chunks.into_remainder());
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(feature = "disable-simd")
+))]
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn rotate_90_avx2(buf: &mut [u8]) {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    // The shuffle works within each 128-bit lane, so both halves use the
+    // same pattern
+    let shuffle = _mm256_broadcastsi128_si256(_mm_loadu_si128(SHUFFLE.as_ptr() as *const __m128i));
+    let invert = _mm256_broadcastsi128_si256(_mm_loadu_si128(INVERT.as_ptr() as *const __m128i));
+    let mut chunks = buf.chunks_exact_mut(32);
+    for c in &mut chunks {
+        let ptr = c.as_mut_ptr() as *mut __m256i;
+        let v = _mm256_shuffle_epi8(_mm256_loadu_si256(ptr), shuffle);
+        _mm256_storeu_si256(ptr, _mm256_xor_si256(v, invert));
+    }
+    rotate_90_scalar(chunks.into_remainder());
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "disable-simd")))]
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn rotate_90_neon(buf: &mut [u8]) {
+    use std::arch::aarch64::*;
+
+    let shuffle = vld1q_u8(SHUFFLE.as_ptr());
+    let invert = vld1q_u8(INVERT.as_ptr());
+    let mut chunks = buf.chunks_exact_mut(16);
+    for c in &mut chunks {
+        let v = vqtbl1q_u8(vld1q_u8(c.as_ptr()), shuffle);
+        vst1q_u8(c.as_mut_ptr(), veorq_u8(v, invert));
+    }
+    rotate_90_scalar(chunks.into_remainder());
+}
@@ -0,0 +1,76 @@
+use super::rotate::{rotate_90, rotate_90_scalar};
+
+/// Deterministic pseudo-random bytes, with a length that leaves a tail for
+/// every SIMD width
+fn test_data() -> Vec<u8> {
+    let mut x: u32 = 0x1234_5678;
+    (0..1000)
+        .map(|_| {
+            x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (x >> 24) as u8
+        })
+        .collect()
+}
+
+fn reference() -> Vec<u8> {
+    let mut buf = test_data();
+    rotate_90_scalar(&mut buf);
+    buf
+}
+
+#[test]
+fn test_rotate_90_scalar() {
+    let mut buf = [0, 1, 2, 3, 4, 5, 6, 7];
+    rotate_90_scalar(&mut buf);
+    assert_eq!([0, 1, 252, 2, 251, 250, 7, 249], buf);
+}
+
+#[test]
+fn test_rotate_90() {
+    let mut buf = test_data();
+    rotate_90(&mut buf);
+    assert_eq!(reference(), buf);
+}
+
+#[test]
+fn test_rotate_90_partial_group() {
+    let mut buf = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+    rotate_90(&mut buf);
+    assert_eq!([0, 1, 252, 2, 251, 250, 7, 249, 8, 9, 10, 11], buf);
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(feature = "disable-simd")
+))]
+#[test]
+fn test_rotate_90_ssse3() {
+    if !is_x86_feature_detected!("ssse3") {
+        return;
+    }
+    let mut buf = test_data();
+    unsafe { super::rotate::rotate_90_ssse3(&mut buf) };
+    assert_eq!(reference(), buf);
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(feature = "disable-simd")
+))]
+#[test]
+fn test_rotate_90_avx2() {
+    if !is_x86_feature_detected!("avx2") {
+        return;
+    }
+    let mut buf = test_data();
+    unsafe { super::rotate::rotate_90_avx2(&mut buf) };
+    assert_eq!(reference(), buf);
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "disable-simd")))]
+#[test]
+fn test_rotate_90_neon() {
+    let mut buf = test_data();
+    unsafe { super::rotate::rotate_90_neon(&mut buf) };
+    assert_eq!(reference(), buf);
+}
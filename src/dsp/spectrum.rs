@@ -0,0 +1,198 @@
+//! Power spectrum of IQ samples via a windowed radix-2 FFT
+
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+pub struct Spectrum {
+    size: usize,
+    window: Vec<f32>, // Hann, normalized to unity coherent gain
+    twiddles: Vec<Complex<f32>>,
+    buf: Vec<Complex<f32>>,
+}
+
+impl Spectrum {
+    /// `size` must be a power of two
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two");
+        let window: Vec<f32> = (0..size)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / size as f32).cos())
+            .collect();
+        let gain: f32 = window.iter().sum();
+        Spectrum {
+            size,
+            window: window.iter().map(|w| w / gain).collect(),
+            twiddles: (0..size / 2)
+                .map(|k| Complex::from_polar(1.0, -2.0 * PI * k as f32 / size as f32))
+                .collect(),
+            buf: vec![Complex::new(0.0, 0.0); size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Linear power of each bin for `size` samples (scaled to +/-1.0), with
+    /// DC in the middle and the lowest frequency first. A full scale tone
+    /// centered on a bin reads 1.0.
+    pub fn power(&mut self, samples: &[Complex<f32>], out: &mut [f32]) {
+        assert_eq!(
+            samples.len(),
+            self.size,
+            "Expected one FFT frame of samples"
+        );
+        assert_eq!(out.len(), self.size, "Output must have one value per bin");
+        for (b, (s, w)) in self.buf.iter_mut().zip(samples.iter().zip(&self.window)) {
+            *b = s * w;
+        }
+        fft(&mut self.buf, &self.twiddles);
+        let half = self.size / 2;
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = self.buf[(i + half) % self.size].norm_sqr();
+        }
+    }
+
+    /// Like `power`, in dBFS
+    pub fn power_db(&mut self, samples: &[Complex<f32>], out: &mut [f32]) {
+        self.power(samples, out);
+        for o in out.iter_mut() {
+            *o = to_db(*o);
+        }
+    }
+}
+
+pub(super) fn to_db(power: f32) -> f32 {
+    10.0 * power.max(1e-20).log10()
+}
+
+/// In-place iterative radix-2 FFT
+fn fft(buf: &mut [Complex<f32>], twiddles: &[Complex<f32>]) {
+    let n = buf.len();
+    let bits = n.trailing_zeros();
+    if bits == 0 {
+        return;
+    }
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            buf.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = n / len;
+        for chunk in buf.chunks_exact_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            for (k, (a, b)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+                let t = *b * twiddles[k * step];
+                *b = *a - t;
+                *a += t;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// How a `Trace` combines successive spectra
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceMode {
+    Average { frames: usize }, // RMS average, exponential over about `frames` spectra
+    PeakHold { decay: f32 },   // dB per spectrum the held peaks fall, 0.0 to hold forever
+    MinHold,
+}
+
+/// Spectrum trace combining FFT frames with an averaging or hold detector
+pub struct Trace {
+    mode: TraceMode,
+    spectrum: Spectrum,
+    pending: Vec<Complex<f32>>, // Samples not yet making a full FFT frame
+    power: Vec<f32>,
+    trace: Vec<f32>, // Linear power per bin
+    count: usize,    // Spectra since the last reset
+}
+
+impl Trace {
+    /// `size` must be a power of two
+    pub fn new(size: usize, mode: TraceMode) -> Self {
+        if let TraceMode::Average { frames } = mode {
+            assert!(frames > 0, "Average must cover at least one frame");
+        }
+        Trace {
+            mode,
+            spectrum: Spectrum::new(size),
+            pending: Vec::with_capacity(size),
+            power: vec![0.0; size],
+            trace: vec![0.0; size],
+            count: 0,
+        }
+    }
+
+    /// Add IQ samples (scaled to +/-1.0), updating the trace for each
+    /// complete FFT frame
+    pub fn process(&mut self, buf: &[Complex<f32>]) {
+        let size = self.spectrum.size();
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let n = (size - self.pending.len()).min(buf.len());
+            self.pending.extend_from_slice(&buf[..n]);
+            buf = &buf[n..];
+            if self.pending.len() == size {
+                self.spectrum.power(&self.pending, &mut self.power);
+                self.pending.clear();
+                self.update();
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        self.count += 1;
+        if self.count == 1 {
+            self.trace.copy_from_slice(&self.power);
+            return;
+        }
+        match self.mode {
+            TraceMode::Average { frames } => {
+                // Plain mean until `frames` spectra have been seen
+                let alpha = 1.0 / self.count.min(frames) as f32;
+                for (t, p) in self.trace.iter_mut().zip(&self.power) {
+                    *t += (p - *t) * alpha;
+                }
+            }
+            TraceMode::PeakHold { decay } => {
+                let decay = 10_f32.powf(-decay.abs() / 10.0);
+                for (t, p) in self.trace.iter_mut().zip(&self.power) {
+                    *t = (*t * decay).max(*p);
+                }
+            }
+            TraceMode::MinHold => {
+                for (t, p) in self.trace.iter_mut().zip(&self.power) {
+                    *t = t.min(*p);
+                }
+            }
+        }
+    }
+
+    /// Current trace in dBFS, DC in the middle and the lowest frequency first
+    pub fn trace_db(&self, out: &mut [f32]) {
+        assert_eq!(
+            out.len(),
+            self.trace.len(),
+            "Output must have one value per bin"
+        );
+        for (o, t) in out.iter_mut().zip(&self.trace) {
+            *o = to_db(*t);
+        }
+    }
+
+    /// Number of spectra combined since the last reset
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Clear the trace and any partial frame
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.trace.fill(0.0);
+        self.count = 0;
+    }
+}
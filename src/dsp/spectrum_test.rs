@@ -0,0 +1,108 @@
+use super::spectrum::{Spectrum, Trace, TraceMode};
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+fn tone(bin: f32, size: usize) -> Vec<Complex<f32>> {
+    (0..size)
+        .map(|n| Complex::from_polar(1.0, 2.0 * PI * bin * n as f32 / size as f32))
+        .collect()
+}
+
+#[test]
+fn test_tone_bin() {
+    let mut spectrum = Spectrum::new(256);
+    let mut out = vec![0.0; 256];
+    // Positive frequencies land above the center bin
+    spectrum.power(&tone(32.0, 256), &mut out);
+    let peak = (0..256).max_by(|a, b| out[*a].total_cmp(&out[*b])).unwrap();
+    assert_eq!(128 + 32, peak);
+    assert!((out[peak] - 1.0).abs() < 1e-4);
+
+    spectrum.power_db(&tone(-10.0, 256), &mut out);
+    assert!(out[118].abs() < 1e-3);
+    // Hann sidelobes fall off quickly away from the tone
+    assert!(out[150] < -80.0);
+}
+
+#[test]
+fn test_parseval() {
+    // Noise-like input: total power matches the Hann-weighted time power
+    let size = 64;
+    let samples: Vec<Complex<f32>> = (0..size)
+        .map(|n| {
+            Complex::new(
+                ((n * 37) % 11) as f32 / 11.0 - 0.5,
+                ((n * 13) % 7) as f32 / 7.0 - 0.5,
+            )
+        })
+        .collect();
+    let mut spectrum = Spectrum::new(size);
+    let mut out = vec![0.0; size];
+    spectrum.power(&samples, &mut out);
+    let gain = size as f32 / 2.0;
+    let expected: f32 = samples
+        .iter()
+        .enumerate()
+        .map(|(n, s)| {
+            let w = (0.5 - 0.5 * (2.0 * PI * n as f32 / size as f32).cos()) / gain;
+            s.norm_sqr() * w * w
+        })
+        .sum::<f32>()
+        * size as f32;
+    assert!((out.iter().sum::<f32>() - expected).abs() < 1e-4);
+}
+
+/// Frames of a tone at `bin` with the given amplitude per frame
+fn frames(bin: f32, amplitudes: &[f32]) -> Vec<Complex<f32>> {
+    amplitudes
+        .iter()
+        .flat_map(|a| tone(bin, 64).into_iter().map(move |s| s * *a))
+        .collect()
+}
+
+fn trace(mode: TraceMode, samples: &[Complex<f32>]) -> Vec<f32> {
+    let mut trace = Trace::new(64, mode);
+    // Odd chunks split frames across calls
+    for c in samples.chunks(50) {
+        trace.process(c);
+    }
+    let mut out = vec![0.0; 64];
+    trace.trace_db(&mut out);
+    out
+}
+
+#[test]
+fn test_trace_average() {
+    let samples = frames(8.0, &[1.0, 0.0, 0.0, 1.0]);
+    // Mean power of 0.5 over four frames
+    let out = trace(TraceMode::Average { frames: 4 }, &samples);
+    assert!((out[40] - -3.01).abs() < 0.01);
+    // Exponential over two frames weights later spectra more
+    let out = trace(TraceMode::Average { frames: 2 }, &samples);
+    assert!((out[40] - 10.0 * 0.625_f32.log10()).abs() < 0.01);
+}
+
+#[test]
+fn test_trace_holds() {
+    let samples = frames(8.0, &[0.1, 1.0, 0.1, 0.1]);
+    let out = trace(TraceMode::PeakHold { decay: 0.0 }, &samples);
+    assert!(out[40].abs() < 0.01);
+    // Held peak falls 1 dB for each of the two following spectra
+    let out = trace(TraceMode::PeakHold { decay: 1.0 }, &samples);
+    assert!((out[40] - -2.0).abs() < 0.01);
+    let out = trace(TraceMode::MinHold, &samples);
+    assert!((out[40] - -20.0).abs() < 0.01);
+}
+
+#[test]
+fn test_trace_reset() {
+    let mut trace = Trace::new(64, TraceMode::MinHold);
+    trace.process(&frames(8.0, &[1.0, 1.0])[..100]);
+    assert_eq!(1, trace.count());
+    trace.reset();
+    trace.process(&frames(8.0, &[0.5])[..64]);
+    assert_eq!(1, trace.count());
+    let mut out = vec![0.0; 64];
+    trace.trace_db(&mut out);
+    assert!((out[40] - -6.02).abs() < 0.01);
+}
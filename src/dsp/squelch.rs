@@ -0,0 +1,102 @@
+//! Squelch: gates output on whether a signal is present in the channel
+//!
+//! `Power` mode compares the channel power against a threshold. `Noise` mode
+//! compares the power of the sample-to-sample difference with the total
+//! power, which drops when a carrier quiets the receiver noise, so it keeps
+//! working as the noise floor changes with gain.
+
+use num_complex::Complex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SquelchMode {
+    Power, // Threshold is channel power in dBFS, opens above it
+    Noise, // Threshold is the noise ratio in dB (about 3 for pure noise), opens below it
+}
+
+pub struct Squelch<F: FnMut(bool)> {
+    mode: SquelchMode,
+    threshold: f32,  // dB
+    hysteresis: f32, // dB the level must move back past the threshold to close
+    level: f32,      // dB, last measurement
+    open: bool,
+    prev: Complex<f32>,
+    callback: F, // Called with true on open and false on close
+}
+
+impl<F: FnMut(bool)> Squelch<F> {
+    pub fn new(mode: SquelchMode, threshold: f32, hysteresis: f32, callback: F) -> Self {
+        Squelch {
+            mode,
+            threshold,
+            hysteresis: hysteresis.abs(),
+            level: f32::NEG_INFINITY,
+            open: false,
+            prev: Complex::new(0.0, 0.0),
+            callback,
+        }
+    }
+
+    /// Measure a buffer of channel samples (scaled to +/-1.0, as from
+    /// `dsp::to_complex`) and update the squelch state, returning whether it
+    /// is open
+    pub fn process(&mut self, buf: &[Complex<f32>]) -> bool {
+        if buf.is_empty() {
+            return self.open;
+        }
+        let power = buf.iter().map(|s| s.norm_sqr()).sum::<f32>() / buf.len() as f32;
+        self.level = match self.mode {
+            SquelchMode::Power => to_db(power),
+            SquelchMode::Noise => {
+                let mut prev = self.prev;
+                let diff = buf
+                    .iter()
+                    .map(|s| {
+                        let d = (s - prev).norm_sqr();
+                        prev = *s;
+                        d
+                    })
+                    .sum::<f32>()
+                    / buf.len() as f32;
+                to_db(diff) - to_db(power)
+            }
+        };
+        self.prev = buf[buf.len() - 1];
+
+        // Signal present means high power, or low noise ratio
+        let signal = match self.mode {
+            SquelchMode::Power => self.level,
+            SquelchMode::Noise => -self.level,
+        };
+        let threshold = match self.mode {
+            SquelchMode::Power => self.threshold,
+            SquelchMode::Noise => -self.threshold,
+        };
+        let open = if self.open {
+            signal >= threshold - self.hysteresis
+        } else {
+            signal >= threshold
+        };
+        if open != self.open {
+            self.open = open;
+            (self.callback)(open);
+        }
+        open
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Most recent measurement in dB, in the units of the threshold
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+}
+
+fn to_db(power: f32) -> f32 {
+    10.0 * power.max(1e-20).log10()
+}
@@ -0,0 +1,58 @@
+use super::{Squelch, SquelchMode};
+use num_complex::Complex;
+
+/// Deterministic white noise with the given RMS amplitude
+fn noise(len: usize, amplitude: f32, seed: u32) -> Vec<Complex<f32>> {
+    let mut x = seed;
+    let mut next = move || {
+        x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (x >> 8) as f32 / (1 << 24) as f32 - 0.5
+    };
+    // Uniform noise on [-0.5, 0.5) has an RMS of 1/sqrt(12) per component
+    let scale = amplitude * 12.0_f32.sqrt() / 2.0_f32.sqrt();
+    (0..len)
+        .map(|_| Complex::new(next() * scale, next() * scale))
+        .collect()
+}
+
+/// Slow carrier on top of noise
+fn carrier(len: usize, amplitude: f32) -> Vec<Complex<f32>> {
+    noise(len, 0.01, 7)
+        .into_iter()
+        .enumerate()
+        .map(|(n, s)| s + Complex::from_polar(amplitude, n as f32 * 0.01))
+        .collect()
+}
+
+#[test]
+fn test_power_squelch_hysteresis() {
+    let mut events = Vec::new();
+    {
+        let mut sq = Squelch::new(SquelchMode::Power, -20.0, 3.0, |open| events.push(open));
+        assert!(!sq.process(&noise(1000, 0.01, 1))); // -40 dBFS
+        assert!(sq.process(&carrier(1000, 0.2))); // -14 dBFS
+                                                  // Dropping below the threshold but within the hysteresis stays open
+        assert!(sq.process(&carrier(1000, 0.085)));
+        assert!(!sq.process(&carrier(1000, 0.05)));
+        assert!((sq.level() + 26.0).abs() < 1.0);
+    }
+    assert_eq!(vec![true, false], events);
+}
+
+#[test]
+fn test_noise_squelch() {
+    let mut sq = Squelch::new(SquelchMode::Noise, -6.0, 2.0, |_| {});
+    // Pure noise: the difference has twice the power of the signal
+    assert!(!sq.process(&noise(4000, 0.001, 3)));
+    assert!((sq.level() - 3.0).abs() < 0.5);
+    // Same noise floor scaled up by more gain stays closed
+    assert!(!sq.process(&noise(4000, 0.1, 4)));
+    assert!(sq.process(&carrier(4000, 0.5)));
+    assert!(sq.is_open());
+}
+
+#[test]
+fn test_squelch_empty_buffer() {
+    let mut sq = Squelch::new(SquelchMode::Power, -20.0, 3.0, |_| panic!("no transition"));
+    assert!(!sq.process(&[]));
+}
@@ -0,0 +1,110 @@
+//! Waterfall rows for display: spectra of consecutive FFT frames reduced to
+//! a fixed number of frequency bins and combined over time
+//!
+//! Each row is `bins` values in dBFS, lowest frequency first, covering
+//! `frames` FFT frames. Row callbacks can feed a GUI texture directly.
+
+use super::spectrum::{to_db, Spectrum};
+use num_complex::Complex;
+
+/// How FFT bins and frames are combined into one waterfall cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detector {
+    Min,     // Shows the noise floor
+    Max,     // Keeps short bursts visible
+    Average, // Mean power
+}
+
+pub struct Waterfall<F: FnMut(&[f32])> {
+    spectrum: Spectrum,
+    bins: usize,
+    frames: usize, // FFT frames per row
+    detector: Detector,
+    pending: Vec<Complex<f32>>, // Samples not yet making a full FFT frame
+    power: Vec<f32>,
+    acc: Vec<f32>, // Linear power per output bin
+    count: usize,  // Frames accumulated into `acc`
+    row: Vec<f32>,
+    callback: F,
+}
+
+impl<F: FnMut(&[f32])> Waterfall<F> {
+    /// `fft_size` must be a power of two and a multiple of `bins`
+    pub fn new(
+        fft_size: usize,
+        bins: usize,
+        frames: usize,
+        detector: Detector,
+        callback: F,
+    ) -> Self {
+        assert!(
+            bins > 0 && fft_size.is_multiple_of(bins),
+            "FFT size must be a multiple of the row width"
+        );
+        assert!(frames > 0, "Rows must cover at least one frame");
+        Waterfall {
+            spectrum: Spectrum::new(fft_size),
+            bins,
+            frames,
+            detector,
+            pending: Vec::with_capacity(fft_size),
+            power: vec![0.0; fft_size],
+            acc: vec![0.0; bins],
+            count: 0,
+            row: vec![0.0; bins],
+            callback,
+        }
+    }
+
+    /// Add IQ samples (scaled to +/-1.0), returning the number of rows emitted
+    pub fn process(&mut self, buf: &[Complex<f32>]) -> usize {
+        let size = self.spectrum.size();
+        let mut rows = 0;
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let n = (size - self.pending.len()).min(buf.len());
+            self.pending.extend_from_slice(&buf[..n]);
+            buf = &buf[n..];
+            if self.pending.len() == size {
+                self.spectrum.power(&self.pending, &mut self.power);
+                self.pending.clear();
+                if self.accumulate() {
+                    rows += 1;
+                }
+            }
+        }
+        rows
+    }
+
+    /// Fold the current spectrum into the row, emitting it once complete
+    fn accumulate(&mut self) -> bool {
+        let width = self.power.len() / self.bins;
+        for (acc, bins) in self.acc.iter_mut().zip(self.power.chunks_exact(width)) {
+            let value = match self.detector {
+                Detector::Min => bins.iter().copied().fold(f32::INFINITY, f32::min),
+                Detector::Max => bins.iter().copied().fold(0.0, f32::max),
+                Detector::Average => bins.iter().sum::<f32>() / width as f32,
+            };
+            *acc = match (self.count, self.detector) {
+                (0, _) => value,
+                (_, Detector::Min) => acc.min(value),
+                (_, Detector::Max) => acc.max(value),
+                (_, Detector::Average) => *acc + value,
+            };
+        }
+        self.count += 1;
+        if self.count < self.frames {
+            return false;
+        }
+        let scale = match self.detector {
+            Detector::Average => 1.0 / self.frames as f32,
+            _ => 1.0,
+        };
+        for (r, acc) in self.row.iter_mut().zip(&self.acc) {
+            *r = to_db(acc * scale);
+        }
+        self.count = 0;
+        (self.callback)(&self.row);
+        true
+    }
+}
@@ -0,0 +1,55 @@
+use super::waterfall::{Detector, Waterfall};
+use num_complex::Complex;
+use std::f32::consts::PI;
+
+/// Tone at `bin` of a 256 point FFT, with amplitude set per frame
+fn frames(bin: f32, amplitudes: &[f32]) -> Vec<Complex<f32>> {
+    amplitudes
+        .iter()
+        .flat_map(|a| {
+            (0..256).map(move |n| Complex::from_polar(*a, 2.0 * PI * bin * n as f32 / 256.0))
+        })
+        .collect()
+}
+
+fn rows(detector: Detector, samples: &[Complex<f32>], chunk: usize) -> Vec<Vec<f32>> {
+    let mut rows = Vec::new();
+    let mut waterfall = Waterfall::new(256, 32, 4, detector, |row| rows.push(row.to_vec()));
+    let mut emitted = 0;
+    for c in samples.chunks(chunk) {
+        emitted += waterfall.process(c);
+    }
+    drop(waterfall);
+    assert_eq!(emitted, rows.len());
+    rows
+}
+
+#[test]
+fn test_row_shape() {
+    let samples = frames(40.0, &[1.0; 9]);
+    // Odd chunk size splits FFT frames across calls
+    let rows = rows(Detector::Max, &samples, 100);
+    assert_eq!(2, rows.len());
+    for row in &rows {
+        assert_eq!(32, row.len());
+        // Bin 128 + 40 falls in column 21
+        let peak = (0..32).max_by(|a, b| row[*a].total_cmp(&row[*b])).unwrap();
+        assert_eq!(21, peak);
+        assert!(row[21].abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_detectors() {
+    let samples = frames(40.0, &[1.0, 0.5, 0.5, 0.5]);
+    let max = rows(Detector::Max, &samples, 256);
+    let min = rows(Detector::Min, &samples, 256);
+    let avg = rows(Detector::Average, &samples, 256);
+    assert!(max[0][21].abs() < 0.01);
+    // Min over the column also sees the tone skirt, so is below the peak
+    assert!(min[0][21] < -6.0);
+    let expected = 10.0 * ((1.0 + 3.0 * 0.25) / 4.0_f32).log10();
+    // Average over the column includes bins around the tone
+    assert!(avg[0][21] < expected && avg[0][21] > expected - 12.0);
+    assert!(min[0][0] <= avg[0][0] && avg[0][0] <= max[0][0]);
+}
@@ -0,0 +1,139 @@
+use super::{
+    generate_serial, Calibration, Eeprom, EepromOverrides, DEFAULT_SERIAL, DEFAULT_SERIAL_PATTERN,
+};
+
+/// Build an EEPROM image the way rtl_eeprom writes it
+fn image(flags: u8, strings: &[&str]) -> [u8; 256] {
+    let mut buf = [0xff_u8; 256];
+    buf[..9].copy_from_slice(&[0x28, 0x32, 0xda, 0x0b, 0x38, 0x28, 0xa5, flags, 0x02]);
+    let mut pos = 0x09;
+    for s in strings {
+        let len = 2 + 2 * s.len();
+        buf[pos] = len as u8;
+        buf[pos + 1] = 0x03;
+        for (i, c) in s.bytes().enumerate() {
+            buf[pos + 2 + 2 * i] = c;
+            buf[pos + 3 + 2 * i] = 0;
+        }
+        pos += len;
+    }
+    buf
+}
+
+#[test]
+fn test_parse_eeprom() {
+    let buf = image(0x02, &["Realtek", "RTL2838UHIDIR", "00000001"]);
+    let eeprom = Eeprom::parse(&buf).unwrap();
+    assert_eq!(0x0bda, eeprom.vendor_id);
+    assert_eq!(0x2838, eeprom.product_id);
+    assert!(eeprom.have_serial);
+    assert!(!eeprom.remote_wakeup);
+    assert!(eeprom.enable_ir);
+    assert_eq!(EepromOverrides::default(), eeprom.overrides());
+    assert_eq!("Realtek", eeprom.manufacturer);
+    assert_eq!("RTL2838UHIDIR", eeprom.product);
+    assert_eq!("00000001", eeprom.serial);
+}
+
+#[test]
+fn test_parse_eeprom_flags() {
+    let buf = image(0x01, &["a", "b", "c"]);
+    let eeprom = Eeprom::parse(&buf).unwrap();
+    assert!(eeprom.remote_wakeup);
+    assert!(!eeprom.enable_ir);
+    assert_eq!(
+        EepromOverrides {
+            bias_tee: true,
+            direct_sampling: true,
+        },
+        eeprom.overrides()
+    );
+}
+
+#[test]
+fn test_parse_blank_eeprom() {
+    assert!(Eeprom::parse(&[0xff; 256]).is_err());
+}
+
+#[test]
+fn test_parse_truncated_string() {
+    let mut buf = image(0x02, &["Realtek", "RTL2838UHIDIR", "00000001"]);
+    // Serial descriptor claims to run past the end of the image
+    buf[0x09 + 16 + 28] = 0xff;
+    assert!(Eeprom::parse(&buf).is_err());
+}
+
+#[test]
+fn test_encode_eeprom_roundtrip() {
+    let mut buf = image(0x02, &["Realtek", "RTL2838UHIDIR", "00000001"]);
+    let mut eeprom = Eeprom::parse(&buf).unwrap();
+    eeprom.serial = "SDR-0042".to_string();
+    eeprom.product = "Blog V4".to_string();
+    eeprom.encode(&mut buf).unwrap();
+    assert_eq!(eeprom, Eeprom::parse(&buf).unwrap());
+}
+
+#[test]
+fn test_encode_eeprom_too_long() {
+    let mut buf = image(0x02, &["a", "b", "c"]);
+    let mut eeprom = Eeprom::parse(&buf).unwrap();
+    eeprom.serial = "x".repeat(120);
+    assert!(eeprom.encode(&mut buf).is_err());
+}
+
+#[test]
+fn test_generate_serial() {
+    let serial = generate_serial(DEFAULT_SERIAL_PATTERN);
+    assert_eq!(8, serial.len());
+    assert!(serial.chars().all(|c| c.is_ascii_digit()));
+    assert_ne!(DEFAULT_SERIAL, serial);
+
+    let serial = generate_serial("SDR-##");
+    assert!(serial.starts_with("SDR-"));
+    assert!(serial[4..].chars().all(|c| c.is_ascii_digit()));
+    assert_eq!("FIXED", generate_serial("FIXED"));
+}
+
+#[test]
+fn test_calibration_roundtrip() {
+    let mut buf = image(0x02, &["Realtek", "RTL2838UHIDIR", "00000001"]);
+    let mut eeprom = Eeprom::parse(&buf).unwrap();
+    assert_eq!(None, eeprom.calibration);
+    eeprom.calibration = Some(Calibration {
+        ppm: -42,
+        gain_offset: 15,
+        label: "roof antenna".to_string(),
+    });
+    eeprom.encode(&mut buf).unwrap();
+    assert_eq!(eeprom, Eeprom::parse(&buf).unwrap());
+
+    eeprom.calibration = None;
+    eeprom.encode(&mut buf).unwrap();
+    assert_eq!(None, Eeprom::parse(&buf).unwrap().calibration);
+}
+
+#[test]
+fn test_calibration_bad_checksum() {
+    let mut buf = image(0x02, &["Realtek", "RTL2838UHIDIR", "00000001"]);
+    let mut eeprom = Eeprom::parse(&buf).unwrap();
+    eeprom.calibration = Some(Calibration {
+        ppm: 12,
+        gain_offset: 0,
+        label: String::new(),
+    });
+    eeprom.encode(&mut buf).unwrap();
+    buf[0xd2] ^= 0x01;
+    assert_eq!(None, Eeprom::parse(&buf).unwrap().calibration);
+}
+
+#[test]
+fn test_calibration_label_too_long() {
+    let mut buf = image(0x02, &["a", "b", "c"]);
+    let mut eeprom = Eeprom::parse(&buf).unwrap();
+    eeprom.calibration = Some(Calibration {
+        ppm: 0,
+        gain_offset: 0,
+        label: "x".repeat(23),
+    });
+    assert!(eeprom.encode(&mut buf).is_err());
+}
@@ -0,0 +1,254 @@
+//! Parsing of the RTL2832 configuration EEPROM image
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::SystemTime;
+
+#[cfg(test)]
+mod eeprom_test;
+
+const HEADER: [u8; 2] = [0x28, 0x32];
+const HAVE_SERIAL: u8 = 0xa5;
+const STR_OFFSET: usize = 0x09;
+const STR_DESCRIPTOR: u8 = 0x03;
+/// Offset of the byte holding the remote wakeup and IR endpoint flags
+pub(crate) const FLAGS_OFFSET: usize = 0x07;
+/// Remote wakeup flag; RTL-SDR Blog dongles use it to force direct sampling
+pub(crate) const FLAG_REMOTE_WAKEUP: u8 = 0x01;
+/// IR endpoint flag; RTL-SDR Blog dongles force the bias tee when it's clear
+pub(crate) const FLAG_ENABLE_IR: u8 = 0x02;
+/// Serial shipped on most dongles, which makes them indistinguishable
+pub const DEFAULT_SERIAL: &str = "00000001";
+/// Pattern for generated serials: each `#` becomes a random digit
+pub const DEFAULT_SERIAL_PATTERN: &str = "########";
+
+// User calibration record, in space the RTL2832 doesn't use
+const CAL_OFFSET: usize = 0xd0;
+const CAL_MAGIC: [u8; 2] = *b"CL";
+const CAL_LEN: usize = 32;
+/// Longest owner label (in UTF-8 bytes) the calibration record can hold
+pub const CAL_LABEL_LEN: usize = 22;
+
+/// Configuration stored in the device EEPROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Eeprom {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub have_serial: bool,
+    pub remote_wakeup: bool, // Used by RTL-SDR Blog devices to force direct sampling
+    pub enable_ir: bool,     // Cleared on RTL-SDR Blog devices to force the bias tee on
+    pub manufacturer: String,
+    pub product: String,
+    pub serial: String,
+    pub calibration: Option<Calibration>, // Present if a valid calibration record is stored
+}
+
+/// Per-dongle calibration saved by the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
+    pub ppm: i32,
+    pub gain_offset: i16, // Tenths of a dB to add to reported gains
+    pub label: String,    // Owner label, up to `CAL_LABEL_LEN` bytes
+}
+
+impl Calibration {
+    // Layout: magic, ppm (i32 LE), gain offset (i16 LE), label length, label,
+    // checksum making the record sum to zero
+    fn parse(buf: &[u8]) -> Option<Calibration> {
+        let block = buf.get(CAL_OFFSET..CAL_OFFSET + CAL_LEN)?;
+        if block[0..2] != CAL_MAGIC || block.iter().fold(0_u8, |a, b| a.wrapping_add(*b)) != 0 {
+            return None;
+        }
+        let label_len = (block[8] as usize).min(CAL_LABEL_LEN);
+        Some(Calibration {
+            ppm: i32::from_le_bytes([block[2], block[3], block[4], block[5]]),
+            gain_offset: i16::from_le_bytes([block[6], block[7]]),
+            label: String::from_utf8_lossy(&block[9..9 + label_len]).into_owned(),
+        })
+    }
+
+    fn encode(&self, block: &mut [u8]) -> Result<()> {
+        let label = self.label.as_bytes();
+        if label.len() > CAL_LABEL_LEN {
+            return Err(RtlsdrErr(format!(
+                "Calibration label too long: {} bytes, {} available",
+                label.len(),
+                CAL_LABEL_LEN
+            )));
+        }
+        block.fill(0);
+        block[0..2].copy_from_slice(&CAL_MAGIC);
+        block[2..6].copy_from_slice(&self.ppm.to_le_bytes());
+        block[6..8].copy_from_slice(&self.gain_offset.to_le_bytes());
+        block[8] = label.len() as u8;
+        block[9..9 + label.len()].copy_from_slice(label);
+        let sum = block[..CAL_LEN - 1]
+            .iter()
+            .fold(0_u8, |a, b| a.wrapping_add(*b));
+        block[CAL_LEN - 1] = sum.wrapping_neg();
+        Ok(())
+    }
+}
+
+/// Bias tee and direct sampling forced on through two otherwise unused
+/// EEPROM bits, as set by the RTL-SDR Blog tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EepromOverrides {
+    pub bias_tee: bool,        // IR endpoint bit cleared
+    pub direct_sampling: bool, // Remote wakeup bit set
+}
+
+impl Eeprom {
+    pub fn overrides(&self) -> EepromOverrides {
+        EepromOverrides {
+            bias_tee: !self.enable_ir,
+            direct_sampling: self.remote_wakeup,
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Eeprom> {
+        if buf.len() < STR_OFFSET || buf[0..2] != HEADER {
+            return Err(RtlsdrErr(
+                "No valid RTL2832 EEPROM header found".to_string(),
+            ));
+        }
+        let mut pos = STR_OFFSET;
+        let manufacturer = read_string(buf, &mut pos)?;
+        let product = read_string(buf, &mut pos)?;
+        let serial = read_string(buf, &mut pos)?;
+        Ok(Eeprom {
+            vendor_id: u16::from_le_bytes([buf[2], buf[3]]),
+            product_id: u16::from_le_bytes([buf[4], buf[5]]),
+            have_serial: buf[6] == HAVE_SERIAL,
+            remote_wakeup: buf[FLAGS_OFFSET] & FLAG_REMOTE_WAKEUP != 0,
+            enable_ir: buf[FLAGS_OFFSET] & FLAG_ENABLE_IR != 0,
+            manufacturer,
+            product,
+            serial,
+            calibration: Calibration::parse(buf),
+        })
+    }
+
+    /// Encode the configuration into an EEPROM image, leaving bytes after the
+    /// string descriptors untouched
+    pub fn encode(&self, buf: &mut [u8]) -> Result<()> {
+        let strings = [&self.manufacturer, &self.product, &self.serial];
+        let len: usize = strings.iter().map(|s| descriptor_len(s)).sum();
+        // Strings must not run into the calibration record
+        let end = match self.calibration {
+            Some(_) => CAL_OFFSET.min(buf.len()),
+            None => buf.len(),
+        };
+        if STR_OFFSET + len > end {
+            return Err(RtlsdrErr(format!(
+                "EEPROM strings too long: {} bytes, {} available",
+                len,
+                end.saturating_sub(STR_OFFSET)
+            )));
+        }
+        match (
+            &self.calibration,
+            buf.get_mut(CAL_OFFSET..CAL_OFFSET + CAL_LEN),
+        ) {
+            (Some(cal), Some(block)) => cal.encode(block)?,
+            (Some(_), None) => {
+                return Err(RtlsdrErr(
+                    "EEPROM too small for calibration record".to_string(),
+                ))
+            }
+            (None, Some(block)) if block[0..2] == CAL_MAGIC => block[0..2].fill(0xff),
+            (None, _) => {}
+        }
+
+        buf[0..2].copy_from_slice(&HEADER);
+        buf[2..4].copy_from_slice(&self.vendor_id.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.product_id.to_le_bytes());
+        buf[6] = if self.have_serial { HAVE_SERIAL } else { 0 };
+        let mut flags = 0;
+        if self.remote_wakeup {
+            flags |= FLAG_REMOTE_WAKEUP;
+        }
+        if self.enable_ir {
+            flags |= FLAG_ENABLE_IR;
+        }
+        set_flags(buf, FLAG_REMOTE_WAKEUP | FLAG_ENABLE_IR, flags)?;
+        let mut pos = STR_OFFSET;
+        for s in strings {
+            let len = descriptor_len(s);
+            buf[pos] = len as u8;
+            buf[pos + 1] = STR_DESCRIPTOR;
+            for (i, c) in s.encode_utf16().enumerate() {
+                buf[pos + 2 + 2 * i..pos + 4 + 2 * i].copy_from_slice(&c.to_le_bytes());
+            }
+            pos += len;
+        }
+        Ok(())
+    }
+}
+
+/// Generate a serial from `pattern`, replacing each `#` with a random digit.
+/// Never returns `DEFAULT_SERIAL` unless the pattern has no `#` to vary.
+pub fn generate_serial(pattern: &str) -> String {
+    let state = RandomState::new();
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    for attempt in 0_u32.. {
+        let mut i = 0_u32;
+        let serial: String = pattern
+            .chars()
+            .map(|c| match c {
+                '#' => {
+                    i += 1;
+                    let digit = state.hash_one((seed, attempt, i)) % 10;
+                    char::from(b'0' + digit as u8)
+                }
+                c => c,
+            })
+            .collect();
+        if serial != DEFAULT_SERIAL || i == 0 {
+            return serial;
+        }
+    }
+    unreachable!()
+}
+
+/// Set the `mask` bits of the flags byte to those of `flags`, leaving the rest
+/// of the image alone. `buf` only needs to cover the header and flags.
+pub(crate) fn set_flags(buf: &mut [u8], mask: u8, flags: u8) -> Result<()> {
+    if buf.len() <= FLAGS_OFFSET || buf[0..2] != HEADER {
+        return Err(RtlsdrErr(
+            "No valid RTL2832 EEPROM header found".to_string(),
+        ));
+    }
+    buf[FLAGS_OFFSET] = (buf[FLAGS_OFFSET] & !mask) | (flags & mask);
+    Ok(())
+}
+
+/// Length of the USB string descriptor holding `s`
+fn descriptor_len(s: &str) -> usize {
+    2 + 2 * s.encode_utf16().count()
+}
+
+/// Read a USB string descriptor at `pos` and advance past it
+fn read_string(buf: &[u8], pos: &mut usize) -> Result<String> {
+    let len = *buf.get(*pos).unwrap_or(&0) as usize;
+    if len < 2 || *pos + len > buf.len() || buf[*pos + 1] != STR_DESCRIPTOR {
+        return Err(RtlsdrErr(format!(
+            "Invalid string descriptor at EEPROM offset {:#x}",
+            pos
+        )));
+    }
+    // UTF-16LE characters follow the length and type bytes
+    let chars: Vec<u16> = buf[*pos + 2..*pos + len]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    *pos += len;
+    Ok(String::from_utf16_lossy(&chars))
+}
@@ -1,3 +1,4 @@
+use std::ops::RangeInclusive;
 use std::{fmt, result};
 // use std::error::Error;
 
@@ -34,8 +35,61 @@ macro_rules! define_errcodes {
     };
 }
 
+/// Requested sample rate is outside the ranges supported by the resampler
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRateError {
+    pub rate: u32,
+    pub supported: &'static [RangeInclusive<u32>],
+    pub nearest: f64, // Nearest exact rate the hardware can produce
+}
+
+impl fmt::Display for SampleRateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid sample rate: {} Hz (supported:", self.rate)?;
+        for (i, range) in self.supported.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}-{} Hz", sep, range.start(), range.end())?;
+        }
+        write!(f, "; nearest: {:.3} Hz)", self.nearest)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EepromError {
+    WriteProtected,                 // Writes did not change the EEPROM contents
+    VerifyFailed { offset: usize }, // Read back differs from what was written
+}
+
+impl fmt::Display for EepromError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EepromError::WriteProtected => write!(f, "EEPROM is write protected"),
+            EepromError::VerifyFailed { offset } => {
+                write!(f, "EEPROM verify failed at offset {:#04x}", offset)
+            }
+        }
+    }
+}
+
+/// Tuner PLL didn't lock on the requested frequency, so the device isn't
+/// receiving it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PllLockError {
+    pub freq: u32,
+}
+
+impl fmt::Display for PllLockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tuner PLL not locked at {} Hz", self.freq)
+    }
+}
+
 define_errcodes![
     RtlsdrError =>
     Usb : rusb::Error,
-    RtlsdrErr: String
+    Io: std::io::Error,
+    RtlsdrErr: String,
+    SampleRate: SampleRateError,
+    Eeprom: EepromError,
+    PllNotLocked: PllLockError
 ];
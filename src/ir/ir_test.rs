@@ -0,0 +1,139 @@
+use super::{decode, parse_pulses, IrCode, IrDecoder, Pulse, IR_UNIT_NS};
+
+/// Encode (mark, duration in us) pairs the way the receiver reports them
+fn encode(pulses: &[(bool, u32)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for &(mark, us) in pulses {
+        let mut units = (us * 1000 + IR_UNIT_NS / 2) / IR_UNIT_NS;
+        while units > 0 {
+            let n = units.min(0x7f);
+            buf.push(((mark as u8) << 7) | n as u8);
+            units -= n;
+        }
+    }
+    buf
+}
+
+fn nec(data: u32) -> Vec<u8> {
+    let mut pulses = vec![(false, 20000), (true, 9000), (false, 4500)];
+    for i in 0..32 {
+        let space = if data & (1 << i) != 0 { 1687 } else { 562 };
+        pulses.extend([(true, 562), (false, space)]);
+    }
+    pulses.push((true, 562));
+    encode(&pulses)
+}
+
+fn rc5(data: u16) -> Vec<u8> {
+    let mut halves = Vec::new();
+    for i in (0..14).rev() {
+        if data & (1 << i) != 0 {
+            halves.extend([false, true]);
+        } else {
+            halves.extend([true, false]);
+        }
+    }
+    let mut pulses: Vec<(bool, u32)> = vec![(false, 20000)];
+    // Skip the leading space half of the first start bit
+    for h in &halves[1..] {
+        match pulses.last_mut() {
+            Some(last) if last.0 == *h => last.1 += 889,
+            _ => pulses.push((*h, 889)),
+        }
+    }
+    encode(&pulses)
+}
+
+#[test]
+fn test_parse_pulses_merges_runs() {
+    let pulses = parse_pulses(&[0xff, 0x81, 0x10]);
+    assert_eq!(
+        pulses,
+        vec![
+            Pulse {
+                mark: true,
+                duration: 128 * IR_UNIT_NS / 1000
+            },
+            Pulse {
+                mark: false,
+                duration: 16 * IR_UNIT_NS / 1000
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_decode_nec() {
+    // Address 0x04, command 0x08
+    let buf = nec(0xf708_fb04);
+    assert_eq!(
+        Some(IrCode::Nec {
+            address: 0x04,
+            command: 0x08
+        }),
+        decode(&buf)
+    );
+}
+
+#[test]
+fn test_decode_extended_nec() {
+    let buf = nec(0xe51a_1240);
+    assert_eq!(
+        Some(IrCode::Nec {
+            address: 0x1240,
+            command: 0x1a
+        }),
+        decode(&buf)
+    );
+}
+
+#[test]
+fn test_decode_nec_repeat() {
+    let buf = encode(&[(true, 9000), (false, 2250), (true, 562)]);
+    assert_eq!(Some(IrCode::NecRepeat), decode(&buf));
+}
+
+#[test]
+fn test_decode_rc5() {
+    // S1=1, S2=1, toggle=1, address 5, command 35
+    let buf = rc5(1 << 13 | 1 << 12 | 1 << 11 | 5 << 6 | 35);
+    assert_eq!(
+        Some(IrCode::Rc5 {
+            toggle: true,
+            address: 5,
+            command: 35
+        }),
+        decode(&buf)
+    );
+}
+
+#[test]
+fn test_decode_rc5_trailing_zero() {
+    // S2=0 selects the upper command range, last bit 0 merges into idle
+    let buf = rc5(1 << 13 | 20 << 6 | 2);
+    assert_eq!(
+        Some(IrCode::Rc5 {
+            toggle: false,
+            address: 20,
+            command: 66
+        }),
+        decode(&buf)
+    );
+}
+
+#[test]
+fn test_decode_garbage() {
+    assert_eq!(None, decode(&[]));
+    assert_eq!(None, decode(&encode(&[(true, 3000), (false, 3000)])));
+}
+
+#[test]
+fn test_decoder_callback() {
+    let mut codes = Vec::new();
+    {
+        let mut decoder = IrDecoder::new(|code| codes.push(code));
+        assert!(decoder.feed(&nec(0xf708_fb04)));
+        assert!(!decoder.feed(&[]));
+    }
+    assert_eq!(1, codes.len());
+}
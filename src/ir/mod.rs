@@ -0,0 +1,201 @@
+//! Decoding of remote control codes from the pulses captured by the IR
+//! receiver (see `RtlSdr::get_ir`)
+
+use crate::error::Result;
+use crate::RtlSdr;
+
+#[cfg(test)]
+mod ir_test;
+
+/// Duration of one unit in the IR pulse buffer, in nanoseconds
+pub const IR_UNIT_NS: u32 = 50_800;
+/// Largest pulse buffer the receiver produces
+pub const IR_BUF_LEN: usize = 128;
+
+// Allowed deviation from nominal timings, in percent
+const TOLERANCE: u32 = 30;
+
+const NEC_LEADER_MARK: u32 = 9000; // us
+const NEC_LEADER_SPACE: u32 = 4500;
+const NEC_REPEAT_SPACE: u32 = 2250;
+const NEC_BIT_MARK: u32 = 562;
+const NEC_ZERO_SPACE: u32 = 562;
+const NEC_ONE_SPACE: u32 = 1687;
+const NEC_BITS: usize = 32;
+
+const RC5_HALF_BIT: u32 = 889; // us
+const RC5_BITS: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrCode {
+    // Address is 16 bits wide for extended NEC
+    Nec {
+        address: u16,
+        command: u8,
+    },
+    // Key held down
+    NecRepeat,
+    Rc5 {
+        toggle: bool,
+        address: u8,
+        command: u8,
+    },
+}
+
+/// A mark (IR carrier present) or space, with its duration in us
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse {
+    pub mark: bool,
+    pub duration: u32,
+}
+
+/// Convert the raw receiver buffer into pulses, merging runs of the same
+/// level that were split because they exceeded the 7-bit duration field
+pub fn parse_pulses(buf: &[u8]) -> Vec<Pulse> {
+    // Accumulate in receiver units, then convert to us
+    let mut pulses: Vec<Pulse> = Vec::new();
+    for b in buf {
+        let mark = b & 0x80 != 0;
+        let units = (b & 0x7f) as u32;
+        match pulses.last_mut() {
+            Some(last) if last.mark == mark => last.duration += units,
+            _ => pulses.push(Pulse {
+                mark,
+                duration: units,
+            }),
+        }
+    }
+    for p in pulses.iter_mut() {
+        p.duration = p.duration * IR_UNIT_NS / 1000;
+    }
+    pulses
+}
+
+/// Decode a captured buffer as NEC or RC5
+pub fn decode(buf: &[u8]) -> Option<IrCode> {
+    let pulses = parse_pulses(buf);
+    // Leading space is the idle time before the code started
+    let start = pulses.iter().position(|p| p.mark)?;
+    let pulses = &pulses[start..];
+    decode_nec(pulses).or_else(|| decode_rc5(pulses))
+}
+
+fn matches(duration: u32, nominal: u32) -> bool {
+    let tol = nominal * TOLERANCE / 100;
+    (nominal - tol..=nominal + tol).contains(&duration)
+}
+
+fn decode_nec(pulses: &[Pulse]) -> Option<IrCode> {
+    if pulses.len() < 2 || !matches(pulses[0].duration, NEC_LEADER_MARK) {
+        return None;
+    }
+    if matches(pulses[1].duration, NEC_REPEAT_SPACE) {
+        return Some(IrCode::NecRepeat);
+    }
+    if !matches(pulses[1].duration, NEC_LEADER_SPACE) || pulses.len() < 2 + 2 * NEC_BITS {
+        return None;
+    }
+    let mut data: u32 = 0;
+    for (i, bit) in pulses[2..2 + 2 * NEC_BITS].chunks_exact(2).enumerate() {
+        if !matches(bit[0].duration, NEC_BIT_MARK) {
+            return None;
+        }
+        if matches(bit[1].duration, NEC_ONE_SPACE) {
+            data |= 1 << i;
+        } else if !matches(bit[1].duration, NEC_ZERO_SPACE) {
+            return None;
+        }
+    }
+    // Sent LSB first: address, inverted address, command, inverted command
+    let [addr, addr_inv, command, command_inv] = data.to_le_bytes();
+    if command != !command_inv {
+        return None;
+    }
+    let address = if addr == !addr_inv {
+        addr as u16
+    } else {
+        u16::from_le_bytes([addr, addr_inv])
+    };
+    Some(IrCode::Nec { address, command })
+}
+
+fn decode_rc5(pulses: &[Pulse]) -> Option<IrCode> {
+    // Expand into half-bit levels. The first start bit is a 1, whose space
+    // half is indistinguishable from idle.
+    let mut halves = vec![false];
+    for p in pulses {
+        let n = if matches(p.duration, RC5_HALF_BIT) {
+            1
+        } else if matches(p.duration, 2 * RC5_HALF_BIT) {
+            2
+        } else if !p.mark && halves.len() >= 2 * RC5_BITS - 1 {
+            // Trailing idle
+            break;
+        } else {
+            return None;
+        };
+        halves.extend(std::iter::repeat_n(p.mark, n));
+    }
+    // A final 0 bit ends with a space that merges into idle
+    if halves.len() == 2 * RC5_BITS - 1 {
+        halves.push(false);
+    }
+    if halves.len() > 2 * RC5_BITS && halves[2 * RC5_BITS..].iter().all(|h| !h) {
+        halves.truncate(2 * RC5_BITS);
+    }
+    if halves.len() != 2 * RC5_BITS {
+        return None;
+    }
+    let mut data: u16 = 0;
+    for half in halves.chunks_exact(2) {
+        let bit = match (half[0], half[1]) {
+            (false, true) => 1,
+            (true, false) => 0,
+            _ => return None,
+        };
+        data = (data << 1) | bit;
+    }
+    // S1, S2 (inverted command bit 6 in RC5X), toggle, 5 address, 6 command
+    let field = (data >> 12) & 0x01;
+    Some(IrCode::Rc5 {
+        toggle: (data >> 11) & 0x01 != 0,
+        address: ((data >> 6) & 0x1f) as u8,
+        command: ((data & 0x3f) | ((field ^ 1) << 6)) as u8,
+    })
+}
+
+/// Polls the IR receiver and invokes a callback for each decoded key code
+pub struct IrDecoder<F: FnMut(IrCode)> {
+    callback: F,
+    buf: [u8; IR_BUF_LEN],
+}
+
+impl<F: FnMut(IrCode)> IrDecoder<F> {
+    pub fn new(callback: F) -> Self {
+        IrDecoder {
+            callback,
+            buf: [0; IR_BUF_LEN],
+        }
+    }
+
+    /// Read any pending code from the device, returning whether one was decoded
+    pub fn poll(&mut self, sdr: &mut RtlSdr) -> Result<bool> {
+        let len = sdr.get_ir(&mut self.buf)?;
+        Ok(self.dispatch(decode(&self.buf[..len])))
+    }
+
+    /// Decode an already captured buffer
+    pub fn feed(&mut self, buf: &[u8]) -> bool {
+        self.dispatch(decode(buf))
+    }
+
+    fn dispatch(&mut self, code: Option<IrCode>) -> bool {
+        match code {
+            Some(code) => {
+                (self.callback)(code);
+                true
+            }
+            None => false,
+        }
+    }
+}
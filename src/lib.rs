@@ -1,83 +1,540 @@
 //! # rtlsdr Library
 //! Library for interfacing with an RTL-SDR device.
 
+pub mod audio;
+pub mod benchmark;
+pub mod bookmarks;
+mod builder;
+#[cfg(feature = "cdylib")]
+pub mod capi;
+#[cfg(feature = "bins")]
+pub mod cli;
+pub mod demod;
 mod device;
+pub mod diagnose;
+pub mod dsp;
+mod eeprom;
 pub mod error;
+pub mod ir;
+pub mod net;
+pub mod pipeline;
+pub mod record;
+pub mod rf_switch;
+#[cfg(test)]
+mod rf_switch_test;
 mod rtlsdr;
+pub mod scan;
+#[cfg(all(test, feature = "serde"))]
+mod serde_test;
+pub mod source;
+pub mod stream;
+#[cfg(all(test, feature = "tracing"))]
+mod tracing_test;
 mod tuners;
+pub mod units;
 
+pub use builder::RtlSdrBuilder;
 use device::Device;
+pub use device::EEPROM_SIZE;
+pub use eeprom::{
+    generate_serial, Calibration, Eeprom, EepromOverrides, CAL_LABEL_LEN, DEFAULT_SERIAL,
+    DEFAULT_SERIAL_PATTERN,
+};
 use error::Result;
-use rtlsdr::RtlSdr as Sdr;
+use error::RtlsdrError::RtlsdrErr;
+use rtlsdr::{OpenOptions, RtlSdr as Sdr};
+pub use rtlsdr::{FIR_LEN, SAMPLE_RATE_RANGES};
+use std::fs::File;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+pub use tuners::{TunerCapabilities, TunerInfo};
+use units::{Hertz, Ppm, SampleRate};
 
 pub const DEFAULT_BUF_LENGTH: usize = 16 * 16384;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TunerGain {
     Auto,
     Manual(i32),
 }
-#[derive(Debug)]
+/// R820T tracking filter (RF_MUX) configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackingFilter {
+    /// Use the per-band defaults from the tuner's frequency table
+    Auto,
+    /// Bypass the tracking filter entirely
+    Bypass,
+    /// Override the tracking filter band capacitor (R27)
+    Manual(u8),
+}
+/// R820T notch filter (open drain) configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotchFilter {
+    /// Use the per-band defaults from the tuner's frequency table
+    Auto,
+    /// Force the broadcast FM/AM notch filters on
+    On,
+    /// Force the notch filters off
+    Off,
+}
+/// RTL2832 ADC inputs enabled for sampling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AdcInput {
+    I,    // In-phase ADC only
+    Q,    // Quadrature ADC only
+    Both, // In-phase and quadrature ADCs
+}
+/// RTL2832 decimation FIR filter coefficients
+///
+/// The filter is symmetric, so only the first half of the taps are given. The
+/// first 8 coefficients must fit in 8 bits and the last 8 in 12 bits (signed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fir {
+    Default,                // librtlsdr default filter
+    Narrowband,             // Lower cutoff with more adjacent-channel rejection
+    WidebandFlat,           // Flatter passband extending closer to Nyquist
+    Custom([i32; FIR_LEN]), // User-supplied coefficients
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DirectSampleMode {
     Off,
     On,
-    OnSwap, // Swap I and Q ADC, allowing to select between two inputs
+    OnSwap,         // Swap I and Q ADC, allowing to select between two inputs
+    AutoBelow(u32), // Q-branch direct sampling below the given frequency (Hz), tuner above
 }
 
+/// A device of a known type found on the USB bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Index to pass to `RtlSdr::open`
+    pub index: usize,
+    pub name: &'static str,
+    /// USB strings, empty if the device couldn't be opened to read them
+    pub manufacturer: String,
+    pub product: String,
+    pub serial: String,
+}
+
+/// Receiver state that `RtlSdr::settings` captures and `RtlSdr::apply`
+/// restores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Settings {
+    pub center_freq: u32, // Hz, 0 if never set and not applied
+    pub sample_rate: u32, // Hz, 0 if never set and not applied
+    pub gain: TunerGain,
+    pub ppm: i32,
+    pub bandwidth: u32, // Hz, 0 to follow the sample rate
+    pub bias_tee: bool,
+    pub direct_sampling: DirectSampleMode,
+    pub agc: bool, // RTL2832 digital AGC
+}
+
+/// An open device. Setters take `&self`, so it can be shared behind an `Arc`
+/// and retuned from one thread while another reads samples; reads don't
+/// wait for settings changes.
+///
+/// ```no_run
+/// use rtlsdr_rs::RtlSdr;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let sdr = Arc::new(RtlSdr::builder().freq(100_000_000).open().unwrap());
+/// let reader = {
+///     let sdr = sdr.clone();
+///     thread::spawn(move || {
+///         let mut buf = vec![0_u8; rtlsdr_rs::DEFAULT_BUF_LENGTH];
+///         for _ in 0..100 {
+///             sdr.read_sync(&mut buf).unwrap();
+///         }
+///     })
+/// };
+/// sdr.set_center_freq(101_100_000).unwrap();
+/// reader.join().unwrap();
+/// ```
 pub struct RtlSdr {
-    sdr: Sdr,
+    sdr: Mutex<Sdr>,
+    device: Arc<Device>, // For reads, without locking `sdr`
+    index: usize,
+    recording: Option<PathBuf>, // Transcript to keep appending to on `reopen`
 }
 impl RtlSdr {
+    /// Devices of a known type currently plugged in, in index order
+    pub fn list_devices() -> Vec<DeviceInfo> {
+        device::device_handle::devices()
+            .iter()
+            .enumerate()
+            .map(|(index, (device, name))| {
+                let (manufacturer, product, serial) =
+                    device::device_handle::usb_strings(device).unwrap_or_default();
+                DeviceInfo {
+                    index,
+                    name,
+                    manufacturer,
+                    product,
+                    serial,
+                }
+            })
+            .collect()
+    }
+    /// Check device `index` for the usual reasons it won't open or loses
+    /// samples, such as the DVB-T kernel driver or missing permissions
+    pub fn diagnose(index: usize) -> diagnose::Report {
+        diagnose::diagnose(index)
+    }
+    /// Open a device with its settings applied, see `RtlSdrBuilder`
+    pub fn builder() -> RtlSdrBuilder {
+        RtlSdrBuilder::default()
+    }
+    /// Open device `index`. A missing or unreadable EEPROM only logs a
+    /// warning; use `RtlSdrBuilder::require_eeprom` to fail instead. Fails if no
+    /// supported tuner is found, see `RtlSdrBuilder::allow_no_tuner`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(err))]
     pub fn open(index: usize) -> Result<RtlSdr> {
-        let dev = Device::new(index)?;
-        let mut sdr = Sdr::new(dev);
+        RtlSdr::open_device(index, OpenOptions::default(), None)
+    }
+    /// Open device `index`, logging its USB transactions to the transcript
+    /// at `recording`, if any
+    pub(crate) fn open_device(
+        index: usize,
+        options: OpenOptions,
+        recording: Option<&Path>,
+    ) -> Result<RtlSdr> {
+        let device = match recording {
+            Some(path) => Device::open_recording(index, path)?,
+            None => Device::new(index)?,
+        };
+        let mut sdr = Sdr::new(device);
+        sdr.set_open_options(options);
         sdr.init()?;
-        Ok(RtlSdr { sdr: sdr })
+        Ok(RtlSdr {
+            device: sdr.device(),
+            sdr: Mutex::new(sdr),
+            index,
+            recording: recording.map(Path::to_path_buf),
+        })
     }
-    pub fn close(&mut self) -> Result<()> {
+    /// Open the device like `open`, logging every USB transaction to a text
+    /// transcript at `path`. Transcripts of real devices can be replayed as
+    /// regression tests for init and tuning sequences.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(path), fields(path = %path.as_ref().display()), err)
+    )]
+    pub fn open_recording<P: AsRef<Path>>(index: usize, path: P) -> Result<RtlSdr> {
+        // Start a new transcript; `reopen` appends to it
+        File::create(path.as_ref())?;
+        RtlSdr::open_device(index, OpenOptions::default(), Some(path.as_ref()))
+    }
+    fn sdr(&self) -> MutexGuard<'_, Sdr> {
+        // The state is only cached hardware settings, so it's usable even
+        // if a panic interrupted a change
+        self.sdr.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Close and open the device again, e.g. after a USB error, restoring
+    /// its settings. Fails if the device is gone or another device now has
+    /// its index. A device opened with `open_recording` keeps appending to
+    /// its transcript.
+    pub fn reopen(self) -> Result<RtlSdr> {
+        let index = self.index;
+        let settings = self.settings();
+        let options = self.sdr().open_options();
+        let recording = self.recording.clone();
+        drop(self);
+        let sdr = RtlSdr::open_device(index, options, recording.as_deref())?;
+        sdr.apply(&settings)?;
+        Ok(sdr)
+    }
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), err))]
+    pub fn close(&self) -> Result<()> {
         // TODO: wait until async is inactive
-        Ok(self.sdr.deinit_baseband()?)
+        self.sdr().deinit_baseband()
     }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     pub fn reset_buffer(&self) -> Result<()> {
-        self.sdr.reset_buffer()
+        self.sdr().reset_buffer()
     }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(len = buf.len()), ret, err)
+    )]
     pub fn read_sync(&self, buf: &mut [u8]) -> Result<usize> {
-        self.sdr.read_sync(buf)
+        self.device.bulk_transfer(buf)
     }
     pub fn get_center_freq(&self) -> u32 {
-        self.sdr.get_center_freq()
+        self.sdr().get_center_freq()
+    }
+    /// Tune to `freq`. Fails with `PllNotLocked` if the tuner PLL didn't lock,
+    /// in which case the device isn't receiving `freq`.
+    pub fn set_center_freq(&self, freq: impl Into<Hertz>) -> Result<()> {
+        self.sdr().set_center_freq(freq.into().0)
+    }
+    /// Frequency the hardware is actually tuned to after PLL and DDC rounding
+    pub fn get_actual_center_freq(&self) -> Result<f64> {
+        self.sdr().get_actual_center_freq()
+    }
+    /// `get_actual_center_freq` rounded to the nearest Hz, for logging and
+    /// clients that expect an integer frequency
+    pub fn get_corrected_center_freq(&self) -> Result<u32> {
+        self.sdr().get_corrected_center_freq()
+    }
+    /// Whether the tuner PLL locked on the most recent tune
+    pub fn pll_locked(&self) -> Result<bool> {
+        self.sdr().pll_locked()
+    }
+    /// Relative signal strength from the gain the tuner AGC settles on.
+    /// Larger values mean a stronger signal; the scale is uncalibrated.
+    /// Requires automatic tuner gain and direct sampling off.
+    pub fn read_rssi(&self) -> Result<i32> {
+        self.sdr().read_rssi()
+    }
+    /// Override the tuner IF frequency in Hz (0 restores the default)
+    pub fn set_if_frequency(&self, freq: impl Into<Hertz>) -> Result<()> {
+        self.sdr().set_if_frequency(freq.into().0)
+    }
+    pub fn get_if_frequency(&self) -> Result<u32> {
+        self.sdr().get_if_frequency()
+    }
+    /// Read and parse the configuration EEPROM
+    pub fn read_eeprom_config(&self) -> Result<Eeprom> {
+        self.sdr().read_eeprom_config()
+    }
+    /// Bias tee and direct sampling overrides the EEPROM held at open. They
+    /// only took effect if opened with `RtlSdrBuilder::honor_eeprom_overrides`.
+    pub fn eeprom_overrides(&self) -> EepromOverrides {
+        self.sdr().eeprom_overrides()
+    }
+    /// Write the configuration to the EEPROM. Takes effect after the device
+    /// is re-plugged.
+    pub fn write_eeprom_config(&self, eeprom: &Eeprom) -> Result<()> {
+        self.sdr().write_eeprom_config(eeprom)
+    }
+    /// Raw EEPROM contents, e.g. for a backup
+    pub fn read_eeprom_image(&self) -> Result<[u8; EEPROM_SIZE]> {
+        self.sdr().read_eeprom_image()
+    }
+    /// Overwrite the EEPROM with a full image, e.g. to restore a backup.
+    /// Takes effect after the device is re-plugged.
+    pub fn write_eeprom_image(&self, image: &[u8]) -> Result<()> {
+        self.sdr().write_eeprom_image(image)
+    }
+    pub fn set_eeprom_manufacturer(&self, manufacturer: &str) -> Result<()> {
+        let mut eeprom = self.read_eeprom_config()?;
+        eeprom.manufacturer = manufacturer.to_string();
+        self.write_eeprom_config(&eeprom)
     }
-    pub fn set_center_freq(&mut self, freq: u32) -> Result<()> {
-        self.sdr.set_center_freq(freq)
+    pub fn set_eeprom_product(&self, product: &str) -> Result<()> {
+        let mut eeprom = self.read_eeprom_config()?;
+        eeprom.product = product.to_string();
+        self.write_eeprom_config(&eeprom)
+    }
+    pub fn set_eeprom_serial(&self, serial: &str) -> Result<()> {
+        let mut eeprom = self.read_eeprom_config()?;
+        eeprom.serial = serial.to_string();
+        eeprom.have_serial = true;
+        self.write_eeprom_config(&eeprom)
+    }
+    /// Program the EEPROM so the bias tee is always on (RTL-SDR Blog
+    /// convention: clear the IR endpoint bit). Takes effect after re-plugging.
+    pub fn set_eeprom_force_bias_tee(&self, on: bool) -> Result<()> {
+        let flag = eeprom::FLAG_ENABLE_IR;
+        self.sdr()
+            .write_eeprom_flags(flag, if on { 0 } else { flag })
+    }
+    /// Program the EEPROM so direct sampling is always on (RTL-SDR Blog
+    /// convention: set the remote wakeup bit). Takes effect after re-plugging.
+    pub fn set_eeprom_force_direct_sampling(&self, on: bool) -> Result<()> {
+        let flag = eeprom::FLAG_REMOTE_WAKEUP;
+        self.sdr()
+            .write_eeprom_flags(flag, if on { flag } else { 0 })
+    }
+    /// Calibration record stored in the EEPROM, if any. Its ppm correction is
+    /// applied automatically when the device is opened.
+    pub fn load_calibration(&self) -> Result<Option<Calibration>> {
+        self.sdr().load_calibration()
+    }
+    /// Store a calibration record in spare EEPROM space, or `None` to erase it
+    pub fn save_calibration(&self, calibration: Option<Calibration>) -> Result<()> {
+        self.sdr().save_calibration(calibration)
+    }
+    /// If the device still has the factory serial (`DEFAULT_SERIAL` or none),
+    /// program a unique one generated from `pattern` (see `generate_serial`).
+    /// Returns the new serial, or `None` if the existing one was kept. Takes
+    /// effect after the device is re-plugged.
+    pub fn assign_unique_serial(&self, pattern: &str) -> Result<Option<String>> {
+        let eeprom = self.read_eeprom_config()?;
+        if eeprom.have_serial && !eeprom.serial.is_empty() && eeprom.serial != DEFAULT_SERIAL {
+            return Ok(None);
+        }
+        let serial = generate_serial(pattern);
+        self.set_eeprom_serial(&serial)?;
+        if self.read_eeprom_config()?.serial != serial {
+            return Err(RtlsdrErr(format!(
+                "EEPROM serial did not read back as {}",
+                serial
+            )));
+        }
+        Ok(Some(serial))
+    }
+    /// Info and capabilities of the detected tuner
+    pub fn get_tuner_info(&self) -> Result<TunerInfo> {
+        self.sdr().get_tuner_info()
     }
     pub fn get_tuner_gains(&self) -> Result<Vec<i32>> {
-        self.sdr.get_tuner_gains()
+        self.sdr().get_tuner_gains()
+    }
+    pub fn set_tuner_gain(&self, gain: TunerGain) -> Result<()> {
+        self.sdr().set_tuner_gain(gain)
     }
-    pub fn set_tuner_gain(&mut self, gain: TunerGain) -> Result<()> {
-        self.sdr.set_tuner_gain(gain)
+    /// Set IF gain `stage`, below `TunerCapabilities::if_gain_stages`, to the
+    /// step closest to `gain` tenths of a dB. It stays through later
+    /// `set_tuner_gain` calls.
+    pub fn set_tuner_if_gain(&self, stage: u8, gain: i32) -> Result<()> {
+        self.sdr().set_tuner_if_gain(stage, gain)
+    }
+    /// Approximate antenna input power in dBm (an S-meter reading) for a
+    /// buffer of raw samples. Needs a manual tuner gain, and includes the
+    /// gain offset of the calibration stored when the device was opened.
+    pub fn estimate_input_power(&self, buf: &[u8]) -> Result<f32> {
+        self.sdr().estimate_input_power(buf)
+    }
+    /// Set the RTL2832 and tuner crystal frequencies in Hz. A tuner frequency
+    /// of 0 uses the RTL2832 clock, and an RTL2832 frequency of 0 leaves it
+    /// unchanged. The RTL2832 crystal must be within 1 kHz of 28.8 MHz and
+    /// the tuner crystal between 16 and 40 MHz.
+    pub fn set_xtal_freq(
+        &self,
+        rtl_freq: impl Into<Hertz>,
+        tuner_freq: impl Into<Hertz>,
+    ) -> Result<()> {
+        self.sdr()
+            .set_xtal_freq(rtl_freq.into().0, tuner_freq.into().0)
+    }
+    /// RTL2832 and tuner crystal frequencies in Hz, with ppm correction applied
+    pub fn get_xtal_freq(&self) -> (u32, u32) {
+        let sdr = self.sdr();
+        (sdr.get_xtal_freq(), sdr.get_tuner_xtal_freq())
     }
     pub fn get_freq_correction(&self) -> i32 {
-        self.sdr.get_freq_correction()
+        self.sdr().get_freq_correction()
     }
-    pub fn set_freq_correction(&mut self, ppm: i32) -> Result<()> {
-        self.sdr.set_freq_correction(ppm)
+    pub fn set_freq_correction(&self, ppm: impl Into<Ppm>) -> Result<()> {
+        self.sdr().set_freq_correction(ppm.into().0)
     }
     pub fn get_sample_rate(&self) -> u32 {
-        self.sdr.get_sample_rate()
+        self.sdr().get_sample_rate()
+    }
+    /// Exact sample rate produced by the resampler, including the fractional
+    /// part truncated by `get_sample_rate`
+    pub fn get_sample_rate_exact(&self) -> f64 {
+        self.sdr().get_sample_rate_exact()
+    }
+    pub fn set_sample_rate(&self, rate: impl Into<SampleRate>) -> Result<()> {
+        self.sdr().set_sample_rate(rate.into().0)
+    }
+    /// Sample rate ranges accepted by `set_sample_rate`, in Hz
+    pub fn supported_sample_rates() -> &'static [RangeInclusive<u32>] {
+        SAMPLE_RATE_RANGES
+    }
+    /// Set the tuner IF filter bandwidth, or 0 to follow the sample rate
+    pub fn set_tuner_bandwidth(&self, bw: impl Into<Hertz>) -> Result<()> {
+        self.sdr().set_tuner_bandwidth(bw.into().0)
+    }
+    pub fn set_testmode(&self, on: bool) -> Result<()> {
+        self.sdr().set_testmode(on)
     }
-    pub fn set_sample_rate(&mut self, rate: u32) -> Result<()> {
-        self.sdr.set_sample_rate(rate)
+    /// Log every USB control transfer at info level, as
+    /// `type request value index length: data` in hex, to diff register
+    /// access against librtlsdr. Set `RTLSDR_USB_DUMP` to dump from `open`,
+    /// including init.
+    pub fn set_usb_dump(&self, on: bool) {
+        self.sdr().set_usb_dump(on)
     }
-    pub fn set_tuner_bandwidth(&mut self, bw: u32) -> Result<()> {
-        self.sdr.set_tuner_bandwidth(bw)
+    /// Read back every register write and log a warning for each mismatch,
+    /// for diagnosing flaky hubs and counterfeit dongles that drop control
+    /// transfers silently. Set `RTLSDR_PARANOID` to verify from `open`,
+    /// including init. Registers with self-clearing or read-only bits can
+    /// read back differently on working hardware.
+    pub fn set_paranoid(&self, on: bool) {
+        self.sdr().set_paranoid(on)
     }
-    pub fn set_testmode(&mut self, on: bool) -> Result<()> {
-        self.sdr.set_testmode(on)
+    /// Register writes that read back differently while paranoid
+    pub fn write_mismatches(&self) -> u64 {
+        self.sdr().write_mismatches()
     }
-    pub fn set_direct_sampling(&mut self, mode: DirectSampleMode) -> Result<()> {
-        self.sdr.set_direct_sampling(mode)
+    /// Turn the RTL2832 digital AGC on or off. Test mode turns it off.
+    pub fn set_agc_mode(&self, on: bool) -> Result<()> {
+        self.sdr().set_agc_mode(on)
+    }
+    pub fn settings(&self) -> Settings {
+        self.sdr().settings()
+    }
+    /// Apply all of `settings`, e.g. ones saved from `settings` earlier
+    pub fn apply(&self, settings: &Settings) -> Result<()> {
+        self.sdr().apply_settings(settings)
+    }
+    pub fn set_direct_sampling(&self, mode: DirectSampleMode) -> Result<()> {
+        self.sdr().set_direct_sampling(mode)
+    }
+    pub fn set_adc_input(&self, input: AdcInput) -> Result<()> {
+        self.sdr().set_adc_input(input)
+    }
+    /// Enable the 4.096 MHz clock output on pin TP_CK0 (off by default)
+    pub fn set_clock_output(&self, on: bool) -> Result<()> {
+        self.sdr().set_clock_output(on)
+    }
+    /// Read raw pulses captured by the IR receiver (like librtlsdr's
+    /// rtlsdr_ir_query). Returns 0 when no code has been received.
+    pub fn get_ir(&self, buf: &mut [u8]) -> Result<usize> {
+        self.sdr().get_ir(buf)
+    }
+    /// Load a FIR preset or custom coefficient set into the demodulator
+    pub fn set_fir(&self, fir: Fir) -> Result<()> {
+        self.sdr().set_fir(fir)
+    }
+    pub fn get_fir(&self) -> [i32; FIR_LEN] {
+        self.sdr().get_fir()
     }
     pub fn set_bias_tee(&self, on: bool) -> Result<()> {
-        self.sdr.set_bias_tee(on)
+        self.sdr().set_bias_tee(on)
+    }
+    /// Switch a bias tee (or other external enable) wired to GPIO `gpio` (0-7)
+    pub fn set_bias_tee_gpio(&self, gpio: u8, on: bool) -> Result<()> {
+        self.sdr().set_bias_tee_gpio(gpio, on)
+    }
+    /// Drive the GPIOs selected by `mask` as outputs with the levels in `values`
+    pub fn set_gpio_pattern(&self, mask: u8, values: u8) -> Result<()> {
+        self.sdr().set_gpio_pattern(mask, values)
+    }
+    pub fn set_tracking_filter(&self, filter: TrackingFilter) -> Result<()> {
+        self.sdr().set_tracking_filter(filter)
+    }
+    pub fn set_notch_filter(&self, notch: NotchFilter) -> Result<()> {
+        self.sdr().set_notch_filter(notch)
+    }
+    /// Re-run the tuner IF filter calibration (up to `retries` attempts) and
+    /// return the resulting calibration code
+    pub fn recalibrate_filter(&self, retries: u8) -> Result<u8> {
+        self.sdr().recalibrate_filter(retries)
+    }
+    /// Enable or disable the tuner PLL sigma-delta dither. Disabling it keeps
+    /// the LO phase coherent between dongles sharing a reference clock.
+    pub fn set_dithering(&self, dither: bool) -> Result<()> {
+        self.sdr().set_dithering(dither)
+    }
+    /// Tune via the given LO harmonic (1 = off, 3 or 5) to reach frequencies
+    /// above the tuner's native range
+    pub fn set_harmonic_mode(&self, harmonic: u8) -> Result<()> {
+        self.sdr().set_harmonic_mode(harmonic)
     }
 }
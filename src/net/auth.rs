@@ -0,0 +1,218 @@
+//! Access control for the rtl_tcp and WebSocket streaming servers, which
+//! otherwise serve anyone who can reach them, in plain text.
+//!
+//! With a token set, clients must present it before they're served:
+//! - rtl_tcp clients send the token and a newline as soon as they connect,
+//!   and get the usual header once it's accepted. Existing rtl_tcp clients
+//!   can't do this by themselves, so a small proxy or tunnel script has to.
+//! - WebSocket clients pass it in the handshake, as a `token` query
+//!   parameter (browsers can't set headers) or `Authorization: Bearer`.
+//!
+//! With the `tls` feature, `Access::with_tls` also encrypts connections,
+//! see `tls`. A token sent without TLS can be read by anyone on the path.
+//!
+//! The HTTP, gRPC and metrics servers and the ZeroMQ sockets don't take an
+//! `Access`: their libraries only do TLS through dependencies this crate
+//! doesn't build, and a token sent in the clear would protect little. Bind
+//! them to a trusted interface, or put an authenticating proxy in front. The
+//! MQTT client logs in to its broker with the credentials in `MqttOptions`.
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::auth::Access;
+//! use rtlsdr_rs::net::rtl_tcp::Server;
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let access = Access::open().with_token("correct-horse-battery-staple");
+//! let server = Server::start_with_access("0.0.0.0:1234", access, || RtlSdr::open(0)).unwrap();
+//! ```
+
+#[cfg(feature = "tls")]
+use super::tls::TlsConfig;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Longest token accepted, so a client can't make the server buffer forever
+pub const MAX_TOKEN_LEN: usize = 256;
+
+/// A client connection, encrypted if TLS is configured
+pub(super) trait Connection: Read + Write + Send {}
+impl<T: Read + Write + Send> Connection for T {}
+
+/// Who may connect to a server, and how
+#[derive(Clone, Default)]
+pub struct Access {
+    token: Option<String>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+impl Access {
+    /// Anyone may connect, without encryption
+    pub fn open() -> Access {
+        Access::default()
+    }
+
+    /// Require clients to present `token`, at most `MAX_TOKEN_LEN` bytes
+    /// without newlines, and URL safe for WebSocket clients
+    pub fn with_token(mut self, token: &str) -> Access {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Encrypt connections with TLS
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsConfig) -> Access {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Run `handshake` on the client's stream, after the TLS handshake if TLS
+    /// is configured, with `timeout` for all of it rather than for each read
+    /// or write, so a client can't stretch it out by trickling bytes. Reads
+    /// and writes then time out after `timeout` each.
+    pub(super) fn admit<T, F>(
+        &self,
+        stream: TcpStream,
+        timeout: Duration,
+        handshake: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(Box<dyn Connection>) -> Result<T>,
+    {
+        let socket = stream.try_clone()?;
+        let done = Arc::new(AtomicBool::new(false));
+        let stream = Deadline {
+            stream,
+            at: Instant::now() + timeout,
+            done: done.clone(),
+        };
+        let admitted = self.wrap(stream).and_then(handshake)?;
+        done.store(true, Ordering::Relaxed);
+        socket.set_read_timeout(Some(timeout))?;
+        socket.set_write_timeout(Some(timeout))?;
+        Ok(admitted)
+    }
+
+    /// The client's stream, after the TLS handshake if TLS is configured
+    fn wrap<S: Read + Write + Send + 'static>(&self, stream: S) -> Result<Box<dyn Connection>> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Ok(Box::new(tls.accept(stream)?));
+        }
+        Ok(Box::new(stream))
+    }
+
+    /// Read a token line from the client, failing unless it's the token.
+    /// Nothing is read when no token is required.
+    pub fn check_line(&self, conn: &mut dyn Read) -> Result<()> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let mut line = Vec::new();
+        let mut byte = [0_u8];
+        loop {
+            conn.read_exact(&mut byte)?;
+            if byte[0] == b'\n' || line.len() > MAX_TOKEN_LEN {
+                break;
+            }
+            line.push(byte[0]);
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if token_matches(token.as_bytes(), &line) {
+            Ok(())
+        } else {
+            Err(RtlsdrErr("Wrong token".to_string()))
+        }
+    }
+
+    /// Whether an HTTP request with URL `query` and `Authorization` header
+    /// value `authorization` carries the token, or none is required
+    pub fn check_request(&self, query: Option<&str>, authorization: Option<&[u8]>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let from_query = query
+            .into_iter()
+            .flat_map(|q| q.split('&'))
+            .filter_map(|pair| pair.strip_prefix("token="))
+            .any(|given| token_matches(token.as_bytes(), given.as_bytes()));
+        let from_header = authorization
+            .and_then(|value| value.strip_prefix(b"Bearer "))
+            .is_some_and(|given| token_matches(token.as_bytes(), given));
+        from_query || from_header
+    }
+}
+
+/// A client socket whose reads and writes fail once `at` has passed, until
+/// the handshake is done
+struct Deadline {
+    stream: TcpStream,
+    at: Instant,
+    done: Arc<AtomicBool>,
+}
+
+impl Deadline {
+    /// Time left for the handshake, or `None` once it's done
+    fn left(&self) -> io::Result<Option<Duration>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        match self.at.checked_duration_since(Instant::now()) {
+            Some(left) if !left.is_zero() => Ok(Some(left)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Handshake took too long",
+            )),
+        }
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(left) = self.left()? {
+            self.stream.set_read_timeout(Some(left))?;
+        }
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(left) = self.left()? {
+            self.stream.set_write_timeout(Some(left))?;
+        }
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Compare without returning early, so the time taken doesn't reveal how
+/// much of the token was right
+pub(super) fn token_matches(expected: &[u8], given: &[u8]) -> bool {
+    let mut diff = expected.len() ^ given.len();
+    for (i, e) in expected.iter().enumerate() {
+        diff |= (e ^ given.get(i).unwrap_or(&0)) as usize;
+    }
+    diff == 0
+}
+
+/// Send `token` as an rtl_tcp client does, e.g. from a proxy
+pub fn send_token<W: Write>(stream: &mut W, token: &str) -> Result<()> {
+    stream.write_all(token.as_bytes())?;
+    stream.write_all(b"\n")?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use super::auth::{send_token, token_matches, Access, MAX_TOKEN_LEN};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_token_matches() {
+    assert!(token_matches(b"secret", b"secret"));
+    assert!(!token_matches(b"secret", b"secreT"));
+    assert!(!token_matches(b"secret", b"secret2"));
+    assert!(!token_matches(b"secret", b"secre"));
+    assert!(!token_matches(b"secret", b""));
+}
+
+#[test]
+fn test_check_line() {
+    let access = Access::open().with_token("secret");
+    let mut sent = Vec::new();
+    send_token(&mut sent, "secret").unwrap();
+    assert!(access.check_line(&mut sent.as_slice()).is_ok());
+    assert!(access.check_line(&mut &b"secret\r\n"[..]).is_ok());
+    assert!(access.check_line(&mut &b"wrong\n"[..]).is_err());
+    // The connection closing before the newline
+    assert!(access.check_line(&mut &b"secret"[..]).is_err());
+    // Reading stops after the longest token
+    let long = vec![b'a'; 10 * MAX_TOKEN_LEN];
+    let mut reader = long.as_slice();
+    assert!(access.check_line(&mut reader).is_err());
+    assert!(!reader.is_empty());
+
+    // Nothing is read without a token
+    let mut reader = &b"data"[..];
+    assert!(Access::open().check_line(&mut reader).is_ok());
+    assert_eq!(b"data", reader);
+}
+
+#[test]
+fn test_check_request() {
+    let access = Access::open().with_token("secret");
+    assert!(access.check_request(Some("token=secret"), None));
+    assert!(access.check_request(Some("feed=iq&token=secret"), None));
+    assert!(access.check_request(None, Some(b"Bearer secret")));
+    assert!(!access.check_request(Some("token=wrong"), Some(b"Bearer wrong")));
+    assert!(!access.check_request(Some("mytoken=secret"), None));
+    assert!(!access.check_request(None, Some(b"Basic secret")));
+    assert!(!access.check_request(None, None));
+    assert!(Access::open().check_request(None, None));
+}
+
+/// Admit one client that sends `token` a byte at a time, `gap` apart,
+/// allowing `timeout` for the handshake
+fn admit_trickled(token: &'static str, gap: Duration, timeout: Duration) -> bool {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        for byte in token.bytes().chain([b'\n']) {
+            thread::sleep(gap);
+            if stream.write_all(&[byte]).is_err() {
+                break;
+            }
+        }
+        stream
+    });
+    let (stream, _) = listener.accept().unwrap();
+    let access = Access::open().with_token(token);
+    let admitted = access.admit(stream, timeout, |mut conn| access.check_line(&mut conn));
+    drop(client.join().unwrap());
+    admitted.is_ok()
+}
+
+#[test]
+fn test_admit_deadline() {
+    assert!(admit_trickled(
+        "secret",
+        Duration::ZERO,
+        Duration::from_secs(5)
+    ));
+    // Each byte arrives within the timeout, but the whole token doesn't
+    let start = Instant::now();
+    let timeout = Duration::from_millis(200);
+    assert!(!admit_trickled(
+        "secret",
+        Duration::from_millis(60),
+        timeout
+    ));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,359 @@
+//! gRPC device control and IQ streaming (feature `grpc`), for remote access
+//! from any language with gRPC support. The service is defined in
+//! `proto/rtlsdr.proto`, and the generated messages and client are in
+//! `proto`.
+//!
+//! Unlike rtl_tcp, any number of clients can stream at once, each with its
+//! own queue. HTTP/2 flow control holds back a slow client's queue, and once
+//! it's full that client misses whole chunks, counted in `IqChunk::dropped`,
+//! without stalling the device or the other clients. The device is only
+//! read while someone is streaming.
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::grpc::Server;
+//!
+//! let server = Server::start("0.0.0.0:50051", 0).unwrap();
+//! println!("Listening on {}", server.local_addr());
+//! // ...
+//! server.stop().unwrap();
+//! ```
+
+use super::rtl_tcp::{tuner_name, Backend, Command};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::{RtlSdr, DEFAULT_BUF_LENGTH};
+use log::{info, warn};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::{mpsc as tokio_mpsc, oneshot};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+/// Messages and client generated from `proto/rtlsdr.proto`
+pub mod proto {
+    tonic::include_proto!("rtlsdr");
+}
+
+use proto::control_request::Setting;
+use proto::device_server::{Device, DeviceServer};
+use proto::{ControlRequest, Info, InfoRequest, IqChunk, StreamRequest};
+
+// Chunks queued per client unless it asks otherwise, and the most it can ask for
+const DEFAULT_QUEUE: usize = 16;
+const MAX_QUEUE: usize = 256;
+// How often the idle device thread checks for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type Reply<T> = oneshot::Sender<Result<T>>;
+type ChunkSender = tokio_mpsc::Sender<std::result::Result<IqChunk, Status>>;
+
+/// Work for the device thread, which owns the backend
+enum Job {
+    Info(Reply<Info>),
+    Apply(Command, Reply<Info>),
+    Subscribe(ChunkSender),
+}
+
+struct Subscriber {
+    tx: ChunkSender,
+    sequence: u64,
+    dropped: u64,
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    device: Option<JoinHandle<Result<()>>>,
+    stop_rpc: Option<oneshot::Sender<()>>,
+    rpc: Option<JoinHandle<Result<()>>>,
+}
+
+impl Server {
+    /// Open device `index` and serve it on `addr`, in plain text to any
+    /// client that can reach it (see `auth`)
+    pub fn start<A: ToSocketAddrs>(addr: A, index: usize) -> Result<Server> {
+        Server::start_with(addr, move || RtlSdr::open(index))
+    }
+
+    /// Serve the backend returned by `open`, which is called on the device
+    /// thread, so backends don't need to be `Send`
+    pub fn start_with<A, B, F>(addr: A, open: F) -> Result<Server>
+    where
+        A: ToSocketAddrs,
+        B: Backend,
+        F: FnOnce() -> Result<B> + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (jobs_tx, jobs) = mpsc::channel();
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let device = {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                let mut backend = match open() {
+                    Ok(backend) => {
+                        let _ = opened_tx.send(Ok(()));
+                        backend
+                    }
+                    Err(e) => {
+                        let _ = opened_tx.send(Err(e));
+                        return Ok(());
+                    }
+                };
+                run_device(&mut backend, &jobs, &shutdown)
+            })
+        };
+        // Report a failure to open before returning
+        opened_rx
+            .recv()
+            .map_err(|_| RtlsdrErr("Device thread panicked".to_string()))??;
+
+        let (stop_rpc, stopped) = oneshot::channel();
+        let service = DeviceServer::new(Service {
+            jobs: Mutex::new(jobs_tx),
+        });
+        let rpc = thread::spawn(move || {
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                tonic::transport::Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                        let _ = stopped.await;
+                    })
+                    .await
+                    .map_err(|e| RtlsdrErr(format!("gRPC server failed: {}", e)))
+            })
+        });
+        info!("gRPC listening on {}", addr);
+        Ok(Server {
+            addr,
+            shutdown,
+            device: Some(device),
+            stop_rpc: Some(stop_rpc),
+            rpc: Some(rpc),
+        })
+    }
+
+    /// Address the server is listening on, e.g. to find the port when
+    /// started on port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Whether the device is still being served. It stops by itself if the
+    /// backend fails or runs out of samples.
+    pub fn is_running(&self) -> bool {
+        self.device.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// End all streams and close the device, returning the error that
+    /// stopped the server if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown_now()
+    }
+
+    fn shutdown_now(&mut self) -> Result<()> {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // Streams end once the device thread drops their senders, which lets
+        // the server shut down gracefully
+        let device = match self.device.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| RtlsdrErr("Device thread panicked".to_string()))?,
+            None => Ok(()),
+        };
+        if let Some(stop) = self.stop_rpc.take() {
+            let _ = stop.send(());
+        }
+        let rpc = match self.rpc.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| RtlsdrErr("gRPC thread panicked".to_string()))?,
+            None => Ok(()),
+        };
+        device.and(rpc)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.shutdown_now();
+    }
+}
+
+/// Handle jobs, and read from the backend for as long as anyone streams
+fn run_device<B: Backend>(
+    backend: &mut B,
+    jobs: &Receiver<Job>,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let mut subscribers: Vec<Subscriber> = Vec::new();
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    while !shutdown.load(Ordering::Relaxed) {
+        // Wait for work while nobody is streaming
+        if subscribers.is_empty() {
+            match jobs.recv_timeout(POLL_INTERVAL) {
+                Ok(job) => handle(backend, job, &mut subscribers),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        loop {
+            match jobs.try_recv() {
+                Ok(job) => handle(backend, job, &mut subscribers),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        subscribers.retain(|s| !s.tx.is_closed());
+        if subscribers.is_empty() {
+            continue;
+        }
+
+        let n = match backend.read_sync(&mut buf) {
+            Ok(n) => n,
+            Err(e) => {
+                for s in &subscribers {
+                    let _ = s.tx.try_send(Err(Status::unavailable(e.to_string())));
+                }
+                return Err(e);
+            }
+        };
+        if n == 0 {
+            info!("Source exhausted, ending streams");
+            return Ok(());
+        }
+        let (center_freq, sample_rate) = (backend.center_freq(), backend.sample_rate());
+        subscribers.retain_mut(|s| {
+            s.sequence += 1;
+            let chunk = IqChunk {
+                sequence: s.sequence,
+                dropped: s.dropped,
+                center_freq,
+                sample_rate,
+                samples: buf[..n].to_vec(),
+            };
+            match s.tx.try_send(Ok(chunk)) {
+                Ok(()) => true,
+                Err(tokio_mpsc::error::TrySendError::Full(_)) => {
+                    s.dropped += 1;
+                    true
+                }
+                Err(tokio_mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle<B: Backend>(backend: &mut B, job: Job, subscribers: &mut Vec<Subscriber>) {
+    match job {
+        Job::Info(reply) => {
+            let _ = reply.send(info(backend));
+        }
+        Job::Apply(command, reply) => {
+            let result = backend.apply(command).and_then(|()| info(backend));
+            if let Err(e) = &result {
+                warn!("Command {:?} failed: {}", command, e);
+            }
+            let _ = reply.send(result);
+        }
+        Job::Subscribe(tx) => subscribers.push(Subscriber {
+            tx,
+            sequence: 0,
+            dropped: 0,
+        }),
+    }
+}
+
+fn info<B: Backend>(backend: &B) -> Result<Info> {
+    let (tuner_type, gain_steps) = backend.info()?;
+    Ok(Info {
+        tuner: tuner_name(tuner_type).to_string(),
+        gain_steps,
+        center_freq: backend.center_freq(),
+        sample_rate: backend.sample_rate(),
+    })
+}
+
+/// The command a control request asks for
+pub(super) fn command(request: &ControlRequest) -> Option<Command> {
+    Some(match request.setting? {
+        Setting::CenterFreq(freq) => Command::SetCenterFreq(freq),
+        Setting::SampleRate(rate) => Command::SetSampleRate(rate),
+        Setting::Gain(gain) => Command::SetGain(gain),
+        Setting::AutoGain(auto) => Command::SetGainMode(!auto),
+        Setting::FreqCorrection(ppm) => Command::SetFreqCorrection(ppm),
+        Setting::BiasTee(on) => Command::SetBiasTee(on),
+        Setting::DirectSampling(mode) => Command::SetDirectSampling(mode),
+    })
+}
+
+/// The gRPC side, passing jobs to the device thread
+struct Service {
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
+
+impl Service {
+    fn send(&self, job: Job) -> std::result::Result<(), Status> {
+        self.jobs
+            .lock()
+            .map_err(|_| Status::internal("Device thread panicked"))?
+            .send(job)
+            .map_err(|_| Status::unavailable("Device closed"))
+    }
+
+    async fn call<T>(&self, job: impl FnOnce(Reply<T>) -> Job) -> std::result::Result<T, Status> {
+        let (tx, rx) = oneshot::channel();
+        self.send(job(tx))?;
+        rx.await
+            .map_err(|_| Status::unavailable("Device closed"))?
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+#[tonic::async_trait]
+impl Device for Service {
+    async fn get_info(
+        &self,
+        _request: Request<InfoRequest>,
+    ) -> std::result::Result<Response<Info>, Status> {
+        self.call(Job::Info).await.map(Response::new)
+    }
+
+    async fn control(
+        &self,
+        request: Request<ControlRequest>,
+    ) -> std::result::Result<Response<Info>, Status> {
+        let command = command(request.get_ref())
+            .ok_or_else(|| Status::invalid_argument("No setting given"))?;
+        self.call(|reply| Job::Apply(command, reply))
+            .await
+            .map(Response::new)
+    }
+
+    type StreamIqStream = ReceiverStream<std::result::Result<IqChunk, Status>>;
+
+    async fn stream_iq(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> std::result::Result<Response<Self::StreamIqStream>, Status> {
+        let queue = match request.get_ref().queue as usize {
+            0 => DEFAULT_QUEUE,
+            n => n.min(MAX_QUEUE),
+        };
+        let (tx, rx) = tokio_mpsc::channel(queue);
+        self.send(Job::Subscribe(tx))?;
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
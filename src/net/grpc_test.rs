@@ -0,0 +1,223 @@
+use super::grpc::proto::control_request::Setting;
+use super::grpc::proto::device_client::DeviceClient;
+use super::grpc::proto::{ControlRequest, InfoRequest, StreamRequest};
+use super::grpc::{command, Server};
+use super::rtl_tcp::{Backend, Command, TUNER_R820T};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use std::thread;
+use std::time::Duration;
+use tonic::transport::Channel;
+use tonic::Code;
+
+/// Counting samples, a block of `len` bytes per millisecond, for `blocks`
+/// blocks if set
+struct Fake {
+    next: u8,
+    len: usize,
+    blocks: Option<usize>,
+    freq: u32,
+}
+
+impl SampleSource for Fake {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if let Some(blocks) = &mut self.blocks {
+            if *blocks == 0 {
+                return Ok(0);
+            }
+            *blocks -= 1;
+        }
+        thread::sleep(Duration::from_millis(1));
+        let buf = &mut buf[..self.len];
+        for b in buf.iter_mut() {
+            *b = self.next;
+            self.next = self.next.wrapping_add(1);
+        }
+        Ok(buf.len())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        1_024_000
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.freq
+    }
+}
+
+impl Backend for Fake {
+    fn info(&self) -> Result<(u32, u32)> {
+        Ok((TUNER_R820T, 29))
+    }
+
+    fn apply(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::SetCenterFreq(freq) => self.freq = freq,
+            Command::SetBiasTee(_) => return Err(RtlsdrErr("No bias tee".to_string())),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn start(len: usize, blocks: Option<usize>) -> Server {
+    Server::start_with("127.0.0.1:0", move || {
+        Ok(Fake {
+            next: 0,
+            len,
+            blocks,
+            freq: 100_000_000,
+        })
+    })
+    .unwrap()
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+async fn connect(server: &Server) -> DeviceClient<Channel> {
+    DeviceClient::connect(format!("http://{}", server.local_addr()))
+        .await
+        .unwrap()
+}
+
+#[test]
+fn test_command() {
+    let request = |setting| ControlRequest {
+        setting: Some(setting),
+    };
+    assert_eq!(
+        Some(Command::SetCenterFreq(144_800_000)),
+        command(&request(Setting::CenterFreq(144_800_000)))
+    );
+    assert_eq!(
+        Some(Command::SetGainMode(false)),
+        command(&request(Setting::AutoGain(true)))
+    );
+    assert_eq!(
+        Some(Command::SetGain(496)),
+        command(&request(Setting::Gain(496)))
+    );
+    assert_eq!(
+        Some(Command::SetDirectSampling(2)),
+        command(&request(Setting::DirectSampling(2)))
+    );
+    assert_eq!(None, command(&ControlRequest { setting: None }));
+}
+
+#[test]
+fn test_info_and_control() {
+    let server = start(1024, None);
+    runtime().block_on(async {
+        let mut client = connect(&server).await;
+        let info = client.get_info(InfoRequest {}).await.unwrap().into_inner();
+        assert_eq!("R820T", info.tuner);
+        assert_eq!(29, info.gain_steps);
+        assert_eq!(100_000_000, info.center_freq);
+        assert_eq!(1_024_000, info.sample_rate);
+
+        let request = ControlRequest {
+            setting: Some(Setting::CenterFreq(144_800_000)),
+        };
+        let info = client.control(request).await.unwrap().into_inner();
+        assert_eq!(144_800_000, info.center_freq);
+
+        let empty = client.control(ControlRequest { setting: None }).await;
+        assert_eq!(Code::InvalidArgument, empty.unwrap_err().code());
+        let request = ControlRequest {
+            setting: Some(Setting::BiasTee(true)),
+        };
+        let failed = client.control(request).await.unwrap_err();
+        assert_eq!(Code::Internal, failed.code());
+        assert!(failed.message().contains("No bias tee"));
+    });
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_streams() {
+    let server = start(1024, None);
+    runtime().block_on(async {
+        let mut a = connect(&server).await;
+        let mut b = connect(&server).await;
+        let mut a = a
+            .stream_iq(StreamRequest { queue: 0 })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut b = b
+            .stream_iq(StreamRequest { queue: 0 })
+            .await
+            .unwrap()
+            .into_inner();
+        for sequence in 1..=3 {
+            let chunk = a.message().await.unwrap().unwrap();
+            assert_eq!(sequence, chunk.sequence);
+            assert_eq!(0, chunk.dropped);
+            assert_eq!(1024, chunk.samples.len());
+            assert_eq!(100_000_000, chunk.center_freq);
+            // Counting samples continue from chunk to chunk
+            assert_eq!(chunk.samples[0].wrapping_add(1), chunk.samples[1]);
+        }
+        assert_eq!(1, b.message().await.unwrap().unwrap().sequence);
+    });
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_slow_client_drops() {
+    let server = start(65536, None);
+    runtime().block_on(async {
+        let mut client = connect(&server).await;
+        let mut stream = client
+            .stream_iq(StreamRequest { queue: 1 })
+            .await
+            .unwrap()
+            .into_inner();
+        stream.message().await.unwrap().unwrap();
+        // Fall far enough behind to fill the flow control window
+        thread::sleep(Duration::from_millis(500));
+        let mut last = None;
+        for _ in 0..100 {
+            last = stream.message().await.unwrap();
+        }
+        let last = last.unwrap();
+        assert!(last.dropped > 0);
+        // Every chunk so far was either received or dropped
+        assert_eq!(101 + last.dropped, last.sequence);
+    });
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_stream_ends_with_source() {
+    let server = start(1024, Some(5));
+    runtime().block_on(async {
+        let mut client = connect(&server).await;
+        let mut stream = client
+            .stream_iq(StreamRequest { queue: 8 })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut chunks = 0;
+        while let Some(_chunk) = stream.message().await.unwrap() {
+            chunks += 1;
+        }
+        assert_eq!(5, chunks);
+    });
+    assert!(!server.is_running());
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_open_error() {
+    let result = Server::start_with("127.0.0.1:0", || -> Result<Fake> {
+        Err(RtlsdrErr("No device".to_string()))
+    });
+    assert!(result.is_err());
+}
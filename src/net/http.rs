@@ -0,0 +1,291 @@
+//! HTTP control API (feature `http`), so home automation and monitoring
+//! can reconfigure a running receiver without a custom protocol.
+//!
+//! The receive loop owns the device, so it calls `Server::poll` between
+//! reads to answer pending requests (or leaves the loop to `Server::run`).
+//! Values are JSON, and a PUT body is the bare new value:
+//!
+//! | Path           | GET and PUT                                |
+//! |----------------|--------------------------------------------|
+//! | `/frequency`   | Center frequency in Hz                     |
+//! | `/sample_rate` | Samples per second                         |
+//! | `/gain`        | Tuner gain in dB, or `"auto"`              |
+//! | `/bias_tee`    | `true` or `false`                          |
+//!
+//! `GET /` returns all of these as an object, `GET /info` the tuner and
+//! `GET /stats` the samples read so far. Gain and bias tee come from the
+//! device settings; for backends that don't track them they're what was last
+//! set through the API, or `null`.
+//!
+//! ```text
+//! curl -X PUT -d 144.8e6 http://localhost:8000/frequency
+//! ```
+
+use super::rtl_tcp::{tuner_name, Backend, Command};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::TunerGain;
+use log::warn;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response};
+
+/// Longest PUT body accepted
+const MAX_BODY: u64 = 1024;
+
+/// Samples read since the server started
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub reads: u64,
+    pub samples: u64, // IQ pairs
+    pub seconds: f64,
+}
+
+impl Stats {
+    /// Average samples per second actually received
+    pub fn rate(&self) -> f64 {
+        if self.seconds > 0.0 {
+            self.samples as f64 / self.seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct Server {
+    http: tiny_http::Server,
+    addr: SocketAddr,
+    gain: Option<TunerGain>, // Last set, for backends without settings
+    bias_tee: Option<bool>,
+    started: Instant,
+    reads: u64,
+    bytes: u64,
+}
+
+/// A request that couldn't be carried out, as status code and message
+type Failure = (u16, String);
+
+impl Server {
+    /// Listen on `addr`, e.g. `0.0.0.0:8000`. Requests aren't authenticated,
+    /// so anyone who can reach `addr` can retune the device; see `auth`.
+    pub fn start<A: ToSocketAddrs>(addr: A) -> Result<Server> {
+        let http = tiny_http::Server::http(addr).map_err(|e| RtlsdrErr(e.to_string()))?;
+        let addr = http
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| RtlsdrErr("Not listening on an IP address".to_string()))?;
+        Ok(Server {
+            http,
+            addr,
+            gain: None,
+            bias_tee: None,
+            started: Instant::now(),
+            reads: 0,
+            bytes: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Count a read of `bytes` raw bytes for `/stats`
+    pub fn record_read(&mut self, bytes: usize) {
+        self.reads += 1;
+        self.bytes += bytes as u64;
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            reads: self.reads,
+            samples: self.bytes / 2,
+            seconds: self.started.elapsed().as_secs_f64(),
+        }
+    }
+
+    /// Answer the requests received since the last call, without waiting,
+    /// applying changes to `backend`. Returns the number answered.
+    pub fn poll<B: Backend>(&mut self, backend: &mut B) -> Result<usize> {
+        let mut answered = 0;
+        while let Some(mut request) = self.http.try_recv()? {
+            let (status, body) = match self.handle(&mut request, backend) {
+                Ok(value) => (200, value),
+                Err((status, message)) => (status, json!({ "error": message })),
+            };
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(
+                    Header::from_bytes("Content-Type", "application/json").expect("Valid header"),
+                );
+            if let Err(e) = request.respond(response) {
+                warn!("HTTP response failed: {}", e);
+            }
+            answered += 1;
+        }
+        Ok(answered)
+    }
+
+    /// Read from `source` until `shutdown` is set or the source runs out,
+    /// passing each block to `callback` and answering requests between reads
+    pub fn run<B: Backend, F: FnMut(&[u8])>(
+        &mut self,
+        source: &mut B,
+        shutdown: &AtomicBool,
+        mut callback: F,
+    ) -> Result<()> {
+        let mut buf = vec![0_u8; crate::DEFAULT_BUF_LENGTH];
+        while !shutdown.load(Ordering::Relaxed) {
+            self.poll(source)?;
+            let n = source.read_sync(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            self.record_read(n);
+            callback(&buf[..n]);
+        }
+        Ok(())
+    }
+
+    fn handle<B: Backend>(
+        &mut self,
+        request: &mut Request,
+        backend: &mut B,
+    ) -> std::result::Result<Value, Failure> {
+        let path = request.url().split('?').next().unwrap_or("");
+        let path = path.trim_end_matches('/').to_string();
+        match request.method() {
+            Method::Get => self.get(&path, backend),
+            Method::Put => {
+                if !is_setting(&path) {
+                    return Err(not_found(&path));
+                }
+                let value = body(request)?;
+                self.put(&path, &value, backend)?;
+                self.get(&path, backend)
+            }
+            method => Err((405, format!("{} not allowed", method))),
+        }
+    }
+
+    fn get<B: Backend>(&self, path: &str, backend: &B) -> std::result::Result<Value, Failure> {
+        let (gain, bias_tee) = match backend.settings() {
+            Some(settings) => (Some(settings.gain), Some(settings.bias_tee)),
+            None => (self.gain, self.bias_tee),
+        };
+        Ok(match path {
+            "" => json!({
+                "frequency": backend.center_freq(),
+                "sample_rate": backend.sample_rate(),
+                "gain": gain_value(gain),
+                "bias_tee": bias_tee,
+            }),
+            "/frequency" => json!(backend.center_freq()),
+            "/sample_rate" => json!(backend.sample_rate()),
+            "/gain" => gain_value(gain),
+            "/bias_tee" => json!(bias_tee),
+            "/info" => {
+                let (tuner_type, gain_count) = backend.info().map_err(device_failure)?;
+                json!({
+                    "tuner": tuner_name(tuner_type),
+                    "gain_steps": gain_count,
+                })
+            }
+            "/stats" => {
+                let stats = self.stats();
+                json!({
+                    "reads": stats.reads,
+                    "samples": stats.samples,
+                    "seconds": stats.seconds,
+                    "rate": stats.rate(),
+                })
+            }
+            _ => return Err(not_found(path)),
+        })
+    }
+
+    fn put<B: Backend>(
+        &mut self,
+        path: &str,
+        value: &Value,
+        backend: &mut B,
+    ) -> std::result::Result<(), Failure> {
+        let command = match path {
+            "/frequency" => Command::SetCenterFreq(hertz(value)?),
+            "/sample_rate" => Command::SetSampleRate(hertz(value)?),
+            "/gain" => match value {
+                Value::String(s) if s.eq_ignore_ascii_case("auto") => Command::SetGainMode(false),
+                _ => {
+                    let db = value.as_f64().ok_or_else(|| bad_value(value))?;
+                    Command::SetGain((db * 10.0).round() as i32)
+                }
+            },
+            "/bias_tee" => Command::SetBiasTee(switch(value)?),
+            _ => return Err(not_found(path)),
+        };
+        backend.apply(command).map_err(device_failure)?;
+        match command {
+            Command::SetGainMode(false) => self.gain = Some(TunerGain::Auto),
+            Command::SetGain(gain) => self.gain = Some(TunerGain::Manual(gain)),
+            Command::SetBiasTee(on) => self.bias_tee = Some(on),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn is_setting(path: &str) -> bool {
+    matches!(path, "/frequency" | "/sample_rate" | "/gain" | "/bias_tee")
+}
+
+/// The PUT body as JSON, falling back to a string for bare words like auto
+fn body(request: &mut Request) -> std::result::Result<Value, Failure> {
+    let mut text = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY)
+        .read_to_string(&mut text)
+        .map_err(|e| (400, format!("Unreadable body: {}", e)))?;
+    let text = text.trim();
+    Ok(serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())))
+}
+
+fn hertz(value: &Value) -> std::result::Result<u32, Failure> {
+    match value.as_f64() {
+        Some(hz) if hz > 0.0 && hz <= u32::MAX as f64 => Ok(hz.round() as u32),
+        _ => Err(bad_value(value)),
+    }
+}
+
+fn switch(value: &Value) -> std::result::Result<bool, Failure> {
+    match value {
+        Value::Bool(on) => Ok(*on),
+        Value::Number(n) if n.as_u64() == Some(0) => Ok(false),
+        Value::Number(n) if n.as_u64() == Some(1) => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("on") => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("off") => Ok(false),
+        _ => Err(bad_value(value)),
+    }
+}
+
+fn gain_value(gain: Option<TunerGain>) -> Value {
+    match gain {
+        Some(TunerGain::Auto) => json!("auto"),
+        Some(TunerGain::Manual(tenths)) => json!(tenths as f64 / 10.0),
+        None => Value::Null,
+    }
+}
+
+fn bad_value(value: &Value) -> Failure {
+    (400, format!("Invalid value {}", value))
+}
+
+fn not_found(path: &str) -> Failure {
+    (404, format!("No such setting {}", path))
+}
+
+fn device_failure(e: crate::error::RtlsdrError) -> Failure {
+    (500, e.to_string())
+}
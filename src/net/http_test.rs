@@ -0,0 +1,241 @@
+use super::http::Server;
+use super::rtl_tcp::{Backend, Command, TUNER_R820T};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::{DirectSampleMode, Settings, TunerGain};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Keeps the settings it's given, failing above 1.7 GHz
+#[derive(Default)]
+struct Fake {
+    freq: u32,
+    rate: u32,
+    commands: Vec<Command>,
+    settings: Option<Settings>, // Reported as the backend's settings
+}
+
+impl SampleSource for Fake {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        thread::sleep(Duration::from_millis(1));
+        Ok(buf.len().min(1000))
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.freq
+    }
+}
+
+impl Backend for Fake {
+    fn info(&self) -> Result<(u32, u32)> {
+        Ok((TUNER_R820T, 29))
+    }
+
+    fn apply(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::SetCenterFreq(freq) if freq > 1_700_000_000 => {
+                return Err(RtlsdrErr(format!("Can't tune to {}", freq)));
+            }
+            Command::SetCenterFreq(freq) => self.freq = freq,
+            Command::SetSampleRate(rate) => self.rate = rate,
+            _ => {}
+        }
+        self.commands.push(command);
+        Ok(())
+    }
+
+    fn settings(&self) -> Option<Settings> {
+        self.settings
+    }
+}
+
+/// Send a request from another thread while polling the server, returning
+/// the status code and JSON body
+fn request(
+    server: &mut Server,
+    fake: &mut Fake,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> (u16, Value) {
+    let addr = server.local_addr();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    );
+    let client = thread::spawn(move || send(addr, &request));
+    let start = Instant::now();
+    while !client.is_finished() {
+        assert!(start.elapsed() < Duration::from_secs(5), "No response");
+        server.poll(fake).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    client.join().unwrap()
+}
+
+fn send(addr: SocketAddr, request: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn start() -> (Server, Fake) {
+    let fake = Fake {
+        freq: 100_000_000,
+        rate: 2_048_000,
+        ..Default::default()
+    };
+    (Server::start("127.0.0.1:0").unwrap(), fake)
+}
+
+#[test]
+fn test_get_settings() {
+    let (mut server, mut fake) = start();
+    assert_eq!(
+        (200, json!(100_000_000)),
+        request(&mut server, &mut fake, "GET", "/frequency", "")
+    );
+    let (status, all) = request(&mut server, &mut fake, "GET", "/", "");
+    assert_eq!(200, status);
+    assert_eq!(json!(2_048_000), all["sample_rate"]);
+    // Unknown until set
+    assert_eq!(Value::Null, all["gain"]);
+    assert_eq!(Value::Null, all["bias_tee"]);
+}
+
+#[test]
+fn test_get_device_settings() {
+    let (mut server, mut fake) = start();
+    fake.settings = Some(Settings {
+        center_freq: 100_000_000,
+        sample_rate: 2_048_000,
+        gain: TunerGain::Manual(197),
+        ppm: 0,
+        bandwidth: 0,
+        bias_tee: true,
+        direct_sampling: DirectSampleMode::Off,
+        agc: false,
+    });
+    // Reported by the device without being set through the API
+    let (_, all) = request(&mut server, &mut fake, "GET", "/", "");
+    assert_eq!(json!(19.7), all["gain"]);
+    assert_eq!(json!(true), all["bias_tee"]);
+    assert_eq!(
+        (200, json!(true)),
+        request(&mut server, &mut fake, "GET", "/bias_tee", "")
+    );
+}
+
+#[test]
+fn test_put_settings() {
+    let (mut server, mut fake) = start();
+    assert_eq!(
+        (200, json!(144_800_000)),
+        request(&mut server, &mut fake, "PUT", "/frequency", "144.8e6")
+    );
+    assert_eq!(
+        (200, json!(1_024_000)),
+        request(&mut server, &mut fake, "PUT", "/sample_rate/", "1024000")
+    );
+    assert_eq!(
+        (200, json!(38.6)),
+        request(&mut server, &mut fake, "PUT", "/gain", "38.6")
+    );
+    assert_eq!(
+        (200, json!("auto")),
+        request(&mut server, &mut fake, "PUT", "/gain", "auto")
+    );
+    assert_eq!(
+        (200, json!(true)),
+        request(&mut server, &mut fake, "PUT", "/bias_tee", "on")
+    );
+    assert_eq!(
+        vec![
+            Command::SetCenterFreq(144_800_000),
+            Command::SetSampleRate(1_024_000),
+            Command::SetGain(386),
+            Command::SetGainMode(false),
+            Command::SetBiasTee(true),
+        ],
+        fake.commands
+    );
+    let (_, all) = request(&mut server, &mut fake, "GET", "/", "");
+    assert_eq!(json!("auto"), all["gain"]);
+    assert_eq!(json!(true), all["bias_tee"]);
+}
+
+#[test]
+fn test_errors() {
+    let (mut server, mut fake) = start();
+    let (status, body) = request(&mut server, &mut fake, "PUT", "/frequency", "fast");
+    assert_eq!(400, status);
+    assert!(body["error"].is_string());
+    assert_eq!(
+        400,
+        request(&mut server, &mut fake, "PUT", "/bias_tee", "2").0
+    );
+    // Rejected by the device
+    assert_eq!(
+        500,
+        request(&mut server, &mut fake, "PUT", "/frequency", "2e9").0
+    );
+    assert_eq!(404, request(&mut server, &mut fake, "GET", "/volume", "").0);
+    assert_eq!(404, request(&mut server, &mut fake, "PUT", "/stats", "0").0);
+    assert_eq!(
+        405,
+        request(&mut server, &mut fake, "DELETE", "/gain", "").0
+    );
+    assert!(fake.commands.is_empty());
+    assert_eq!(100_000_000, fake.freq);
+}
+
+#[test]
+fn test_info_and_stats() {
+    let (mut server, mut fake) = start();
+    let (_, info) = request(&mut server, &mut fake, "GET", "/info", "");
+    assert_eq!(json!({ "tuner": "R820T", "gain_steps": 29 }), info);
+
+    server.record_read(2000);
+    server.record_read(2000);
+    let (_, stats) = request(&mut server, &mut fake, "GET", "/stats", "");
+    assert_eq!(json!(2), stats["reads"]);
+    assert_eq!(json!(2000), stats["samples"]);
+    assert!(stats["rate"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn test_run_counts_reads() {
+    let (mut server, mut fake) = start();
+    let shutdown = AtomicBool::new(false);
+    let mut blocks = 0;
+    server
+        .run(&mut fake, &shutdown, |buf| {
+            assert_eq!(1000, buf.len());
+            blocks += 1;
+            if blocks == 3 {
+                shutdown.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+        })
+        .unwrap();
+    assert_eq!(3, server.stats().reads);
+    assert_eq!(1500, server.stats().samples);
+}
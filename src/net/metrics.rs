@@ -0,0 +1,265 @@
+//! Prometheus metrics for long running receivers (feature `metrics`).
+//!
+//! `Metrics` is a cheap to clone set of counters the receive loop updates as
+//! it goes, and `Exporter` serves them in the Prometheus text format on
+//! `/metrics` from its own thread:
+//!
+//! | Metric                         | Type    | Description                |
+//! |--------------------------------|---------|----------------------------|
+//! | `rtlsdr_samples_total`         | counter | IQ samples received        |
+//! | `rtlsdr_samples_per_second`    | gauge   | Rate over the last second  |
+//! | `rtlsdr_reads_total`           | counter | Buffers read               |
+//! | `rtlsdr_dropped_buffers_total` | counter | Buffers lost or discarded  |
+//! | `rtlsdr_usb_errors_total`      | counter | Failed USB transfers       |
+//! | `rtlsdr_retunes_total`         | counter | Center frequency changes   |
+//! | `rtlsdr_queue_depth`           | gauge   | Buffers waiting to be used |
+//!
+//! ```no_run
+//! use rtlsdr_rs::error::RtlsdrError;
+//! use rtlsdr_rs::net::metrics::{Exporter, Metrics};
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let metrics = Metrics::new();
+//! let _exporter = Exporter::start("0.0.0.0:9100", metrics.clone()).unwrap();
+//! let sdr = RtlSdr::open(0).unwrap();
+//! let mut buf = vec![0_u8; rtlsdr_rs::DEFAULT_BUF_LENGTH];
+//! loop {
+//!     match sdr.read_sync(&mut buf) {
+//!         Ok(n) => metrics.record_read(n),
+//!         Err(RtlsdrError::Usb(_)) => metrics.record_usb_error(),
+//!         Err(e) => panic!("{}", e),
+//!     }
+//! }
+//! ```
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use log::warn;
+use std::fmt::Write;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Response};
+
+// Period the sample rate is measured over
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+// How often the exporter checks for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Counters shared between the receive loop and the exporter
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    reads: AtomicU64,
+    samples: AtomicU64,
+    dropped: AtomicU64,
+    usb_errors: AtomicU64,
+    retunes: AtomicU64,
+    queue_depth: AtomicU64,
+    rate: Mutex<Rate>,
+}
+
+/// Samples counted in the current window, and the rate over the last one
+#[derive(Default)]
+struct Rate {
+    start: Option<Instant>,
+    samples: u64,
+    last: f64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Count a read of `bytes` raw bytes
+    pub fn record_read(&self, bytes: usize) {
+        let samples = bytes as u64 / 2;
+        self.inner.reads.fetch_add(1, Ordering::Relaxed);
+        self.inner.samples.fetch_add(samples, Ordering::Relaxed);
+        if let Ok(mut rate) = self.inner.rate.lock() {
+            let now = Instant::now();
+            // The first read only starts the window, its samples came before
+            let Some(start) = rate.start else {
+                rate.start = Some(now);
+                return;
+            };
+            rate.samples += samples;
+            let elapsed = now - start;
+            if elapsed >= RATE_WINDOW {
+                rate.last = rate.samples as f64 / elapsed.as_secs_f64();
+                rate.start = Some(now);
+                rate.samples = 0;
+            }
+        }
+    }
+
+    /// Count a buffer lost to a slow consumer or a short read
+    pub fn record_drop(&self) {
+        self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_usb_error(&self) {
+        self.inner.usb_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_retune(&self) {
+        self.inner.retunes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Buffers currently queued between the reader and its consumers
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.inner
+            .queue_depth
+            .store(depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.inner.samples.load(Ordering::Relaxed)
+    }
+
+    /// Samples per second over the last second, 0 once reads stop
+    pub fn sample_rate(&self) -> f64 {
+        match self.inner.rate.lock() {
+            Ok(rate) if rate.start.is_some_and(|s| s.elapsed() < 2 * RATE_WINDOW) => rate.last,
+            _ => 0.0,
+        }
+    }
+
+    /// All metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = write!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            );
+        };
+        let c = &self.inner;
+        metric(
+            "rtlsdr_samples_total",
+            "counter",
+            "IQ samples received",
+            load(&c.samples).to_string(),
+        );
+        metric(
+            "rtlsdr_samples_per_second",
+            "gauge",
+            "IQ samples received per second over the last second",
+            self.sample_rate().to_string(),
+        );
+        metric(
+            "rtlsdr_reads_total",
+            "counter",
+            "Buffers read from the device",
+            load(&c.reads).to_string(),
+        );
+        metric(
+            "rtlsdr_dropped_buffers_total",
+            "counter",
+            "Buffers lost or discarded",
+            load(&c.dropped).to_string(),
+        );
+        metric(
+            "rtlsdr_usb_errors_total",
+            "counter",
+            "Failed USB transfers",
+            load(&c.usb_errors).to_string(),
+        );
+        metric(
+            "rtlsdr_retunes_total",
+            "counter",
+            "Center frequency changes",
+            load(&c.retunes).to_string(),
+        );
+        metric(
+            "rtlsdr_queue_depth",
+            "gauge",
+            "Buffers waiting to be processed",
+            load(&c.queue_depth).to_string(),
+        );
+        text
+    }
+}
+
+/// Serves `Metrics` on `/metrics` for Prometheus to scrape
+pub struct Exporter {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Exporter {
+    /// Listen on `addr`, e.g. `0.0.0.0:9100`, without authentication like
+    /// most Prometheus exporters
+    pub fn start<A: ToSocketAddrs>(addr: A, metrics: Metrics) -> Result<Exporter> {
+        let http = tiny_http::Server::http(addr).map_err(|e| RtlsdrErr(e.to_string()))?;
+        let addr = http
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| RtlsdrErr("Not listening on an IP address".to_string()))?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || serve(&http, &metrics, &shutdown))
+        };
+        Ok(Exporter {
+            addr,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown_now();
+    }
+
+    fn shutdown_now(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.shutdown_now();
+    }
+}
+
+fn serve(http: &tiny_http::Server, metrics: &Metrics, shutdown: &AtomicBool) {
+    while !shutdown.load(Ordering::Relaxed) {
+        let request = match http.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Metrics exporter failed: {}", e);
+                return;
+            }
+        };
+        let path = request.url().split('?').next().unwrap_or("");
+        let response = match (request.method(), path) {
+            (Method::Get, "/metrics") => Response::from_string(metrics.render()).with_header(
+                Header::from_bytes("Content-Type", CONTENT_TYPE).expect("Valid header"),
+            ),
+            (Method::Get, _) => Response::from_string("Not found").with_status_code(404),
+            _ => Response::from_string("Method not allowed").with_status_code(405),
+        };
+        if let Err(e) = request.respond(response) {
+            warn!("Metrics response failed: {}", e);
+        }
+    }
+}
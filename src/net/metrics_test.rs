@@ -0,0 +1,88 @@
+use super::metrics::{Exporter, Metrics};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// Value of an unlabeled metric in the text format
+fn value(text: &str, name: &str) -> f64 {
+    text.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("No {} in\n{}", name, text))
+        .parse()
+        .unwrap()
+}
+
+fn get(addr: SocketAddr, path: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let (headers, body) = response.split_once("\r\n\r\n").unwrap();
+    if status == 200 {
+        assert!(headers.contains("text/plain; version=0.0.4"), "{}", headers);
+    }
+    (status, body.to_string())
+}
+
+#[test]
+fn test_render() {
+    let metrics = Metrics::new();
+    let other = metrics.clone();
+    metrics.record_read(2048);
+    other.record_read(1000);
+    metrics.record_drop();
+    metrics.record_usb_error();
+    metrics.record_usb_error();
+    metrics.record_retune();
+    metrics.set_queue_depth(7);
+    let text = other.render();
+    assert_eq!(1524.0, value(&text, "rtlsdr_samples_total"));
+    assert_eq!(2.0, value(&text, "rtlsdr_reads_total"));
+    assert_eq!(1.0, value(&text, "rtlsdr_dropped_buffers_total"));
+    assert_eq!(2.0, value(&text, "rtlsdr_usb_errors_total"));
+    assert_eq!(1.0, value(&text, "rtlsdr_retunes_total"));
+    assert_eq!(7.0, value(&text, "rtlsdr_queue_depth"));
+    assert!(text.contains("# TYPE rtlsdr_samples_total counter\n"));
+    assert!(text.contains("# TYPE rtlsdr_queue_depth gauge\n"));
+}
+
+#[test]
+fn test_sample_rate() {
+    let metrics = Metrics::new();
+    assert_eq!(0.0, metrics.sample_rate());
+    // 100 kS/s in 10 ms reads
+    for _ in 0..120 {
+        metrics.record_read(2000);
+        thread::sleep(Duration::from_millis(10));
+    }
+    let rate = metrics.sample_rate();
+    // Sleeps overshoot, so reads come slower than asked for
+    assert!(rate > 50_000.0 && rate <= 100_000.0, "{}", rate);
+}
+
+#[test]
+fn test_exporter() {
+    let metrics = Metrics::new();
+    let exporter = Exporter::start("127.0.0.1:0", metrics.clone()).unwrap();
+    let addr = exporter.local_addr();
+    metrics.record_read(262_144);
+    let (status, body) = get(addr, "/metrics");
+    assert_eq!(200, status);
+    assert_eq!(131_072.0, value(&body, "rtlsdr_samples_total"));
+    metrics.record_retune();
+    let (_, body) = get(addr, "/metrics?x=1");
+    assert_eq!(1.0, value(&body, "rtlsdr_retunes_total"));
+    assert_eq!(404, get(addr, "/").0);
+    exporter.stop();
+    assert!(TcpStream::connect(addr).is_err());
+}
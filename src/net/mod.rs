@@ -0,0 +1,36 @@
+//! Serving a device over the network
+pub mod auth;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod rtl_tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+#[cfg(test)]
+mod auth_test;
+#[cfg(all(test, feature = "grpc"))]
+mod grpc_test;
+#[cfg(all(test, feature = "http"))]
+mod http_test;
+#[cfg(all(test, feature = "metrics"))]
+mod metrics_test;
+#[cfg(all(test, feature = "mqtt"))]
+mod mqtt_test;
+#[cfg(test)]
+mod rtl_tcp_test;
+#[cfg(all(test, feature = "tls"))]
+mod tls_test;
+#[cfg(all(test, feature = "websocket"))]
+mod websocket_test;
+#[cfg(all(test, feature = "zmq"))]
+mod zmq_test;
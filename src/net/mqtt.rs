@@ -0,0 +1,273 @@
+//! MQTT control and telemetry (feature `mqtt`), for Home Assistant style
+//! deployments.
+//!
+//! Topics are under a prefix such as `rtlsdr/attic`:
+//! - `<prefix>/set/frequency`, `set/gain` and `set/squelch` take controls as
+//!   text: Hz (`144.8e6`), dB or `auto`, and dBFS.
+//! - `<prefix>/state/<name>` is retained with each applied setting.
+//! - `<prefix>/level`, `drops` and `event/<kind>` carry telemetry.
+//! - `<prefix>/status` is a retained `online`, or `offline` once the client
+//!   stops or loses its connection.
+//!
+//! The receive loop owns the device, so it collects controls with
+//! `Client::poll_control` between reads:
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::mqtt::{Client, MqttOptions};
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let mut sdr = RtlSdr::open(0).unwrap();
+//! let mqtt = Client::connect(MqttOptions::new("attic-sdr", "broker", 1883), "rtlsdr/attic")
+//!     .unwrap();
+//! let mut buf = vec![0_u8; rtlsdr_rs::DEFAULT_BUF_LENGTH];
+//! loop {
+//!     for control in mqtt.poll_control() {
+//!         // A squelch threshold is the application's to apply, as well
+//!         if let Err(e) = mqtt.apply(&mut sdr, control) {
+//!             eprintln!("{:?} failed: {}", control, e);
+//!         }
+//!     }
+//!     sdr.read_sync(&mut buf).unwrap();
+//!     mqtt.publish_level(-42.0);
+//! }
+//! ```
+
+use super::rtl_tcp::{Backend, Command};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::TunerGain;
+use log::{debug, info, warn};
+use rumqttc::{Connection, Event, LastWill, Outgoing, Packet, QoS, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub use rumqttc::MqttOptions;
+
+// Requests queued for the connection before publishes are dropped
+const QUEUE_LEN: usize = 64;
+// How often the connection thread checks for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+// How long stopping waits for the offline status to go out
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A setting received on a `set` topic
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Tune(u32),
+    Gain(TunerGain),
+    Squelch(f32), // dBFS, for the application's `dsp::Squelch`
+}
+
+impl Control {
+    /// Topic name under `set` and `state`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Tune(_) => "frequency",
+            Control::Gain(_) => "gain",
+            Control::Squelch(_) => "squelch",
+        }
+    }
+
+    /// What the device needs to do, None for squelch
+    pub fn command(&self) -> Option<Command> {
+        match *self {
+            Control::Tune(freq) => Some(Command::SetCenterFreq(freq)),
+            Control::Gain(TunerGain::Auto) => Some(Command::SetGainMode(false)),
+            Control::Gain(TunerGain::Manual(gain)) => Some(Command::SetGain(gain)),
+            Control::Squelch(_) => None,
+        }
+    }
+
+    /// Payload for the state topic, parsed back by `parse_control`
+    fn value(&self) -> String {
+        match *self {
+            Control::Tune(freq) => freq.to_string(),
+            Control::Gain(TunerGain::Auto) => "auto".to_string(),
+            Control::Gain(TunerGain::Manual(gain)) => format!("{:.1}", gain as f32 / 10.0),
+            Control::Squelch(db) => format!("{:.1}", db),
+        }
+    }
+}
+
+pub struct Client {
+    client: rumqttc::Client,
+    prefix: String,
+    controls: Receiver<Control>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    /// Connect to the broker in `options` in the background, using topics
+    /// under `prefix`. Connection problems are logged and retried.
+    pub fn connect(mut options: MqttOptions, prefix: &str) -> Result<Client> {
+        let prefix = prefix.trim_end_matches('/').to_string();
+        if prefix.is_empty() || prefix.contains(['+', '#']) {
+            return Err(RtlsdrErr(format!("Invalid topic prefix '{}'", prefix)));
+        }
+        let status = format!("{}/status", prefix);
+        options.set_last_will(LastWill::new(&status, "offline", QoS::AtLeastOnce, true));
+        let (client, connection) = rumqttc::Client::new(options, QUEUE_LEN);
+        let (tx, controls) = mpsc::channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let client = client.clone();
+            let prefix = prefix.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || drive(connection, &client, &prefix, &tx, &shutdown))
+        };
+        Ok(Client {
+            client,
+            prefix,
+            controls,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// Controls received since the last call, without waiting
+    pub fn poll_control(&self) -> Vec<Control> {
+        self.controls.try_iter().collect()
+    }
+
+    /// Apply `control` to `backend` and publish it as the new state
+    pub fn apply<B: Backend>(&self, backend: &mut B, control: Control) -> Result<()> {
+        if let Some(command) = control.command() {
+            backend.apply(command)?;
+        }
+        self.publish_state(control.name(), &control.value());
+        Ok(())
+    }
+
+    /// Retain `value` as the current state of setting `name`
+    pub fn publish_state(&self, name: &str, value: &str) {
+        self.publish(&format!("state/{}", name), value, true);
+    }
+
+    /// Signal level in dBFS
+    pub fn publish_level(&self, dbfs: f32) {
+        self.publish("level", &format!("{:.1}", dbfs), false);
+    }
+
+    /// Total samples or buffers dropped so far
+    pub fn publish_drops(&self, dropped: u64) {
+        self.publish("drops", &dropped.to_string(), false);
+    }
+
+    /// A decoded event, e.g. `publish_event("ads_b", json)`
+    pub fn publish_event(&self, kind: &str, payload: &str) {
+        self.publish(&format!("event/{}", kind), payload, false);
+    }
+
+    /// Telemetry is best effort: it's dropped while the connection is down
+    /// and the queue is full
+    fn publish(&self, topic: &str, payload: &str, retain: bool) {
+        let qos = if retain {
+            QoS::AtLeastOnce
+        } else {
+            QoS::AtMostOnce
+        };
+        let topic = format!("{}/{}", self.prefix, topic);
+        if let Err(e) = self.client.try_publish(&topic, qos, retain, payload) {
+            debug!("MQTT publish to {} dropped: {}", topic, e);
+        }
+    }
+
+    /// Publish the offline status and disconnect
+    pub fn stop(mut self) {
+        self.shutdown_now();
+    }
+
+    fn shutdown_now(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        let status = format!("{}/status", self.prefix);
+        let _ = self
+            .client
+            .try_publish(status, QoS::AtLeastOnce, true, "offline");
+        let _ = self.client.try_disconnect();
+        self.shutdown.store(true, Ordering::Relaxed);
+        let _ = thread.join();
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.shutdown_now();
+    }
+}
+
+/// Run the connection, subscribing and announcing on each (re)connect and
+/// passing controls on, until `shutdown` is set
+fn drive(
+    mut connection: Connection,
+    client: &rumqttc::Client,
+    prefix: &str,
+    controls: &Sender<Control>,
+    shutdown: &AtomicBool,
+) {
+    let set_prefix = format!("{}/set/", prefix);
+    let mut deadline = None;
+    loop {
+        if deadline.is_none() && shutdown.load(Ordering::Relaxed) {
+            deadline = Some(Instant::now() + STOP_TIMEOUT);
+        }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            break;
+        }
+        match connection.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(Event::Incoming(Packet::ConnAck(_)))) => {
+                info!("MQTT connected, controls on {}+", set_prefix);
+                let _ = client.try_subscribe(format!("{}+", set_prefix), QoS::AtLeastOnce);
+                let status = format!("{}/status", prefix);
+                let _ = client.try_publish(status, QoS::AtLeastOnce, true, "online");
+            }
+            Ok(Ok(Event::Incoming(Packet::Publish(publish)))) => {
+                let Some(name) = publish.topic.strip_prefix(&set_prefix) else {
+                    continue;
+                };
+                match parse_control(name, &publish.payload) {
+                    Some(control) => {
+                        let _ = controls.send(control);
+                    }
+                    None => warn!("Ignoring MQTT control {} {:?}", name, publish.payload),
+                }
+            }
+            Ok(Ok(Event::Outgoing(Outgoing::Disconnect))) => break,
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(_)) if deadline.is_some() => break,
+            Ok(Err(e)) => {
+                warn!("MQTT connection failed: {}", e);
+                thread::sleep(RECONNECT_DELAY);
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// The control for a message on `set/<name>`, None if not understood
+pub(super) fn parse_control(name: &str, payload: &[u8]) -> Option<Control> {
+    let text = std::str::from_utf8(payload).ok()?.trim();
+    if name == "gain" && text.eq_ignore_ascii_case("auto") {
+        return Some(Control::Gain(TunerGain::Auto));
+    }
+    let value: f64 = text.parse().ok()?;
+    if !value.is_finite() {
+        return None;
+    }
+    match name {
+        "frequency" if value > 0.0 && value <= u32::MAX as f64 => {
+            Some(Control::Tune(value.round() as u32))
+        }
+        "gain" => Some(Control::Gain(TunerGain::Manual(
+            (value * 10.0).round() as i32
+        ))),
+        "squelch" => Some(Control::Squelch(value as f32)),
+        _ => None,
+    }
+}
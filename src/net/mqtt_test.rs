@@ -0,0 +1,78 @@
+use super::mqtt::{parse_control, Client, Control, MqttOptions};
+use super::rtl_tcp::Command;
+use crate::TunerGain;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_parse_control() {
+    assert_eq!(
+        Some(Control::Tune(144_800_000)),
+        parse_control("frequency", b"144.8e6")
+    );
+    assert_eq!(
+        Some(Control::Gain(TunerGain::Manual(386))),
+        parse_control("gain", b" 38.6\n")
+    );
+    assert_eq!(
+        Some(Control::Gain(TunerGain::Auto)),
+        parse_control("gain", b"AUTO")
+    );
+    assert_eq!(
+        Some(Control::Squelch(-30.5)),
+        parse_control("squelch", b"-30.5")
+    );
+}
+
+#[test]
+fn test_parse_invalid_control() {
+    assert_eq!(None, parse_control("frequency", b"-1"));
+    assert_eq!(None, parse_control("frequency", b"5e9"));
+    assert_eq!(None, parse_control("frequency", b"auto"));
+    assert_eq!(None, parse_control("squelch", b"NaN"));
+    assert_eq!(None, parse_control("volume", b"11"));
+    assert_eq!(None, parse_control("gain", &[0xff, 0xfe]));
+}
+
+#[test]
+fn test_control_command() {
+    assert_eq!(
+        Some(Command::SetCenterFreq(100_000_000)),
+        Control::Tune(100_000_000).command()
+    );
+    assert_eq!(
+        Some(Command::SetGainMode(false)),
+        Control::Gain(TunerGain::Auto).command()
+    );
+    assert_eq!(
+        Some(Command::SetGain(-10)),
+        Control::Gain(TunerGain::Manual(-10)).command()
+    );
+    assert_eq!(None, Control::Squelch(-20.0).command());
+    assert_eq!("squelch", Control::Squelch(-20.0).name());
+}
+
+#[test]
+fn test_invalid_prefix() {
+    let options = MqttOptions::new("test", "127.0.0.1", 1883);
+    assert!(Client::connect(options.clone(), "rtlsdr/#").is_err());
+    assert!(Client::connect(options, "/").is_err());
+}
+
+#[test]
+fn test_stop_without_broker() {
+    // Nothing listening on the port
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let options = MqttOptions::new("test", "127.0.0.1", port);
+    let client = Client::connect(options, "rtlsdr/test/").unwrap();
+    client.publish_level(-50.0);
+    client.publish_event("test", "{}");
+    assert!(client.poll_control().is_empty());
+    let start = Instant::now();
+    client.stop();
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
@@ -0,0 +1,461 @@
+//! Server for the rtl_tcp protocol, so a dongle can be used remotely by SDR#,
+//! gqrx and other rtl_tcp clients.
+//!
+//! On connecting, the client receives a 12 byte header (`RTL0`, the tuner
+//! type and number of gain steps, big endian) followed by the raw sample
+//! stream. The client sends 5 byte commands: a command byte and a big
+//! endian parameter. Besides librtlsdr's commands up to 0x0e, the extended
+//! commands from the librtlsdr fork's rtl_tcp are understood for the tuner
+//! bandwidth (0x40) and for switching a bias tee on another GPIO (0x4a).
+//!
+//! One client is served at a time. Samples are read on the server's thread
+//! and queued for a connection thread, so a slow network drops whole buffers
+//! rather than stalling the USB transfers. The connection thread passes the
+//! client's commands back to be applied between reads.
+//!
+//! `Server::start_with_access` can require a token from clients and, with
+//! the `tls` feature, encrypt the stream, see `auth`.
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::rtl_tcp::Server;
+//!
+//! let server = Server::start("0.0.0.0:1234", 0).unwrap();
+//! println!("Listening on {}", server.local_addr());
+//! // ...
+//! server.stop().unwrap();
+//! ```
+
+use super::auth::{Access, Connection};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::{DirectSampleMode, RtlSdr, Settings, TunerGain, DEFAULT_BUF_LENGTH};
+use log::{info, warn};
+use std::io::{self, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub const MAGIC: &[u8; 4] = b"RTL0";
+pub const HEADER_LEN: usize = 12;
+pub const COMMAND_LEN: usize = 5;
+/// Tuner type reported to clients, from librtlsdr's `rtlsdr_tuner` enum
+pub const TUNER_UNKNOWN: u32 = 0;
+pub const TUNER_E4000: u32 = 1;
+pub const TUNER_FC0012: u32 = 2;
+pub const TUNER_FC0013: u32 = 3;
+pub const TUNER_FC2580: u32 = 4;
+pub const TUNER_R820T: u32 = 5;
+pub const TUNER_R828D: u32 = 6;
+
+// Buffers queued for the sender thread before new ones are dropped
+const QUEUE_LEN: usize = 64;
+// How often the idle server checks for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Disconnect a client that stops reading for this long, or takes longer for
+// the TLS handshake and token together
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+// How long the connection thread waits for samples, then for commands,
+// before checking the other
+const SEND_POLL: Duration = Duration::from_millis(10);
+const COMMAND_POLL: Duration = Duration::from_millis(1);
+
+/// A command sent by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    SetCenterFreq(u32), // Hz
+    SetSampleRate(u32), // Hz
+    SetGainMode(bool),  // True for manual gain
+    SetGain(i32),       // Tenths of a dB
+    SetFreqCorrection(i32),
+    SetTestMode(bool),
+    SetDirectSampling(u32), // 0 off, 1 I branch, 2 Q branch
+    SetRtlXtal(u32),        // Hz
+    SetTunerXtal(u32),      // Hz
+    SetGainByIndex(u32),    // Index into the tuner's gain steps
+    SetBiasTee(bool),
+    SetTunerBandwidth(u32),   // Hz, 0 for automatic
+    SetBiasTeeGpio(u8, bool), // GPIO pin and state
+    Unsupported(u8, u32),     // Command byte and parameter
+}
+
+impl Command {
+    pub fn parse(buf: [u8; COMMAND_LEN]) -> Command {
+        let param = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+        match buf[0] {
+            0x01 => Command::SetCenterFreq(param),
+            0x02 => Command::SetSampleRate(param),
+            0x03 => Command::SetGainMode(param != 0),
+            0x04 => Command::SetGain(param as i32),
+            0x05 => Command::SetFreqCorrection(param as i32),
+            0x07 => Command::SetTestMode(param != 0),
+            0x09 => Command::SetDirectSampling(param),
+            0x0b => Command::SetRtlXtal(param),
+            0x0c => Command::SetTunerXtal(param),
+            0x0d => Command::SetGainByIndex(param),
+            0x0e => Command::SetBiasTee(param != 0),
+            0x40 => Command::SetTunerBandwidth(param),
+            // Pin in the high half, level in the low half
+            0x4a => Command::SetBiasTeeGpio((param >> 16) as u8, param & 0xffff != 0),
+            cmd => Command::Unsupported(cmd, param),
+        }
+    }
+
+    /// Encode as a client would send it
+    pub fn to_bytes(self) -> [u8; COMMAND_LEN] {
+        let (cmd, param) = match self {
+            Command::SetCenterFreq(f) => (0x01, f),
+            Command::SetSampleRate(r) => (0x02, r),
+            Command::SetGainMode(manual) => (0x03, manual as u32),
+            Command::SetGain(g) => (0x04, g as u32),
+            Command::SetFreqCorrection(ppm) => (0x05, ppm as u32),
+            Command::SetTestMode(on) => (0x07, on as u32),
+            Command::SetDirectSampling(mode) => (0x09, mode),
+            Command::SetRtlXtal(f) => (0x0b, f),
+            Command::SetTunerXtal(f) => (0x0c, f),
+            Command::SetGainByIndex(i) => (0x0d, i),
+            Command::SetBiasTee(on) => (0x0e, on as u32),
+            Command::SetTunerBandwidth(bw) => (0x40, bw),
+            Command::SetBiasTeeGpio(gpio, on) => (0x4a, (gpio as u32) << 16 | on as u32),
+            Command::Unsupported(cmd, param) => (cmd, param),
+        };
+        let p = param.to_be_bytes();
+        [cmd, p[0], p[1], p[2], p[3]]
+    }
+}
+
+/// Header sent to each client on connecting
+pub fn header(tuner_type: u32, gain_count: u32) -> [u8; HEADER_LEN] {
+    let mut buf = [0_u8; HEADER_LEN];
+    buf[..4].copy_from_slice(MAGIC);
+    buf[4..8].copy_from_slice(&tuner_type.to_be_bytes());
+    buf[8..].copy_from_slice(&gain_count.to_be_bytes());
+    buf
+}
+
+/// The tuner type to report for a tuner id, see `TunerInfo::id`
+pub fn tuner_type(id: &str) -> u32 {
+    match id {
+        "e4000" => TUNER_E4000,
+        "fc0012" => TUNER_FC0012,
+        "fc0013" => TUNER_FC0013,
+        "fc2580" => TUNER_FC2580,
+        crate::tuners::r820t::TUNER_ID => TUNER_R820T,
+        "r828d" => TUNER_R828D,
+        _ => TUNER_UNKNOWN,
+    }
+}
+
+/// Display name of a reported tuner type
+pub fn tuner_name(tuner_type: u32) -> &'static str {
+    match tuner_type {
+        TUNER_E4000 => "E4000",
+        TUNER_FC0012 => "FC0012",
+        TUNER_FC0013 => "FC0013",
+        TUNER_FC2580 => "FC2580",
+        TUNER_R820T => "R820T",
+        TUNER_R828D => "R828D",
+        _ => "unknown",
+    }
+}
+
+/// A sample source the server can stream from and control
+pub trait Backend: SampleSource {
+    /// Tuner type and gain step count for the header
+    fn info(&self) -> Result<(u32, u32)>;
+    fn apply(&mut self, command: Command) -> Result<()>;
+    /// Current settings, if the backend tracks them
+    fn settings(&self) -> Option<Settings> {
+        None
+    }
+}
+
+impl Backend for RtlSdr {
+    fn info(&self) -> Result<(u32, u32)> {
+        let tuner_type = tuner_type(self.get_tuner_info()?.id);
+        Ok((tuner_type, self.get_tuner_gains()?.len() as u32))
+    }
+
+    fn apply(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::SetCenterFreq(freq) => self.set_center_freq(freq),
+            Command::SetSampleRate(rate) => self.set_sample_rate(rate),
+            // Manual mode takes effect with the next gain command
+            Command::SetGainMode(true) => Ok(()),
+            Command::SetGainMode(false) => self.set_tuner_gain(TunerGain::Auto),
+            Command::SetGain(gain) => self.set_tuner_gain(TunerGain::Manual(gain)),
+            Command::SetFreqCorrection(ppm) => self.set_freq_correction(ppm),
+            Command::SetTestMode(on) => self.set_testmode(on),
+            Command::SetDirectSampling(mode) => self.set_direct_sampling(match mode {
+                0 => DirectSampleMode::Off,
+                1 => DirectSampleMode::On,
+                _ => DirectSampleMode::OnSwap,
+            }),
+            Command::SetRtlXtal(freq) => self.set_xtal_freq(freq, self.get_xtal_freq().1),
+            Command::SetTunerXtal(freq) => self.set_xtal_freq(self.get_xtal_freq().0, freq),
+            Command::SetGainByIndex(i) => {
+                let gains = self.get_tuner_gains()?;
+                let gain = gains
+                    .get(i as usize)
+                    .ok_or_else(|| RtlsdrErr(format!("No gain step {}", i)))?;
+                self.set_tuner_gain(TunerGain::Manual(*gain))
+            }
+            Command::SetBiasTee(on) => self.set_bias_tee(on),
+            Command::SetTunerBandwidth(bw) => self.set_tuner_bandwidth(bw),
+            Command::SetBiasTeeGpio(gpio, on) => self.set_bias_tee_gpio(gpio, on),
+            Command::Unsupported(cmd, _) => {
+                Err(RtlsdrErr(format!("Unsupported command {:#04x}", cmd)))
+            }
+        }
+    }
+
+    fn settings(&self) -> Option<Settings> {
+        Some(RtlSdr::settings(self))
+    }
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Server {
+    /// Open device `index` and serve it on `addr`
+    pub fn start<A: ToSocketAddrs>(addr: A, index: usize) -> Result<Server> {
+        Server::start_with(addr, move || RtlSdr::open(index))
+    }
+
+    /// Serve the backend returned by `open`, which is called on the server's
+    /// thread, so backends don't need to be `Send`
+    pub fn start_with<A, B, F>(addr: A, open: F) -> Result<Server>
+    where
+        A: ToSocketAddrs,
+        B: Backend,
+        F: FnOnce() -> Result<B> + Send + 'static,
+    {
+        Server::start_with_access(addr, Access::open(), open)
+    }
+
+    /// Like `start_with`, serving only clients allowed by `access`
+    pub fn start_with_access<A, B, F>(addr: A, access: Access, open: F) -> Result<Server>
+    where
+        A: ToSocketAddrs,
+        B: Backend,
+        F: FnOnce() -> Result<B> + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let thread = {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                let backend = match open() {
+                    Ok(backend) => {
+                        let _ = opened_tx.send(Ok(()));
+                        backend
+                    }
+                    Err(e) => {
+                        let _ = opened_tx.send(Err(e));
+                        return Ok(());
+                    }
+                };
+                serve(listener, backend, &access, &shutdown)
+            })
+        };
+        // Report a failure to open before returning
+        opened_rx
+            .recv()
+            .map_err(|_| RtlsdrErr("Server thread panicked".to_string()))??;
+        info!("rtl_tcp listening on {}", addr);
+        Ok(Server {
+            addr,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// Address the server is listening on, e.g. to find the port when
+    /// started on port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Whether the server is still serving. It stops by itself if the
+    /// backend fails or runs out of samples.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Disconnect any client and close the device, returning the error that
+    /// stopped the server if any
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown.store(true, Ordering::Relaxed);
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| RtlsdrErr("Server thread panicked".to_string()))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Accept clients one at a time until shut down
+fn serve<B: Backend>(
+    listener: TcpListener,
+    mut backend: B,
+    access: &Access,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let (tuner_type, gain_count) = backend.info()?;
+    let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+    while !shutdown.load(Ordering::Relaxed) {
+        let (stream, peer) = match listener.accept() {
+            Ok(client) => client,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        info!("rtl_tcp client connected from {}", peer);
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        let socket = stream.try_clone()?;
+        let mut conn = match admit(stream, access) {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("rtl_tcp client {} refused: {}", peer, e);
+                let _ = socket.shutdown(Shutdown::Both);
+                continue;
+            }
+        };
+        // Commands are polled between sends from here on
+        socket.set_read_timeout(Some(COMMAND_POLL))?;
+        let more = conn
+            .write_all(&header(tuner_type, gain_count))
+            .map_err(|e| e.into())
+            .and_then(|()| stream_to(conn, &mut backend, &mut buf, shutdown));
+        let _ = socket.shutdown(Shutdown::Both);
+        info!("rtl_tcp client {} disconnected", peer);
+        if !more? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The TLS handshake and token check, if required
+fn admit(stream: TcpStream, access: &Access) -> Result<Box<dyn Connection>> {
+    access.admit(stream, CLIENT_TIMEOUT, |mut conn| {
+        access.check_line(&mut conn)?;
+        Ok(conn)
+    })
+}
+
+/// Stream samples to one client and apply its commands until it disconnects
+/// or the server is shut down. Returns false once the backend runs out.
+fn stream_to<B: Backend>(
+    conn: Box<dyn Connection>,
+    backend: &mut B,
+    buf: &mut [u8],
+    shutdown: &AtomicBool,
+) -> Result<bool> {
+    let (data_tx, data_rx) = mpsc::sync_channel(QUEUE_LEN);
+    let (command_tx, command_rx) = mpsc::channel();
+    let connection = spawn_connection(conn, data_rx, command_tx);
+    let result = stream_loop(backend, buf, shutdown, &data_tx, &command_rx);
+    // Let the connection thread finish sending what is queued
+    drop(data_tx);
+    let _ = connection.join();
+    result
+}
+
+fn stream_loop<B: Backend>(
+    backend: &mut B,
+    buf: &mut [u8],
+    shutdown: &AtomicBool,
+    data_tx: &SyncSender<Vec<u8>>,
+    command_rx: &Receiver<Command>,
+) -> Result<bool> {
+    while !shutdown.load(Ordering::Relaxed) {
+        loop {
+            match command_rx.try_recv() {
+                Ok(command) => {
+                    if let Err(e) = backend.apply(command) {
+                        warn!("rtl_tcp command {:?} failed: {}", command, e);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                // The client closed its end
+                Err(TryRecvError::Disconnected) => return Ok(true),
+            }
+        }
+        let n = backend.read_sync(buf)?;
+        if n == 0 {
+            return Ok(false);
+        }
+        match data_tx.try_send(buf[..n].to_vec()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => warn!("rtl_tcp client too slow, samples dropped"),
+            Err(TrySendError::Disconnected(_)) => return Ok(true),
+        }
+    }
+    Ok(true)
+}
+
+/// Send queued samples and read commands on one thread, as a TLS stream
+/// can't be split. Reads time out after `COMMAND_POLL` so neither waits
+/// long for the other.
+fn spawn_connection(
+    mut conn: Box<dyn Connection>,
+    data_rx: Receiver<Vec<u8>>,
+    command_tx: mpsc::Sender<Command>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut pending = Vec::with_capacity(2 * COMMAND_LEN);
+        let mut buf = [0_u8; 64];
+        loop {
+            match data_rx.recv_timeout(SEND_POLL) {
+                Ok(data) => {
+                    if conn.write_all(&data).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            match conn.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => pending.extend_from_slice(&buf[..n]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(_) => break,
+            }
+            while pending.len() >= COMMAND_LEN {
+                let cmd = pending[..COMMAND_LEN].try_into().expect("Whole command");
+                pending.drain(..COMMAND_LEN);
+                if command_tx.send(Command::parse(cmd)).is_err() {
+                    return;
+                }
+            }
+        }
+    })
+}
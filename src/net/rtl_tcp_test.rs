@@ -0,0 +1,234 @@
+use super::auth::{send_token, Access};
+use super::rtl_tcp::{
+    header, tuner_name, tuner_type, Backend, Command, Server, COMMAND_LEN, TUNER_R820T,
+    TUNER_R828D, TUNER_UNKNOWN,
+};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::{FileSdr, SampleSource};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Endless counting samples, recording the commands applied
+struct Fake {
+    next: u8,
+    commands: Arc<Mutex<Vec<Command>>>,
+}
+
+impl SampleSource for Fake {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        thread::sleep(Duration::from_millis(1));
+        let buf = &mut buf[..1024];
+        for b in buf.iter_mut() {
+            *b = self.next;
+            self.next = self.next.wrapping_add(1);
+        }
+        Ok(buf.len())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        1000
+    }
+
+    fn center_freq(&self) -> u32 {
+        0
+    }
+}
+
+impl Backend for Fake {
+    fn info(&self) -> Result<(u32, u32)> {
+        Ok((TUNER_R820T, 29))
+    }
+
+    fn apply(&mut self, command: Command) -> Result<()> {
+        self.commands.lock().unwrap().push(command);
+        Ok(())
+    }
+}
+
+impl Backend for FileSdr {
+    fn info(&self) -> Result<(u32, u32)> {
+        Ok((0, 0))
+    }
+
+    fn apply(&mut self, _command: Command) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn start_fake() -> (Server, Arc<Mutex<Vec<Command>>>) {
+    start_fake_with(Access::open())
+}
+
+fn start_fake_with(access: Access) -> (Server, Arc<Mutex<Vec<Command>>>) {
+    let commands = Arc::new(Mutex::new(Vec::new()));
+    let applied = commands.clone();
+    let server = Server::start_with_access("127.0.0.1:0", access, move || {
+        Ok(Fake { next: 0, commands })
+    })
+    .unwrap();
+    (server, applied)
+}
+
+fn connect(server: &Server) -> TcpStream {
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut buf = [0_u8; 12];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(header(TUNER_R820T, 29), buf);
+    stream
+}
+
+#[test]
+fn test_command_bytes() {
+    let buf = [0x01, 0x05, 0xa7, 0x5c, 0xe0];
+    assert_eq!(Command::SetCenterFreq(94_854_368), Command::parse(buf));
+    assert_eq!(buf, Command::parse(buf).to_bytes());
+    let ppm = Command::SetFreqCorrection(-12);
+    assert_eq!(ppm, Command::parse(ppm.to_bytes()));
+    assert_eq!(
+        Command::Unsupported(0x08, 1),
+        Command::parse([0x08, 0, 0, 0, 1])
+    );
+    assert_eq!(
+        Command::SetBiasTee(true),
+        Command::parse([0x0e, 0, 0, 0, 1])
+    );
+}
+
+#[test]
+fn test_extended_commands() {
+    assert_eq!(
+        Command::SetTunerBandwidth(1_500_000),
+        Command::parse([0x40, 0x00, 0x16, 0xe3, 0x60])
+    );
+    let gpio = Command::SetBiasTeeGpio(5, true);
+    assert_eq!([0x4a, 0, 5, 0, 1], gpio.to_bytes());
+    assert_eq!(gpio, Command::parse(gpio.to_bytes()));
+    assert_eq!(
+        Command::SetBiasTeeGpio(1, false),
+        Command::parse([0x4a, 0, 1, 0, 0])
+    );
+}
+
+#[test]
+fn test_tuner_type() {
+    assert_eq!(TUNER_R820T, tuner_type(crate::tuners::r820t::TUNER_ID));
+    assert_eq!(TUNER_R828D, tuner_type("r828d"));
+    assert_eq!(TUNER_UNKNOWN, tuner_type("mystery"));
+    assert_eq!("R820T", tuner_name(TUNER_R820T));
+    assert_eq!("unknown", tuner_name(42));
+}
+
+#[test]
+fn test_header() {
+    assert_eq!(
+        [b'R', b'T', b'L', b'0', 0, 0, 0, 5, 0, 0, 0, 29],
+        header(TUNER_R820T, 29)
+    );
+}
+
+#[test]
+fn test_serve() {
+    let (server, commands) = start_fake();
+    for _ in 0..2 {
+        // Clients are served in turn
+        let mut stream = connect(&server);
+        let mut buf = [0_u8; 4096];
+        stream.read_exact(&mut buf).unwrap();
+        // Whole buffers in order, some may be dropped in between
+        assert!(buf
+            .windows(2)
+            .take(1023)
+            .all(|w| w[1] == w[0].wrapping_add(1)));
+
+        let sent = [Command::SetCenterFreq(100_000_000), Command::SetGain(496)];
+        for command in sent {
+            stream.write_all(&command.to_bytes()).unwrap();
+        }
+        let start = Instant::now();
+        while commands.lock().unwrap().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(sent.to_vec(), *commands.lock().unwrap());
+        commands.lock().unwrap().clear();
+    }
+    assert!(server.is_running());
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_token() {
+    let (server, commands) = start_fake_with(Access::open().with_token("secret"));
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    send_token(&mut stream, "secret").unwrap();
+    let mut buf = [0_u8; 12];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(header(TUNER_R820T, 29), buf);
+    let command = Command::SetCenterFreq(433_920_000);
+    stream.write_all(&command.to_bytes()).unwrap();
+    let start = Instant::now();
+    while commands.lock().unwrap().is_empty() && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(vec![command], *commands.lock().unwrap());
+    drop(stream);
+
+    // Refused without the header, and the next client is served
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    send_token(&mut stream, "guess").unwrap();
+    let mut received = Vec::new();
+    let _ = stream.read_to_end(&mut received);
+    assert!(received.is_empty());
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    send_token(&mut stream, "secret").unwrap();
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(b"RTL0", &buf[..4]);
+    server.stop().unwrap();
+}
+
+#[test]
+fn test_stop_with_client() {
+    let (server, _) = start_fake();
+    let mut stream = connect(&server);
+    server.stop().unwrap();
+    // The client is disconnected
+    let mut buf = vec![0_u8; COMMAND_LEN];
+    while stream.read(&mut buf).unwrap_or(0) > 0 {}
+}
+
+#[test]
+fn test_open_failure() {
+    let result = Server::start_with("127.0.0.1:0", || -> Result<Fake> {
+        Err(RtlsdrErr("No device".to_string()))
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_source_exhausted() {
+    let path = std::env::temp_dir().join(format!("rtlsdr-tcp-{}.bin", std::process::id()));
+    let data: Vec<u8> = (0..10_000_u32).map(|i| i as u8).collect();
+    fs::write(&path, &data).unwrap();
+    let file = path.clone();
+    let server = Server::start_with("127.0.0.1:0", move || FileSdr::open(file, 1000, 0)).unwrap();
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    let mut received = Vec::new();
+    stream.read_to_end(&mut received).unwrap();
+    assert_eq!(header(0, 0).to_vec(), received[..12]);
+    assert_eq!(data, received[12..]);
+    server.stop().unwrap();
+    fs::remove_file(path).unwrap();
+}
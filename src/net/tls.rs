@@ -0,0 +1,70 @@
+//! TLS for the rtl_tcp and WebSocket servers (feature `tls`), with rustls.
+//! Pass a `TlsConfig` to a server through `auth::Access::with_tls`.
+//!
+//! Clients need to trust the certificate. For rtl_tcp, whose clients don't
+//! speak TLS themselves, a tunnel such as `stunnel` or `socat` on the client
+//! machine can make the connection:
+//! ```text
+//! socat TCP-LISTEN:1234,fork OPENSSL:receiver.lan:1234,cafile=ca.pem
+//! ```
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Certificate and key the servers present to clients
+#[derive(Clone)]
+pub struct TlsConfig {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsConfig {
+    /// A PEM certificate chain, leaf certificate first, and its PEM private
+    /// key (PKCS#8, PKCS#1 or SEC1)
+    pub fn from_pem(cert_chain: &[u8], key: &[u8]) -> Result<TlsConfig> {
+        let certs = CertificateDer::pem_slice_iter(cert_chain)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| RtlsdrErr(format!("Invalid certificate: {}", e)))?;
+        if certs.is_empty() {
+            return Err(RtlsdrErr("No certificate found".to_string()));
+        }
+        let key = PrivateKeyDer::from_pem_slice(key)
+            .map_err(|e| RtlsdrErr(format!("Invalid private key: {}", e)))?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(|e| RtlsdrErr(format!("TLS setup failed: {}", e)))?;
+        Ok(TlsConfig {
+            config: Arc::new(config),
+        })
+    }
+
+    /// Read the certificate chain and key from PEM files
+    pub fn from_pem_files<P: AsRef<Path>, Q: AsRef<Path>>(
+        cert_chain: P,
+        key: Q,
+    ) -> Result<TlsConfig> {
+        TlsConfig::from_pem(&fs::read(cert_chain)?, &fs::read(key)?)
+    }
+
+    /// Complete the handshake with a client, within the stream's timeouts
+    pub(super) fn accept<S: Read + Write>(
+        &self,
+        mut stream: S,
+    ) -> Result<StreamOwned<ServerConnection, S>> {
+        let mut conn = ServerConnection::new(self.config.clone())
+            .map_err(|e| RtlsdrErr(format!("TLS setup failed: {}", e)))?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream)
+                .map_err(|e| RtlsdrErr(format!("TLS handshake failed: {}", e)))?;
+        }
+        Ok(StreamOwned::new(conn, stream))
+    }
+}
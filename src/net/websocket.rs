@@ -0,0 +1,352 @@
+//! WebSocket streaming for browser frontends (feature `websocket`), e.g. a
+//! waterfall display for a headless receiver.
+//!
+//! The application passes each buffer it reads to `Server::process`, which
+//! computes the feed once and sends it to every connected client as binary
+//! frames:
+//! - `Feed::Spectrum`: one frame per waterfall row, `bins` little endian f32
+//!   values in dBFS, lowest frequency first.
+//! - `Feed::Iq`: decimated IQ as interleaved little endian i16.
+//!
+//! Each client first receives a text frame describing the feed, and again
+//! whenever the center frequency changes:
+//! ```text
+//! {"feed":"spectrum","sample_rate":2400000,"center_freq":100000000,"bins":1024}
+//! ```
+//! Clients that fall behind miss whole frames rather than slowing the
+//! receiver. `Server::start_with_access` can require a token and, with the
+//! `tls` feature, serve `wss://`, see `auth`.
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::websocket::{Feed, Server};
+//! use rtlsdr_rs::dsp::Detector;
+//! use rtlsdr_rs::{RtlSdr, DEFAULT_BUF_LENGTH};
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! let feed = Feed::Spectrum { fft_size: 2048, bins: 1024, frames: 32, detector: Detector::Max };
+//! let mut server = Server::start("0.0.0.0:8080", feed, sdr.get_sample_rate(), sdr.get_center_freq())
+//!     .unwrap();
+//! let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+//! loop {
+//!     let n = sdr.read_sync(&mut buf).unwrap();
+//!     server.process(&buf[..n]);
+//! }
+//! ```
+
+use super::auth::{Access, Connection};
+use crate::dsp::{convert, to_complex_into, CicDecimator, Detector, Waterfall};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use log::{info, warn};
+use num_complex::Complex;
+use serde_json::json;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::{header, StatusCode};
+use tungstenite::{Message, WebSocket};
+
+// Frames queued per client before new ones are dropped
+const QUEUE_LEN: usize = 32;
+// How often the accept thread checks for a stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Disconnect clients that take longer than this for the whole handshake, or
+// stall a write this long
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+// Stages of the IQ decimator
+const CIC_STAGES: usize = 4;
+
+/// What is streamed to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feed {
+    /// IQ decimated by `decimation`
+    Iq { decimation: usize },
+    /// Waterfall rows, see `dsp::Waterfall`
+    Spectrum {
+        fft_size: usize,
+        bins: usize,
+        frames: usize,
+        detector: Detector,
+    },
+}
+
+type Clients = Arc<Mutex<Vec<SyncSender<Message>>>>;
+type RowCallback = Box<dyn FnMut(&[f32]) + Send>;
+
+enum Processor {
+    Iq(CicDecimator),
+    Spectrum {
+        waterfall: Waterfall<RowCallback>,
+        samples: Vec<Complex<f32>>,
+    },
+}
+
+impl Processor {
+    fn new(feed: Feed, clients: &Clients) -> Result<Processor> {
+        match feed {
+            Feed::Iq { decimation } => {
+                if decimation == 0 {
+                    return Err(RtlsdrErr("Decimation must be at least 1".to_string()));
+                }
+                Ok(Processor::Iq(CicDecimator::new(decimation, CIC_STAGES, 2)))
+            }
+            Feed::Spectrum {
+                fft_size,
+                bins,
+                frames,
+                detector,
+            } => {
+                if !fft_size.is_power_of_two() || bins == 0 || !fft_size.is_multiple_of(bins) {
+                    return Err(RtlsdrErr(format!(
+                        "FFT size {} must be a power of two and a multiple of {} bins",
+                        fft_size, bins
+                    )));
+                }
+                let clients = clients.clone();
+                let send_row = move |row: &[f32]| {
+                    let bytes: Vec<u8> = row.iter().flat_map(|v| v.to_le_bytes()).collect();
+                    broadcast(&clients, Message::binary(bytes));
+                };
+                Ok(Processor::Spectrum {
+                    waterfall: Waterfall::new(
+                        fft_size,
+                        bins,
+                        frames.max(1),
+                        detector,
+                        Box::new(send_row),
+                    ),
+                    samples: Vec::new(),
+                })
+            }
+        }
+    }
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    feed: Feed,
+    sample_rate: u32,
+    center_freq: u32,
+    header: Arc<Mutex<Message>>, // Sent to each new client
+    clients: Clients,
+    processor: Processor,
+    shutdown: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `addr` for clients of `feed`, computed from samples at
+    /// `sample_rate` tuned to `center_freq`
+    pub fn start<A: ToSocketAddrs>(
+        addr: A,
+        feed: Feed,
+        sample_rate: u32,
+        center_freq: u32,
+    ) -> Result<Server> {
+        Server::start_with_access(addr, Access::open(), feed, sample_rate, center_freq)
+    }
+
+    /// Like `start`, serving only clients allowed by `access`
+    pub fn start_with_access<A: ToSocketAddrs>(
+        addr: A,
+        access: Access,
+        feed: Feed,
+        sample_rate: u32,
+        center_freq: u32,
+    ) -> Result<Server> {
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let processor = Processor::new(feed, &clients)?;
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut server = Server {
+            addr: listener.local_addr()?,
+            feed,
+            sample_rate,
+            center_freq,
+            header: Arc::new(Mutex::new(Message::text(""))),
+            clients: clients.clone(),
+            processor,
+            shutdown: shutdown.clone(),
+            accept: None,
+        };
+        server.update_header();
+        let header = server.header.clone();
+        server.accept = Some(thread::spawn(move || {
+            accept_loop(listener, clients, header, &access, &shutdown)
+        }));
+        info!("WebSocket server listening on {}", server.addr);
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Number of clients connected
+    pub fn clients(&self) -> usize {
+        self.clients.lock().map_or(0, |c| c.len())
+    }
+
+    /// Tell clients the receiver was retuned
+    pub fn set_center_freq(&mut self, freq: u32) {
+        self.center_freq = freq;
+        self.update_header();
+        let header = self.header.lock().map(|h| h.clone());
+        if let Ok(header) = header {
+            broadcast(&self.clients, header);
+        }
+    }
+
+    /// Compute the feed from raw samples as read from the device and send
+    /// it to the clients
+    pub fn process(&mut self, buf: &[u8]) {
+        match &mut self.processor {
+            Processor::Iq(cic) => {
+                let iq = cic.process_i16(&convert::<i16>(buf));
+                if !iq.is_empty() {
+                    let bytes: Vec<u8> = iq.iter().flat_map(|v| v.to_le_bytes()).collect();
+                    broadcast(&self.clients, Message::binary(bytes));
+                }
+            }
+            Processor::Spectrum { waterfall, samples } => {
+                samples.resize(buf.len() / 2, Complex::default());
+                to_complex_into(&buf[..samples.len() * 2], samples);
+                waterfall.process(samples);
+            }
+        }
+    }
+
+    /// Disconnect all clients and stop listening
+    pub fn stop(mut self) {
+        self.shutdown_now();
+    }
+
+    fn shutdown_now(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // Dropping the senders ends the client threads
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.clear();
+        }
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+    }
+
+    fn update_header(&mut self) {
+        let mut header = json!({
+            "sample_rate": self.sample_rate,
+            "center_freq": self.center_freq,
+        });
+        match self.feed {
+            Feed::Iq { decimation } => {
+                header["feed"] = json!("iq");
+                header["sample_rate"] = json!(self.sample_rate as f64 / decimation as f64);
+            }
+            Feed::Spectrum { bins, .. } => {
+                header["feed"] = json!("spectrum");
+                header["bins"] = json!(bins);
+            }
+        }
+        if let Ok(mut current) = self.header.lock() {
+            *current = Message::text(header.to_string());
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown_now();
+    }
+}
+
+/// Queue `message` for every client, forgetting those that disconnected
+fn broadcast(clients: &Clients, message: Message) {
+    let Ok(mut clients) = clients.lock() else {
+        return;
+    };
+    clients.retain(|client| match client.try_send(message.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            warn!("WebSocket client too slow, frame dropped");
+            true
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    clients: Clients,
+    header: Arc<Mutex<Message>>,
+    access: &Access,
+    shutdown: &AtomicBool,
+) {
+    let mut threads = Vec::new();
+    while !shutdown.load(Ordering::Relaxed) {
+        let (stream, peer) = match listener.accept() {
+            Ok(client) => client,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                warn!("WebSocket accept failed: {}", e);
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+        if let Ok(header) = header.lock() {
+            let _ = tx.try_send(header.clone());
+        }
+        if let Ok(mut clients) = clients.lock() {
+            clients.push(tx);
+        }
+        threads.retain(|t: &JoinHandle<()>| !t.is_finished());
+        let access = access.clone();
+        threads.push(thread::spawn(move || {
+            info!("WebSocket client connected from {}", peer);
+            if let Err(e) = serve_client(stream, &access, rx) {
+                info!("WebSocket client {} disconnected: {}", peer, e);
+            }
+        }));
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+/// Handshake, then send queued frames until the server drops the queue
+fn serve_client(stream: TcpStream, access: &Access, rx: Receiver<Message>) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    // The error type is set by tungstenite's Callback
+    #[allow(clippy::result_large_err)]
+    let check = |request: &Request, response: Response| {
+        let authorization = request.headers().get(header::AUTHORIZATION);
+        if access.check_request(request.uri().query(), authorization.map(|v| v.as_bytes())) {
+            Ok(response)
+        } else {
+            let mut refused = ErrorResponse::new(Some("Wrong or missing token".to_string()));
+            *refused.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(refused)
+        }
+    };
+    let mut socket: WebSocket<Box<dyn Connection>> =
+        access.admit(stream, CLIENT_TIMEOUT, |conn| {
+            tungstenite::accept_hdr(conn, check)
+                .map_err(|e| RtlsdrErr(format!("Handshake failed: {}", e)))
+        })?;
+    let ws_err = |e: tungstenite::Error| RtlsdrErr(e.to_string());
+    for message in rx {
+        socket.send(message).map_err(ws_err)?;
+    }
+    socket.close(None).map_err(ws_err)?;
+    let _ = socket.flush();
+    Ok(())
+}
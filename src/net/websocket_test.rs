@@ -0,0 +1,161 @@
+use super::auth::Access;
+use super::websocket::{Feed, Server};
+use crate::dsp::Detector;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+type Client = WebSocket<MaybeTlsStream<TcpStream>>;
+
+fn connect(server: &Server) -> Client {
+    let url = format!("ws://{}", server.local_addr());
+    let (client, _) = tungstenite::connect(url).unwrap();
+    if let MaybeTlsStream::Plain(stream) = client.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+    }
+    client
+}
+
+fn wait_for_clients(server: &Server, n: usize) {
+    let start = Instant::now();
+    while server.clients() < n {
+        assert!(start.elapsed() < Duration::from_secs(5), "Client not seen");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn header(client: &mut Client) -> serde_json::Value {
+    match client.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        m => panic!("Expected a header, got {:?}", m),
+    }
+}
+
+fn binary(client: &mut Client) -> Vec<u8> {
+    loop {
+        match client.read().unwrap() {
+            Message::Binary(data) => return data.to_vec(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            m => panic!("Expected binary data, got {:?}", m),
+        }
+    }
+}
+
+#[test]
+fn test_spectrum_rows() {
+    let feed = Feed::Spectrum {
+        fft_size: 256,
+        bins: 64,
+        frames: 2,
+        detector: Detector::Max,
+    };
+    let mut server = Server::start("127.0.0.1:0", feed, 2_048_000, 100_000_000).unwrap();
+    let mut client = connect(&server);
+    wait_for_clients(&server, 1);
+    let h = header(&mut client);
+    assert_eq!(h["feed"], "spectrum");
+    assert_eq!(h["bins"], 64);
+    assert_eq!(h["center_freq"], 100_000_000);
+
+    // A tone a quarter of the way up the band: I = cos, Q = sin
+    let raw: Vec<u8> = (0..512)
+        .flat_map(|n| {
+            let phase = std::f32::consts::FRAC_PI_2 * n as f32;
+            [
+                (127.5 + 100.0 * phase.cos()) as u8,
+                (127.5 + 100.0 * phase.sin()) as u8,
+            ]
+        })
+        .collect();
+    server.process(&raw);
+    let row: Vec<f32> = binary(&mut client)
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(row.len(), 64);
+    let peak = (0..64).max_by(|&a, &b| row[a].total_cmp(&row[b])).unwrap();
+    assert_eq!(peak, 48);
+}
+
+#[test]
+fn test_iq_decimated() {
+    let mut server = Server::start(
+        "127.0.0.1:0",
+        Feed::Iq { decimation: 4 },
+        2_048_000,
+        100_000_000,
+    )
+    .unwrap();
+    let mut client = connect(&server);
+    wait_for_clients(&server, 1);
+    let h = header(&mut client);
+    assert_eq!(h["feed"], "iq");
+    assert_eq!(h["sample_rate"], 512_000.0);
+
+    server.process(&[128_u8; 1024]);
+    let data = binary(&mut client);
+    // 512 IQ pairs decimated by 4, as i16
+    assert_eq!(data.len(), 128 * 2 * 2);
+}
+
+#[test]
+fn test_retune_resends_header() {
+    let mut server = Server::start(
+        "127.0.0.1:0",
+        Feed::Iq { decimation: 2 },
+        1_024_000,
+        100_000_000,
+    )
+    .unwrap();
+    let mut client = connect(&server);
+    wait_for_clients(&server, 1);
+    header(&mut client);
+    server.set_center_freq(144_800_000);
+    assert_eq!(header(&mut client)["center_freq"], 144_800_000);
+}
+
+#[test]
+fn test_stop_closes_clients() {
+    let server = Server::start(
+        "127.0.0.1:0",
+        Feed::Iq { decimation: 2 },
+        1_024_000,
+        100_000_000,
+    )
+    .unwrap();
+    let mut client = connect(&server);
+    wait_for_clients(&server, 1);
+    header(&mut client);
+    server.stop();
+    assert!(matches!(client.read(), Ok(Message::Close(_)) | Err(_)));
+}
+
+#[test]
+fn test_invalid_feed() {
+    let feed = Feed::Spectrum {
+        fft_size: 1000,
+        bins: 10,
+        frames: 1,
+        detector: Detector::Average,
+    };
+    assert!(Server::start("127.0.0.1:0", feed, 2_048_000, 100_000_000).is_err());
+    assert!(Server::start("127.0.0.1:0", Feed::Iq { decimation: 0 }, 2_048_000, 0).is_err());
+}
+
+#[test]
+fn test_token() {
+    let access = Access::open().with_token("secret");
+    let feed = Feed::Iq { decimation: 2 };
+    let server = Server::start_with_access("127.0.0.1:0", access, feed, 1_024_000, 0).unwrap();
+    let url = format!("ws://{}", server.local_addr());
+    match tungstenite::connect(&url) {
+        Err(tungstenite::Error::Http(response)) => assert_eq!(401, response.status()),
+        Err(e) => panic!("Expected a 401, got {}", e),
+        Ok(_) => panic!("Connected without the token"),
+    }
+    let (mut client, _) = tungstenite::connect(format!("{}/?token=secret", url)).unwrap();
+    header(&mut client);
+}
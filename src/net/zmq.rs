@@ -0,0 +1,257 @@
+//! ZeroMQ IQ publisher (feature `zmq`), for feeding GNU Radio flowgraphs
+//! running on other machines.
+//!
+//! Each block read is published on a PUB socket as one message of raw items,
+//! which a GNU Radio "ZMQ SUB Source" with tags disabled receives. Items are
+//! either interleaved `u8` IQ (item type byte, vector length 2) or
+//! `gr_complex`.
+//!
+//! An optional SUB socket takes tuning commands from a GNU Radio "ZMQ PUB
+//! Message Sink", as a PMT pair such as `(freq . 100e6)` or a dict of them.
+//! Plain text messages like `freq 100e6` also work. The keys are `freq`,
+//! `rate`, `gain` (dB, or the symbol `auto`), `ppm` and `bias`.
+//!
+//! ```no_run
+//! use rtlsdr_rs::net::zmq::{ItemFormat, Publisher};
+//! use rtlsdr_rs::RtlSdr;
+//! use std::sync::atomic::AtomicBool;
+//!
+//! let mut sdr = RtlSdr::open(0).unwrap();
+//! let mut publisher = Publisher::bind("tcp://*:5555").unwrap();
+//! publisher.set_format(ItemFormat::Complex);
+//! publisher.set_control("tcp://controller:5556").unwrap();
+//! publisher.run(&mut sdr, &AtomicBool::new(false)).unwrap();
+//! ```
+
+use super::rtl_tcp::{Backend, Command};
+use crate::dsp::encode;
+use crate::error::Result;
+use crate::error::RtlsdrError::{self, RtlsdrErr};
+use crate::DEFAULT_BUF_LENGTH;
+use log::warn;
+use num_complex::Complex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Messages queued per subscriber before new ones are dropped
+const SEND_HWM: i32 = 64;
+
+// PMT serialization type tags
+const PMT_TRUE: u8 = 0x00;
+const PMT_FALSE: u8 = 0x01;
+const PMT_SYMBOL: u8 = 0x02;
+const PMT_INT32: u8 = 0x03;
+const PMT_DOUBLE: u8 = 0x04;
+const PMT_NULL: u8 = 0x06;
+const PMT_PAIR: u8 = 0x07;
+const PMT_UINT64: u8 = 0x0b;
+const PMT_INT64: u8 = 0x0d;
+
+impl From<zmq::Error> for RtlsdrError {
+    fn from(e: zmq::Error) -> Self {
+        RtlsdrError::Io(e.into())
+    }
+}
+
+/// Item type published
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemFormat {
+    Raw,     // Interleaved u8 IQ as read from the device
+    Complex, // gr_complex, little endian f32 pairs
+}
+
+pub struct Publisher {
+    context: zmq::Context,
+    socket: zmq::Socket,
+    control: Option<zmq::Socket>,
+    format: ItemFormat,
+    buf: Vec<u8>, // Encoded items
+}
+
+impl Publisher {
+    /// Publish on `endpoint`, e.g. `tcp://*:5555`, to any subscriber that can
+    /// reach it
+    pub fn bind(endpoint: &str) -> Result<Publisher> {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::PUB)?;
+        socket.set_sndhwm(SEND_HWM)?;
+        socket.set_linger(0)?;
+        socket.bind(endpoint)?;
+        Ok(Publisher {
+            context,
+            socket,
+            control: None,
+            format: ItemFormat::Raw,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Endpoint actually bound, e.g. to find the port chosen for `tcp://*:*`
+    pub fn endpoint(&self) -> Result<String> {
+        self.socket
+            .get_last_endpoint()?
+            .map_err(|_| RtlsdrErr("Endpoint is not UTF-8".to_string()))
+    }
+
+    pub fn set_format(&mut self, format: ItemFormat) {
+        self.format = format;
+    }
+
+    /// Subscribe to tuning commands published on `endpoint`
+    pub fn set_control(&mut self, endpoint: &str) -> Result<()> {
+        let socket = self.context.socket(zmq::SUB)?;
+        socket.set_subscribe(b"")?;
+        socket.set_linger(0)?;
+        socket.connect(endpoint)?;
+        self.control = Some(socket);
+        Ok(())
+    }
+
+    /// Publish a block of raw samples. Subscribers that fall behind miss
+    /// whole blocks.
+    pub fn publish(&mut self, buf: &[u8]) -> Result<()> {
+        let data = match self.format {
+            ItemFormat::Raw => buf,
+            ItemFormat::Complex => {
+                self.buf.clear();
+                encode::<Complex<f32>>(buf, &mut self.buf);
+                &self.buf
+            }
+        };
+        self.socket.send(data, zmq::DONTWAIT)?;
+        Ok(())
+    }
+
+    /// Commands received since the last call, without waiting
+    pub fn poll_control(&self) -> Result<Vec<Command>> {
+        let Some(control) = &self.control else {
+            return Ok(Vec::new());
+        };
+        let mut commands = Vec::new();
+        loop {
+            match control.recv_bytes(zmq::DONTWAIT) {
+                Ok(msg) => match parse_control(&msg) {
+                    Some(parsed) => commands.extend(parsed),
+                    None => warn!("Ignoring unknown control message {:02x?}", msg),
+                },
+                Err(zmq::Error::EAGAIN) => return Ok(commands),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Publish samples from `source` until `shutdown` is set or the source
+    /// runs out, applying control commands between reads
+    pub fn run<B: Backend>(&mut self, source: &mut B, shutdown: &AtomicBool) -> Result<()> {
+        let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+        while !shutdown.load(Ordering::Relaxed) {
+            for command in self.poll_control()? {
+                if let Err(e) = source.apply(command) {
+                    warn!("Control command {:?} failed: {}", command, e);
+                }
+            }
+            let n = source.read_sync(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            self.publish(&buf[..n])?;
+        }
+        Ok(())
+    }
+}
+
+/// Commands in a control message, None if not understood
+pub(super) fn parse_control(msg: &[u8]) -> Option<Vec<Command>> {
+    if msg.first() == Some(&PMT_PAIR) {
+        let (pmt, _) = Pmt::parse(msg)?;
+        return pmt_commands(&pmt);
+    }
+    let text = std::str::from_utf8(msg).ok()?;
+    let mut words = text.split_whitespace();
+    let key = words.next()?;
+    let value = match words.next()? {
+        "auto" => Pmt::Symbol("auto".to_string()),
+        v => Pmt::Number(v.parse().ok()?),
+    };
+    Some(vec![command(key, &value)?])
+}
+
+/// A PMT value, as far as control messages need
+#[derive(Debug, Clone, PartialEq)]
+enum Pmt {
+    Symbol(String),
+    Number(f64),
+    Pair(Box<Pmt>, Box<Pmt>),
+    Null,
+}
+
+impl Pmt {
+    /// Deserialize one value, returning it and the bytes after it
+    fn parse(buf: &[u8]) -> Option<(Pmt, &[u8])> {
+        let (&tag, rest) = buf.split_first()?;
+        match tag {
+            PMT_SYMBOL => {
+                let len = u16::from_be_bytes(rest.get(..2)?.try_into().ok()?) as usize;
+                let text = std::str::from_utf8(rest.get(2..2 + len)?).ok()?;
+                Some((Pmt::Symbol(text.to_string()), &rest[2 + len..]))
+            }
+            PMT_INT32 => {
+                let v = i32::from_be_bytes(rest.get(..4)?.try_into().ok()?);
+                Some((Pmt::Number(v as f64), &rest[4..]))
+            }
+            PMT_DOUBLE => {
+                let v = f64::from_be_bytes(rest.get(..8)?.try_into().ok()?);
+                Some((Pmt::Number(v), &rest[8..]))
+            }
+            PMT_UINT64 => {
+                let v = u64::from_be_bytes(rest.get(..8)?.try_into().ok()?);
+                Some((Pmt::Number(v as f64), &rest[8..]))
+            }
+            PMT_INT64 => {
+                let v = i64::from_be_bytes(rest.get(..8)?.try_into().ok()?);
+                Some((Pmt::Number(v as f64), &rest[8..]))
+            }
+            PMT_TRUE => Some((Pmt::Number(1.0), rest)),
+            PMT_FALSE => Some((Pmt::Number(0.0), rest)),
+            PMT_NULL => Some((Pmt::Null, rest)),
+            PMT_PAIR => {
+                let (car, rest) = Pmt::parse(rest)?;
+                let (cdr, rest) = Pmt::parse(rest)?;
+                Some((Pmt::Pair(Box::new(car), Box::new(cdr)), rest))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Commands from a `(key . value)` pair or a dict (a list of them)
+fn pmt_commands(pmt: &Pmt) -> Option<Vec<Command>> {
+    match pmt {
+        Pmt::Pair(car, cdr) => match (car.as_ref(), cdr.as_ref()) {
+            (Pmt::Symbol(key), value) => Some(vec![command(key, value)?]),
+            (entry @ Pmt::Pair(..), rest) => {
+                let mut commands = pmt_commands(entry)?;
+                commands.extend(pmt_commands(rest)?);
+                Some(commands)
+            }
+            _ => None,
+        },
+        Pmt::Null => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+fn command(key: &str, value: &Pmt) -> Option<Command> {
+    let number = match value {
+        Pmt::Number(n) => Some(*n),
+        _ => None,
+    };
+    match (key, value) {
+        ("gain", Pmt::Symbol(s)) if s == "auto" => Some(Command::SetGainMode(false)),
+        ("freq", _) => Some(Command::SetCenterFreq(number?.round() as u32)),
+        ("rate", _) => Some(Command::SetSampleRate(number?.round() as u32)),
+        ("gain", _) => Some(Command::SetGain((number? * 10.0).round() as i32)),
+        ("ppm", _) => Some(Command::SetFreqCorrection(number?.round() as i32)),
+        ("bias", _) => Some(Command::SetBiasTee(number? != 0.0)),
+        _ => None,
+    }
+}
@@ -0,0 +1,121 @@
+use super::rtl_tcp::Command;
+use super::zmq::{parse_control, ItemFormat, Publisher};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Serialized PMT symbol
+fn symbol(name: &str) -> Vec<u8> {
+    let mut buf = vec![0x02];
+    buf.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    buf
+}
+
+fn double(v: f64) -> Vec<u8> {
+    let mut buf = vec![0x04];
+    buf.extend_from_slice(&v.to_be_bytes());
+    buf
+}
+
+fn pair(car: Vec<u8>, cdr: Vec<u8>) -> Vec<u8> {
+    [vec![0x07], car, cdr].concat()
+}
+
+/// Retry `f` until it returns Some, for the time subscriptions take
+fn retry<T, F: FnMut() -> Option<T>>(mut f: F) -> T {
+    let start = Instant::now();
+    loop {
+        if let Some(v) = f() {
+            return v;
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_parse_text() {
+    assert_eq!(
+        Some(vec![Command::SetCenterFreq(100_000_000)]),
+        parse_control(b"freq 100e6")
+    );
+    assert_eq!(
+        Some(vec![Command::SetGain(496)]),
+        parse_control(b"gain 49.6")
+    );
+    assert_eq!(
+        Some(vec![Command::SetGainMode(false)]),
+        parse_control(b"gain auto")
+    );
+    assert_eq!(
+        Some(vec![Command::SetFreqCorrection(-3)]),
+        parse_control(b"ppm -3")
+    );
+    assert_eq!(None, parse_control(b"volume 11"));
+    assert_eq!(None, parse_control(b"freq"));
+}
+
+#[test]
+fn test_parse_pmt() {
+    let msg = pair(symbol("freq"), double(94.9e6));
+    assert_eq!(
+        Some(vec![Command::SetCenterFreq(94_900_000)]),
+        parse_control(&msg)
+    );
+
+    // A dict is a list of pairs ending in null
+    let dict = pair(
+        pair(symbol("rate"), double(2.4e6)),
+        pair(pair(symbol("bias"), vec![0x00]), vec![0x06]),
+    );
+    assert_eq!(
+        Some(vec![
+            Command::SetSampleRate(2_400_000),
+            Command::SetBiasTee(true)
+        ]),
+        parse_control(&dict)
+    );
+    // Truncated
+    assert_eq!(None, parse_control(&msg[..msg.len() - 1]));
+}
+
+#[test]
+fn test_publish() {
+    let mut publisher = Publisher::bind("tcp://127.0.0.1:*").unwrap();
+    publisher.set_format(ItemFormat::Complex);
+    let context = zmq::Context::new();
+    let sub = context.socket(zmq::SUB).unwrap();
+    sub.set_subscribe(b"").unwrap();
+    sub.connect(&publisher.endpoint().unwrap()).unwrap();
+
+    let msg = retry(|| {
+        publisher.publish(&[255, 0, 127, 128]).unwrap();
+        sub.recv_bytes(zmq::DONTWAIT).ok()
+    });
+    let items: Vec<f32> = msg
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(4, items.len());
+    assert!(items[0] > 0.99 && items[1] < -0.99);
+}
+
+#[test]
+fn test_control() {
+    let context = zmq::Context::new();
+    let control = context.socket(zmq::PUB).unwrap();
+    control.bind("tcp://127.0.0.1:*").unwrap();
+    let endpoint = control.get_last_endpoint().unwrap().unwrap();
+
+    let mut publisher = Publisher::bind("tcp://127.0.0.1:*").unwrap();
+    assert!(publisher.poll_control().unwrap().is_empty());
+    publisher.set_control(&endpoint).unwrap();
+    let commands = retry(|| {
+        control
+            .send(pair(symbol("freq"), double(1.09e9)), 0)
+            .unwrap();
+        let commands = publisher.poll_control().unwrap();
+        (!commands.is_empty()).then_some(commands)
+    });
+    assert_eq!(Command::SetCenterFreq(1_090_000_000), commands[0]);
+}
@@ -0,0 +1,234 @@
+//! Chains of processing blocks driven by the sample stream
+//!
+//! Each `Block` turns a buffer of one sample type into another, keeping any
+//! state it needs between buffers. A `Pipeline` chains blocks whose types
+//! line up and hands the final output to a sink.
+//!
+//! ```no_run
+//! use rtlsdr_rs::dsp::{Nco, ResampleQuality, Resampler};
+//! use rtlsdr_rs::pipeline::{Pipeline, ToComplex};
+//! use rtlsdr_rs::RtlSdr;
+//! use num_complex::Complex;
+//! use std::sync::atomic::AtomicBool;
+//!
+//! let mut sdr = RtlSdr::open(0).unwrap();
+//! let shutdown = AtomicBool::new(false);
+//! let mut pipeline = Pipeline::builder(ToComplex)
+//!     .then(Nco::new(-200_000.0, 2_400_000))
+//!     .then(Resampler::<Complex<f32>>::new(2_400_000, 48_000, ResampleQuality::Medium))
+//!     .sink(|channel: &[Complex<f32>]| println!("{} samples", channel.len()));
+//! pipeline.run(&mut sdr, &shutdown).unwrap();
+//! ```
+
+use crate::demod::fm::FmDemod;
+use crate::dsp::{self, CicDecimator, DcBlocker, IqBalancer, Nco, Resampler, SampleFormat};
+use crate::error::Result;
+use crate::source::SampleSource;
+use crate::DEFAULT_BUF_LENGTH;
+use num_complex::Complex;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(test)]
+mod pipeline_test;
+
+/// A processing step in a pipeline
+pub trait Block {
+    type In;
+    type Out;
+    /// Process `input`, appending the results to `out`
+    fn process(&mut self, input: &[Self::In], out: &mut Vec<Self::Out>);
+}
+
+/// Two blocks run one after the other
+pub struct Chain<A: Block, B: Block<In = A::Out>> {
+    first: A,
+    second: B,
+    buf: Vec<A::Out>, // Output of `first`, reused between buffers
+}
+
+impl<A: Block, B: Block<In = A::Out>> Block for Chain<A, B> {
+    type In = A::In;
+    type Out = B::Out;
+
+    fn process(&mut self, input: &[A::In], out: &mut Vec<B::Out>) {
+        self.buf.clear();
+        self.first.process(input, &mut self.buf);
+        self.second.process(&self.buf, out);
+    }
+}
+
+pub struct PipelineBuilder<B: Block> {
+    blocks: B,
+}
+
+impl<B: Block> PipelineBuilder<B> {
+    /// Append a block taking the output of the previous one
+    pub fn then<C: Block<In = B::Out>>(self, block: C) -> PipelineBuilder<Chain<B, C>> {
+        PipelineBuilder {
+            blocks: Chain {
+                first: self.blocks,
+                second: block,
+                buf: Vec::new(),
+            },
+        }
+    }
+
+    /// Finish the pipeline with a sink called with each buffer of output
+    pub fn sink<S: FnMut(&[B::Out])>(self, sink: S) -> Pipeline<B, S> {
+        Pipeline {
+            blocks: self.blocks,
+            sink,
+            out: Vec::new(),
+        }
+    }
+}
+
+pub struct Pipeline<B: Block, S: FnMut(&[B::Out])> {
+    blocks: B,
+    sink: S,
+    out: Vec<B::Out>,
+}
+
+impl<B: Block> Pipeline<B, fn(&[B::Out])> {
+    /// Start a pipeline with its first block
+    pub fn builder(first: B) -> PipelineBuilder<B> {
+        PipelineBuilder { blocks: first }
+    }
+}
+
+impl<B: Block, S: FnMut(&[B::Out])> Pipeline<B, S> {
+    /// Run a buffer through the blocks and pass the output to the sink
+    pub fn process(&mut self, input: &[B::In]) {
+        self.out.clear();
+        self.blocks.process(input, &mut self.out);
+        if !self.out.is_empty() {
+            (self.sink)(&self.out);
+        }
+    }
+}
+
+impl<B: Block<In = u8>, S: FnMut(&[B::Out])> Pipeline<B, S> {
+    /// Read from `source` and process each buffer until `shutdown` is set,
+    /// the source is exhausted or a read fails. A device must be configured
+    /// and its buffer reset.
+    pub fn run<R: SampleSource>(&mut self, source: &mut R, shutdown: &AtomicBool) -> Result<()> {
+        let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+        while !shutdown.load(Ordering::Relaxed) {
+            let n = source.read_sync(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            self.process(&buf[..n]);
+        }
+        Ok(())
+    }
+}
+
+/// Converts raw samples to complex floats with `dsp::to_complex`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToComplex;
+
+impl Block for ToComplex {
+    type In = u8;
+    type Out = Complex<f32>;
+
+    fn process(&mut self, input: &[u8], out: &mut Vec<Complex<f32>>) {
+        let start = out.len();
+        out.resize(start + input.len() / 2, Complex::new(0.0, 0.0));
+        dsp::to_complex_into(&input[..input.len() / 2 * 2], &mut out[start..]);
+    }
+}
+
+/// Converts raw samples to any `SampleFormat`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Convert<T> {
+    format: PhantomData<T>,
+}
+
+impl<T: SampleFormat> Convert<T> {
+    pub fn new() -> Self {
+        Convert {
+            format: PhantomData,
+        }
+    }
+}
+
+impl<T: SampleFormat> Block for Convert<T> {
+    type In = u8;
+    type Out = T;
+
+    fn process(&mut self, input: &[u8], out: &mut Vec<T>) {
+        let start = out.len();
+        let len = input.len() / T::RAW_LEN;
+        out.resize(start + len, T::default());
+        dsp::convert_into(&input[..len * T::RAW_LEN], &mut out[start..]);
+    }
+}
+
+/// Blocks that modify complex samples in place
+macro_rules! in_place_block {
+    ($($t:ty),*) => {
+        $(
+            impl Block for $t {
+                type In = Complex<f32>;
+                type Out = Complex<f32>;
+
+                fn process(&mut self, input: &[Complex<f32>], out: &mut Vec<Complex<f32>>) {
+                    let start = out.len();
+                    out.extend_from_slice(input);
+                    <$t>::process(self, &mut out[start..]);
+                }
+            }
+        )*
+    };
+}
+in_place_block!(DcBlocker, IqBalancer);
+
+impl Block for Nco {
+    type In = Complex<f32>;
+    type Out = Complex<f32>;
+
+    fn process(&mut self, input: &[Complex<f32>], out: &mut Vec<Complex<f32>>) {
+        let start = out.len();
+        out.extend_from_slice(input);
+        self.mix(&mut out[start..]);
+    }
+}
+
+impl<T> Block for Resampler<T>
+where
+    T: Copy + Default + Add<Output = T> + Mul<f32, Output = T>,
+{
+    type In = T;
+    type Out = T;
+
+    fn process(&mut self, input: &[T], out: &mut Vec<T>) {
+        self.process_into(input, out);
+    }
+}
+
+/// Decimates complex samples; the decimator must have 2 channels
+impl Block for CicDecimator {
+    type In = Complex<f32>;
+    type Out = Complex<f32>;
+
+    fn process(&mut self, input: &[Complex<f32>], out: &mut Vec<Complex<f32>>) {
+        assert_eq!(2, self.channels(), "Complex decimation needs 2 channels");
+        let flat: Vec<f32> = input.iter().flat_map(|s| [s.re, s.im]).collect();
+        let decimated = self.process_f32(&flat);
+        out.extend(decimated.chunks_exact(2).map(|c| Complex::new(c[0], c[1])));
+    }
+}
+
+/// Demodulates raw samples to audio with `FmDemod::demodulate`
+impl Block for FmDemod {
+    type In = u8;
+    type Out = i16;
+
+    fn process(&mut self, input: &[u8], out: &mut Vec<i16>) {
+        let mut buf = input.to_vec();
+        out.extend(self.demodulate(&mut buf));
+    }
+}
@@ -0,0 +1,106 @@
+use super::{Block, Convert, Pipeline, ToComplex};
+use crate::dsp::{CicDecimator, DcBlocker, Nco};
+use num_complex::Complex;
+use std::cell::RefCell;
+
+/// Doubles its input, to check ordering and buffer handling
+struct Double;
+
+impl Block for Double {
+    type In = f32;
+    type Out = f32;
+
+    fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        out.extend(input.iter().map(|x| x * 2.0));
+    }
+}
+
+/// Keeps the real part of complex samples
+struct Real;
+
+impl Block for Real {
+    type In = Complex<f32>;
+    type Out = f32;
+
+    fn process(&mut self, input: &[Complex<f32>], out: &mut Vec<f32>) {
+        out.extend(input.iter().map(|s| s.re));
+    }
+}
+
+#[test]
+fn test_chain_order() {
+    let received = RefCell::new(Vec::new());
+    let mut pipeline = Pipeline::builder(ToComplex)
+        .then(Real)
+        .then(Double)
+        .then(Double)
+        .sink(|buf: &[f32]| received.borrow_mut().extend_from_slice(buf));
+    pipeline.process(&[127 + 32, 0, 127 - 64, 0, 127]);
+    pipeline.process(&[127 + 16, 200]);
+    // The odd trailing byte of a buffer is dropped
+    assert_eq!(vec![1.0, -2.0, 0.5], *received.borrow());
+}
+
+#[test]
+fn test_sink_skips_empty_output() {
+    let mut calls = 0;
+    let mut pipeline = Pipeline::builder(ToComplex)
+        .then(CicDecimator::new(4, 1, 2))
+        .sink(|_: &[Complex<f32>]| calls += 1);
+    pipeline.process(&[127; 6]);
+    pipeline.process(&[127; 2]);
+    drop(pipeline);
+    assert_eq!(1, calls);
+}
+
+#[test]
+fn test_matches_blocks_run_separately() {
+    let input: Vec<u8> = (0..4000).map(|n| ((n * 37) % 256) as u8).collect();
+    let make = || {
+        (
+            Nco::new(10_000.0, 240_000),
+            DcBlocker::new(0.01),
+            CicDecimator::new(4, 3, 2),
+        )
+    };
+
+    let mut chained = Vec::new();
+    let (nco, dc, cic) = make();
+    let mut pipeline = Pipeline::builder(ToComplex)
+        .then(nco)
+        .then(dc)
+        .then(cic)
+        .sink(|buf: &[Complex<f32>]| chained.extend_from_slice(buf));
+    for c in input.chunks(1000) {
+        pipeline.process(c);
+    }
+    drop(pipeline);
+
+    let (mut nco, mut dc, mut cic) = make();
+    let mut samples = crate::dsp::to_complex(&input);
+    nco.mix(&mut samples);
+    dc.process(&mut samples);
+    let flat: Vec<f32> = samples.iter().flat_map(|s| [s.re, s.im]).collect();
+    let separate: Vec<Complex<f32>> = cic
+        .process_f32(&flat)
+        .chunks_exact(2)
+        .map(|c| Complex::new(c[0], c[1]))
+        .collect();
+
+    assert_eq!(500, chained.len());
+    for (a, b) in chained.iter().zip(&separate) {
+        assert!((a - b).norm() < 1e-5);
+    }
+}
+
+#[test]
+fn test_convert() {
+    let mut out = Vec::new();
+    Convert::<i8>::new().process(&[0, 127, 255], &mut out);
+    assert_eq!(vec![-127, 0, 127], out);
+
+    // Complex conversion drops a trailing odd byte like ToComplex
+    let mut out = Vec::new();
+    Convert::<Complex<f32>>::new().process(&[255, 127, 63], &mut out);
+    assert_eq!(vec![Complex::new(1.0, 0.0)], out);
+}
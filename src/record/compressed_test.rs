@@ -0,0 +1,76 @@
+use super::compressed::{ZstdReader, ZstdRecorder};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+fn test_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rtlsdr-zstd-{}-{}.zst", name, std::process::id()))
+}
+
+/// Noisy samples around the IQ midpoint, like a quiet band
+fn samples(len: usize) -> Vec<u8> {
+    let mut state = 0x1234_5678_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (126 + state % 5) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_roundtrip() {
+    let path = test_path("roundtrip");
+    let data = samples(10_000);
+    let mut recorder = ZstdRecorder::create(&path).unwrap();
+    recorder.set_frame_len(3001);
+    recorder.write(&data[..5]).unwrap();
+    recorder.write(&data[5..]).unwrap();
+    assert_eq!(Some(path.clone()), recorder.close().unwrap());
+    assert_eq!(None, recorder.close().unwrap());
+    assert!(fs::metadata(&path).unwrap().len() < data.len() as u64 / 2);
+
+    // Readable by any zstd decoder, which skips the seek table
+    let decoded = zstd::stream::decode_all(fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(data, decoded);
+
+    let mut reader = ZstdReader::open(&path).unwrap();
+    assert_eq!(10_000, reader.len());
+    let mut read = Vec::new();
+    reader.read_to_end(&mut read).unwrap();
+    assert_eq!(data, read);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_seek() {
+    let path = test_path("seek");
+    let data = samples(10_000);
+    let mut recorder = ZstdRecorder::create(&path).unwrap();
+    recorder.set_frame_len(1000);
+    recorder.write(&data).unwrap();
+    drop(recorder);
+
+    let mut reader = ZstdReader::open(&path).unwrap();
+    let mut buf = [0_u8; 100];
+    // Spans the boundary between the fifth and sixth frames
+    reader.seek(SeekFrom::Start(4950)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(data[4950..5050], buf);
+    reader.seek(SeekFrom::End(-100)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(data[9900..], buf);
+    assert_eq!(0, reader.read(&mut buf).unwrap());
+    assert!(reader.seek(SeekFrom::Current(-20_000)).is_err());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_not_seekable() {
+    let path = test_path("plain");
+    fs::write(&path, zstd::bulk::compress(&samples(100), 3).unwrap()).unwrap();
+    assert!(ZstdReader::open(&path).is_err());
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,208 @@
+//! IQ recording as interleaved little endian complex float32, the format of
+//! GNU Radio's File Source with type complex, scaled to about -1.0 to 1.0.
+//!
+//! Optionally a detached `.hdr` header is written next to the data, as
+//! GNU Radio's File Meta Sink does, for the File Meta Source and
+//! `gr_read_file_metadata`. It holds the sample rate and start time
+//! (`rx_rate` and `rx_time`) and an extra `rx_freq` entry with the center
+//! frequency.
+
+use super::part_name;
+use crate::dsp::{encode, SampleFormat};
+use crate::error::Result;
+use num_complex::Complex;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const HEADER_EXTENSION: &str = "hdr";
+// Serialized size of the fixed header dictionary, METADATA_HEADER_SIZE
+const HEADER_LEN: usize = 149;
+const METADATA_VERSION: i32 = 0;
+// GR_FILE_FLOAT in gr_file_types
+const FILE_FLOAT: i32 = 5;
+
+// PMT serialization tags
+const PST_TRUE: u8 = 0x00;
+const PST_FALSE: u8 = 0x01;
+const PST_SYMBOL: u8 = 0x02;
+const PST_INT32: u8 = 0x03;
+const PST_DOUBLE: u8 = 0x04;
+const PST_NULL: u8 = 0x06;
+const PST_PAIR: u8 = 0x07;
+const PST_UINT64: u8 = 0x0b;
+const PST_TUPLE: u8 = 0x0c;
+
+pub struct Cf32Recorder {
+    file: Option<BufWriter<File>>,
+    path: PathBuf,
+    rate: u32,
+    center_freq: u32,
+    started: SystemTime,
+    header: bool,
+    data_len: u64,
+    samples: Vec<u8>,
+}
+
+impl Cf32Recorder {
+    /// Create `path` (written as `path.part` until closed) for samples
+    /// captured at `rate` and `center_freq`
+    pub fn create<P: AsRef<Path>>(path: P, rate: u32, center_freq: u32) -> Result<Cf32Recorder> {
+        let path = path.as_ref().to_path_buf();
+        Ok(Cf32Recorder {
+            file: Some(BufWriter::new(File::create(part_name(&path))?)),
+            path,
+            rate,
+            center_freq,
+            started: SystemTime::now(),
+            header: false,
+            data_len: 0,
+            samples: Vec::new(),
+        })
+    }
+
+    /// Write a detached `.hdr` header when closed (off by default)
+    pub fn set_header(&mut self, on: bool) {
+        self.header = on;
+    }
+
+    /// Append raw interleaved u8 IQ samples as read from the device
+    pub fn write(&mut self, buf: &[u8]) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        self.samples.clear();
+        encode::<Complex<f32>>(buf, &mut self.samples);
+        file.write_all(&self.samples)?;
+        self.data_len += self.samples.len() as u64;
+        Ok(())
+    }
+
+    /// Append samples that have already been converted or processed
+    pub fn write_complex(&mut self, samples: &[Complex<f32>]) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let size = Complex::<f32>::SIZE;
+        self.samples.resize(samples.len() * size, 0);
+        for (s, d) in samples.iter().zip(self.samples.chunks_exact_mut(size)) {
+            s.write_le(d);
+        }
+        file.write_all(&self.samples)?;
+        self.data_len += self.samples.len() as u64;
+        Ok(())
+    }
+
+    /// Move the file to its final name, after writing the header if enabled
+    pub fn close(&mut self) -> Result<Option<PathBuf>> {
+        let Some(file) = self.file.take() else {
+            return Ok(None);
+        };
+        let file = file.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        if self.header {
+            let name = header_name(&self.path);
+            fs::write(part_name(&name), self.header_bytes())?;
+            fs::rename(part_name(&name), name)?;
+        }
+        fs::rename(part_name(&self.path), &self.path)?;
+        Ok(Some(self.path.clone()))
+    }
+
+    /// The header dictionary followed by the extra dictionary, as File Meta
+    /// Sink writes them for a single segment
+    fn header_bytes(&self) -> Vec<u8> {
+        let extra = dict(&[("rx_freq", Pmt::Double(self.center_freq as f64))]);
+        let since_epoch = self
+            .started
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut header = dict(&[
+            ("version", Pmt::Int32(METADATA_VERSION)),
+            ("rx_rate", Pmt::Double(self.rate as f64)),
+            (
+                "rx_time",
+                Pmt::Tuple(vec![
+                    Pmt::Uint64(since_epoch.as_secs()),
+                    Pmt::Double(since_epoch.subsec_nanos() as f64 / 1e9),
+                ]),
+            ),
+            ("size", Pmt::Int32(Complex::<f32>::SIZE as i32)),
+            ("type", Pmt::Int32(FILE_FLOAT)),
+            ("cplx", Pmt::Bool(true)),
+            ("strt", Pmt::Uint64((HEADER_LEN + extra.len()) as u64)),
+            ("bytes", Pmt::Uint64(self.data_len)),
+        ]);
+        debug_assert_eq!(HEADER_LEN, header.len());
+        header.extend_from_slice(&extra);
+        header
+    }
+}
+
+impl Drop for Cf32Recorder {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// Name of the detached header for a data file, e.g. `capture.cf32.hdr`
+pub fn header_name(data: &Path) -> PathBuf {
+    let mut name = data.as_os_str().to_owned();
+    name.push(".");
+    name.push(HEADER_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// The PMT values used in the header
+enum Pmt {
+    Bool(bool),
+    Int32(i32),
+    Uint64(u64),
+    Double(f64),
+    Tuple(Vec<Pmt>),
+}
+
+impl Pmt {
+    fn serialize(&self, out: &mut Vec<u8>) {
+        match self {
+            Pmt::Bool(true) => out.push(PST_TRUE),
+            Pmt::Bool(false) => out.push(PST_FALSE),
+            Pmt::Int32(i) => {
+                out.push(PST_INT32);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+            Pmt::Uint64(u) => {
+                out.push(PST_UINT64);
+                out.extend_from_slice(&u.to_be_bytes());
+            }
+            Pmt::Double(d) => {
+                out.push(PST_DOUBLE);
+                out.extend_from_slice(&d.to_be_bytes());
+            }
+            Pmt::Tuple(items) => {
+                out.push(PST_TUPLE);
+                out.extend_from_slice(&(items.len() as u32).to_be_bytes());
+                for item in items {
+                    item.serialize(out);
+                }
+            }
+        }
+    }
+}
+
+/// Serialize a dictionary built by adding `entries` in order. A PMT dict
+/// is a list of key/value pairs with the most recently added first.
+fn dict(entries: &[(&str, Pmt)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (key, value) in entries.iter().rev() {
+        out.push(PST_PAIR); // List cell
+        out.push(PST_PAIR); // Key and value
+        out.push(PST_SYMBOL);
+        out.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        out.extend_from_slice(key.as_bytes());
+        value.serialize(&mut out);
+    }
+    out.push(PST_NULL);
+    out
+}
@@ -0,0 +1,105 @@
+use super::gnuradio::{header_name, Cf32Recorder};
+use num_complex::Complex;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+fn test_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtlsdr-gnuradio-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("capture.cf32")
+}
+
+fn floats(buf: &[u8]) -> Vec<f32> {
+    buf.chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect()
+}
+
+/// The serialized value following dictionary key `key`
+fn value<'a>(header: &'a [u8], key: &str) -> &'a [u8] {
+    let mut symbol = vec![0x02, 0, key.len() as u8];
+    symbol.extend_from_slice(key.as_bytes());
+    let pos = header
+        .windows(symbol.len())
+        .position(|w| w == symbol)
+        .unwrap_or_else(|| panic!("No {} in header", key));
+    &header[pos + symbol.len()..]
+}
+
+fn u64_value(header: &[u8], key: &str) -> u64 {
+    let v = value(header, key);
+    assert_eq!(0x0b, v[0]);
+    u64::from_be_bytes(v[1..9].try_into().unwrap())
+}
+
+fn f64_value(header: &[u8], key: &str) -> f64 {
+    let v = value(header, key);
+    assert_eq!(0x04, v[0]);
+    f64::from_be_bytes(v[1..9].try_into().unwrap())
+}
+
+fn i32_value(header: &[u8], key: &str) -> i32 {
+    let v = value(header, key);
+    assert_eq!(0x03, v[0]);
+    i32::from_be_bytes(v[1..5].try_into().unwrap())
+}
+
+#[test]
+fn test_samples() {
+    let path = test_path("samples");
+    let mut recorder = Cf32Recorder::create(&path, 2_048_000, 100_000_000).unwrap();
+    recorder.write(&[127, 255, 0, 127, 1]).unwrap();
+    recorder.write_complex(&[Complex::new(0.25, -0.5)]).unwrap();
+    assert!(!path.exists());
+    assert_eq!(Some(path.clone()), recorder.close().unwrap());
+    assert_eq!(None, recorder.close().unwrap());
+
+    // The trailing odd byte is dropped
+    assert_eq!(
+        vec![0.0, 1.0, -127.0 / 128.0, 0.0, 0.25, -0.5],
+        floats(&fs::read(&path).unwrap())
+    );
+    assert!(!header_name(&path).exists());
+}
+
+#[test]
+fn test_header() {
+    let path = test_path("header");
+    let before = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut recorder = Cf32Recorder::create(&path, 2_400_000, 433_920_000).unwrap();
+    recorder.set_header(true);
+    recorder.write(&[127; 1000]).unwrap();
+    recorder.close().unwrap();
+
+    let header = fs::read(header_name(&path)).unwrap();
+    assert_eq!("capture.cf32.hdr", header_name(&path).file_name().unwrap());
+    // 149 byte header dictionary, then the extra dictionary
+    assert_eq!(149 + 22, header.len());
+    assert_eq!([0x07, 0x07, 0x02, 0, 5], header[..5]);
+    assert_eq!(b"bytes", &header[5..10]);
+    assert_eq!(0x06, header[148]);
+    assert_eq!(0x06, header[170]);
+
+    assert_eq!(4000, u64_value(&header, "bytes"));
+    assert_eq!(171, u64_value(&header, "strt"));
+    assert_eq!(0x00, value(&header, "cplx")[0]);
+    assert_eq!(5, i32_value(&header, "type"));
+    assert_eq!(8, i32_value(&header, "size"));
+    assert_eq!(0, i32_value(&header, "version"));
+    assert_eq!(2_400_000.0, f64_value(&header, "rx_rate"));
+    assert_eq!(433_920_000.0, f64_value(&header, "rx_freq"));
+
+    // Tuple of whole and fractional seconds
+    let time = value(&header, "rx_time");
+    assert_eq!([0x0c, 0, 0, 0, 2, 0x0b], time[..6]);
+    let secs = u64::from_be_bytes(time[6..14].try_into().unwrap());
+    assert!(secs >= before && secs <= before + 5);
+    assert_eq!(0x04, time[14]);
+    let frac = f64::from_be_bytes(time[15..23].try_into().unwrap());
+    assert!((0.0..1.0).contains(&frac));
+}
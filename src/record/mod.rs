@@ -0,0 +1,364 @@
+//! Recording of the raw IQ stream to disk, in the same format as rtl_sdr, as
+//! WAV (see `wav`), as complex float for GNU Radio (see `gnuradio`) or zstd
+//! compressed (see `compressed`, feature `zstd`).
+//! `ring` keeps the most recent samples in memory to save on demand,
+//! `schedule` records at set times and `multi` records several dongles at
+//! once.
+//!
+//! Files are written under a `.part` name and renamed once closed, so a file
+//! with its final name is always complete. With rotation enabled a new file
+//! is started when the current one reaches a size or age limit, and files
+//! are numbered: `capture.bin` becomes `capture-0000.bin`, `capture-0001.bin`
+//! and so on.
+//!
+//! With timestamps enabled each data file gets a `.timestamps` CSV next to
+//! it. Every written block adds a line with the index of its first sample,
+//! counted from the start of the recording across all files, and the host
+//! time it was received. Drops reported with `log_drop` are listed at the
+//! sample index where they happened, so captures can be aligned with
+//! external events after the fact:
+//! ```text
+//! event,sample,time,samples
+//! block,0,1700000000.123456789,131072
+//! drop,131072,1700000000.190000000,0
+//! block,131072,1700000000.190000000,131072
+//! ```
+//!
+//! Gaps can also be marked in the recording itself. With gap fill enabled,
+//! drops of a known size are replaced by that many samples at the ADC
+//! midpoint, so sample indices keep matching time. With SigMF metadata
+//! enabled, a `.sigmf-meta` file is written for each data file, with a
+//! `gap` annotation at each drop.
+//!
+//! ```no_run
+//! use rtlsdr_rs::record::RawRecorder;
+//! use rtlsdr_rs::{RtlSdr, DEFAULT_BUF_LENGTH};
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! let mut recorder = RawRecorder::new("capture.bin");
+//! recorder.set_max_size(Some(1 << 30));
+//! recorder.set_timestamps(true);
+//! let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+//! for _ in 0..100 {
+//!     let n = sdr.read_sync(&mut buf).unwrap();
+//!     recorder.write(&buf[..n]).unwrap();
+//!     if n < buf.len() {
+//!         // Samples were lost after this block, the amount is unknown
+//!         recorder.log_drop(0).unwrap();
+//!     }
+//! }
+//! recorder.close().unwrap();
+//! ```
+
+use crate::dsp::IQ_OFFSET;
+use crate::error::Result;
+use sigmf::Gap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "zstd")]
+pub mod compressed;
+pub mod gnuradio;
+pub mod multi;
+pub mod ring;
+pub mod schedule;
+mod sigmf;
+pub mod wav;
+#[cfg(feature = "zstd")]
+pub use compressed::{ZstdReader, ZstdRecorder};
+pub use gnuradio::Cf32Recorder;
+pub use ring::RingRecorder;
+pub use wav::{WavFormat, WavRecorder};
+
+#[cfg(all(test, feature = "zstd"))]
+mod compressed_test;
+#[cfg(test)]
+mod gnuradio_test;
+#[cfg(test)]
+mod multi_test;
+#[cfg(test)]
+mod record_test;
+#[cfg(test)]
+mod ring_test;
+#[cfg(test)]
+mod schedule_test;
+#[cfg(test)]
+mod wav_test;
+
+const PART_EXTENSION: &str = "part";
+const TIMESTAMPS_EXTENSION: &str = "timestamps";
+const TIMESTAMPS_HEADER: &str = "event,sample,time,samples";
+
+pub struct RawRecorder {
+    path: PathBuf,
+    max_size: Option<u64>, // Bytes, rounded down to whole IQ pairs
+    max_duration: Option<Duration>,
+    file: Option<BufWriter<File>>,
+    name: PathBuf, // Final name of the file being written
+    written: u64,  // Bytes in the current file
+    started: Instant,
+    index: u32, // Number of the next rotated file
+    completed: Vec<PathBuf>,
+    timestamps: Option<BufWriter<File>>, // Sidecar of the current file
+    timestamped: bool,
+    total: u64,                // Bytes written since the recording started
+    sigmf: Option<(u32, u32)>, // Sample rate and center frequency
+    gap_fill: bool,
+    gaps: Vec<Gap>,         // In the current file
+    new_gap: bool,          // Next fill starts a gap rather than continuing one
+    file_start: SystemTime, // Host time the current file was opened
+}
+
+impl RawRecorder {
+    /// Record to `path`. No file is created until the first write.
+    pub fn new<P: AsRef<Path>>(path: P) -> RawRecorder {
+        RawRecorder {
+            path: path.as_ref().to_path_buf(),
+            max_size: None,
+            max_duration: None,
+            file: None,
+            name: PathBuf::new(),
+            written: 0,
+            started: Instant::now(),
+            index: 0,
+            completed: Vec::new(),
+            timestamps: None,
+            timestamped: false,
+            total: 0,
+            sigmf: None,
+            gap_fill: false,
+            gaps: Vec::new(),
+            new_gap: false,
+            file_start: SystemTime::now(),
+        }
+    }
+
+    /// Write a `.sigmf-meta` file for each data file, describing samples
+    /// captured at `sample_rate` and `center_freq` and annotating any gaps.
+    /// Name the recording `*.sigmf-data` so it can be played back.
+    pub fn set_sigmf(&mut self, sample_rate: u32, center_freq: u32) {
+        self.sigmf = Some((sample_rate, center_freq));
+    }
+
+    /// Write placeholder samples in place of drops of a known size
+    pub fn set_gap_fill(&mut self, on: bool) {
+        self.gap_fill = on;
+    }
+
+    /// Write a `.timestamps` file next to each data file, taking effect from
+    /// the next file opened
+    pub fn set_timestamps(&mut self, on: bool) {
+        self.timestamped = on;
+    }
+
+    /// Samples (IQ pairs) written since the recording started
+    pub fn samples(&self) -> u64 {
+        self.total / 2
+    }
+
+    /// Start a new file once the current one holds `size` bytes
+    pub fn set_max_size(&mut self, size: Option<u64>) {
+        self.max_size = size.map(|s| (s & !1).max(2));
+    }
+
+    /// Start a new file once the current one has been open for `duration`
+    pub fn set_max_duration(&mut self, duration: Option<Duration>) {
+        self.max_duration = duration;
+    }
+
+    /// Append raw samples, rotating files as needed
+    pub fn write(&mut self, buf: &[u8]) -> Result<()> {
+        self.write_at(buf, SystemTime::now())
+    }
+
+    /// Append raw samples received by the host at `time`
+    pub fn write_at(&mut self, buf: &[u8], time: SystemTime) -> Result<()> {
+        self.write_samples(buf, time, None)
+    }
+
+    /// Note that `missing` samples were lost before the next write, 0 if the
+    /// amount isn't known. Logged in the timestamps and SigMF metadata, and
+    /// filled in if gap fill is enabled.
+    pub fn log_drop(&mut self, missing: u64) -> Result<()> {
+        if !self.timestamped && self.sigmf.is_none() && !self.gap_fill {
+            return Ok(());
+        }
+        self.ensure_open()?;
+        let time = SystemTime::now();
+        self.log_event("drop", time, missing)?;
+        if !self.gap_fill || missing == 0 {
+            self.gaps.push(Gap {
+                sample: self.written / 2,
+                filled: 0,
+                missing,
+            });
+            return Ok(());
+        }
+        // Write in blocks so a large gap doesn't need a large buffer
+        let fill = vec![IQ_OFFSET; 2 * missing.min(1 << 16) as usize];
+        let mut left = 2 * missing as usize;
+        self.new_gap = true;
+        while left > 0 {
+            let n = left.min(fill.len());
+            self.write_samples(&fill[..n], time, Some(missing))?;
+            left -= n;
+        }
+        Ok(())
+    }
+
+    /// Write samples, or placeholders for a gap of `missing` samples
+    fn write_samples(&mut self, buf: &[u8], time: SystemTime, missing: Option<u64>) -> Result<()> {
+        let mut buf = buf;
+        while !buf.is_empty() {
+            self.ensure_open()?;
+            let n = match self.max_size {
+                Some(max) => ((max - self.written) as usize).min(buf.len()),
+                None => buf.len(),
+            };
+            let samples = n as u64 / 2;
+            match missing {
+                Some(missing) => {
+                    self.log_event("fill", time, samples)?;
+                    // Extend the gap if this continues it in the same file
+                    match self.gaps.last_mut() {
+                        Some(gap) if !self.new_gap && self.written > 0 => gap.filled += samples,
+                        _ => self.gaps.push(Gap {
+                            sample: self.written / 2,
+                            filled: samples,
+                            missing,
+                        }),
+                    }
+                    self.new_gap = false;
+                }
+                None => self.log_event("block", time, samples)?,
+            }
+            if let Some(file) = self.file.as_mut() {
+                file.write_all(&buf[..n])?;
+            }
+            self.written += n as u64;
+            self.total += n as u64;
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    /// Flush and close the current file, moving it to its final name.
+    /// Returns that name, or None if no file was open.
+    pub fn close(&mut self) -> Result<Option<PathBuf>> {
+        let Some(file) = self.file.take() else {
+            return Ok(None);
+        };
+        let file = file.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(part_name(&self.name), &self.name)?;
+        if let Some(timestamps) = self.timestamps.take() {
+            timestamps.into_inner().map_err(|e| e.into_error())?;
+            let name = timestamps_name(&self.name);
+            fs::rename(part_name(&name), name)?;
+        }
+        if let Some((rate, freq)) = self.sigmf {
+            sigmf::write_meta(&self.name, rate, freq, self.file_start, &self.gaps)?;
+        }
+        self.gaps.clear();
+        self.completed.push(self.name.clone());
+        Ok(Some(self.name.clone()))
+    }
+
+    /// Files closed so far, oldest first
+    pub fn files(&self) -> &[PathBuf] {
+        &self.completed
+    }
+
+    fn rotating(&self) -> bool {
+        self.max_size.is_some() || self.max_duration.is_some()
+    }
+
+    fn expired(&self) -> bool {
+        self.max_size.is_some_and(|max| self.written >= max)
+            || self
+                .max_duration
+                .is_some_and(|max| self.started.elapsed() >= max)
+    }
+
+    fn ensure_open(&mut self) -> Result<()> {
+        if self.file.is_some() && self.expired() {
+            self.close()?;
+        }
+        if self.file.is_none() {
+            self.open()?;
+        }
+        Ok(())
+    }
+
+    fn log_event(&mut self, event: &str, time: SystemTime, samples: u64) -> Result<()> {
+        if let Some(out) = self.timestamps.as_mut() {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                event,
+                self.total / 2,
+                unix_time(time),
+                samples
+            )?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<()> {
+        self.name = if self.rotating() {
+            let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+            let mut name = format!("{}-{:04}", stem, self.index);
+            if let Some(ext) = self.path.extension() {
+                name = format!("{}.{}", name, ext.to_string_lossy());
+            }
+            self.index += 1;
+            self.path.with_file_name(name)
+        } else {
+            self.path.clone()
+        };
+        self.file = Some(BufWriter::new(File::create(part_name(&self.name))?));
+        if self.timestamped {
+            let name = part_name(&timestamps_name(&self.name));
+            let mut out = BufWriter::new(File::create(name)?);
+            writeln!(out, "{}", TIMESTAMPS_HEADER)?;
+            self.timestamps = Some(out);
+        }
+        self.written = 0;
+        self.started = Instant::now();
+        self.file_start = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Drop for RawRecorder {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// Seconds since the Unix epoch with nanosecond precision, as used in the
+/// timestamps and timing logs
+fn unix_time(time: SystemTime) -> String {
+    let time = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:09}", time.as_secs(), time.subsec_nanos())
+}
+
+/// Name of the timestamps file for a data file
+fn timestamps_name(name: &Path) -> PathBuf {
+    let mut timestamps = name.as_os_str().to_owned();
+    timestamps.push(".");
+    timestamps.push(TIMESTAMPS_EXTENSION);
+    PathBuf::from(timestamps)
+}
+
+/// Name a file is written under until it is closed
+fn part_name(name: &Path) -> PathBuf {
+    let mut part = name.as_os_str().to_owned();
+    part.push(".");
+    part.push(PART_EXTENSION);
+    PathBuf::from(part)
+}
@@ -0,0 +1,226 @@
+//! Simultaneous recording from several dongles, e.g. for TDOA experiments.
+//!
+//! Each channel is read on its own thread into its own raw file. All devices
+//! are opened and configured before any starts streaming, and every block
+//! read is logged with its channel, sample index and host time in one shared
+//! timing log, so the channels can be aligned afterwards:
+//! ```text
+//! channel,sample,time,samples
+//! 0,0,1700000000.120000000,131072
+//! 1,0,1700000000.120400000,131072
+//! ```
+//! Host timestamps are only as good as USB scheduling allows. Sample accurate
+//! alignment needs dongles sharing a clock, with a reference signal in the
+//! recordings.
+//!
+//! ```no_run
+//! use rtlsdr_rs::record::multi::MultiRecorder;
+//! use rtlsdr_rs::record::schedule::Profile;
+//! use rtlsdr_rs::TunerGain;
+//! use std::sync::atomic::AtomicBool;
+//! use std::time::Duration;
+//!
+//! let profile = Profile::new(1_090_000_000, 2_400_000, TunerGain::Manual(400));
+//! let mut recorder = MultiRecorder::new("timing.csv");
+//! recorder.add_channel(0, profile, "ch0.bin");
+//! recorder.add_channel(1, profile, "ch1.bin");
+//! recorder.set_duration(Some(Duration::from_secs(10)));
+//! let files = recorder.run(&AtomicBool::new(false)).unwrap();
+//! ```
+
+use super::schedule::Profile;
+use super::{part_name, unix_time, RawRecorder};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::{RtlSdr, DEFAULT_BUF_LENGTH};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const TIMING_HEADER: &str = "channel,sample,time,samples";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    pub device: usize, // Index passed to `RtlSdr::open`
+    pub profile: Profile,
+    pub path: PathBuf,
+}
+
+pub struct MultiRecorder {
+    channels: Vec<Channel>,
+    timing: PathBuf,
+    duration: Option<Duration>,
+}
+
+// A block read by one channel, sent to the timing log
+struct Block {
+    channel: usize,
+    sample: u64,
+    time: SystemTime,
+    samples: u64,
+}
+
+impl MultiRecorder {
+    /// Record with the shared timing log written to `timing`
+    pub fn new<P: AsRef<Path>>(timing: P) -> MultiRecorder {
+        MultiRecorder {
+            channels: Vec::new(),
+            timing: timing.as_ref().to_path_buf(),
+            duration: None,
+        }
+    }
+
+    /// Record device `device` tuned to `profile` into `path`
+    pub fn add_channel<P: AsRef<Path>>(&mut self, device: usize, profile: Profile, path: P) {
+        self.channels.push(Channel {
+            device,
+            profile,
+            path: path.as_ref().to_path_buf(),
+        });
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Stop after each channel has recorded `duration` of samples, or only
+    /// when shut down if None
+    pub fn set_duration(&mut self, duration: Option<Duration>) {
+        self.duration = duration;
+    }
+
+    /// Open and configure every device, then record until the duration is
+    /// reached, `shutdown` is set or a channel fails. Returns the file
+    /// written for each channel.
+    pub fn run(&self, shutdown: &AtomicBool) -> Result<Vec<PathBuf>> {
+        self.run_with(shutdown, |channel| {
+            let mut sdr = RtlSdr::open(channel.device)?;
+            channel.profile.apply(&mut sdr)?;
+            Ok(sdr)
+        })
+    }
+
+    /// Like `run`, with each channel's source opened by `open` on the thread
+    /// that reads it
+    pub fn run_with<S, F>(&self, shutdown: &AtomicBool, open: F) -> Result<Vec<PathBuf>>
+    where
+        S: SampleSource,
+        F: Fn(&Channel) -> Result<S> + Sync,
+    {
+        if self.channels.is_empty() {
+            return Err(RtlsdrErr("No channels to record".to_string()));
+        }
+        let mut timing = BufWriter::new(File::create(part_name(&self.timing))?);
+        writeln!(timing, "{}", TIMING_HEADER)?;
+
+        let barrier = Barrier::new(self.channels.len());
+        // Set when any channel fails, to stop the others
+        let failed = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel();
+        let results: Vec<Result<PathBuf>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .channels
+                .iter()
+                .enumerate()
+                .map(|(i, channel)| {
+                    let tx = tx.clone();
+                    let (open, barrier, failed) = (&open, &barrier, &failed);
+                    scope.spawn(move || {
+                        let source = open(channel);
+                        if source.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        // Start streaming together, once all are configured
+                        barrier.wait();
+                        let result = self.record(i, source?, shutdown, failed, tx);
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        result
+                    })
+                })
+                .collect();
+            drop(tx);
+            for block in rx {
+                // A failed log write shouldn't stop the recordings
+                let _ = writeln!(
+                    timing,
+                    "{},{},{},{}",
+                    block.channel,
+                    block.sample,
+                    unix_time(block.time),
+                    block.samples
+                );
+            }
+            handles
+                .into_iter()
+                .enumerate()
+                .map(|(i, h)| {
+                    h.join().unwrap_or_else(|_| {
+                        Err(RtlsdrErr(format!(
+                            "Channel {} recording thread panicked",
+                            i
+                        )))
+                    })
+                })
+                .collect()
+        });
+
+        timing
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(part_name(&self.timing), &self.timing)?;
+        results.into_iter().collect()
+    }
+
+    fn record<S: SampleSource>(
+        &self,
+        channel: usize,
+        mut source: S,
+        shutdown: &AtomicBool,
+        failed: &AtomicBool,
+        tx: mpsc::Sender<Block>,
+    ) -> Result<PathBuf> {
+        let path = &self.channels[channel].path;
+        // Bytes to record, whole IQ pairs
+        let limit = self
+            .duration
+            .map(|d| 2 * (source.sample_rate() as f64 * d.as_secs_f64()).round() as u64);
+        let mut recorder = RawRecorder::new(path);
+        let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+        let mut written = 0_u64;
+        while !shutdown.load(Ordering::Relaxed) && !failed.load(Ordering::Relaxed) {
+            let want = match limit {
+                Some(limit) if written >= limit => break,
+                Some(limit) => ((limit - written) as usize).min(buf.len()),
+                None => buf.len(),
+            };
+            let n = source.read_sync(&mut buf[..want])?;
+            let time = SystemTime::now();
+            if n == 0 {
+                break;
+            }
+            recorder.write_at(&buf[..n], time)?;
+            let _ = tx.send(Block {
+                channel,
+                sample: written / 2,
+                time,
+                samples: n as u64 / 2,
+            });
+            written += n as u64;
+        }
+        if recorder.close()?.is_none() {
+            // Nothing was read, leave an empty file rather than none
+            File::create(path)?;
+        }
+        Ok(path.clone())
+    }
+}
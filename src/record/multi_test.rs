@@ -0,0 +1,96 @@
+use super::multi::MultiRecorder;
+use super::schedule::Profile;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::FileSdr;
+use crate::TunerGain;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// Empty directory for a test's files
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtlsdr-multi-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn profile() -> Profile {
+    Profile::new(100_000_000, 1000, TunerGain::Auto)
+}
+
+#[test]
+fn test_multi_record() {
+    let dir = test_dir("record");
+    // Each "device" plays back its own file
+    let inputs: Vec<Vec<u8>> = (0..2_u8).map(|i| vec![i; 5000]).collect();
+    for (i, input) in inputs.iter().enumerate() {
+        fs::write(dir.join(format!("in{}.bin", i)), input).unwrap();
+    }
+    let mut recorder = MultiRecorder::new(dir.join("timing.csv"));
+    recorder.add_channel(0, profile(), dir.join("ch0.bin"));
+    recorder.add_channel(1, profile(), dir.join("ch1.bin"));
+    recorder.set_duration(Some(Duration::from_secs(2)));
+    let files = recorder
+        .run_with(&AtomicBool::new(false), |channel| {
+            let path = dir.join(format!("in{}.bin", channel.device));
+            FileSdr::open(path, 1000, channel.profile.center_freq)
+        })
+        .unwrap();
+
+    assert_eq!(vec![dir.join("ch0.bin"), dir.join("ch1.bin")], files);
+    // 2 s at 1000 S/s
+    assert_eq!(vec![0; 4000], fs::read(&files[0]).unwrap());
+    assert_eq!(vec![1; 4000], fs::read(&files[1]).unwrap());
+    let timing = fs::read_to_string(dir.join("timing.csv")).unwrap();
+    let lines: Vec<&str> = timing.lines().collect();
+    assert_eq!("channel,sample,time,samples", lines[0]);
+    for channel in ["0", "1"] {
+        let blocks: Vec<Vec<&str>> = lines[1..]
+            .iter()
+            .map(|l| l.split(',').collect::<Vec<_>>())
+            .filter(|f| f[0] == channel)
+            .collect();
+        assert_eq!(
+            vec![vec![channel, "0"]],
+            blocks.iter().map(|f| f[..2].to_vec()).collect::<Vec<_>>()
+        );
+        assert_eq!("2000", blocks[0][3]);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_multi_record_open_failure() {
+    let dir = test_dir("failure");
+    fs::write(dir.join("in.bin"), vec![0_u8; 100]).unwrap();
+    let mut recorder = MultiRecorder::new(dir.join("timing.csv"));
+    recorder.add_channel(0, profile(), dir.join("ch0.bin"));
+    recorder.add_channel(1, profile(), dir.join("ch1.bin"));
+    let result = recorder.run_with(&AtomicBool::new(false), |channel| match channel.device {
+        0 => FileSdr::open(dir.join("in.bin"), 1000, 0),
+        _ => Err(RtlsdrErr("No device".to_string())),
+    });
+    assert!(result.is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_multi_record_thread_panic() {
+    let dir = test_dir("panic");
+    let mut recorder = MultiRecorder::new(dir.join("timing.csv"));
+    recorder.add_channel(0, profile(), dir.join("ch0.bin"));
+    let result = recorder.run_with(&AtomicBool::new(false), |_| -> Result<FileSdr> {
+        panic!("Source failed")
+    });
+    assert!(result.is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_multi_record_no_channels() {
+    let recorder = MultiRecorder::new(std::env::temp_dir().join("unused-timing.csv"));
+    assert!(recorder.run(&AtomicBool::new(false)).is_err());
+}
@@ -0,0 +1,182 @@
+use super::RawRecorder;
+use crate::source::{SampleSource, SigmfReader};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Empty directory for a test's files
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtlsdr-record-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_single_file() {
+    let dir = test_dir("single");
+    let path = dir.join("capture.bin");
+    let mut recorder = RawRecorder::new(&path);
+    recorder.write(&[1, 2, 3, 4]).unwrap();
+    recorder.write(&[5, 6]).unwrap();
+    // Incomplete until closed
+    assert!(!path.exists());
+    assert!(dir.join("capture.bin.part").exists());
+    assert_eq!(Some(path.clone()), recorder.close().unwrap());
+    assert_eq!(None, recorder.close().unwrap());
+    assert_eq!(vec![1, 2, 3, 4, 5, 6], fs::read(&path).unwrap());
+    assert!(!dir.join("capture.bin.part").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_rotate_by_size() {
+    let dir = test_dir("size");
+    let mut recorder = RawRecorder::new(dir.join("capture.bin"));
+    // Odd sizes round down so IQ pairs are not split between files
+    recorder.set_max_size(Some(5));
+    let data: Vec<u8> = (0..10).collect();
+    recorder.write(&data[..3]).unwrap();
+    recorder.write(&data[3..]).unwrap();
+    drop(recorder);
+    let files: Vec<Vec<u8>> = (0..3)
+        .map(|i| fs::read(dir.join(format!("capture-{:04}.bin", i))).unwrap())
+        .collect();
+    assert_eq!(vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]], files);
+    assert_eq!(3, fs::read_dir(&dir).unwrap().count());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_rotate_by_duration() {
+    let dir = test_dir("duration");
+    let mut recorder = RawRecorder::new(dir.join("iq"));
+    recorder.set_max_duration(Some(Duration::from_millis(20)));
+    recorder.write(&[1, 2]).unwrap();
+    recorder.write(&[3, 4]).unwrap();
+    thread::sleep(Duration::from_millis(30));
+    recorder.write(&[5, 6]).unwrap();
+    recorder.close().unwrap();
+    assert_eq!(
+        &[dir.join("iq-0000"), dir.join("iq-0001")],
+        recorder.files()
+    );
+    assert_eq!(vec![1, 2, 3, 4], fs::read(dir.join("iq-0000")).unwrap());
+    assert_eq!(vec![5, 6], fs::read(dir.join("iq-0001")).unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_timestamps() {
+    let dir = test_dir("timestamps");
+    let mut recorder = RawRecorder::new(dir.join("capture.bin"));
+    recorder.set_timestamps(true);
+    recorder.set_max_size(Some(6));
+    let t0 = UNIX_EPOCH + Duration::new(1_700_000_000, 5);
+    recorder.write_at(&[0; 4], t0).unwrap();
+    recorder.log_drop(100).unwrap();
+    // Split across files, both parts carry the same host time
+    recorder
+        .write_at(&[0; 4], t0 + Duration::from_millis(500))
+        .unwrap();
+    assert_eq!(4, recorder.samples());
+    recorder.close().unwrap();
+
+    let first = fs::read_to_string(dir.join("capture-0000.bin.timestamps")).unwrap();
+    let lines: Vec<&str> = first.lines().collect();
+    assert_eq!(
+        vec![
+            "event,sample,time,samples",
+            "block,0,1700000000.000000005,2",
+        ],
+        lines[..2]
+    );
+    assert!(lines[2].starts_with("drop,2,"));
+    assert!(lines[2].ends_with(",100"));
+    assert_eq!("block,2,1700000000.500000005,1", lines[3]);
+    let second = fs::read_to_string(dir.join("capture-0001.bin.timestamps")).unwrap();
+    assert_eq!(
+        "event,sample,time,samples\nblock,3,1700000000.500000005,1\n",
+        second
+    );
+    // Only the data and timestamps files remain
+    assert_eq!(4, fs::read_dir(&dir).unwrap().count());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_no_timestamps() {
+    let dir = test_dir("no-timestamps");
+    let mut recorder = RawRecorder::new(dir.join("capture.bin"));
+    recorder.write(&[1, 2]).unwrap();
+    recorder.log_drop(0).unwrap();
+    recorder.close().unwrap();
+    assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_gap_fill() {
+    let dir = test_dir("gap-fill");
+    let path = dir.join("capture.bin");
+    let mut recorder = RawRecorder::new(&path);
+    recorder.set_gap_fill(true);
+    recorder.write(&[1, 2]).unwrap();
+    recorder.log_drop(3).unwrap();
+    // Unknown size, nothing to fill
+    recorder.log_drop(0).unwrap();
+    recorder.write(&[3, 4]).unwrap();
+    assert_eq!(5, recorder.samples());
+    recorder.close().unwrap();
+    assert_eq!(
+        vec![1, 2, 127, 127, 127, 127, 127, 127, 3, 4],
+        fs::read(&path).unwrap()
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Gap annotations in a SigMF metadata file as (start, count)
+fn gaps(meta: &Path) -> Vec<(u64, u64)> {
+    let meta: Value = serde_json::from_str(&fs::read_to_string(meta).unwrap()).unwrap();
+    meta["annotations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| {
+            assert_eq!("gap", a["core:label"]);
+            (
+                a["core:sample_start"].as_u64().unwrap(),
+                a["core:sample_count"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_sigmf_gaps() {
+    let dir = test_dir("sigmf");
+    let mut recorder = RawRecorder::new(dir.join("capture.sigmf-data"));
+    recorder.set_sigmf(1_000_000, 100_000_000);
+    recorder.set_gap_fill(true);
+    recorder.set_max_size(Some(8));
+    recorder.write(&[1, 2, 3, 4]).unwrap();
+    recorder.log_drop(0).unwrap();
+    // Split between files
+    recorder.log_drop(3).unwrap();
+    recorder.close().unwrap();
+
+    assert_eq!(
+        vec![(2, 0), (2, 2)],
+        gaps(&dir.join("capture-0000.sigmf-meta"))
+    );
+    assert_eq!(vec![(0, 1)], gaps(&dir.join("capture-0001.sigmf-meta")));
+    let mut reader = SigmfReader::open(dir.join("capture-0000")).unwrap();
+    assert_eq!(100_000_000, reader.center_freq());
+    assert_eq!(1_000_000, reader.sample_rate());
+    let mut buf = [0_u8; 16];
+    assert_eq!(8, reader.read_sync(&mut buf).unwrap());
+    assert_eq!([1, 2, 3, 4, 127, 127, 127, 127], buf[..8]);
+    fs::remove_dir_all(dir).unwrap();
+}
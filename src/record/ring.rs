@@ -0,0 +1,122 @@
+//! Pre-trigger capture: keeps the last few seconds of raw IQ in memory so
+//! they can be saved once something interesting happens, such as a squelch
+//! opening on an intermittent signal.
+//!
+//! ```no_run
+//! use rtlsdr_rs::record::RingRecorder;
+//! use rtlsdr_rs::{RtlSdr, DEFAULT_BUF_LENGTH};
+//! use std::time::Duration;
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! let mut ring = RingRecorder::new(sdr.get_sample_rate(), Duration::from_secs(10));
+//! let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+//! loop {
+//!     let n = sdr.read_sync(&mut buf).unwrap();
+//!     ring.write(&buf[..n]);
+//!     # let triggered = true;
+//!     if triggered {
+//!         ring.dump("event.bin").unwrap();
+//!         break;
+//!     }
+//! }
+//! ```
+
+use super::part_name;
+use crate::error::Result;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub struct RingRecorder {
+    rate: u32,
+    buf: Vec<u8>, // Allocated up front, whole IQ pairs
+    head: usize,  // Next byte to overwrite, the oldest once full
+    full: bool,
+    last: SystemTime, // When the newest samples were written
+}
+
+impl RingRecorder {
+    /// Keep `duration` of samples captured at `rate`
+    pub fn new(rate: u32, duration: Duration) -> RingRecorder {
+        let pairs = (rate as f64 * duration.as_secs_f64()).round() as usize;
+        RingRecorder {
+            rate,
+            buf: vec![0; 2 * pairs.max(1)],
+            head: 0,
+            full: false,
+            last: SystemTime::now(),
+        }
+    }
+
+    /// Append raw samples, overwriting the oldest once full
+    pub fn write(&mut self, buf: &[u8]) {
+        self.last = SystemTime::now();
+        let cap = self.buf.len();
+        // Only the newest `cap` bytes can be kept
+        let buf = &buf[buf.len().saturating_sub(cap)..];
+        let n = (cap - self.head).min(buf.len());
+        self.buf[self.head..self.head + n].copy_from_slice(&buf[..n]);
+        self.buf[..buf.len() - n].copy_from_slice(&buf[n..]);
+        let end = self.head + buf.len();
+        self.full |= end >= cap;
+        self.head = end % cap;
+    }
+
+    /// Bytes currently held
+    pub fn len(&self) -> usize {
+        if self.full {
+            self.buf.len()
+        } else {
+            self.head
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Duration of the samples currently held
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.len() as f64 / 2.0 / self.rate as f64)
+    }
+
+    /// Estimated host time of the oldest sample held
+    pub fn start_time(&self) -> SystemTime {
+        self.last - self.duration()
+    }
+
+    /// The samples held, oldest first, as two slices
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        if self.full {
+            (&self.buf[self.head..], &self.buf[..self.head])
+        } else {
+            (&self.buf[..self.head], &[])
+        }
+    }
+
+    /// Write the samples held, oldest first
+    pub fn dump_to<W: Write>(&self, out: &mut W) -> Result<()> {
+        let (a, b) = self.as_slices();
+        out.write_all(a)?;
+        out.write_all(b)?;
+        Ok(())
+    }
+
+    /// Save the samples held to `path` in raw format, written as `path.part`
+    /// until complete. Recording continues unaffected.
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let path = path.as_ref().to_path_buf();
+        let mut out = BufWriter::new(File::create(part_name(&path))?);
+        self.dump_to(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(part_name(&path), &path)?;
+        Ok(path)
+    }
+
+    /// Discard the samples held, e.g. so the next dump doesn't overlap
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.full = false;
+    }
+}
@@ -0,0 +1,50 @@
+use super::RingRecorder;
+use std::fs;
+use std::time::Duration;
+
+fn contents(ring: &RingRecorder) -> Vec<u8> {
+    let mut out = Vec::new();
+    ring.dump_to(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_fill() {
+    // 4 IQ pairs
+    let mut ring = RingRecorder::new(4, Duration::from_secs(1));
+    assert!(ring.is_empty());
+    ring.write(&[1, 2, 3, 4]);
+    assert_eq!(vec![1, 2, 3, 4], contents(&ring));
+    assert_eq!(Duration::from_millis(500), ring.duration());
+    ring.write(&[5, 6, 7, 8, 9, 10]);
+    assert_eq!(vec![3, 4, 5, 6, 7, 8, 9, 10], contents(&ring));
+    assert_eq!(8, ring.len());
+    ring.write(&[11, 12]);
+    assert_eq!((&[5, 6, 7, 8][..], &[9, 10, 11, 12][..]), ring.as_slices());
+    assert_eq!(Duration::from_secs(1), ring.duration());
+}
+
+#[test]
+fn test_oversized_write() {
+    let mut ring = RingRecorder::new(2, Duration::from_secs(1));
+    ring.write(&[1, 2]);
+    ring.write(&[3, 4, 5, 6, 7, 8]);
+    assert_eq!(vec![5, 6, 7, 8], contents(&ring));
+    ring.clear();
+    assert!(ring.is_empty());
+    ring.write(&[9, 10]);
+    assert_eq!(vec![9, 10], contents(&ring));
+}
+
+#[test]
+fn test_dump() {
+    let path = std::env::temp_dir().join(format!("rtlsdr-ring-{}.bin", std::process::id()));
+    let mut ring = RingRecorder::new(2, Duration::from_secs(1));
+    ring.write(&[1, 2, 3, 4, 5, 6]);
+    assert_eq!(path, ring.dump(&path).unwrap());
+    assert_eq!(vec![3, 4, 5, 6], fs::read(&path).unwrap());
+    // Still recording after a dump
+    ring.write(&[7, 8]);
+    assert_eq!(vec![5, 6, 7, 8], contents(&ring));
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,190 @@
+//! Unattended recording at set wall-clock times, e.g. for satellite passes.
+//! Each session re-applies its own tuning profile when it starts, so
+//! sessions on different frequencies can share one dongle.
+//!
+//! ```no_run
+//! use rtlsdr_rs::record::schedule::{Profile, Schedule, Session};
+//! use rtlsdr_rs::{RtlSdr, TunerGain};
+//! use std::sync::atomic::AtomicBool;
+//! use std::time::{Duration, SystemTime};
+//!
+//! let mut sdr = RtlSdr::open(0).unwrap();
+//! let noaa_19 = Profile::new(137_100_000, 1_024_000, TunerGain::Manual(400));
+//! let mut schedule = Schedule::new();
+//! schedule
+//!     .add(Session::new(
+//!         SystemTime::now() + Duration::from_secs(3600),
+//!         Duration::from_secs(900),
+//!         noaa_19,
+//!         "noaa19.bin",
+//!     ))
+//!     .unwrap();
+//! let shutdown = AtomicBool::new(false);
+//! schedule
+//!     .run(&mut sdr, &shutdown, |session, path| {
+//!         println!("Recorded {} to {}", session.profile.center_freq, path.display())
+//!     })
+//!     .unwrap();
+//! ```
+
+use super::RawRecorder;
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::units::{Hertz, SampleRate};
+use crate::{RtlSdr, TunerGain, DEFAULT_BUF_LENGTH};
+use log::info;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Longest sleep between shutdown checks while waiting for a session
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Device settings applied at the start of a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Profile {
+    pub center_freq: u32, // Hz
+    pub sample_rate: u32, // Hz
+    pub gain: TunerGain,
+    pub ppm: i32,
+}
+
+impl Profile {
+    pub fn new(
+        center_freq: impl Into<Hertz>,
+        sample_rate: impl Into<SampleRate>,
+        gain: TunerGain,
+    ) -> Profile {
+        Profile {
+            center_freq: center_freq.into().0,
+            sample_rate: sample_rate.into().0,
+            gain,
+            ppm: 0,
+        }
+    }
+
+    pub fn apply(&self, sdr: &mut RtlSdr) -> Result<()> {
+        sdr.set_freq_correction(self.ppm)?;
+        sdr.set_sample_rate(self.sample_rate)?;
+        sdr.set_center_freq(self.center_freq)?;
+        sdr.set_tuner_gain(self.gain)?;
+        sdr.reset_buffer()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    pub start: SystemTime,
+    pub duration: Duration,
+    pub profile: Profile,
+    pub path: PathBuf, // Raw recording, see `RawRecorder`
+}
+
+impl Session {
+    pub fn new<P: AsRef<Path>>(
+        start: SystemTime,
+        duration: Duration,
+        profile: Profile,
+        path: P,
+    ) -> Session {
+        Session {
+            start,
+            duration,
+            profile,
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn end(&self) -> SystemTime {
+        self.start + self.duration
+    }
+}
+
+/// Sessions in start time order, without overlaps
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    sessions: Vec<Session>,
+}
+
+impl Schedule {
+    pub fn new() -> Schedule {
+        Schedule::default()
+    }
+
+    /// Add a session, which may not overlap any already scheduled
+    pub fn add(&mut self, session: Session) -> Result<()> {
+        if session.duration.is_zero() {
+            return Err(RtlsdrErr("Session duration must not be zero".to_string()));
+        }
+        let i = self.sessions.partition_point(|s| s.start < session.start);
+        let overlaps_prev = i > 0 && self.sessions[i - 1].end() > session.start;
+        let overlaps_next = self
+            .sessions
+            .get(i)
+            .is_some_and(|next| session.end() > next.start);
+        if overlaps_prev || overlaps_next {
+            return Err(RtlsdrErr(format!(
+                "Session for {} overlaps another session",
+                session.path.display()
+            )));
+        }
+        self.sessions.insert(i, session);
+        Ok(())
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Sessions that have not ended by `now`, including one in progress
+    pub fn pending(&self, now: SystemTime) -> &[Session] {
+        let i = self.sessions.partition_point(|s| s.end() <= now);
+        &self.sessions[i..]
+    }
+
+    /// Record each pending session in turn, calling `done` with each file
+    /// written. A session already in progress starts immediately and is cut
+    /// short to its scheduled end. Returns early if `shutdown` is set.
+    pub fn run<F>(&self, sdr: &mut RtlSdr, shutdown: &AtomicBool, mut done: F) -> Result<()>
+    where
+        F: FnMut(&Session, &Path),
+    {
+        for session in self.pending(SystemTime::now()) {
+            if !wait_until(session.start, shutdown) {
+                break;
+            }
+            info!(
+                "Recording {} Hz to {}",
+                session.profile.center_freq,
+                session.path.display()
+            );
+            session.profile.apply(sdr)?;
+            let mut recorder = RawRecorder::new(&session.path);
+            let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+            while SystemTime::now() < session.end() && !shutdown.load(Ordering::Relaxed) {
+                let n = sdr.read_sync(&mut buf)?;
+                recorder.write(&buf[..n])?;
+            }
+            if let Some(path) = recorder.close()? {
+                done(session, &path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sleep until `time`, returning false if `shutdown` was set first
+pub(super) fn wait_until(time: SystemTime, shutdown: &AtomicBool) -> bool {
+    loop {
+        if shutdown.load(Ordering::Relaxed) {
+            return false;
+        }
+        match time.duration_since(SystemTime::now()) {
+            Ok(wait) if !wait.is_zero() => thread::sleep(wait.min(POLL_INTERVAL)),
+            _ => return true,
+        }
+    }
+}
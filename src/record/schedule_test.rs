@@ -0,0 +1,71 @@
+use super::schedule::{wait_until, Profile, Schedule, Session};
+use crate::TunerGain;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+fn session(start: u64, duration: u64, path: &str) -> Session {
+    Session::new(
+        UNIX_EPOCH + Duration::from_secs(start),
+        Duration::from_secs(duration),
+        Profile::new(137_100_000, 1_024_000, TunerGain::Auto),
+        path,
+    )
+}
+
+fn paths(sessions: &[Session]) -> Vec<String> {
+    sessions
+        .iter()
+        .map(|s| s.path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn test_schedule_order() {
+    let mut schedule = Schedule::new();
+    schedule.add(session(200, 50, "b")).unwrap();
+    schedule.add(session(100, 100, "a")).unwrap();
+    schedule.add(session(300, 10, "c")).unwrap();
+    assert_eq!(vec!["a", "b", "c"], paths(schedule.sessions()));
+}
+
+#[test]
+fn test_schedule_overlap() {
+    let mut schedule = Schedule::new();
+    schedule.add(session(100, 100, "a")).unwrap();
+    assert!(schedule.add(session(150, 10, "inside")).is_err());
+    assert!(schedule.add(session(50, 51, "before")).is_err());
+    assert!(schedule.add(session(199, 10, "after")).is_err());
+    assert!(schedule.add(session(300, 0, "empty")).is_err());
+    assert_eq!(1, schedule.sessions().len());
+}
+
+#[test]
+fn test_schedule_pending() {
+    let mut schedule = Schedule::new();
+    schedule.add(session(100, 100, "a")).unwrap();
+    schedule.add(session(200, 50, "b")).unwrap();
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(vec!["a", "b"], paths(schedule.pending(at(0))));
+    // In progress
+    assert_eq!(vec!["a", "b"], paths(schedule.pending(at(199))));
+    assert_eq!(vec!["b"], paths(schedule.pending(at(200))));
+    assert!(schedule.pending(at(250)).is_empty());
+}
+
+#[test]
+fn test_wait_until() {
+    let start = Instant::now();
+    let shutdown = AtomicBool::new(false);
+    assert!(wait_until(
+        SystemTime::now() + Duration::from_millis(30),
+        &shutdown
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert!(wait_until(UNIX_EPOCH, &shutdown));
+
+    let shutdown = AtomicBool::new(true);
+    assert!(!wait_until(
+        SystemTime::now() + Duration::from_secs(3600),
+        &shutdown
+    ));
+}
@@ -0,0 +1,88 @@
+//! SigMF metadata for raw recordings, readable by `source::SigmfReader`
+
+use super::part_name;
+use crate::error::Result;
+use crate::scan::power::civil_from_days;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub(super) const META_EXTENSION: &str = "sigmf-meta";
+
+/// Samples lost while recording a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Gap {
+    pub sample: u64,  // Index in the file where the samples are missing
+    pub filled: u64,  // Placeholder samples written in their place
+    pub missing: u64, // Samples lost, 0 if unknown
+}
+
+/// Name of the metadata file for a data file
+pub(super) fn meta_name(data: &Path) -> PathBuf {
+    data.with_extension(META_EXTENSION)
+}
+
+/// Write the metadata for a cu8 data file, with an annotation per gap
+pub(super) fn write_meta(
+    data: &Path,
+    sample_rate: u32,
+    center_freq: u32,
+    start: SystemTime,
+    gaps: &[Gap],
+) -> Result<()> {
+    let annotations: Vec<Value> = gaps
+        .iter()
+        .map(|gap| {
+            let comment = match gap.missing {
+                0 => "Unknown number of samples lost".to_string(),
+                n => format!("{} samples lost", n),
+            };
+            json!({
+                "core:sample_start": gap.sample,
+                "core:sample_count": gap.filled,
+                "core:label": "gap",
+                "core:comment": comment,
+            })
+        })
+        .collect();
+    let meta = json!({
+        "global": {
+            "core:datatype": "cu8",
+            "core:sample_rate": sample_rate,
+            "core:version": "1.0.0",
+            "core:recorder": "rtlsdr-rs",
+        },
+        "captures": [{
+            "core:sample_start": 0,
+            "core:frequency": center_freq,
+            "core:datetime": datetime(start),
+        }],
+        "annotations": annotations,
+    });
+    let name = meta_name(data);
+    let text = serde_json::to_string_pretty(&meta).map_err(std::io::Error::from)?;
+    fs::write(part_name(&name), text)?;
+    fs::rename(part_name(&name), name)?;
+    Ok(())
+}
+
+/// ISO 8601 UTC time as SigMF expects
+fn datetime(time: SystemTime) -> String {
+    let time = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = time.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_millis()
+    )
+}
@@ -0,0 +1,199 @@
+//! IQ recording as 2-channel WAV with the `auxi` chunk HDSDR and SDR# read
+//! for the center frequency and start time. Files that outgrow the 4 GB
+//! RIFF limit are converted to RF64 (EBU Tech 3306) when closed.
+
+use super::part_name;
+use crate::dsp::encode;
+use crate::error::Result;
+use crate::scan::power::civil_from_days;
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Chunk offsets in the header written by `WavRecorder::create`
+const JUNK_OFFSET: u64 = 12; // Reserved for the RF64 ds64 chunk
+const DS64_LEN: u32 = 28;
+const FMT_OFFSET: u64 = JUNK_OFFSET + 8 + DS64_LEN as u64;
+const AUXI_OFFSET: u64 = FMT_OFFSET + 8 + 16;
+const AUXI_LEN: u32 = 68;
+const DATA_OFFSET: u64 = AUXI_OFFSET + 8 + AUXI_LEN as u64;
+const HEADER_LEN: u64 = DATA_OFFSET + 8;
+
+/// Sample format of the WAV data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WavFormat {
+    U8,  // Raw samples as written by the device, half the size
+    I16, // Signed 16-bit, read by more software
+}
+
+impl WavFormat {
+    fn bits(self) -> u16 {
+        match self {
+            WavFormat::U8 => 8,
+            WavFormat::I16 => 16,
+        }
+    }
+}
+
+pub struct WavRecorder {
+    file: Option<BufWriter<File>>,
+    path: PathBuf,
+    format: WavFormat,
+    rate: u32,
+    center_freq: u32,
+    started: SystemTime,
+    data_len: u64,   // Bytes of sample data written
+    riff_limit: u64, // Largest RIFF size before switching to RF64
+}
+
+impl WavRecorder {
+    /// Create `path` (written as `path.part` until closed) for raw samples
+    /// captured at `rate` and `center_freq`
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        rate: u32,
+        center_freq: u32,
+        format: WavFormat,
+    ) -> Result<WavRecorder> {
+        let path = path.as_ref().to_path_buf();
+        let mut recorder = WavRecorder {
+            file: Some(BufWriter::new(File::create(part_name(&path))?)),
+            path,
+            format,
+            rate,
+            center_freq,
+            started: SystemTime::now(),
+            data_len: 0,
+            riff_limit: u32::MAX as u64,
+        };
+        recorder.write_header(SystemTime::now())?;
+        Ok(recorder)
+    }
+
+    /// Append raw interleaved u8 IQ samples as read from the device
+    pub fn write(&mut self, buf: &[u8]) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        match self.format {
+            WavFormat::U8 => file.write_all(buf)?,
+            WavFormat::I16 => {
+                let mut samples = Vec::with_capacity(2 * buf.len());
+                encode::<i16>(buf, &mut samples);
+                file.write_all(&samples)?;
+            }
+        }
+        self.data_len += buf.len() as u64 * (self.format.bits() / 8) as u64;
+        Ok(())
+    }
+
+    /// Fill in the chunk sizes and stop time, and move the file to its
+    /// final name
+    pub fn close(&mut self) -> Result<Option<PathBuf>> {
+        if self.file.is_none() {
+            return Ok(None);
+        }
+        self.write_header(SystemTime::now())?;
+        if let Some(file) = self.file.take() {
+            let file = file.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+        }
+        fs::rename(part_name(&self.path), &self.path)?;
+        Ok(Some(self.path.clone()))
+    }
+
+    #[cfg(test)]
+    pub(super) fn set_riff_limit(&mut self, limit: u64) {
+        self.riff_limit = limit;
+    }
+
+    fn write_header(&mut self, stopped: SystemTime) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let riff_len = HEADER_LEN - 8 + self.data_len;
+        let rf64 = riff_len > self.riff_limit;
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(if rf64 { b"RF64" } else { b"RIFF" });
+        header.extend_from_slice(&(if rf64 { u32::MAX } else { riff_len as u32 }).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+
+        // ds64 holds the real sizes in RF64 files, otherwise the space is
+        // kept as a JUNK chunk so the header can be converted in place
+        header.extend_from_slice(if rf64 { b"ds64" } else { b"JUNK" });
+        header.extend_from_slice(&DS64_LEN.to_le_bytes());
+        if rf64 {
+            let frames = self.data_len / (2 * self.format.bits() as u64 / 8);
+            header.extend_from_slice(&riff_len.to_le_bytes());
+            header.extend_from_slice(&self.data_len.to_le_bytes());
+            header.extend_from_slice(&frames.to_le_bytes());
+            header.extend_from_slice(&0_u32.to_le_bytes());
+        } else {
+            header.extend_from_slice(&[0; DS64_LEN as usize]);
+        }
+
+        let block_align = 2 * self.format.bits() / 8;
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&16_u32.to_le_bytes());
+        header.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+        header.extend_from_slice(&2_u16.to_le_bytes()); // I and Q channels
+        header.extend_from_slice(&self.rate.to_le_bytes());
+        header.extend_from_slice(&(self.rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&self.format.bits().to_le_bytes());
+
+        header.extend_from_slice(b"auxi");
+        header.extend_from_slice(&AUXI_LEN.to_le_bytes());
+        header.extend_from_slice(&system_time(self.started));
+        header.extend_from_slice(&system_time(stopped));
+        header.extend_from_slice(&self.center_freq.to_le_bytes());
+        header.extend_from_slice(&self.rate.to_le_bytes()); // ADC frequency
+        header.extend_from_slice(&0_u32.to_le_bytes()); // IF frequency
+        header.extend_from_slice(&self.rate.to_le_bytes()); // Bandwidth
+        header.extend_from_slice(&[0; 20]); // IQ offset, unused
+
+        header.extend_from_slice(b"data");
+        let data_len = if rf64 { u32::MAX } else { self.data_len as u32 };
+        header.extend_from_slice(&data_len.to_le_bytes());
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+}
+
+impl Drop for WavRecorder {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// Windows SYSTEMTIME (UTC) as stored in the auxi chunk
+fn system_time(time: SystemTime) -> [u8; 16] {
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let days = since_epoch.as_secs() / 86400;
+    let secs = since_epoch.as_secs() % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    // 1970-01-01 was a Thursday
+    let weekday = (days + 4) % 7;
+    let fields = [
+        year as u16,
+        month as u16,
+        weekday as u16,
+        day as u16,
+        (secs / 3600) as u16,
+        (secs / 60 % 60) as u16,
+        (secs % 60) as u16,
+        since_epoch.subsec_millis() as u16,
+    ];
+    let mut out = [0; 16];
+    for (o, f) in out.chunks_exact_mut(2).zip(fields) {
+        o.copy_from_slice(&f.to_le_bytes());
+    }
+    out
+}
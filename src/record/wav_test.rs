@@ -0,0 +1,102 @@
+use super::wav::{WavFormat, WavRecorder};
+use std::fs;
+use std::path::PathBuf;
+
+fn test_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtlsdr-wav-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("capture.wav")
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+}
+
+/// Position of the first chunk with the given id after the RIFF header
+fn chunk(buf: &[u8], id: &[u8]) -> usize {
+    let mut pos = 12;
+    while &buf[pos..pos + 4] != id {
+        pos += 8 + u32_at(buf, pos + 4) as usize;
+    }
+    pos
+}
+
+#[test]
+fn test_u8_wav() {
+    let path = test_path("u8");
+    let mut wav = WavRecorder::create(&path, 2_048_000, 100_000_000, WavFormat::U8).unwrap();
+    wav.write(&[1, 2, 3, 4]).unwrap();
+    wav.write(&[5, 6]).unwrap();
+    assert!(!path.exists());
+    assert_eq!(Some(path.clone()), wav.close().unwrap());
+
+    let buf = fs::read(&path).unwrap();
+    assert_eq!(b"RIFF", &buf[0..4]);
+    assert_eq!(buf.len() - 8, u32_at(&buf, 4) as usize);
+    assert_eq!(b"WAVE", &buf[8..12]);
+
+    let fmt = chunk(&buf, b"fmt ");
+    assert_eq!(2, u16_at(&buf, fmt + 10));
+    assert_eq!(2_048_000, u32_at(&buf, fmt + 12));
+    assert_eq!(2, u16_at(&buf, fmt + 20));
+    assert_eq!(8, u16_at(&buf, fmt + 22));
+
+    let auxi = chunk(&buf, b"auxi");
+    assert!(u16_at(&buf, auxi + 8) >= 2024);
+    assert_eq!(100_000_000, u32_at(&buf, auxi + 8 + 32));
+
+    let data = chunk(&buf, b"data");
+    assert_eq!(6, u32_at(&buf, data + 4));
+    assert_eq!(&[1, 2, 3, 4, 5, 6], &buf[data + 8..]);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_i16_wav() {
+    let path = test_path("i16");
+    let mut wav = WavRecorder::create(&path, 1_000_000, 7_000_000, WavFormat::I16).unwrap();
+    wav.write(&[127, 255, 0, 128]).unwrap();
+    drop(wav);
+
+    let buf = fs::read(&path).unwrap();
+    let fmt = chunk(&buf, b"fmt ");
+    assert_eq!(4, u16_at(&buf, fmt + 20));
+    assert_eq!(16, u16_at(&buf, fmt + 22));
+    let data = chunk(&buf, b"data");
+    let samples: Vec<i16> = buf[data + 8..]
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    // Full scale positive clips rather than wrapping
+    assert_eq!(vec![0, i16::MAX, -127 << 8, 1 << 8], samples);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_rf64_conversion() {
+    let path = test_path("rf64");
+    let mut wav = WavRecorder::create(&path, 1_000_000, 7_000_000, WavFormat::U8).unwrap();
+    // Pretend the RIFF limit is just past the header
+    wav.set_riff_limit(200);
+    wav.write(&[127; 200]).unwrap();
+    wav.close().unwrap();
+
+    let buf = fs::read(&path).unwrap();
+    assert_eq!(b"RF64", &buf[0..4]);
+    assert_eq!(u32::MAX, u32_at(&buf, 4));
+    assert_eq!(b"ds64", &buf[12..16]);
+    let riff_len = u64::from_le_bytes(buf[20..28].try_into().unwrap());
+    let data_len = u64::from_le_bytes(buf[28..36].try_into().unwrap());
+    let frames = u64::from_le_bytes(buf[36..44].try_into().unwrap());
+    assert_eq!(buf.len() as u64 - 8, riff_len);
+    assert_eq!(200, data_len);
+    assert_eq!(100, frames);
+    let data = chunk(&buf, b"data");
+    assert_eq!(u32::MAX, u32_at(&buf, data + 4));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
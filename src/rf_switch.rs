@@ -0,0 +1,73 @@
+//! Named RF switch ports (antenna selection, noise source control) driven
+//! through the RTL2832 GPIOs
+//!
+//! ```no_run
+//! use rtlsdr_rs::{rf_switch::RfSwitch, RtlSdr};
+//! use std::time::Duration;
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! // Antenna select on GPIO 1, noise source on GPIO 2
+//! let mut switch = RfSwitch::new(0b110, Duration::from_millis(20));
+//! switch.add_port("antenna_a", 0b000).unwrap();
+//! switch.add_port("antenna_b", 0b010).unwrap();
+//! switch.add_port("noise_on", 0b100).unwrap();
+//! switch.select(&sdr, "noise_on").unwrap();
+//! ```
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::RtlSdr;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RfSwitch {
+    mask: u8,         // GPIOs controlled by the switch
+    settle: Duration, // Time to wait after switching before sampling
+    ports: Vec<(String, u8)>,
+    current: Option<usize>,
+}
+
+impl RfSwitch {
+    pub fn new(mask: u8, settle: Duration) -> RfSwitch {
+        RfSwitch {
+            mask,
+            settle,
+            ports: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Add a named port selected by driving the switch GPIOs to `pattern`
+    pub fn add_port(&mut self, name: &str, pattern: u8) -> Result<()> {
+        if pattern & !self.mask != 0 {
+            return Err(RtlsdrErr(format!(
+                "Port {} pattern {:#010b} uses GPIOs outside mask {:#010b}",
+                name, pattern, self.mask
+            )));
+        }
+        match self.ports.iter_mut().find(|(n, _)| n == name) {
+            Some(port) => port.1 = pattern,
+            None => self.ports.push((name.to_string(), pattern)),
+        }
+        Ok(())
+    }
+
+    /// Switch to the named port and wait for it to settle
+    pub fn select(&mut self, sdr: &RtlSdr, name: &str) -> Result<()> {
+        let index = self
+            .ports
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| RtlsdrErr(format!("Unknown RF switch port: {}", name)))?;
+        sdr.set_gpio_pattern(self.mask, self.ports[index].1)?;
+        self.current = Some(index);
+        thread::sleep(self.settle);
+        Ok(())
+    }
+
+    /// Name of the most recently selected port
+    pub fn current(&self) -> Option<&str> {
+        self.current.map(|i| self.ports[i].0.as_str())
+    }
+}
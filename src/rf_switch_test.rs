@@ -0,0 +1,83 @@
+use crate::device::mock_device_handle::MockDeviceHandle;
+use crate::device::{Device, BLOCK_SYS, GPO};
+use crate::rf_switch::RfSwitch;
+use crate::{RtlSdr, Sdr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// An unopened RtlSdr whose GPIO output register reads back the last value
+/// written to it, shared as the second value. Other registers read zero.
+fn gpio_sdr() -> (RtlSdr, Arc<Mutex<u8>>) {
+    let gpo = Arc::new(Mutex::new(0));
+    let (read, write) = (gpo.clone(), gpo.clone());
+    let mut mock_handle = MockDeviceHandle::new();
+    mock_handle
+        .expect_read_control()
+        .returning(move |_, _, value, index, data, _| {
+            data.fill(0);
+            if (value, index) == (GPO, BLOCK_SYS << 8) {
+                data[0] = *read.lock().unwrap();
+            }
+            Ok(data.len())
+        });
+    mock_handle
+        .expect_write_control()
+        .returning(move |_, _, value, index, data, _| {
+            if (value, index) == (GPO, (BLOCK_SYS << 8) | 0x10) {
+                *write.lock().unwrap() = data[0];
+            }
+            Ok(data.len())
+        });
+    let sdr = Sdr::new(Device::from_handle(mock_handle));
+    let sdr = RtlSdr {
+        device: sdr.device(),
+        sdr: Mutex::new(sdr),
+        index: 0,
+        recording: None,
+    };
+    (sdr, gpo)
+}
+
+#[test]
+fn test_add_port_mask() {
+    let mut switch = RfSwitch::new(0b110, Duration::ZERO);
+    switch.add_port("antenna_a", 0b000).unwrap();
+    switch.add_port("antenna_b", 0b110).unwrap();
+    assert!(switch.add_port("bias_tee", 0b001).is_err());
+    assert!(switch.add_port("noise_on", 0b1100).is_err());
+}
+
+#[test]
+fn test_replace_port() {
+    let (sdr, gpo) = gpio_sdr();
+    let mut switch = RfSwitch::new(0b110, Duration::ZERO);
+    switch.add_port("antenna", 0b010).unwrap();
+    switch.add_port("antenna", 0b100).unwrap();
+    switch.select(&sdr, "antenna").unwrap();
+    assert_eq!(0b100, *gpo.lock().unwrap());
+    // A rejected pattern leaves the existing port alone
+    assert!(switch.add_port("antenna", 0b001).is_err());
+    switch.select(&sdr, "antenna").unwrap();
+    assert_eq!(0b100, *gpo.lock().unwrap());
+}
+
+#[test]
+fn test_current() {
+    let (sdr, gpo) = gpio_sdr();
+    // GPIO 0 is outside the switch and keeps its level
+    *gpo.lock().unwrap() = 0b001;
+    let mut switch = RfSwitch::new(0b110, Duration::ZERO);
+    switch.add_port("antenna_a", 0b000).unwrap();
+    switch.add_port("noise_on", 0b100).unwrap();
+    assert_eq!(None, switch.current());
+
+    assert!(switch.select(&sdr, "antenna_c").is_err());
+    assert_eq!(None, switch.current());
+
+    switch.select(&sdr, "noise_on").unwrap();
+    assert_eq!(Some("noise_on"), switch.current());
+    assert_eq!(0b101, *gpo.lock().unwrap());
+    switch.select(&sdr, "antenna_a").unwrap();
+    assert_eq!(Some("antenna_a"), switch.current());
+    assert_eq!(0b001, *gpo.lock().unwrap());
+}
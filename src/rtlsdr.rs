@@ -1,83 +1,172 @@
-use super::{DirectSampleMode, TunerGain};
+use super::{AdcInput, DirectSampleMode, Fir, NotchFilter, Settings, TrackingFilter, TunerGain};
+use crate::device::demod::{
+    ADC_EN, ADJ_CHAN_REJ, AGC_LOOP, DDC_LEN, EN_DAGC, FIR_COEFF, FSM_STATE, PID_FILTER, SDR_MODE,
+};
 use crate::device::{
-    Device, BLOCK_SYS, BLOCK_USB, DEMOD_CTL, DEMOD_CTL_1, EEPROM_SIZE, GPD, GPO, GPOE, USB_EPA_CTL,
-    USB_EPA_MAXPKT, USB_SYSCTL,
+    Device, RegWidth, WriteVerify, BLOCK_IRB, BLOCK_SYS, BLOCK_USB, DEMOD_CTL, DEMOD_CTL_1,
+    EEPROM_SIZE, GPD, GPO, GPOE, IR_GLITCH_LEN, IR_IDLE_LEN0, IR_IDLE_LEN1, IR_MAX_DURATION0,
+    IR_MAX_DURATION1, IR_MAX_H_TOL_LEN, IR_MAX_L_TOL_LEN, IR_RX_BC, IR_RX_BUF, IR_RX_BUF_CTRL,
+    IR_RX_CFG, IR_RX_CLK, IR_RX_CTRL, IR_RX_IF, USB_CTRL, USB_EPA_CTL, USB_EPA_MAXPKT, USB_SYSCTL,
 };
-use crate::error::Result;
-use crate::error::RtlsdrError::RtlsdrErr;
+use crate::dsp::{dbfs_to_dbm, power_dbfs};
+use crate::eeprom::{self, Calibration, Eeprom, EepromOverrides};
+use crate::error::RtlsdrError::{PllNotLocked, RtlsdrErr};
+use crate::error::{PllLockError, Result, SampleRateError};
 use crate::tuners::r820t::{R820T, R82XX_IF_FREQ, TUNER_ID};
-use crate::tuners::{NoTuner, Tuner, KNOWN_TUNERS};
-use log::{error, info};
+use crate::tuners::{NoTuner, Tuner, TunerInfo, KNOWN_TUNERS};
+use log::{error, info, warn};
+use std::cell::Cell;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 const INTERFACE_ID: u8 = 0;
 
 const DEF_RTL_XTAL_FREQ: u32 = 28_800_000;
 const MIN_RTL_XTAL_FREQ: u32 = DEF_RTL_XTAL_FREQ - 1000;
 const MAX_RTL_XTAL_FREQ: u32 = DEF_RTL_XTAL_FREQ + 1000;
+// Crystals the R820T PLL supports
+const MIN_TUNER_XTAL_FREQ: u32 = 16_000_000;
+const MAX_TUNER_XTAL_FREQ: u32 = 40_000_000;
+
+/// Sample rates supported by the RTL2832 resampler, in Hz
+pub const SAMPLE_RATE_RANGES: &[RangeInclusive<u32>] = &[225_001..=300_000, 900_001..=3_200_000];
 
-pub(crate) const FIR_LEN: usize = 16;
-const DEFAULT_FIR: &'static [i32; FIR_LEN] = &[
+pub const FIR_LEN: usize = 16;
+const DEFAULT_FIR: &[i32; FIR_LEN] = &[
     -54, -36, -41, -40, -32, -14, 14, 53, // i8
     101, 156, 215, 273, 327, 372, 404, 421, // i12
 ];
+// Kaiser-windowed sinc presets with the same DC gain as the default
+const NARROWBAND_FIR: &[i32; FIR_LEN] = &[
+    -2, -1, 4, 12, 24, 42, 65, 93, // i8
+    125, 160, 196, 231, 262, 288, 306, 316, // i12
+];
+const WIDEBAND_FLAT_FIR: &[i32; FIR_LEN] = &[
+    -3, -9, -18, -28, -36, -38, -30, -6, // i8
+    36, 96, 171, 255, 340, 415, 472, 502, // i12
+];
+
+// (block, register, value, mask) sequence to start the IR receiver
+const IR_INIT: &[(u16, u16, u8, u8)] = &[
+    (BLOCK_SYS, DEMOD_CTL_1, 0x00, 0x04),
+    (BLOCK_SYS, DEMOD_CTL_1, 0x00, 0x08),
+    (BLOCK_USB, USB_CTRL, 0x20, 0x20),
+    (BLOCK_SYS, GPD, 0x00, 0x08),
+    (BLOCK_SYS, GPOE, 0x08, 0x08),
+    (BLOCK_SYS, GPO, 0x08, 0x08),
+    (BLOCK_IRB, IR_MAX_DURATION0, 0xd0, 0xff),
+    (BLOCK_IRB, IR_MAX_DURATION1, 0x07, 0xff),
+    (BLOCK_IRB, IR_IDLE_LEN0, 0xc0, 0xff),
+    (BLOCK_IRB, IR_IDLE_LEN1, 0x00, 0xff),
+    (BLOCK_IRB, IR_GLITCH_LEN, 0x03, 0xff),
+    (BLOCK_IRB, IR_RX_CLK, 0x09, 0xff),
+    (BLOCK_IRB, IR_RX_CFG, 0x1c, 0xff),
+    (BLOCK_IRB, IR_MAX_H_TOL_LEN, 0x1e, 0xff),
+    (BLOCK_IRB, IR_MAX_L_TOL_LEN, 0x1e, 0xff),
+    (BLOCK_IRB, IR_RX_CTRL, 0x80, 0xff),
+];
+// Sequence to release the IR buffer so it can capture the next code
+const IR_REFRESH: &[(u16, u16, u8, u8)] = &[
+    (BLOCK_IRB, IR_RX_IF, 0x03, 0xff),
+    (BLOCK_IRB, IR_RX_BUF_CTRL, 0x80, 0xff),
+    (BLOCK_IRB, IR_RX_CTRL, 0x80, 0xff),
+];
+
+/// How `init` handles the EEPROM configuration and a missing tuner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Fail if the EEPROM configuration can't be read
+    pub require_eeprom: bool,
+    /// Continue in direct sampling mode without a tuner
+    pub allow_no_tuner: bool,
+    /// Apply the bias tee and direct sampling the EEPROM forces on
+    pub honor_eeprom_overrides: bool,
+}
 
 #[derive(Debug)]
 pub struct RtlSdr {
-    handle: Device,
+    handle: Arc<Device>,
     tuner: Box<dyn Tuner>,
-    freq: u32, // Hz
-    rate: u32, // Hz
+    freq: u32,       // Hz
+    rate: u32,       // Hz
+    rate_exact: f64, // Hz
     bw: u32,
     direct_sampling: DirectSampleMode,
+    auto_ds_threshold: Option<u32>, // Hz
     xtal: u32,
     tuner_xtal: u32,
-    ppm_correction: u32,
     offset_freq: u32,
     corr: i32, // PPM
     force_bt: bool,
     force_ds: bool,
+    eeprom_overrides: EepromOverrides,
+    options: OpenOptions,
+    ir_active: bool,
     fir: [i32; FIR_LEN],
+    gain: TunerGain,
+    gain_offset: i16, // Tenths of a dB, from the stored calibration
+    agc: bool,
+    bias_tee: Cell<bool>,    // GPIO 0
+    i2c_session: Cell<bool>, // Repeater held on by with_i2c_repeater
 }
 
 impl RtlSdr {
     pub fn new(handle: Device) -> Self {
         RtlSdr {
-            handle: handle,
+            handle: Arc::new(handle),
             tuner: Box::new(NoTuner {}),
             freq: 0,
             rate: 0,
+            rate_exact: 0.0,
             bw: 0,
-            ppm_correction: 0,
             xtal: DEF_RTL_XTAL_FREQ,
             tuner_xtal: DEF_RTL_XTAL_FREQ,
             direct_sampling: DirectSampleMode::Off,
+            auto_ds_threshold: None,
             offset_freq: 0,
             corr: 0,
             force_bt: false,
             force_ds: false,
+            eeprom_overrides: EepromOverrides::default(),
+            options: OpenOptions::default(),
+            ir_active: false,
             fir: *DEFAULT_FIR,
+            gain: TunerGain::Auto,
+            gain_offset: 0,
+            agc: false,
+            bias_tee: Cell::new(false),
+            i2c_session: Cell::new(false),
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(tuner = tracing::field::Empty), err)
+    )]
     pub fn init(&mut self) -> Result<()> {
         self.handle.claim_interface(INTERFACE_ID)?;
         self.handle.test_write()?;
         self.init_baseband()?;
         self.set_i2c_repeater(true)?;
 
-        self.tuner = {
-            let tuner_id = match self.search_tuner() {
-                Some(tid) => {
-                    info!("Got tuner ID {}", tid);
-                    tid
-                }
-                None => {
-                    panic!("Failed to find tuner, aborting");
+        let tuner_id = self.search_tuner();
+        let no_tuner = tuner_id.is_none();
+        self.tuner = match tuner_id {
+            Some(tid) => {
+                info!("Got tuner ID {}", tid);
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("tuner", tid);
+                match tid {
+                    TUNER_ID => Box::new(R820T::new(&self.handle)),
+                    tid => return Err(RtlsdrErr(format!("Unsupported tuner {}", tid))),
                 }
-            };
-            match tuner_id {
-                TUNER_ID => Box::new(R820T::new(&mut self.handle)),
-                _ => panic!("Unable to find recognized tuner"),
+            }
+            None if self.options.allow_no_tuner => {
+                warn!("Failed to find tuner, continuing in direct sampling mode");
+                Box::new(NoTuner {})
+            }
+            None => {
+                return Err(RtlsdrErr("Failed to find tuner".to_string()));
             }
         };
         // Use the RTL clock value by default
@@ -85,42 +174,126 @@ impl RtlSdr {
         self.tuner.set_xtal_freq(self.get_tuner_xtal_freq())?;
 
         // disable Zero-IF mode
-        self.handle.demod_write_reg(1, 0xb1, 0x1a, 1)?;
+        self.handle.set_zero_if(false)?;
 
         // only enable In-phase ADC input
-        self.handle.demod_write_reg(0, 0x08, 0x4d, 1)?;
+        self.set_adc_input(AdcInput::I)?;
 
         // the R82XX use 3.57 MHz IF for the DVB-T 6 MHz mode, and
         // 4.57 MHz for the 8 MHz mode
         self.set_if_freq(R82XX_IF_FREQ)?;
 
         // enable spectrum inversion
-        self.handle.demod_write_reg(1, 0x15, 0x01, 1)?;
-
-        // Hack to force the Bias T to always be on if we set the IR-Endpoint bit in the EEPROM to 0. Default on EEPROM is 1.
-        let mut buf: [u8; EEPROM_SIZE] = [0; EEPROM_SIZE];
-        self.handle.read_eeprom(&mut buf, 0, EEPROM_SIZE)?;
-        if buf[7] & 0x02 != 0 {
-            self.force_bt = false;
-        } else {
-            self.force_bt = true;
+        self.handle.set_spectrum_inversion(true)?;
+
+        let mut calibration = None;
+        match self.read_eeprom_config() {
+            Ok(eeprom) => {
+                self.eeprom_overrides = eeprom.overrides();
+                if self.options.honor_eeprom_overrides {
+                    // Hack to force the Bias T to always be on if we set the IR-Endpoint bit in the EEPROM to 0. Default on EEPROM is 1.
+                    self.force_bt = !eeprom.enable_ir;
+                    // Hack to force direct sampling mode to always be on if we set the remote-enabled bit in the EEPROM to 1. Default on EEPROM is 0.
+                    self.force_ds = eeprom.remote_wakeup;
+                } else if self.eeprom_overrides != EepromOverrides::default() {
+                    info!("Not honoring EEPROM overrides {:?}", self.eeprom_overrides);
+                }
+                calibration = eeprom.calibration;
+            }
+            Err(e) if self.options.require_eeprom => return Err(e),
+            Err(e) => warn!(
+                "Ignoring EEPROM configuration, no forced bias tee or direct sampling: {}",
+                e
+            ),
         }
-        // Hack to force direct sampling mode to always be on if we set the remote-enabled bit in the EEPROM to 1. Default on EEPROM is 0.
-        if buf[7] & 0x01 != 0 {
-            self.force_ds = true;
-        } else {
-            self.force_ds = false;
+        // Forced direct sampling never uses the tuner, so as librtlsdr does
+        // treat it as missing instead of initializing it
+        if self.force_ds {
+            info!("Direct sampling forced by the EEPROM, skipping tuner init");
+            self.tuner = Box::new(NoTuner {});
         }
-        // TODO: if(force_ds){tuner_type = TUNER_UNKNOWN}
         info!("Init tuner");
         self.tuner.init(&self.handle)?;
 
         // Finished Init
         self.set_i2c_repeater(false)?;
+
+        // Only direct sampling receives without a tuner, as librtlsdr falls
+        // back to for unknown tuners
+        if no_tuner || self.force_ds {
+            self.apply_direct_sampling(DirectSampleMode::On)?;
+        }
+
+        if let Some(cal) = calibration {
+            info!("Applying stored calibration of {} ppm", cal.ppm);
+            self.set_freq_correction(cal.ppm)?;
+            self.gain_offset = cal.gain_offset;
+        }
         info!("Init complete");
         Ok(())
     }
 
+    pub fn load_calibration(&self) -> Result<Option<Calibration>> {
+        Ok(self.read_eeprom_config()?.calibration)
+    }
+
+    pub fn save_calibration(&self, calibration: Option<Calibration>) -> Result<()> {
+        let mut eeprom = self.read_eeprom_config()?;
+        eeprom.calibration = calibration;
+        self.write_eeprom_config(&eeprom)
+    }
+
+    pub fn read_eeprom_config(&self) -> Result<Eeprom> {
+        let mut buf: [u8; EEPROM_SIZE] = [0; EEPROM_SIZE];
+        self.handle.read_eeprom(&mut buf, 0, EEPROM_SIZE)?;
+        Eeprom::parse(&buf)
+    }
+
+    /// Update the EEPROM with the given configuration
+    pub fn write_eeprom_config(&self, eeprom: &Eeprom) -> Result<()> {
+        let mut buf: [u8; EEPROM_SIZE] = [0; EEPROM_SIZE];
+        self.handle.read_eeprom(&mut buf, 0, EEPROM_SIZE)?;
+        eeprom.encode(&mut buf)?;
+        self.handle.write_eeprom(&buf, 0)?;
+        Ok(())
+    }
+
+    /// Set the `mask` bits of the EEPROM flags byte to those of `flags`,
+    /// reading and writing back only that byte
+    pub fn write_eeprom_flags(&self, mask: u8, flags: u8) -> Result<()> {
+        let mut buf = [0_u8; eeprom::FLAGS_OFFSET + 1];
+        let len = buf.len();
+        self.handle.read_eeprom(&mut buf, 0, len)?;
+        eeprom::set_flags(&mut buf, mask, flags)?;
+        self.handle
+            .write_eeprom(&buf[eeprom::FLAGS_OFFSET..], eeprom::FLAGS_OFFSET as u8)?;
+        Ok(())
+    }
+
+    pub fn read_eeprom_image(&self) -> Result<[u8; EEPROM_SIZE]> {
+        let mut buf: [u8; EEPROM_SIZE] = [0; EEPROM_SIZE];
+        self.handle.read_eeprom(&mut buf, 0, EEPROM_SIZE)?;
+        Ok(buf)
+    }
+
+    /// Overwrite the whole EEPROM, refusing images without a valid header
+    pub fn write_eeprom_image(&self, image: &[u8]) -> Result<()> {
+        if image.len() != EEPROM_SIZE {
+            return Err(RtlsdrErr(format!(
+                "EEPROM image is {} bytes, expected {}",
+                image.len(),
+                EEPROM_SIZE
+            )));
+        }
+        Eeprom::parse(image)?;
+        self.handle.write_eeprom(image, 0)?;
+        Ok(())
+    }
+
+    pub fn get_tuner_info(&self) -> Result<TunerInfo> {
+        self.tuner.get_info()
+    }
+
     pub fn get_tuner_gains(&self) -> Result<Vec<i32>> {
         self.tuner.get_gains()
     }
@@ -130,14 +303,112 @@ impl RtlSdr {
         self.set_i2c_repeater(true)?;
         self.tuner.set_gain(&self.handle, gain)?;
         self.set_i2c_repeater(false)?;
+        self.gain = gain;
         Ok(())
     }
 
-    // TODO: set_bias_tee
+    /// Set IF gain `stage` to the step closest to `gain` tenths of a dB
+    pub fn set_tuner_if_gain(&mut self, stage: u8, gain: i32) -> Result<()> {
+        let stages = self.tuner.get_info()?.caps.if_gain_stages;
+        if stage >= stages {
+            return Err(RtlsdrErr(format!(
+                "No IF gain stage {}, the tuner has {}",
+                stage, stages
+            )));
+        }
+        self.set_i2c_repeater(true)?;
+        self.tuner.set_if_gain(&self.handle, stage, gain)?;
+        self.set_i2c_repeater(false)?;
+        Ok(())
+    }
+
+    /// Gain in tenths of a dB the tuner actually applies for the current
+    /// manual setting, plus the stored calibration offset
+    fn effective_gain(&self) -> Result<i32> {
+        let gain = match self.gain {
+            TunerGain::Manual(gain) => gain,
+            TunerGain::Auto => {
+                return Err(RtlsdrErr(
+                    "Input power can only be estimated with a manual tuner gain".to_string(),
+                ))
+            }
+        };
+        // The tuner steps up to the first table entry reaching the request
+        let gains = self.tuner.get_gains()?;
+        let actual = gains
+            .iter()
+            .copied()
+            .find(|g| *g >= gain)
+            .or(gains.last().copied())
+            .unwrap_or(gain);
+        Ok(actual + self.gain_offset as i32)
+    }
+
+    /// Approximate power at the antenna input in dBm for a buffer of raw
+    /// samples read at the current settings
+    pub fn estimate_input_power(&self, buf: &[u8]) -> Result<f32> {
+        Ok(dbfs_to_dbm(power_dbfs(buf), self.effective_gain()?))
+    }
+
+    pub fn set_tracking_filter(&mut self, filter: TrackingFilter) -> Result<()> {
+        self.set_i2c_repeater(true)?;
+        self.tuner.set_tracking_filter(&self.handle, filter)?;
+        self.set_i2c_repeater(false)?;
+        Ok(())
+    }
+
+    pub fn set_notch_filter(&mut self, notch: NotchFilter) -> Result<()> {
+        self.set_i2c_repeater(true)?;
+        self.tuner.set_notch_filter(&self.handle, notch)?;
+        self.set_i2c_repeater(false)?;
+        Ok(())
+    }
+
+    /// Re-run the tuner IF filter calibration, returning the calibration code
+    pub fn recalibrate_filter(&mut self, retries: u8) -> Result<u8> {
+        self.set_i2c_repeater(true)?;
+        let code = self.tuner.recalibrate_filter(&self.handle, retries)?;
+        // Calibration clobbers the filter and PLL settings, so restore them
+        if self.rate != 0 {
+            let bw = if self.bw > 0 { self.bw } else { self.rate };
+            self.tuner.set_bandwidth(&self.handle, bw, self.rate)?;
+        }
+        self.set_i2c_repeater(false)?;
+        if self.freq != 0 {
+            self.tune(self.freq)?;
+        }
+        Ok(code)
+    }
+
+    pub fn set_harmonic_mode(&mut self, harmonic: u8) -> Result<()> {
+        if !matches!(harmonic, 1 | 3 | 5) {
+            return Err(RtlsdrErr(format!(
+                "Unsupported harmonic: {} (must be 1, 3 or 5)",
+                harmonic
+            )));
+        }
+        self.tuner.set_harmonic(harmonic)?;
+        // Retune to apply the new LO
+        if self.freq != 0 {
+            self.tune(self.freq)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_dithering(&mut self, dither: bool) -> Result<()> {
+        self.tuner.set_dither(dither)?;
+        // Retune so the PLL picks up the new setting
+        if self.freq != 0 {
+            self.tune(self.freq)?;
+        }
+        Ok(())
+    }
 
     pub fn reset_buffer(&self) -> Result<()> {
-        self.handle.write_reg(BLOCK_USB, USB_EPA_CTL, 0x1002, 2)?;
-        self.handle.write_reg(BLOCK_USB, USB_EPA_CTL, 0x0000, 2)?;
+        self.handle
+            .write_reg(BLOCK_USB, USB_EPA_CTL, 0x1002, RegWidth::Word)?;
+        self.handle
+            .write_reg(BLOCK_USB, USB_EPA_CTL, 0x0000, RegWidth::Word)?;
         Ok(())
     }
 
@@ -145,35 +416,140 @@ impl RtlSdr {
         self.freq
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     pub fn set_center_freq(&mut self, freq: u32) -> Result<()> {
+        self.tune(freq)?;
+        self.check_pll_lock()
+    }
+
+    // Retunes after another setting changes use this, so a frequency that
+    // never locked doesn't fail the unrelated setting
+    fn tune(&mut self, freq: u32) -> Result<()> {
+        if let Some(threshold) = self.auto_ds_threshold {
+            // Forced direct sampling holds at every frequency, so compare
+            // against the mode that will actually be applied
+            let mode = if self.force_ds || freq < threshold {
+                DirectSampleMode::OnSwap
+            } else {
+                DirectSampleMode::Off
+            };
+            if mode != self.direct_sampling {
+                self.apply_direct_sampling(mode)?;
+            }
+        }
         if !matches!(self.direct_sampling, DirectSampleMode::Off) {
             self.set_if_freq(freq)?;
         } else {
             self.set_i2c_repeater(true)?;
             // TODO: figure out offset_freq, currently never set
-            self.tuner.set_freq(&self.handle, freq - self.offset_freq)?;
+            let tuner_freq = freq
+                .checked_sub(self.offset_freq)
+                .ok_or_else(|| RtlsdrErr(format!("Can't tune to {} Hz with the offset", freq)))?;
+            self.tuner.set_freq(&self.handle, tuner_freq)?;
             self.set_i2c_repeater(false)?;
         }
         self.freq = freq;
         Ok(())
     }
 
+    fn check_pll_lock(&self) -> Result<()> {
+        if self.pll_locked()? {
+            Ok(())
+        } else {
+            Err(PllNotLocked(PllLockError { freq: self.freq }))
+        }
+    }
+
+    pub fn pll_locked(&self) -> Result<bool> {
+        // The tuner PLL is powered down in direct sampling mode
+        if !matches!(self.direct_sampling, DirectSampleMode::Off) {
+            return Ok(true);
+        }
+        self.tuner.get_pll_locked()
+    }
+
+    pub fn read_rssi(&self) -> Result<i32> {
+        // The demod IF AGC loop is off in SDR mode, so the only level that
+        // tracks the input is the gain the tuner's own AGC settles on
+        if !matches!(self.gain, TunerGain::Auto) {
+            return Err(RtlsdrErr(
+                "Signal strength can only be read with automatic tuner gain".to_string(),
+            ));
+        }
+        if !matches!(self.direct_sampling, DirectSampleMode::Off) {
+            return Err(RtlsdrErr(
+                "Signal strength can't be read while the tuner is bypassed".to_string(),
+            ));
+        }
+        // The AGC lowers its gain as the signal gets stronger, so invert it
+        // to get a value that grows with signal strength
+        Ok(-self.tuner.read_gain(&self.handle)?)
+    }
+
     pub fn set_if_freq(&self, freq: u32) -> Result<()> {
-        // Get corrected clock value - start with default
-        let rtl_xtal: u32 = DEF_RTL_XTAL_FREQ;
-        // Apply PPM correction
+        self.handle.set_ddc_if_freq(self.if_freq_to_reg(freq))
+    }
+
+    /// Convert an IF frequency to the demod DDC register value
+    fn if_freq_to_reg(&self, freq: u32) -> i32 {
+        // Use the PPM-corrected clock value
+        let rtl_xtal: u32 = self.get_xtal_freq();
         let base = 1u32 << 22;
-        let if_freq: i32 = (freq as f64 * base as f64 / rtl_xtal as f64 * -1f64) as i32;
+        -(freq as f64 * base as f64 / rtl_xtal as f64) as i32
+    }
+
+    /// IF frequency the DDC actually mixes with, after register quantization
+    fn if_freq_actual(&self, freq: u32) -> f64 {
+        let rtl_xtal: u32 = self.get_xtal_freq();
+        let base = 1u32 << 22;
+        -self.if_freq_to_reg(freq) as f64 * rtl_xtal as f64 / base as f64
+    }
+
+    /// Center frequency the hardware is actually tuned to, accounting for PLL
+    /// and DDC rounding
+    pub fn get_actual_center_freq(&self) -> Result<f64> {
+        if !matches!(self.direct_sampling, DirectSampleMode::Off) {
+            return Ok(self.if_freq_actual(self.freq));
+        }
+        // The sample stream is centered on LO - IF, where IF is the DDC setting,
+        // shifted back up by the offset tuning applied in set_center_freq
+        let lo = self.tuner.get_lo_freq()?;
+        Ok(lo - self.if_freq_actual(self.tuner.get_if_freq()?) + self.offset_freq as f64)
+    }
 
-        let tmp = ((if_freq >> 16) as u16) & 0x3f;
-        self.handle.demod_write_reg(1, 0x19, tmp, 1)?;
-        let tmp = ((if_freq >> 8) as u16) & 0xff;
-        self.handle.demod_write_reg(1, 0x1a, tmp, 1)?;
-        let tmp = if_freq as u16 & 0xff;
-        self.handle.demod_write_reg(1, 0x1b, tmp, 1)?;
+    pub fn get_corrected_center_freq(&self) -> Result<u32> {
+        Ok(self.get_actual_center_freq()?.round() as u32)
+    }
+
+    /// Override the tuner IF (0 restores the default chosen by the bandwidth setting)
+    pub fn set_if_frequency(&mut self, freq: u32) -> Result<()> {
+        // The DDC IF register is a signed 22-bit fraction of the crystal frequency
+        if freq >= self.xtal / 2 {
+            return Err(RtlsdrErr(format!(
+                "IF frequency {} Hz out of range (must be below {} Hz)",
+                freq,
+                self.xtal / 2
+            )));
+        }
+        self.tuner.set_if_freq(freq)?;
+        if self.tuner.get_info()?.id == TUNER_ID
+            && matches!(self.direct_sampling, DirectSampleMode::Off)
+        {
+            self.set_if_freq(self.tuner.get_if_freq()?)?;
+            if self.freq != 0 {
+                self.tune(self.freq)?;
+            }
+        }
         Ok(())
     }
 
+    pub fn get_if_frequency(&self) -> Result<u32> {
+        self.tuner.get_if_freq()
+    }
+
     pub fn get_freq_correction(&self) -> i32 {
         self.corr
     }
@@ -188,8 +564,17 @@ impl RtlSdr {
         // Read corrected clock value into tuner
         self.tuner.set_xtal_freq(self.get_tuner_xtal_freq())?;
 
+        // The DDC IF is derived from the corrected RTL clock as well
+        if self.tuner.get_info()?.id == TUNER_ID
+            && matches!(self.direct_sampling, DirectSampleMode::Off)
+        {
+            self.set_if_freq(self.tuner.get_if_freq()?)?;
+        }
+
         // Retune to apply new correction value
-        self.set_center_freq(self.freq)?;
+        if self.freq != 0 {
+            self.tune(self.freq)?;
+        }
         Ok(())
     }
 
@@ -197,27 +582,38 @@ impl RtlSdr {
         self.rate
     }
 
+    pub fn get_sample_rate_exact(&self) -> f64 {
+        self.rate_exact
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     pub fn set_sample_rate(&mut self, rate: u32) -> Result<()> {
         // Check if rate is supported by the resampler
-        if rate <= 225_000 || rate > 3_200_000 || (rate > 300000 && rate <= 900000) {
-            return Err(RtlsdrErr(format!("Invalid sample rate: {} Hz", rate)));
+        if !SAMPLE_RATE_RANGES.iter().any(|r| r.contains(&rate)) {
+            return Err(SampleRateError {
+                rate,
+                supported: SAMPLE_RATE_RANGES,
+                nearest: self.nearest_sample_rate(rate),
+            }
+            .into());
         }
 
         // Compute exact sample rate
-        let rsamp_ratio =
-            ((self.xtal as u128 * 2_u128.pow(22) / rate as u128) & 0x0ffffffc) as u128;
+        let rsamp_ratio = self.resample_ratio(rate);
         info!(
             "set_sample_rate: rate: {}, xtal: {}, rsamp_ratio: {}",
             rate, self.xtal, rsamp_ratio
         );
-        let real_resamp_ratio = rsamp_ratio | ((rsamp_ratio & 0x08000000) << 1);
-        info!("real_resamp_ratio: {}", real_resamp_ratio);
-        let real_rate = (self.xtal as u128 * 2_u128.pow(22)) as f64 / real_resamp_ratio as f64;
+        let real_rate = self.exact_sample_rate(rsamp_ratio);
         if rate as f64 != real_rate {
             info!("Exact sample rate is {} Hz", real_rate);
         }
         // Save exact rate
         self.rate = real_rate as u32;
+        self.rate_exact = real_rate;
 
         // Configure tuner
         self.set_i2c_repeater(true)?;
@@ -226,19 +622,15 @@ impl RtlSdr {
         self.set_i2c_repeater(false)?;
         if self.tuner.get_info()?.id == TUNER_ID {
             self.set_if_freq(self.tuner.get_if_freq()?)?;
-            self.set_center_freq(self.freq)?;
+            self.tune(self.freq)?;
         }
 
-        let mut tmp: u16 = (rsamp_ratio >> 16) as u16;
-        self.handle.demod_write_reg(1, 0x9f, tmp, 2)?;
-        tmp = (rsamp_ratio & 0xffff) as u16;
-        self.handle.demod_write_reg(1, 0xa1, tmp, 2)?;
+        self.handle.set_resample_ratio(rsamp_ratio)?;
 
         self.set_sample_freq_correction(self.corr)?;
 
         // Reset demod (bit 3, soft_rst)
-        self.handle.demod_write_reg(1, 0x01, 0x14, 1)?;
-        self.handle.demod_write_reg(1, 0x01, 0x10, 1)?;
+        self.reset_demod()?;
 
         // Recalculate offset frequency if offset tuning is enabled
         if self.offset_freq != 0 {
@@ -247,32 +639,97 @@ impl RtlSdr {
         Ok(())
     }
 
-    pub fn set_tuner_bandwidth(&mut self, mut bw: u32) -> Result<()> {
-        bw = if bw > 0 { bw } else { self.rate };
+    fn resample_ratio(&self, rate: u32) -> u32 {
+        ((self.xtal as u128 * 2_u128.pow(22) / rate as u128) & 0x0ffffffc) as u32
+    }
+
+    /// Sample rate produced by the given resampler ratio
+    fn exact_sample_rate(&self, rsamp_ratio: u32) -> f64 {
+        let real_resamp_ratio = rsamp_ratio | ((rsamp_ratio & 0x08000000) << 1);
+        (self.xtal as u128 * 2_u128.pow(22)) as f64 / real_resamp_ratio as f64
+    }
+
+    /// Closest exact rate to `rate` within the supported ranges
+    fn nearest_sample_rate(&self, rate: u32) -> f64 {
+        let clamped = SAMPLE_RATE_RANGES
+            .iter()
+            .map(|r| rate.clamp(*r.start(), *r.end()))
+            .min_by_key(|r| r.abs_diff(rate))
+            .unwrap();
+        self.exact_sample_rate(self.resample_ratio(clamped))
+    }
+
+    /// Set the tuner IF filter bandwidth in Hz, or 0 to follow the sample rate
+    pub fn set_tuner_bandwidth(&mut self, bw: u32) -> Result<()> {
+        let val = if bw > 0 { bw } else { self.rate };
         self.set_i2c_repeater(true)?;
-        self.tuner.set_bandwidth(&self.handle, bw, self.rate)?;
+        self.tuner.set_bandwidth(&self.handle, val, self.rate)?;
         self.set_i2c_repeater(false)?;
         if self.tuner.get_info()?.id == TUNER_ID {
             self.set_if_freq(self.tuner.get_if_freq()?)?;
-            self.set_center_freq(self.freq)?;
+            self.tune(self.freq)?;
         }
         self.bw = bw;
         Ok(())
     }
 
     pub fn set_testmode(&mut self, on: bool) -> Result<()> {
-        match on {
-            true => {
-                self.handle.demod_write_reg(0, 0x19, 0x03, 1)?;
+        self.handle.set_test_mode(on)?;
+        // Test mode shares the register that enables the digital AGC
+        self.agc = false;
+        Ok(())
+    }
+
+    pub fn set_agc_mode(&mut self, on: bool) -> Result<()> {
+        self.handle.set_agc_mode(on)?;
+        self.agc = on;
+        Ok(())
+    }
+
+    /// Options for the next `init`
+    pub fn set_open_options(&mut self, options: OpenOptions) {
+        self.options = options;
+    }
+
+    pub fn open_options(&self) -> OpenOptions {
+        self.options
+    }
+
+    pub fn eeprom_overrides(&self) -> EepromOverrides {
+        self.eeprom_overrides
+    }
+
+    pub fn set_usb_dump(&self, on: bool) {
+        self.handle.set_usb_dump(on)
+    }
+
+    pub fn set_paranoid(&self, on: bool) {
+        self.handle.set_paranoid(on)
+    }
+
+    pub fn write_mismatches(&self) -> u64 {
+        self.handle.write_mismatches()
+    }
+
+    pub fn set_direct_sampling(&mut self, mode: DirectSampleMode) -> Result<()> {
+        match mode {
+            DirectSampleMode::AutoBelow(threshold) => {
+                // tune picks the mode on each retune
+                info!("Auto direct sampling below {} Hz", threshold);
+                self.auto_ds_threshold = Some(threshold);
             }
-            false => {
-                self.handle.demod_write_reg(0, 0x19, 0x05, 1)?;
+            _ => {
+                self.auto_ds_threshold = None;
+                self.apply_direct_sampling(mode)?;
             }
         }
+        if self.freq != 0 {
+            self.tune(self.freq)?;
+        }
         Ok(())
     }
 
-    pub fn set_direct_sampling(&mut self, mut mode: DirectSampleMode) -> Result<()> {
+    fn apply_direct_sampling(&mut self, mut mode: DirectSampleMode) -> Result<()> {
         if self.force_ds {
             mode = DirectSampleMode::OnSwap;
         }
@@ -283,20 +740,20 @@ impl RtlSdr {
                 self.set_i2c_repeater(false)?;
 
                 // Disable Zero-IF mode
-                self.handle.demod_write_reg(1, 0xb1, 0x1a, 1)?;
+                self.handle.set_zero_if(false)?;
 
                 // Disable spectrum inversion
-                self.handle.demod_write_reg(1, 0x15, 0x00, 1)?;
+                self.handle.set_spectrum_inversion(false)?;
 
                 // Only enable in-phase ADC input
-                self.handle.demod_write_reg(0, 0x08, 0x4d, 1)?;
+                self.set_adc_input(AdcInput::I)?;
 
                 // Check whether to swap I and Q ADC
                 if matches!(mode, DirectSampleMode::OnSwap) {
-                    self.handle.demod_write_reg(0, 0x06, 0x90, 1)?;
+                    self.handle.set_iq_swap(true)?;
                     info!("Enabled direct sampling mode: ON (swapped)");
                 } else {
-                    self.handle.demod_write_reg(0, 0x06, 0x80, 1)?;
+                    self.handle.set_iq_swap(false)?;
                     info!("Enabled direct sampling mode: ON");
                 }
                 self.direct_sampling = mode;
@@ -310,23 +767,38 @@ impl RtlSdr {
                     // tuner init already does all this
                     // self.set_if_freq(R82XX_IF_FREQ);
                     // Enable spectrum inversion
-                    // handle.demod_write_reg(1, 0x15, 0x01, 1);
+                    // self.handle.set_spectrum_inversion(true);
                 } else {
                     self.set_if_freq(0)?;
 
                     // Enable in-phase + Quadrature ADC input
-                    self.handle.demod_write_reg(0, 0x08, 0xcd, 1)?;
+                    self.set_adc_input(AdcInput::Both)?;
 
                     // Enable Zero-IF mode
-                    self.handle.demod_write_reg(1, 0xb1, 0x1b, 1)?;
+                    self.handle.set_zero_if(true)?;
                 }
                 // opt_adc_iq = 0, default ADC_I/ADC_Q datapath
-                self.handle.demod_write_reg(0, 0x06, 0x80, 1)?;
+                self.handle.set_iq_swap(false)?;
                 info!("Disabled direct sampling mode");
                 self.direct_sampling = DirectSampleMode::Off;
             }
+            DirectSampleMode::AutoBelow(_) => {
+                // set_center_freq resolves AutoBelow to On or Off
+                return Err(RtlsdrErr("AutoBelow can't be applied directly".to_string()));
+            }
         }
-        self.set_center_freq(self.freq)?;
+        Ok(())
+    }
+
+    /// Select which ADC inputs feed the demodulator
+    pub fn set_adc_input(&self, input: AdcInput) -> Result<()> {
+        // Bit 6 enables the in-phase ADC, bit 7 the quadrature ADC
+        let val = match input {
+            AdcInput::I => 0x4d,
+            AdcInput::Q => 0x8d,
+            AdcInput::Both => 0xcd,
+        };
+        self.handle.demod_write(ADC_EN, val, RegWidth::Byte)?;
         Ok(())
     }
 
@@ -341,27 +813,120 @@ impl RtlSdr {
         Ok(())
     }
 
+    pub fn set_clock_output(&self, on: bool) -> Result<()> {
+        self.handle.set_clock_output(on)
+    }
+
     pub fn set_bias_tee(&self, on: bool) -> Result<()> {
-        Ok(self.set_gpio(0, on)?)
+        self.set_bias_tee_gpio(0, on)
+    }
+
+    pub fn set_bias_tee_gpio(&self, gpio: u8, on: bool) -> Result<()> {
+        if gpio > 7 {
+            return Err(RtlsdrErr(format!("Invalid GPIO pin: {}", gpio)));
+        }
+        self.set_gpio(gpio, on)?;
+        if gpio == 0 {
+            self.bias_tee.set(on);
+        }
+        Ok(())
+    }
+
+    pub fn settings(&self) -> Settings {
+        Settings {
+            center_freq: self.freq,
+            sample_rate: self.rate,
+            gain: self.gain,
+            ppm: self.corr,
+            bandwidth: self.bw,
+            bias_tee: self.bias_tee.get(),
+            direct_sampling: match self.auto_ds_threshold {
+                Some(threshold) => DirectSampleMode::AutoBelow(threshold),
+                None => self.direct_sampling,
+            },
+            agc: self.agc,
+        }
+    }
+
+    /// Apply all of `settings` with the I2C repeater on throughout
+    pub fn apply_settings(&mut self, settings: &Settings) -> Result<()> {
+        self.with_i2c_repeater(|sdr| {
+            // The correction changes the exact rate and frequency, so it goes first
+            sdr.set_freq_correction(settings.ppm)?;
+            if settings.sample_rate != 0 {
+                sdr.set_sample_rate(settings.sample_rate)?;
+            }
+            sdr.set_tuner_bandwidth(settings.bandwidth)?;
+            sdr.set_direct_sampling(settings.direct_sampling)?;
+            if settings.center_freq != 0 {
+                sdr.tune(settings.center_freq)?;
+            }
+            sdr.set_tuner_gain(settings.gain)?;
+            sdr.set_agc_mode(settings.agc)?;
+            sdr.set_bias_tee(settings.bias_tee)?;
+            // Report a failed lock once everything else is applied
+            if settings.center_freq != 0 {
+                sdr.check_pll_lock()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Read captured IR pulses into `buf`, returning the number of bytes read
+    /// (0 if nothing was received). Each byte holds the level in bit 7 and
+    /// the duration in the low 7 bits. A code longer than `buf` is truncated.
+    pub fn get_ir(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.ir_active {
+            self.write_reg_masks(IR_INIT)?;
+            self.ir_active = true;
+        }
+        let status = self.handle.read_reg(BLOCK_IRB, IR_RX_IF, RegWidth::Byte)?;
+        if status != 0x83 {
+            // 0x00 means no signal, 0x81/0x82 show up mid-capture
+            if !matches!(status, 0x00 | 0x81 | 0x82) {
+                warn!("Unexpected IR_RX_IF value: {:#04x}", status);
+            }
+            return Ok(0);
+        }
+        let len = self.handle.read_reg(BLOCK_IRB, IR_RX_BC, RegWidth::Byte)? as usize;
+        let len = len.min(buf.len());
+        let read = self
+            .handle
+            .read_array(BLOCK_IRB, IR_RX_BUF, &mut buf[..len], len as u8);
+        // Let the hardware receive the next code, even if this one was lost
+        self.write_reg_masks(IR_REFRESH)?;
+        read?;
+        Ok(len)
+    }
+
+    fn write_reg_masks(&self, regs: &[(u16, u16, u8, u8)]) -> Result<()> {
+        for (block, addr, val, mask) in regs {
+            self.handle.write_reg_mask(*block, *addr, *val, *mask)?;
+        }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn get_xtal_freq(&self) -> u32 {
-        (self.xtal as f32 * (1.0 + self.ppm_correction as f32 / 1e6)) as u32
+        (self.xtal as f64 * (1.0 + self.corr as f64 / 1e6)) as u32
     }
 
     pub fn get_tuner_xtal_freq(&self) -> u32 {
-        (self.tuner_xtal as f32 * (1.0 + self.ppm_correction as f32 / 1e6)) as u32
+        (self.tuner_xtal as f64 * (1.0 + self.corr as f64 / 1e6)) as u32
     }
 
-    #[allow(dead_code)]
     pub fn set_xtal_freq(&mut self, rtl_freq: u32, tuner_freq: u32) -> Result<()> {
-        if rtl_freq > 0 && (rtl_freq < MIN_RTL_XTAL_FREQ || rtl_freq > MAX_RTL_XTAL_FREQ) {
+        if rtl_freq > 0 && !(MIN_RTL_XTAL_FREQ..=MAX_RTL_XTAL_FREQ).contains(&rtl_freq) {
             return Err(RtlsdrErr(format!(
                 "set_xtal_freq error: rtl_freq {} out of bounds",
                 rtl_freq
             )));
         }
+        if tuner_freq > 0 && !(MIN_TUNER_XTAL_FREQ..=MAX_TUNER_XTAL_FREQ).contains(&tuner_freq) {
+            return Err(RtlsdrErr(format!(
+                "set_xtal_freq error: tuner_freq {} out of bounds",
+                tuner_freq
+            )));
+        }
         if rtl_freq > 0 && self.xtal != rtl_freq {
             self.xtal = rtl_freq;
 
@@ -371,77 +936,99 @@ impl RtlSdr {
             }
         }
 
-        if self.tuner.get_xtal_freq()? != tuner_freq {
-            if tuner_freq == 0 {
-                self.tuner_xtal = self.xtal;
-            } else {
-                self.tuner_xtal = tuner_freq;
-            }
+        // 0 means the tuner shares the RTL clock
+        let tuner_freq = if tuner_freq == 0 {
+            self.xtal
+        } else {
+            tuner_freq
+        };
+        if self.tuner_xtal != tuner_freq {
+            self.tuner_xtal = tuner_freq;
 
             // Read corrected clock value into tuner
             self.tuner.set_xtal_freq(self.get_tuner_xtal_freq())?;
 
             // Update xtal-dependent settings
             if self.freq != 0 {
-                self.set_center_freq(self.freq)?;
+                self.tune(self.freq)?;
             }
         }
         Ok(())
     }
 
-    pub fn read_sync(&self, buf: &mut [u8]) -> Result<usize> {
-        self.handle.bulk_transfer(buf)
+    /// The device, for reading samples without borrowing `self`
+    pub fn device(&self) -> Arc<Device> {
+        self.handle.clone()
     }
 
     fn init_baseband(&self) -> Result<()> {
         // Init baseband
         // info!("Initialize USB");
-        self.handle.write_reg(BLOCK_USB, USB_SYSCTL, 0x09, 1)?;
         self.handle
-            .write_reg(BLOCK_USB, USB_EPA_MAXPKT, 0x0002, 2)?;
-        self.handle.write_reg(BLOCK_USB, USB_EPA_CTL, 0x1002, 2)?;
+            .write_reg(BLOCK_USB, USB_SYSCTL, 0x09, RegWidth::Byte)?;
+        self.handle
+            .write_reg(BLOCK_USB, USB_EPA_MAXPKT, 0x0002, RegWidth::Word)?;
+        self.handle
+            .write_reg(BLOCK_USB, USB_EPA_CTL, 0x1002, RegWidth::Word)?;
 
         // info!("Power-on demod");
-        self.handle.write_reg(BLOCK_SYS, DEMOD_CTL_1, 0x22, 1)?;
-        self.handle.write_reg(BLOCK_SYS, DEMOD_CTL, 0xe8, 1)?;
+        self.handle
+            .write_reg(BLOCK_SYS, DEMOD_CTL_1, 0x22, RegWidth::Byte)?;
+        self.handle
+            .write_reg(BLOCK_SYS, DEMOD_CTL, 0xe8, RegWidth::Byte)?;
 
+        // Only verify the demod writes once at the end of the sequence
+        self.handle.set_write_verify(WriteVerify::Deferred)?;
+        let res = self.init_demod();
+        self.handle.set_write_verify(WriteVerify::EveryWrite)?;
+        res
+    }
+
+    fn init_demod(&self) -> Result<()> {
         // info!("Reset demod (bit 3, soft_rst)");
         self.handle.reset_demod()?;
 
         // info!("Disable spectrum inversion and adjust channel rejection");
-        self.handle.demod_write_reg(1, 0x15, 0x00, 1)?;
-        self.handle.demod_write_reg(1, 0x16, 0x00, 2)?;
+        self.handle.set_spectrum_inversion(false)?;
+        self.handle
+            .demod_write(ADJ_CHAN_REJ, 0x00, RegWidth::Word)?;
 
         // info!("Clear DDC shift and IF registers");
-        for i in 0..5 {
-            self.handle.demod_write_reg(1, 0x16 + i, 0x00, 1)?;
+        for i in 0..DDC_LEN {
+            self.handle.demod_write_reg(
+                ADJ_CHAN_REJ.page,
+                ADJ_CHAN_REJ.addr + i,
+                0x00,
+                RegWidth::Byte,
+            )?;
         }
-        self.set_fir(DEFAULT_FIR)?;
+        self.write_fir(&self.fir)?;
 
         // info!("Enable SDR mode, disable DAGC (bit 5)");
-        self.handle.demod_write_reg(0, 0x19, 0x05, 1)?;
+        self.handle.demod_write(SDR_MODE, 0x05, RegWidth::Byte)?;
 
         // info!("Init FSM state-holding register");
-        self.handle.demod_write_reg(1, 0x93, 0xf0, 1)?;
-        self.handle.demod_write_reg(1, 0x94, 0x0f, 1)?;
+        self.handle.demod_write(FSM_STATE, 0xf0, RegWidth::Byte)?;
+        self.handle
+            .demod_write_reg(FSM_STATE.page, FSM_STATE.addr + 1, 0x0f, RegWidth::Byte)?;
 
         // Disable AGC (en_dagc, bit 0) (seems to have no effect)
-        self.handle.demod_write_reg(1, 0x11, 0x00, 1)?;
+        self.handle.demod_write(EN_DAGC, 0x00, RegWidth::Byte)?;
 
         // Disable RF and IF AGC loop
-        self.handle.demod_write_reg(1, 0x04, 0x00, 1)?;
+        self.handle.demod_write(AGC_LOOP, 0x00, RegWidth::Byte)?;
 
         // Disable PID filter
-        self.handle.demod_write_reg(0, 0x61, 0x60, 1)?;
+        self.handle.demod_write(PID_FILTER, 0x60, RegWidth::Byte)?;
 
         // opt_adc_iq = 0, default ADC_I/ADC_Q datapath
-        self.handle.demod_write_reg(0, 0x06, 0x80, 1)?;
+        self.handle.set_iq_swap(false)?;
 
         // Enable Zero-IF mode, DC cancellation, and IQ estimation/compensation
-        self.handle.demod_write_reg(1, 0xb1, 0x1b, 1)?;
+        self.handle.set_zero_if(true)?;
 
         // Disable 4.096 MHz clock output on pin TP_CK0
-        self.handle.demod_write_reg(0, 0x0d, 0x83, 1)?;
+        self.handle.set_clock_output(false)?;
 
         Ok(())
     }
@@ -453,67 +1040,120 @@ impl RtlSdr {
         self.set_i2c_repeater(false)?;
 
         // Power-off demodulator and ADCs
-        self.handle.write_reg(BLOCK_SYS, DEMOD_CTL, 0x20, 1)?;
+        self.handle
+            .write_reg(BLOCK_SYS, DEMOD_CTL, 0x20, RegWidth::Byte)?;
         Ok(())
     }
 
     fn set_sample_freq_correction(&self, ppm: i32) -> Result<()> {
-        let offs = (ppm * (-1) * 2_i32.pow(24) / 1_000_000) as i16;
-        self.handle
-            .demod_write_reg(1, 0x3f, (offs & 0xff) as u16, 1)?;
-        self.handle
-            .demod_write_reg(1, 0x3e, ((offs >> 8) & 0x3f) as u16, 1)?;
-        Ok(())
+        let offs = (-ppm * 2_i32.pow(24) / 1_000_000) as i16;
+        self.handle.set_sample_freq_corr(offs)
     }
 
     fn set_gpio(&self, gpio_pin: u8, mut on: bool) -> Result<()> {
         // If force_bt is on from the EEPROM, do not allow bias tee to turn off
-        if self.force_bt {
+        if self.force_bt && gpio_pin == 0 {
             on = true;
         }
         self.set_gpio_output(gpio_pin)?;
-        Ok(self.set_gpio_bit(gpio_pin, on)?)
+        self.set_gpio_bit(gpio_pin, on)
+    }
+
+    /// Drive the GPIOs selected by `mask` as outputs with the levels in `values`
+    pub fn set_gpio_pattern(&self, mask: u8, mut values: u8) -> Result<()> {
+        if self.force_bt {
+            values |= mask & 0x01;
+        }
+        self.handle.write_reg_mask(BLOCK_SYS, GPD, 0x00, mask)?;
+        self.handle.write_reg_mask(BLOCK_SYS, GPOE, mask, mask)?;
+        self.handle.write_reg_mask(BLOCK_SYS, GPO, values, mask)?;
+        if mask & 0x01 != 0 {
+            self.bias_tee.set(values & 0x01 != 0);
+        }
+        Ok(())
     }
 
     fn set_gpio_bit(&self, mut gpio: u8, val: bool) -> Result<()> {
         gpio = 1 << gpio;
-        let mut r = self.handle.read_reg(BLOCK_SYS, GPO, 1)?;
+        let mut r = self.handle.read_reg(BLOCK_SYS, GPO, RegWidth::Byte)?;
         r = if val {
             r | gpio as u16
         } else {
             r & !gpio as u16
         };
-        self.handle.write_reg(BLOCK_SYS, GPO, r, 1)?;
+        self.handle.write_reg(BLOCK_SYS, GPO, r, RegWidth::Byte)?;
         Ok(())
     }
 
     fn set_gpio_output(&self, mut gpio: u8) -> Result<()> {
         gpio = 1 << gpio;
-        let mut r = self.handle.read_reg(BLOCK_SYS, GPD, 1)?;
-        self.handle.write_reg(BLOCK_SYS, GPD, r & !gpio as u16, 1)?;
-        r = self.handle.read_reg(BLOCK_SYS, GPOE, 1)?;
-        self.handle.write_reg(BLOCK_SYS, GPOE, r | gpio as u16, 1)?;
+        let mut r = self.handle.read_reg(BLOCK_SYS, GPD, RegWidth::Byte)?;
+        self.handle
+            .write_reg(BLOCK_SYS, GPD, r & !gpio as u16, RegWidth::Byte)?;
+        r = self.handle.read_reg(BLOCK_SYS, GPOE, RegWidth::Byte)?;
+        self.handle
+            .write_reg(BLOCK_SYS, GPOE, r | gpio as u16, RegWidth::Byte)?;
         Ok(())
     }
 
     fn set_i2c_repeater(&self, enable: bool) -> Result<()> {
-        let val = match enable {
-            true => 0x18,
-            false => 0x10,
+        // Inside with_i2c_repeater the repeater stays on until it returns
+        if self.i2c_session.get() {
+            return Ok(());
+        }
+        self.handle.set_i2c_repeater(enable)
+    }
+
+    /// Run `f` with the I2C repeater on throughout, rather than switching it
+    /// on and off around every tuner access
+    fn with_i2c_repeater<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.i2c_session.get() {
+            return f(self);
+        }
+        self.set_i2c_repeater(true)?;
+        self.i2c_session.set(true);
+        let res = f(self);
+        self.i2c_session.set(false);
+        self.set_i2c_repeater(false)?;
+        res
+    }
+
+    fn reset_demod(&self) -> Result<()> {
+        self.handle.reset_demod()?;
+        // The reset clears the repeater bit as well
+        if self.i2c_session.get() {
+            self.handle.set_i2c_repeater(true)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_fir(&mut self, fir: Fir) -> Result<()> {
+        let coeffs = match fir {
+            Fir::Default => *DEFAULT_FIR,
+            Fir::Narrowband => *NARROWBAND_FIR,
+            Fir::WidebandFlat => *WIDEBAND_FLAT_FIR,
+            Fir::Custom(coeffs) => coeffs,
         };
-        self.handle
-            .demod_write_reg(1, 0x01, val, 1)
-            .and_then(|_| return Ok(()))
+        self.write_fir(&coeffs)?;
+        self.fir = coeffs;
+        Ok(())
+    }
+
+    pub fn get_fir(&self) -> [i32; FIR_LEN] {
+        self.fir
     }
 
-    pub fn set_fir(&self, fir: &[i32; FIR_LEN]) -> Result<()> {
+    fn write_fir(&self, fir: &[i32; FIR_LEN]) -> Result<()> {
         const TMP_LEN: usize = 20;
         let mut tmp: [u8; TMP_LEN] = [0; TMP_LEN];
         // First 8 values are i8
         for i in 0..8 {
             let val = fir[i];
-            if val < -128 || val > 127 {
-                panic!("i8 FIR coefficient out of bounds! {}", val);
+            if !(-128..=127).contains(&val) {
+                return Err(RtlsdrErr(format!(
+                    "i8 FIR coefficient {} out of bounds: {}",
+                    i, val
+                )));
             }
             tmp[i] = val as u8;
         }
@@ -524,20 +1164,21 @@ impl RtlSdr {
         for i in (0..8).step_by(2) {
             let val0 = fir[8 + i];
             let val1 = fir[8 + i + 1];
-            if val0 < -2048 || val0 > 2047 {
-                panic!("i12 FIR coefficient out of bounds: {}", val0)
-            } else if val1 < -2048 || val1 > 2047 {
-                panic!("i12 FIR coefficient out of bounds: {}", val1)
+            for (j, val) in [(8 + i, val0), (8 + i + 1, val1)] {
+                if !(-2048..=2047).contains(&val) {
+                    return Err(RtlsdrErr(format!(
+                        "i12 FIR coefficient {} out of bounds: {}",
+                        j, val
+                    )));
+                }
             }
             tmp[8 + i * 3 / 2] = (val0 >> 4) as u8;
             tmp[8 + i * 3 / 2 + 1] = ((val0 << 4) | ((val1 >> 8) & 0x0f)) as u8;
             tmp[8 + i * 3 / 2 + 2] = val1 as u8;
         }
 
-        for i in 0..TMP_LEN {
-            self.handle
-                .demod_write_reg(1, 0x1c + i as u16, tmp[i] as u16, 1)?;
-        }
+        self.handle
+            .demod_write_array(FIR_COEFF.page, FIR_COEFF.addr, &tmp)?;
         Ok(())
     }
 
@@ -0,0 +1,6 @@
+//! Scanning across frequency ranges wider than one tuner bandwidth
+pub mod power;
+pub use power::{write_csv, Hop, PowerScan, ScanConfig};
+
+#[cfg(test)]
+mod power_test;
@@ -0,0 +1,265 @@
+//! Wideband power scan in the style of rtl_power
+//!
+//! The range is split into hops one (cropped) FFT wide. For each hop the
+//! tuner is retuned, spectra are averaged over the integration time, and the
+//! bins inside the hop are reported. Hops are contiguous, so concatenating
+//! the bins of a sweep gives the spectrum of the whole range.
+//!
+//! ```no_run
+//! use rtlsdr_rs::scan::{write_csv, PowerScan, ScanConfig};
+//! use rtlsdr_rs::RtlSdr;
+//! use std::time::SystemTime;
+//!
+//! let mut sdr = RtlSdr::open(0).unwrap();
+//! let mut scan = PowerScan::new(ScanConfig::new(88_000_000, 108_000_000, 10_000)).unwrap();
+//! let mut out = std::io::stdout();
+//! scan.sweep(&mut sdr, |hop| write_csv(&mut out, SystemTime::now(), hop).unwrap())
+//!     .unwrap();
+//! ```
+
+use crate::dsp::{to_complex_into, Spectrum};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::{RtlSdr, DEFAULT_BUF_LENGTH};
+use num_complex::Complex;
+use std::io::Write;
+use std::time::{Duration, SystemTime};
+
+/// Highest sample rate that streams without dropping samples
+pub const SCAN_SAMPLE_RATE: u32 = 2_400_000;
+// Largest FFT, limiting the finest bin width to about 2.3 Hz
+const MAX_FFT_SIZE: usize = 1 << 20;
+// Bytes read and discarded after retuning while the PLL and AGC settle
+const SETTLE_LEN: usize = 16384;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanConfig {
+    pub start: u32,     // Hz
+    pub stop: u32,      // Hz
+    pub bin_width: u32, // Hz, rounded down so the FFT size is a power of two
+    pub integration: Duration,
+    pub crop: f32, // Fraction of each hop discarded at the edges, 0.0 to <1.0
+}
+
+impl ScanConfig {
+    /// Scan with rtl_power's defaults: 10 s integration, no crop
+    pub fn new(start: u32, stop: u32, bin_width: u32) -> ScanConfig {
+        ScanConfig {
+            start,
+            stop,
+            bin_width,
+            integration: Duration::from_secs(10),
+            crop: 0.0,
+        }
+    }
+}
+
+/// Averaged spectrum of one hop
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hop {
+    pub low: u32,  // Hz, center of the first bin
+    pub high: u32, // Hz, one step past the last bin, where the next hop starts
+    pub step: f64, // Hz per bin
+    pub samples: usize,
+    pub power: Vec<f32>, // dBFS per bin, lowest frequency first
+}
+
+impl Hop {
+    /// Power of the bin nearest `freq`, None if outside the hop
+    pub fn power_at(&self, freq: u32) -> Option<f32> {
+        if freq < self.low || freq >= self.high {
+            return None;
+        }
+        let bin = ((freq - self.low) as f64 / self.step).round() as usize;
+        self.power
+            .get(bin.min(self.power.len().saturating_sub(1)))
+            .copied()
+    }
+}
+
+pub struct PowerScan {
+    config: ScanConfig,
+    fft_size: usize,
+    first: usize, // First FFT bin kept after cropping
+    bins: usize,  // Bins kept per hop
+    hops: usize,
+    spectrum: Spectrum,
+    pending: Vec<Complex<f32>>, // Samples not yet making a full FFT frame
+    power: Vec<f32>,
+    acc: Vec<f32>, // Summed linear power per FFT bin
+    frames: usize,
+}
+
+impl PowerScan {
+    pub fn new(config: ScanConfig) -> Result<PowerScan> {
+        if config.start >= config.stop {
+            return Err(RtlsdrErr(format!(
+                "Scan start {} Hz must be below stop {} Hz",
+                config.start, config.stop
+            )));
+        }
+        if !(0.0..1.0).contains(&config.crop) {
+            return Err(RtlsdrErr(format!(
+                "Scan crop must be from 0.0 to below 1.0: {}",
+                config.crop
+            )));
+        }
+        let fft_size = (SCAN_SAMPLE_RATE / config.bin_width.max(1)).next_power_of_two() as usize;
+        if fft_size > MAX_FFT_SIZE {
+            return Err(RtlsdrErr(format!(
+                "Scan bin width too small: {} Hz",
+                config.bin_width
+            )));
+        }
+        let bins = ((fft_size as f32 * (1.0 - config.crop)) as usize).max(1);
+        let step = SCAN_SAMPLE_RATE as f64 / fft_size as f64;
+        let range = (config.stop - config.start) as f64;
+        let hops = (range / (bins as f64 * step)).ceil() as usize;
+        Ok(PowerScan {
+            config,
+            fft_size,
+            first: (fft_size - bins) / 2,
+            bins,
+            hops,
+            spectrum: Spectrum::new(fft_size),
+            pending: Vec::with_capacity(fft_size),
+            power: vec![0.0; fft_size],
+            acc: vec![0.0; fft_size],
+            frames: 0,
+        })
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Number of hops in a sweep
+    pub fn hops(&self) -> usize {
+        self.hops
+    }
+
+    /// Frequency spacing of the bins in Hz
+    pub fn step(&self) -> f64 {
+        SCAN_SAMPLE_RATE as f64 / self.fft_size as f64
+    }
+
+    /// Tuner frequency for hop `index`
+    pub fn center_freq(&self, index: usize) -> u32 {
+        let low = self.low(index);
+        (low + (self.fft_size / 2 - self.first) as f64 * self.step()).round() as u32
+    }
+
+    fn low(&self, index: usize) -> f64 {
+        self.config.start as f64 + (index * self.bins) as f64 * self.step()
+    }
+
+    /// Scan the whole range once, calling `callback` with each hop in order
+    pub fn sweep<F: FnMut(&Hop)>(&mut self, sdr: &mut RtlSdr, mut callback: F) -> Result<()> {
+        if sdr.get_sample_rate() != SCAN_SAMPLE_RATE {
+            sdr.set_sample_rate(SCAN_SAMPLE_RATE)?;
+        }
+        let frames = (self.config.integration.as_secs_f64() * SCAN_SAMPLE_RATE as f64
+            / self.fft_size as f64)
+            .ceil()
+            .max(1.0) as usize;
+        let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+        let mut samples = vec![Complex::new(0.0, 0.0); DEFAULT_BUF_LENGTH / 2];
+        for index in 0..self.hops {
+            sdr.set_center_freq(self.center_freq(index))?;
+            sdr.reset_buffer()?;
+            sdr.read_sync(&mut buf[..SETTLE_LEN])?;
+            while self.frames < frames {
+                let want = (frames - self.frames) * self.fft_size - self.pending.len();
+                let len = (2 * want).next_multiple_of(512).min(buf.len());
+                let n = sdr.read_sync(&mut buf[..len])?;
+                to_complex_into(&buf[..n], &mut samples[..n / 2]);
+                self.add_samples(&samples[..n / 2]);
+            }
+            callback(&self.finish_hop(index));
+        }
+        Ok(())
+    }
+
+    /// Integrate IQ samples (scaled to +/-1.0) into the current hop
+    pub fn add_samples(&mut self, samples: &[Complex<f32>]) {
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let n = (self.fft_size - self.pending.len()).min(samples.len());
+            self.pending.extend_from_slice(&samples[..n]);
+            samples = &samples[n..];
+            if self.pending.len() == self.fft_size {
+                self.spectrum.power(&self.pending, &mut self.power);
+                self.pending.clear();
+                for (a, p) in self.acc.iter_mut().zip(&self.power) {
+                    *a += p;
+                }
+                self.frames += 1;
+            }
+        }
+    }
+
+    /// Average the integrated spectra into hop `index` and start a new hop.
+    /// Samples short of a full FFT frame are dropped.
+    pub fn finish_hop(&mut self, index: usize) -> Hop {
+        let frames = self.frames.max(1) as f32;
+        let power = self.acc[self.first..self.first + self.bins]
+            .iter()
+            .map(|a| 10.0 * (a / frames).max(1e-20).log10())
+            .collect();
+        let low = self.low(index);
+        let hop = Hop {
+            low: low.round() as u32,
+            high: (low + self.bins as f64 * self.step()).round() as u32,
+            step: self.step(),
+            samples: self.frames * self.fft_size,
+            power,
+        };
+        self.acc.fill(0.0);
+        self.pending.clear();
+        self.frames = 0;
+        hop
+    }
+}
+
+/// Write a hop as an rtl_power CSV row. Times are UTC.
+pub fn write_csv<W: Write>(out: &mut W, time: SystemTime, hop: &Hop) -> std::io::Result<()> {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs = secs % 86400;
+    write!(
+        out,
+        "{:04}-{:02}-{:02}, {:02}:{:02}:{:02}, {}, {}, {:.2}, {}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        hop.low,
+        hop.high,
+        hop.step,
+        hop.samples
+    )?;
+    for p in &hop.power {
+        write!(out, ", {:.2}", p)?;
+    }
+    writeln!(out)
+}
+
+/// Gregorian date of a day count since 1970-01-01
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
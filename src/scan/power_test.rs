@@ -0,0 +1,101 @@
+use super::power::{write_csv, Hop, PowerScan, ScanConfig, SCAN_SAMPLE_RATE};
+use num_complex::Complex;
+use std::f32::consts::PI;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_plan() {
+    let mut config = ScanConfig::new(88_000_000, 108_000_000, 10_000);
+    let scan = PowerScan::new(config.clone()).unwrap();
+    assert_eq!(256, scan.fft_size());
+    assert_eq!(9375.0, scan.step());
+    // 20 MHz in 2.4 MHz hops
+    assert_eq!(9, scan.hops());
+    assert_eq!(89_200_000, scan.center_freq(0));
+    assert_eq!(91_600_000, scan.center_freq(1));
+
+    config.crop = 0.5;
+    let scan = PowerScan::new(config.clone()).unwrap();
+    assert_eq!(17, scan.hops());
+    assert_eq!(88_600_000, scan.center_freq(0));
+
+    config.crop = 1.0;
+    assert!(PowerScan::new(config.clone()).is_err());
+    config.crop = 0.0;
+    config.stop = config.start;
+    assert!(PowerScan::new(config).is_err());
+}
+
+#[test]
+fn test_hops_are_contiguous() {
+    let mut config = ScanConfig::new(100_000_000, 110_000_000, 50_000);
+    config.crop = 0.3;
+    let mut scan = PowerScan::new(config).unwrap();
+    let hops: Vec<Hop> = (0..scan.hops()).map(|i| scan.finish_hop(i)).collect();
+    assert_eq!(100_000_000, hops[0].low);
+    assert!(hops.last().unwrap().high >= 110_000_000);
+    for pair in hops.windows(2) {
+        assert_eq!(pair[0].high, pair[1].low);
+    }
+    for hop in &hops {
+        let width = hop.power.len() as f64 * hop.step;
+        assert!((hop.high - hop.low) as f64 - width < 1.0);
+    }
+}
+
+#[test]
+fn test_integrate_tone() {
+    let mut config = ScanConfig::new(100_000_000, 102_400_000, 10_000);
+    config.crop = 0.25;
+    let mut scan = PowerScan::new(config).unwrap();
+    let center = scan.center_freq(0);
+    // Tone 300 kHz above the tuner, split across calls mid-frame
+    let freq = 300_000.0 / SCAN_SAMPLE_RATE as f32;
+    let samples: Vec<Complex<f32>> = (0..1000)
+        .map(|n| Complex::from_polar(0.5, 2.0 * PI * freq * n as f32))
+        .collect();
+    scan.add_samples(&samples[..300]);
+    scan.add_samples(&samples[300..]);
+    let hop = scan.finish_hop(0);
+    assert_eq!(768, hop.samples);
+    let peak = (0..hop.power.len())
+        .max_by(|a, b| hop.power[*a].total_cmp(&hop.power[*b]))
+        .unwrap();
+    let peak_freq = hop.low as f64 + peak as f64 * hop.step;
+    assert!((peak_freq - (center as f64 + 300_000.0)).abs() < 1.0);
+    assert!((hop.power[peak] - 20.0 * 0.5_f32.log10()).abs() < 1.5);
+}
+
+#[test]
+fn test_write_csv() {
+    let hop = Hop {
+        low: 88_000_000,
+        high: 88_028_125,
+        step: 9375.0,
+        samples: 512,
+        power: vec![-20.0, -31.256, -5.5],
+    };
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut out = Vec::new();
+    write_csv(&mut out, time, &hop).unwrap();
+    assert_eq!(
+        "2023-11-14, 22:13:20, 88000000, 88028125, 9375.00, 512, -20.00, -31.26, -5.50\n",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn test_power_at() {
+    let hop = Hop {
+        low: 88_000_000,
+        high: 88_028_125,
+        step: 9375.0,
+        samples: 512,
+        power: vec![-20.0, -31.0, -5.5],
+    };
+    assert_eq!(Some(-20.0), hop.power_at(88_000_000));
+    assert_eq!(Some(-31.0), hop.power_at(88_010_000));
+    assert_eq!(Some(-5.5), hop.power_at(88_028_124));
+    assert_eq!(None, hop.power_at(87_999_999));
+    assert_eq!(None, hop.power_at(88_028_125));
+}
@@ -0,0 +1,89 @@
+use crate::bookmarks::{Bookmark, Mode};
+use crate::record::schedule::{Profile, Session};
+use crate::scan::ScanConfig;
+use crate::source::sigmf::Datatype;
+use crate::{
+    Calibration, DirectSampleMode, Eeprom, EepromOverrides, Fir, Settings, TunerGain, FIR_LEN,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: T) {
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(value, serde_json::from_str::<T>(&json).unwrap(), "{}", json);
+}
+
+#[test]
+fn test_settings() {
+    round_trip(TunerGain::Auto);
+    round_trip(TunerGain::Manual(-10));
+    round_trip(DirectSampleMode::AutoBelow(24_000_000));
+    round_trip(Fir::Custom([-54; FIR_LEN]));
+    round_trip(ScanConfig::new(88_000_000, 108_000_000, 10_000));
+    round_trip(Settings {
+        center_freq: 100_000_000,
+        sample_rate: 2_048_000,
+        gain: TunerGain::Auto,
+        ppm: 1,
+        bandwidth: 0,
+        bias_tee: false,
+        direct_sampling: DirectSampleMode::Off,
+        agc: false,
+    });
+}
+
+#[test]
+fn test_session() {
+    let profile = Profile::new(137_100_000, 2_048_000, TunerGain::Manual(496));
+    round_trip(Session::new(
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        Duration::from_secs(900),
+        profile,
+        "noaa19.bin",
+    ));
+}
+
+#[test]
+fn test_eeprom() {
+    round_trip(Eeprom {
+        vendor_id: 0x0bda,
+        product_id: 0x2838,
+        have_serial: true,
+        remote_wakeup: false,
+        enable_ir: true,
+        manufacturer: "RTLSDRBlog".to_string(),
+        product: "Blog V4".to_string(),
+        serial: "00000042".to_string(),
+        calibration: Some(Calibration {
+            ppm: -3,
+            gain_offset: 15,
+            label: "attic".to_string(),
+        }),
+    });
+    round_trip(EepromOverrides {
+        bias_tee: true,
+        direct_sampling: false,
+    });
+}
+
+#[test]
+fn test_names() {
+    // Same names as in bookmark and SigMF files
+    let bookmark = Bookmark::new("Tower", 118_300_000, Mode::Am, 8_000);
+    let json = serde_json::to_value(&bookmark).unwrap();
+    assert_eq!("am", json["mode"]);
+    round_trip(bookmark);
+    assert_eq!(
+        "\"ci16_le\"",
+        serde_json::to_string(&Datatype::Ci16Le).unwrap()
+    );
+}
+
+#[test]
+fn test_tuner_info() {
+    let json = serde_json::to_value(crate::tuners::r820t::TUNER_INFO).unwrap();
+    assert_eq!("r820t", json["id"]);
+    assert_eq!(29, json["caps"]["gains"].as_array().unwrap().len());
+}
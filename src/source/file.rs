@@ -0,0 +1,70 @@
+//! Playback of raw u8 IQ files, as written by rtl_sdr or `RawRecorder`.
+//! The files carry no metadata, so the sample rate and frequency they were
+//! captured at are given when opening.
+
+use super::{read_full, Pacer, SampleSource};
+use crate::error::Result;
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+pub struct FileSdr {
+    file: BufReader<File>,
+    sample_rate: u32,
+    center_freq: u32,
+    looping: bool,
+    pacer: Option<Pacer>, // Set for realtime playback
+}
+
+impl FileSdr {
+    pub fn open<P: AsRef<Path>>(path: P, sample_rate: u32, center_freq: u32) -> Result<FileSdr> {
+        Ok(FileSdr {
+            file: BufReader::new(File::open(path)?),
+            sample_rate,
+            center_freq,
+            looping: false,
+            pacer: None,
+        })
+    }
+
+    /// Pace reads to the sample rate, as a live device would
+    pub fn set_realtime(&mut self, on: bool) {
+        self.pacer = on.then(|| Pacer::new(self.sample_rate));
+    }
+
+    /// Restart from the beginning at the end of the file instead of
+    /// returning 0
+    pub fn set_looping(&mut self, on: bool) {
+        self.looping = on;
+    }
+}
+
+impl SampleSource for FileSdr {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len() / 2 * 2;
+        let mut n = read_full(&mut self.file, &mut buf[..len])?;
+        if self.looping {
+            while n < len {
+                self.file.seek(SeekFrom::Start(0))?;
+                match read_full(&mut self.file, &mut buf[n..len])? {
+                    // Empty file
+                    0 => break,
+                    m => n += m,
+                }
+            }
+        }
+        let n = n / 2 * 2;
+        if let Some(pacer) = self.pacer.as_mut() {
+            pacer.wait(n);
+        }
+        Ok(n)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.center_freq
+    }
+}
@@ -0,0 +1,61 @@
+use super::{FileSdr, SampleSource};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn capture(name: &str, data: &[u8]) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("rtlsdr-file-{}-{}.bin", name, std::process::id()));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+/// Read a source to the end, in the way an application written against the
+/// trait would
+fn read_all<S: SampleSource>(source: &mut S, chunk: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut buf = vec![0; chunk];
+    loop {
+        let n = source.read_sync(&mut buf).unwrap();
+        if n == 0 {
+            return out;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[test]
+fn test_playback() {
+    let path = capture("playback", &[1, 2, 3, 4, 5, 6, 7]);
+    let mut sdr = FileSdr::open(&path, 1_024_000, 100_000_000).unwrap();
+    assert_eq!(1_024_000, sdr.sample_rate());
+    assert_eq!(100_000_000, sdr.center_freq());
+    // Reads are whole IQ pairs, the odd trailing byte is dropped
+    assert_eq!(vec![1, 2, 3, 4, 5, 6], read_all(&mut sdr, 5));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_looping() {
+    let path = capture("looping", &[1, 2, 3, 4]);
+    let mut sdr = FileSdr::open(&path, 1_024_000, 0).unwrap();
+    sdr.set_looping(true);
+    let mut buf = [0; 10];
+    assert_eq!(10, sdr.read_sync(&mut buf).unwrap());
+    assert_eq!([1, 2, 3, 4, 1, 2, 3, 4, 1, 2], buf);
+    assert_eq!(10, sdr.read_sync(&mut buf).unwrap());
+    assert_eq!([3, 4, 1, 2, 3, 4, 1, 2, 3, 4], buf);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_realtime() {
+    // 1000 samples at 10 kS/s take 100 ms
+    let path = capture("realtime", &[127; 2000]);
+    let mut sdr = FileSdr::open(&path, 10_000, 0).unwrap();
+    sdr.set_realtime(true);
+    let start = Instant::now();
+    assert_eq!(2000, read_all(&mut sdr, 400).len());
+    assert!(start.elapsed() >= Duration::from_millis(95));
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,86 @@
+//! Sources of raw IQ samples: a live device or a recording played back
+//! through the same interface
+
+use crate::error::Result;
+use crate::RtlSdr;
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod file;
+pub use file::FileSdr;
+pub mod sigmf;
+pub use sigmf::SigmfReader;
+pub mod sim;
+pub use sim::{Signal, SimSdr};
+
+#[cfg(test)]
+mod file_test;
+#[cfg(test)]
+mod sigmf_test;
+#[cfg(test)]
+mod sim_test;
+
+/// Produces interleaved 8-bit unsigned IQ samples, as read from the device
+pub trait SampleSource {
+    /// Fill `buf` with samples, returning the number of bytes read. Returns
+    /// 0 once a finite source is exhausted.
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize>;
+    /// Samples per second
+    fn sample_rate(&self) -> u32;
+    /// Center frequency in Hz
+    fn center_freq(&self) -> u32;
+}
+
+impl SampleSource for RtlSdr {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        RtlSdr::read_sync(self, buf)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.get_sample_rate()
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.get_center_freq()
+    }
+}
+
+/// Paces playback to a sample rate, the way a live device delivers samples
+struct Pacer {
+    rate: u32,
+    started: Option<Instant>, // Time of the first read
+    samples: u64,             // Samples delivered since then
+}
+
+impl Pacer {
+    fn new(rate: u32) -> Pacer {
+        Pacer {
+            rate,
+            started: None,
+            samples: 0,
+        }
+    }
+
+    /// Wait until `bytes` more of IQ samples are due
+    fn wait(&mut self, bytes: usize) {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.samples += bytes as u64 / 2;
+        let due = Duration::from_secs_f64(self.samples as f64 / self.rate as f64);
+        if let Some(wait) = due.checked_sub(started.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Read until `buf` is full or the end of the input
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
@@ -0,0 +1,151 @@
+//! Playback of SigMF recordings (https://sigmf.org)
+//!
+//! Complex 8, 16 and 32-bit float datatypes are converted to the unsigned
+//! 8-bit samples the device produces, so recordings from other receivers can
+//! be processed like a live stream. Only the first capture segment's
+//! frequency is used.
+
+use super::{read_full, Pacer, SampleSource};
+use crate::dsp::{decode_into, SampleFormat};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+const META_EXTENSION: &str = "sigmf-meta";
+const DATA_EXTENSION: &str = "sigmf-data";
+
+/// Sample formats that can be played back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))] // SigMF names
+pub enum Datatype {
+    Cu8,
+    Ci8,
+    Ci16Le,
+    Cf32Le,
+}
+
+impl Datatype {
+    fn parse(name: &str) -> Option<Datatype> {
+        match name {
+            "cu8" => Some(Datatype::Cu8),
+            "ci8" => Some(Datatype::Ci8),
+            "ci16_le" => Some(Datatype::Ci16Le),
+            "cf32_le" => Some(Datatype::Cf32Le),
+            _ => None,
+        }
+    }
+
+    /// Bytes per I or Q value
+    fn size(self) -> usize {
+        match self {
+            Datatype::Cu8 => u8::SIZE,
+            Datatype::Ci8 => i8::SIZE,
+            Datatype::Ci16Le => i16::SIZE,
+            Datatype::Cf32Le => f32::SIZE,
+        }
+    }
+
+    fn decode(self, src: &[u8], dst: &mut [u8]) {
+        match self {
+            Datatype::Cu8 => decode_into::<u8>(src, dst),
+            Datatype::Ci8 => decode_into::<i8>(src, dst),
+            Datatype::Ci16Le => decode_into::<i16>(src, dst),
+            Datatype::Cf32Le => decode_into::<f32>(src, dst),
+        }
+    }
+}
+
+pub struct SigmfReader {
+    data: BufReader<File>,
+    datatype: Datatype,
+    sample_rate: u32,
+    center_freq: u32,
+    pacer: Option<Pacer>, // Set for realtime playback
+    raw: Vec<u8>,         // Read buffer for non-native datatypes
+}
+
+impl SigmfReader {
+    /// Open a recording from its `.sigmf-meta` or `.sigmf-data` file, or the
+    /// base name shared by both
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SigmfReader> {
+        let path = path.as_ref();
+        let base = match path.extension().and_then(|e| e.to_str()) {
+            Some(META_EXTENSION) | Some(DATA_EXTENSION) => path.with_extension(""),
+            _ => path.to_path_buf(),
+        };
+        let meta: Value =
+            serde_json::from_str(&fs::read_to_string(with_ext(&base, META_EXTENSION))?)
+                .map_err(|e| RtlsdrErr(format!("Invalid SigMF metadata: {}", e)))?;
+        let global = &meta["global"];
+        let name = global["core:datatype"]
+            .as_str()
+            .ok_or_else(|| RtlsdrErr("SigMF metadata has no core:datatype".to_string()))?;
+        let datatype = Datatype::parse(name)
+            .ok_or_else(|| RtlsdrErr(format!("Unsupported SigMF datatype: {}", name)))?;
+        let sample_rate = global["core:sample_rate"]
+            .as_f64()
+            .ok_or_else(|| RtlsdrErr("SigMF metadata has no core:sample_rate".to_string()))?;
+        let center_freq = meta["captures"][0]["core:frequency"]
+            .as_f64()
+            .unwrap_or(0.0);
+        Ok(SigmfReader {
+            data: BufReader::new(File::open(with_ext(&base, DATA_EXTENSION))?),
+            datatype,
+            sample_rate: sample_rate.round() as u32,
+            center_freq: center_freq.round() as u32,
+            pacer: None,
+            raw: Vec::new(),
+        })
+    }
+
+    /// Pace reads to the recorded sample rate, as a live device would
+    pub fn set_realtime(&mut self, on: bool) {
+        self.pacer = on.then(|| Pacer::new(self.sample_rate));
+    }
+
+    pub fn datatype(&self) -> Datatype {
+        self.datatype
+    }
+
+    /// Read as many whole IQ pairs as fit in `buf`
+    fn read_samples(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len() / 2 * 2;
+        if self.datatype == Datatype::Cu8 {
+            return read_full(&mut self.data, &mut buf[..len]);
+        }
+        let size = self.datatype.size();
+        self.raw.resize(len * size, 0);
+        let n = read_full(&mut self.data, &mut self.raw)? / size;
+        self.datatype.decode(&self.raw[..n * size], &mut buf[..n]);
+        Ok(n)
+    }
+}
+
+impl SampleSource for SigmfReader {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.read_samples(buf)? / 2 * 2;
+        if let Some(pacer) = self.pacer.as_mut() {
+            pacer.wait(n);
+        }
+        Ok(n)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.center_freq
+    }
+}
+
+fn with_ext(base: &Path, ext: &str) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
@@ -0,0 +1,105 @@
+use super::sigmf::{Datatype, SigmfReader};
+use super::SampleSource;
+use crate::pipeline::{Pipeline, ToComplex};
+use num_complex::Complex;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+/// Write a recording to a fresh temp directory, returning its base path
+fn recording(name: &str, datatype: &str, rate: f64, data: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtlsdr-sigmf-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let base = dir.join("capture");
+    let meta = format!(
+        r#"{{"global": {{"core:datatype": "{}", "core:sample_rate": {}, "core:version": "1.0.0"}},
+            "captures": [{{"core:sample_start": 0, "core:frequency": 94900000}}],
+            "annotations": []}}"#,
+        datatype, rate
+    );
+    fs::write(dir.join("capture.sigmf-meta"), meta).unwrap();
+    fs::write(dir.join("capture.sigmf-data"), data).unwrap();
+    base
+}
+
+fn cleanup(base: PathBuf) {
+    fs::remove_dir_all(base.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_cu8_metadata() {
+    let base = recording("cu8", "cu8", 2.4e6, &[1, 2, 3, 4, 5]);
+    let mut reader = SigmfReader::open(base.with_extension("sigmf-meta")).unwrap();
+    assert_eq!(Datatype::Cu8, reader.datatype());
+    assert_eq!(2_400_000, reader.sample_rate());
+    assert_eq!(94_900_000, reader.center_freq());
+    let mut buf = [0_u8; 16];
+    // The trailing half sample is dropped
+    assert_eq!(4, reader.read_sync(&mut buf).unwrap());
+    assert_eq!([1, 2, 3, 4], buf[..4]);
+    assert_eq!(0, reader.read_sync(&mut buf).unwrap());
+    cleanup(base);
+}
+
+#[test]
+fn test_converted_datatypes() {
+    let ci16: Vec<u8> = [0_i16, 32767, -32768, 256]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let cf32: Vec<u8> = [0.0_f32, 0.5, -1.0, 2.0]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    for (name, data) in [
+        ("ci8", vec![0, 127, 0x80, 1]),
+        ("ci16_le", ci16),
+        ("cf32_le", cf32),
+    ] {
+        let base = recording(name, name, 1e6, &data);
+        let mut reader = SigmfReader::open(&base).unwrap();
+        let mut buf = [0_u8; 4];
+        assert_eq!(4, reader.read_sync(&mut buf).unwrap());
+        assert_eq!(127, buf[0]);
+        assert!(buf[1] >= 191);
+        assert_eq!(0, buf[2]);
+        assert!(buf[3] == 128 || buf[3] == 255);
+        cleanup(base);
+    }
+}
+
+#[test]
+fn test_unsupported_datatype() {
+    let base = recording("ru8", "ru8", 1e6, &[0; 4]);
+    assert!(SigmfReader::open(&base).is_err());
+    cleanup(base);
+}
+
+#[test]
+fn test_realtime_pacing() {
+    // 1000 samples at 10 kS/s take 100 ms
+    let base = recording("realtime", "cu8", 1e4, &[127; 2000]);
+    let mut reader = SigmfReader::open(base.with_extension("sigmf-data")).unwrap();
+    reader.set_realtime(true);
+    let start = Instant::now();
+    let mut buf = [0_u8; 500];
+    while reader.read_sync(&mut buf).unwrap() > 0 {}
+    assert!(start.elapsed() >= Duration::from_millis(95));
+    cleanup(base);
+}
+
+#[test]
+fn test_pipeline_playback() {
+    let base = recording("pipeline", "cu8", 1e6, &[127 + 64, 127].repeat(1000));
+    let mut reader = SigmfReader::open(&base).unwrap();
+    let mut samples = Vec::new();
+    let mut pipeline =
+        Pipeline::builder(ToComplex).sink(|buf: &[Complex<f32>]| samples.extend_from_slice(buf));
+    pipeline.run(&mut reader, &AtomicBool::new(false)).unwrap();
+    drop(pipeline);
+    assert_eq!(1000, samples.len());
+    assert!(samples.iter().all(|s| *s == Complex::new(0.5, 0.0)));
+    cleanup(base);
+}
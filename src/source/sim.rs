@@ -0,0 +1,182 @@
+//! A virtual device generating test signals, so demodulators and
+//! applications can be developed and tested without hardware. Signals are
+//! summed and quantized like the ADC output; the test mode counter replaces
+//! them, as it does on the device.
+//!
+//! ```
+//! use rtlsdr_rs::source::{SampleSource, Signal, SimSdr};
+//!
+//! let mut sdr = SimSdr::new(2_048_000, 100_000_000)
+//!     .with_signal(Signal::Fm {
+//!         offset: 250_000,
+//!         amplitude: 0.5,
+//!         deviation: 75_000.0,
+//!         audio_freq: 1_000.0,
+//!     })
+//!     .with_signal(Signal::Noise { amplitude: 0.01 });
+//! let mut buf = vec![0; 16384];
+//! assert_eq!(buf.len(), sdr.read_sync(&mut buf).unwrap());
+//! ```
+
+use super::{Pacer, SampleSource};
+use crate::dsp::SampleFormat;
+use crate::error::Result;
+use num_complex::Complex;
+use std::f64::consts::TAU;
+
+// Noise seed used unless `set_seed` is called, so output is reproducible
+const DEFAULT_SEED: u64 = 0x2832_2838;
+
+/// A generated signal. Amplitudes are relative to the ADC full scale of
+/// about 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// Unmodulated carrier `offset` Hz from the center frequency
+    Tone { offset: i32, amplitude: f32 },
+    /// Carrier `offset` Hz from the center frequency, frequency modulated
+    /// by an `audio_freq` Hz tone with a peak `deviation` in Hz
+    Fm {
+        offset: i32,
+        amplitude: f32,
+        deviation: f32,
+        audio_freq: f32,
+    },
+    /// White Gaussian noise with a standard deviation of `amplitude` on
+    /// each of I and Q
+    Noise { amplitude: f32 },
+    /// The demodulator test mode's 8-bit counter, incrementing every byte
+    Counter,
+}
+
+/// A signal with its oscillator phases, in radians
+struct Generator {
+    signal: Signal,
+    phase: f64,
+    audio_phase: f64,
+}
+
+pub struct SimSdr {
+    sample_rate: u32,
+    center_freq: u32,
+    generators: Vec<Generator>,
+    counter: Option<u8>,  // Next counter value, if the counter is on
+    rng: u64,             // xorshift64* state
+    pacer: Option<Pacer>, // Set for realtime generation
+}
+
+impl SimSdr {
+    /// A device producing only the ADC midpoint until signals are added
+    pub fn new(sample_rate: u32, center_freq: u32) -> SimSdr {
+        SimSdr {
+            sample_rate,
+            center_freq,
+            generators: Vec::new(),
+            counter: None,
+            rng: DEFAULT_SEED,
+            pacer: None,
+        }
+    }
+
+    pub fn with_signal(mut self, signal: Signal) -> SimSdr {
+        self.add_signal(signal);
+        self
+    }
+
+    pub fn add_signal(&mut self, signal: Signal) {
+        if signal == Signal::Counter {
+            self.counter.get_or_insert(0);
+        } else {
+            self.generators.push(Generator {
+                signal,
+                phase: 0.0,
+                audio_phase: 0.0,
+            });
+        }
+    }
+
+    /// Seed the noise generator. The same seed and signals always produce
+    /// the same samples.
+    pub fn set_seed(&mut self, seed: u64) {
+        // xorshift gets stuck at 0
+        self.rng = seed.max(1);
+    }
+
+    /// Pace reads to the sample rate, as a live device would
+    pub fn set_realtime(&mut self, on: bool) {
+        self.pacer = on.then(|| Pacer::new(self.sample_rate));
+    }
+
+    /// Uniform in (0, 1]
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let x = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        ((x >> 11) + 1) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// A pair of independent standard normal values, by Box-Muller
+    fn gaussian(&mut self) -> Complex<f64> {
+        let r = (-2.0 * self.uniform().ln()).sqrt();
+        Complex::from_polar(r, TAU * self.uniform())
+    }
+
+    fn next_sample(&mut self) -> Complex<f32> {
+        let rate = self.sample_rate as f64;
+        let mut sample = Complex::new(0.0, 0.0);
+        for i in 0..self.generators.len() {
+            let generator = &mut self.generators[i];
+            match generator.signal {
+                Signal::Tone { offset, amplitude } => {
+                    sample += Complex::from_polar(amplitude as f64, generator.phase);
+                    generator.phase = (generator.phase + TAU * offset as f64 / rate) % TAU;
+                }
+                Signal::Fm {
+                    offset,
+                    amplitude,
+                    deviation,
+                    audio_freq,
+                } => {
+                    sample += Complex::from_polar(amplitude as f64, generator.phase);
+                    let freq = offset as f64 + deviation as f64 * generator.audio_phase.sin();
+                    generator.phase = (generator.phase + TAU * freq / rate) % TAU;
+                    generator.audio_phase =
+                        (generator.audio_phase + TAU * audio_freq as f64 / rate) % TAU;
+                }
+                Signal::Noise { amplitude } => {
+                    sample += self.gaussian() * amplitude as f64;
+                }
+                Signal::Counter => {}
+            }
+        }
+        Complex::new(sample.re as f32, sample.im as f32)
+    }
+}
+
+impl SampleSource for SimSdr {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len() / 2 * 2;
+        if let Some(counter) = self.counter.as_mut() {
+            for b in &mut buf[..len] {
+                *b = *counter;
+                *counter = counter.wrapping_add(1);
+            }
+        } else {
+            for iq in buf[..len].chunks_exact_mut(2) {
+                self.next_sample().to_raw(iq);
+            }
+        }
+        if let Some(pacer) = self.pacer.as_mut() {
+            pacer.wait(len);
+        }
+        Ok(len)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn center_freq(&self) -> u32 {
+        self.center_freq
+    }
+}
@@ -0,0 +1,94 @@
+use super::{SampleSource, Signal, SimSdr};
+use crate::dsp::to_complex;
+use num_complex::Complex;
+use std::f32::consts::TAU;
+
+const RATE: u32 = 1_024_000;
+
+fn read(sdr: &mut SimSdr, samples: usize) -> Vec<Complex<f32>> {
+    let mut buf = vec![0; samples * 2];
+    assert_eq!(buf.len(), sdr.read_sync(&mut buf).unwrap());
+    to_complex(&buf)
+}
+
+/// Instantaneous frequency of each sample after the first, in Hz
+fn frequencies(samples: &[Complex<f32>]) -> Vec<f32> {
+    samples
+        .windows(2)
+        .map(|w| (w[1] * w[0].conj()).arg() * RATE as f32 / TAU)
+        .collect()
+}
+
+fn power(samples: &[Complex<f32>]) -> f32 {
+    samples.iter().map(|s| s.norm_sqr()).sum::<f32>() / samples.len() as f32
+}
+
+#[test]
+fn test_tone() {
+    let mut sdr = SimSdr::new(RATE, 100_000_000).with_signal(Signal::Tone {
+        offset: -16_000,
+        amplitude: 0.8,
+    });
+    assert_eq!((RATE, 100_000_000), (sdr.sample_rate(), sdr.center_freq()));
+    let samples = read(&mut sdr, 4096);
+    for freq in frequencies(&samples) {
+        assert!((freq + 16_000.0).abs() < 1_000.0, "{}", freq);
+    }
+    assert!((power(&samples) - 0.64).abs() < 0.02);
+}
+
+#[test]
+fn test_fm() {
+    let mut sdr = SimSdr::new(RATE, 100_000_000).with_signal(Signal::Fm {
+        offset: 100_000,
+        amplitude: 0.8,
+        deviation: 75_000.0,
+        audio_freq: 1_000.0,
+    });
+    // A whole audio cycle sweeps the full deviation
+    let freqs = frequencies(&read(&mut sdr, RATE as usize / 1_000 + 1));
+    let max = freqs.iter().cloned().fold(f32::MIN, f32::max);
+    let min = freqs.iter().cloned().fold(f32::MAX, f32::min);
+    assert!((max - 175_000.0).abs() < 2_000.0, "{}", max);
+    assert!((min - 25_000.0).abs() < 2_000.0, "{}", min);
+}
+
+#[test]
+fn test_noise() {
+    let noise = || SimSdr::new(RATE, 100_000_000).with_signal(Signal::Noise { amplitude: 0.1 });
+    let samples = read(&mut noise(), 65536);
+    // Both of I and Q carry the noise power
+    assert!(
+        (power(&samples) - 0.02).abs() < 0.002,
+        "{}",
+        power(&samples)
+    );
+    let mean = samples.iter().sum::<Complex<f32>>() / samples.len() as f32;
+    assert!(mean.norm() < 0.01, "{}", mean);
+
+    // Reproducible for a seed
+    assert_eq!(samples, read(&mut noise(), 65536));
+    let mut reseeded = noise();
+    reseeded.set_seed(42);
+    assert_ne!(samples, read(&mut reseeded, 65536));
+}
+
+#[test]
+fn test_counter() {
+    let mut sdr = SimSdr::new(RATE, 100_000_000)
+        .with_signal(Signal::Tone {
+            offset: 0,
+            amplitude: 1.0,
+        })
+        .with_signal(Signal::Counter);
+    let mut buf = vec![0; 1001];
+    // Reads are whole IQ pairs
+    assert_eq!(1000, sdr.read_sync(&mut buf).unwrap());
+    assert_eq!([0, 1, 2], buf[..3]);
+    assert_eq!([255, 0, 1], buf[255..258]);
+    // The counter continues across reads
+    let last = buf[999];
+    assert_eq!(1000, sdr.read_sync(&mut buf).unwrap());
+    assert_eq!(last.wrapping_add(1), buf[0]);
+    assert!(buf[..1000].windows(2).all(|w| w[1] == w[0].wrapping_add(1)));
+}
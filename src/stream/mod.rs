@@ -0,0 +1,195 @@
+//! Reading samples on a thread of their own, so slow processing or output
+//! doesn't hold up the device. Buffers are queued for the consumer; once it
+//! falls too far behind, new buffers are dropped and counted instead of
+//! letting the device overflow.
+//!
+//! ```no_run
+//! use rtlsdr_rs::stream::{Stream, DEFAULT_QUEUE_LEN};
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let stream = Stream::start(
+//!     || {
+//!         let sdr = RtlSdr::open(0)?;
+//!         sdr.set_center_freq(100_000_000)?;
+//!         sdr.reset_buffer()?;
+//!         Ok(sdr)
+//!     },
+//!     DEFAULT_QUEUE_LEN,
+//! )
+//! .unwrap();
+//! for buf in stream.iter().take(100) {
+//!     // Process buf
+//! }
+//! let stats = stream.stop().unwrap();
+//! eprintln!("{} samples dropped", stats.dropped_samples);
+//! ```
+
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::DEFAULT_BUF_LENGTH;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(test)]
+mod stream_test;
+
+/// Buffers queued before new ones are dropped, about 2 s at 2 MS/s
+pub const DEFAULT_QUEUE_LEN: usize = 32;
+
+/// Sample counts since the stream started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    /// Samples read from the source, including those dropped
+    pub samples: u64,
+    /// Samples dropped because the consumer fell behind
+    pub dropped_samples: u64,
+    /// Reads that returned less than a full buffer, where the device may
+    /// have lost samples
+    pub short_reads: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    samples: AtomicU64,
+    dropped_samples: AtomicU64,
+    short_reads: AtomicU64,
+}
+
+pub struct Stream {
+    rx: Receiver<Vec<u8>>,
+    counters: Arc<Counters>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Stream {
+    /// Read from the source returned by `open`, queueing up to `queue_len`
+    /// buffers. `open` is called on the reading thread, so sources don't
+    /// need to be `Send`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(open), err))]
+    pub fn start<S, F>(open: F, queue_len: usize) -> Result<Stream>
+    where
+        S: SampleSource,
+        F: FnOnce() -> Result<S> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(queue_len);
+        let counters = Arc::new(Counters::default());
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (opened_tx, opened_rx) = mpsc::channel();
+        // Spans don't follow the reading thread, so it enters its own
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("stream");
+        let thread = {
+            let counters = counters.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                #[cfg(feature = "tracing")]
+                let _span = span.entered();
+                let mut source = match open() {
+                    Ok(source) => {
+                        let _ = opened_tx.send(Ok(()));
+                        source
+                    }
+                    Err(e) => {
+                        let _ = opened_tx.send(Err(e));
+                        return Ok(());
+                    }
+                };
+                let mut buf = vec![0_u8; DEFAULT_BUF_LENGTH];
+                while !shutdown.load(Ordering::Relaxed) {
+                    let n = source.read_sync(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    let samples = n as u64 / 2;
+                    counters.samples.fetch_add(samples, Ordering::Relaxed);
+                    if n < buf.len() {
+                        counters.short_reads.fetch_add(1, Ordering::Relaxed);
+                    }
+                    match tx.try_send(buf[..n].to_vec()) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => {
+                            counters
+                                .dropped_samples
+                                .fetch_add(samples, Ordering::Relaxed);
+                        }
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
+                }
+                #[cfg(feature = "tracing")]
+                tracing::info!(
+                    samples = counters.samples.load(Ordering::Relaxed),
+                    dropped_samples = counters.dropped_samples.load(Ordering::Relaxed),
+                    short_reads = counters.short_reads.load(Ordering::Relaxed),
+                    "Stream ended"
+                );
+                Ok(())
+            })
+        };
+        // Report a failure to open before returning
+        opened_rx
+            .recv()
+            .map_err(|_| RtlsdrErr("Stream thread panicked".to_string()))??;
+        Ok(Stream {
+            rx,
+            counters,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// The next buffer, or `None` once the source is exhausted or failed
+    pub fn recv(&self) -> Option<Vec<u8>> {
+        self.rx.recv().ok()
+    }
+
+    /// The next buffer, waiting at most `timeout`, e.g. to check a shutdown
+    /// flag in between. Fails with `Disconnected` once the source is
+    /// exhausted or failed.
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<Vec<u8>, RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    /// Buffers until the source is exhausted or failed
+    pub fn iter(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.rx.iter()
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        StreamStats {
+            samples: self.counters.samples.load(Ordering::Relaxed),
+            dropped_samples: self.counters.dropped_samples.load(Ordering::Relaxed),
+            short_reads: self.counters.short_reads.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop reading and close the source, returning the final counts, or
+    /// the error that stopped the stream if any. Buffers still queued are
+    /// discarded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), ret, err))]
+    pub fn stop(mut self) -> Result<StreamStats> {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .map_err(|_| RtlsdrErr("Stream thread panicked".to_string()))??;
+        }
+        Ok(self.stats())
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
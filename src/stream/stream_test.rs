@@ -0,0 +1,91 @@
+use super::{Stream, StreamStats};
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::DEFAULT_BUF_LENGTH;
+
+/// Full buffers numbered from 0, then a short one, then the end or an error
+struct Fake {
+    buffers: u8,
+    read: u8,
+    fail: bool,
+}
+
+impl SampleSource for Fake {
+    fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = match self.read {
+            r if r < self.buffers => buf.len(),
+            r if r == self.buffers => 100,
+            _ if self.fail => return Err(RtlsdrErr("Unplugged".to_string())),
+            _ => 0,
+        };
+        buf[..n].fill(self.read);
+        self.read = self.read.saturating_add(1);
+        Ok(n)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        2_048_000
+    }
+
+    fn center_freq(&self) -> u32 {
+        100_000_000
+    }
+}
+
+fn fake(buffers: u8, fail: bool) -> impl FnOnce() -> Result<Fake> {
+    move || {
+        Ok(Fake {
+            buffers,
+            read: 0,
+            fail,
+        })
+    }
+}
+
+#[test]
+fn test_stream() {
+    let stream = Stream::start(fake(4, false), 8).unwrap();
+    let received: Vec<Vec<u8>> = stream.iter().collect();
+    assert_eq!(5, received.len());
+    for (i, buf) in received.iter().enumerate() {
+        assert!(buf.iter().all(|&b| b == i as u8));
+    }
+    assert_eq!(100, received[4].len());
+    assert_eq!(
+        StreamStats {
+            samples: (4 * DEFAULT_BUF_LENGTH as u64 + 100) / 2,
+            dropped_samples: 0,
+            short_reads: 1,
+        },
+        stream.stop().unwrap()
+    );
+}
+
+#[test]
+fn test_slow_consumer() {
+    let stream = Stream::start(fake(10, false), 2).unwrap();
+    // Wait for the source to run out while nothing is consumed
+    while stream.thread.as_ref().is_some_and(|t| !t.is_finished()) {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    // The first buffers were kept, and the rest dropped
+    let received: Vec<Vec<u8>> = stream.iter().collect();
+    assert_eq!(2, received.len());
+    assert!(received[1].iter().all(|&b| b == 1));
+    let stats = stream.stop().unwrap();
+    assert_eq!(
+        stats.samples - DEFAULT_BUF_LENGTH as u64,
+        stats.dropped_samples
+    );
+}
+
+#[test]
+fn test_errors() {
+    let open = || -> Result<Fake> { Err(RtlsdrErr("No device".to_string())) };
+    assert!(Stream::start(open, 8).is_err());
+
+    let stream = Stream::start(fake(1, true), 8).unwrap();
+    assert_eq!(2, stream.iter().count());
+    assert!(stream.stop().is_err());
+}
@@ -0,0 +1,95 @@
+use crate::error::Result;
+use crate::error::RtlsdrError::RtlsdrErr;
+use crate::source::SampleSource;
+use crate::stream::Stream;
+use std::fmt::{Debug, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Spans and events as "name field=value ..." lines
+#[derive(Clone, Default)]
+struct Recorder {
+    lines: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct Fields<'a>(&'a mut String);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = write!(self.0, " {}={:?}", field.name(), value);
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut line = span.metadata().name().to_string();
+        span.record(&mut Fields(&mut line));
+        self.lines.lock().unwrap().push(line);
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut line = "event".to_string();
+        event.record(&mut Fields(&mut line));
+        self.lines.lock().unwrap().push(line);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+struct Empty;
+
+impl SampleSource for Empty {
+    fn read_sync(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        2_048_000
+    }
+
+    fn center_freq(&self) -> u32 {
+        100_000_000
+    }
+}
+
+#[test]
+fn test_stream_spans() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let stream = Stream::start(|| Ok(Empty), 8).unwrap();
+        while stream.recv().is_some() {}
+        stream.stop().unwrap();
+        let open = || -> Result<Empty> { Err(RtlsdrErr("No device".to_string())) };
+        assert!(Stream::start(open, 4).is_err());
+    });
+    let lines = recorder.lines.lock().unwrap();
+    assert_eq!("start queue_len=8", lines[0]);
+    assert_eq!("stream", lines[1]);
+    assert!(lines.contains(&"stop".to_string()), "{:?}", lines);
+    assert!(lines
+        .iter()
+        .any(|l| l.starts_with("event return=StreamStats")));
+    assert!(lines.contains(&"start queue_len=4".to_string()));
+    assert!(
+        lines
+            .iter()
+            .any(|l| l.contains("error=") && l.contains("No device")),
+        "{:?}",
+        lines
+    );
+}
@@ -1,31 +1,58 @@
 pub mod r820t;
 use crate::device::Device;
 use crate::error::Result;
-use crate::TunerGain;
+use crate::{NotchFilter, TrackingFilter, TunerGain};
 
 pub const KNOWN_TUNERS: [TunerInfo; 1] = [r820t::TUNER_INFO];
 
 #[derive(Debug, Clone, Copy)]
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TunerInfo {
     pub id: &'static str,
     pub name: &'static str,
     pub i2c_addr: u8,
     pub check_addr: u8,
     pub check_val: u8,
-    // pub gains: Vec<i8>,
+    pub caps: TunerCapabilities,
 }
 
-pub trait Tuner: std::fmt::Debug {
+/// Describes what a tuner supports so applications can adapt without matching on tuner IDs
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TunerCapabilities {
+    /// Supported gain steps in tenths of a dB
+    pub gains: &'static [i32],
+    /// Number of independently adjustable IF gain stages, see
+    /// `RtlSdr::set_tuner_if_gain`
+    pub if_gain_stages: u8,
+    /// Supported IF filter bandwidths in Hz
+    pub bandwidths: &'static [u32],
+    /// Lowest tunable frequency in Hz, in the current harmonic mode
+    pub min_freq: u32,
+    /// Highest tunable frequency in Hz, in the current harmonic mode
+    pub max_freq: u32,
+    /// Whether offset tuning applies (zero-IF tuners only)
+    pub offset_tuning: bool,
+}
+
+pub trait Tuner: std::fmt::Debug + Send {
     fn init(&mut self, handle: &Device) -> Result<()>;
     fn get_info(&self) -> Result<TunerInfo>;
     fn get_gains(&self) -> Result<Vec<i32>>;
     fn read_gain(&self, handle: &Device) -> Result<i32>;
     fn set_gain(&mut self, handle: &Device, gain: TunerGain) -> Result<()>;
+    fn set_if_gain(&mut self, handle: &Device, stage: u8, gain: i32) -> Result<()>;
     fn set_freq(&mut self, handle: &Device, freq: u32) -> Result<()>;
+    fn get_pll_locked(&self) -> Result<bool>;
+    fn recalibrate_filter(&mut self, handle: &Device, retries: u8) -> Result<u8>;
+    fn get_lo_freq(&self) -> Result<f64>;
+    fn set_harmonic(&mut self, harmonic: u8) -> Result<()>;
+    fn set_dither(&mut self, dither: bool) -> Result<()>;
     fn set_bandwidth(&mut self, handle: &Device, bw: u32, rate: u32) -> Result<()>;
+    fn set_tracking_filter(&mut self, handle: &Device, filter: TrackingFilter) -> Result<()>;
+    fn set_notch_filter(&mut self, handle: &Device, notch: NotchFilter) -> Result<()>;
     fn get_if_freq(&self) -> Result<u32>;
-    fn get_xtal_freq(&self) -> Result<u32>;
+    fn set_if_freq(&mut self, freq: u32) -> Result<()>;
     fn set_xtal_freq(&mut self, freq: u32) -> Result<()>;
     fn exit(&mut self, handle: &Device) -> Result<()>;
 }
@@ -42,6 +69,14 @@ impl Tuner for NoTuner {
             i2c_addr: 0,
             check_addr: 0,
             check_val: 0,
+            caps: TunerCapabilities {
+                gains: &[],
+                if_gain_stages: 0,
+                bandwidths: &[],
+                min_freq: 0,
+                max_freq: 0,
+                offset_tuning: false,
+            },
         })
     }
     fn get_gains(&self) -> Result<Vec<i32>> {
@@ -53,14 +88,35 @@ impl Tuner for NoTuner {
     fn set_gain(&mut self, _handle: &Device, _gain: TunerGain) -> Result<()> {
         Ok(())
     }
+    fn set_if_gain(&mut self, _handle: &Device, _stage: u8, _gain: i32) -> Result<()> {
+        Ok(())
+    }
     fn set_freq(&mut self, _handle: &Device, _freq: u32) -> Result<()> {
         Ok(())
     }
+    fn get_pll_locked(&self) -> Result<bool> {
+        Ok(true)
+    }
+    fn recalibrate_filter(&mut self, _handle: &Device, _retries: u8) -> Result<u8> {
+        Ok(0)
+    }
+    fn get_lo_freq(&self) -> Result<f64> {
+        Ok(0.0)
+    }
+    fn set_harmonic(&mut self, _harmonic: u8) -> Result<()> {
+        Ok(())
+    }
+    fn set_dither(&mut self, _dither: bool) -> Result<()> {
+        Ok(())
+    }
     fn set_bandwidth(&mut self, _handle: &Device, _bw: u32, _rate: u32) -> Result<()> {
         Ok(())
     }
-    fn get_xtal_freq(&self) -> Result<u32> {
-        Ok(0)
+    fn set_tracking_filter(&mut self, _handle: &Device, _filter: TrackingFilter) -> Result<()> {
+        Ok(())
+    }
+    fn set_notch_filter(&mut self, _handle: &Device, _notch: NotchFilter) -> Result<()> {
+        Ok(())
     }
     fn set_xtal_freq(&mut self, _freq: u32) -> Result<()> {
         Ok(())
@@ -68,6 +124,9 @@ impl Tuner for NoTuner {
     fn get_if_freq(&self) -> Result<u32> {
         Ok(0)
     }
+    fn set_if_freq(&mut self, _freq: u32) -> Result<()> {
+        Ok(())
+    }
     fn exit(&mut self, _handle: &Device) -> Result<()> {
         Ok(())
     }
@@ -1,8 +1,9 @@
-use super::{Tuner, TunerGain, TunerInfo};
+use super::{Tuner, TunerCapabilities, TunerGain, TunerInfo};
 use crate::device::Device;
 use crate::error::Result;
 use crate::error::RtlsdrError::RtlsdrErr;
-use log::info;
+use crate::{NotchFilter, TrackingFilter};
+use log::{info, warn};
 
 const R820T_I2C_ADDR: u16 = 0x34;
 // const R828D_I2C_ADDR: u8 = 0x74; for now only support the T
@@ -13,6 +14,12 @@ const RW_REG_START: usize = 5; // registers 0-4 are read-only
 const NUM_CACHE_REGS: usize = NUM_REGS - RW_REG_START; // only cache RW regs
 const MAX_I2C_MSG_LEN: usize = 8;
 
+// Filter calibration
+const FILT_CAL_LO: u32 = 56000; /* 52000->56000 */
+const FILT_CAL_RETRIES: u8 = 2;
+const FILT_Q: u8 = 0x10; /* r10[4]:low q(1'b1) */
+const HP_COR: u8 = 0x6b; /* 1.7m disable, +2cap, 1.0mhz */
+
 // Init registers (32 total, first 5 are read-only)
 const REG_INIT: [u8; NUM_CACHE_REGS] = [
     0x83, 0x32, 0x75, /* 05 to 07 */
@@ -33,7 +40,16 @@ const GAINS: [i32; 29] = [
     0, 9, 14, 27, 37, 77, 87, 125, 144, 157, 166, 197, 207, 229, 254, 280, 297, 328, 338, 364, 372,
     386, 402, 421, 434, 439, 445, 480, 496,
 ];
-const _R82XX_VGA_GAIN_STEPS: [i32; 16] = [
+// IF filter bandwidths reachable through set_bandwidth. Below 6 MHz these are
+// the low-pass filter settings, widened by one or both high-pass corners.
+const BANDWIDTHS: [u32; 22] = [
+    350_000, 450_000, 550_000, 700_000, 900_000, 1_200_000, 1_450_000, 1_550_000, 1_600_000,
+    1_700_000, 1_800_000, 1_900_000, 1_950_000, 2_050_000, 2_080_000, 2_180_000, 2_280_000,
+    2_330_000, 2_430_000, 6_000_000, 7_000_000, 8_000_000,
+];
+// VGA gain at code 0, from which R82XX_VGA_GAIN_STEPS count up
+const R82XX_VGA_BASE_GAIN: i32 = -47;
+const R82XX_VGA_GAIN_STEPS: [i32; 16] = [
     0, 26, 26, 30, 42, 35, 24, 13, 14, 32, 36, 34, 35, 37, 35, 36,
 ];
 
@@ -247,9 +263,9 @@ const FREQ_RANGES: [FreqRange; 21] = [
 
 #[allow(dead_code)]
 enum TunerType {
-    TunerRadio,
-    TunerAnalogTv,
-    TunerDigitalTv,
+    Radio,
+    AnalogTv,
+    DigitalTv,
 }
 
 #[derive(Debug)]
@@ -285,12 +301,19 @@ pub struct R820T {
     regs: [u8; NUM_CACHE_REGS],
     pub freq: u32,
     int_freq: u32,
+    if_override: u32, // User-selected IF in Hz, 0 to use int_freq
     xtal_cap_sel: XtalCapValue,
     xtal: u32,
     use_predetect: bool,
     has_lock: bool,
     fil_cal_code: u8,
     init_done: bool,
+    tracking_filter: TrackingFilter,
+    notch: NotchFilter,
+    harmonic: u8,
+    dither: bool,
+    pll_freq: f64,   // Exact PLL output frequency from the last set_pll, in Hz
+    vga: Option<u8>, // VGA gain code from set_if_gain, None for the defaults
 }
 
 pub const TUNER_ID: &str = "r820t";
@@ -301,27 +324,37 @@ pub const TUNER_INFO: TunerInfo = TunerInfo {
     i2c_addr: 0x34,
     check_addr: 0x00,
     check_val: 0x69,
-    // gains: vec![
-    //     0, 9, 14, 27, 37, 77, 87, 125, 144, 157, 166, 197, 207, 229, 254, 280, 297, 328, 338, 364,
-    //     372, 386, 402, 421, 434, 439, 445, 480, 496,
-    // ],
+    caps: TunerCapabilities {
+        gains: &GAINS,
+        if_gain_stages: 1, // The VGA
+        bandwidths: &BANDWIDTHS,
+        min_freq: 24_000_000,
+        max_freq: 1_766_000_000,
+        offset_tuning: false,
+    },
 };
 
 impl R820T {
-    pub fn new(_handle: &mut Device) -> R820T {
-        let tuner = R820T {
+    pub fn new(_handle: &Device) -> R820T {
+        R820T {
             info: TUNER_INFO,
             regs: REG_INIT,
             freq: 0,
             int_freq: 0,
+            if_override: 0,
             xtal_cap_sel: XtalCapValue::XtalLowCap30p,
             xtal: 0,
             has_lock: false,
             init_done: false,
             use_predetect: false,
             fil_cal_code: 0,
-        };
-        tuner
+            tracking_filter: TrackingFilter::Auto,
+            notch: NotchFilter::Auto,
+            harmonic: 1,
+            dither: true,
+            pll_freq: 0.0,
+            vga: None,
+        }
     }
 }
 
@@ -337,26 +370,28 @@ impl Tuner for R820T {
         // Initialize registers
         self.write_regs(handle, 0x05, &REG_INIT)?;
 
-        self.set_tv_standard(handle, 3, TunerType::TunerDigitalTv)?;
-        self.sysfreq_sel(
-            handle,
-            0,
-            TunerType::TunerDigitalTv,
-            DeliverySystem::SysDvbt,
-        )?;
+        self.set_tv_standard(handle, 3, TunerType::DigitalTv)?;
+        self.sysfreq_sel(handle, 0, TunerType::DigitalTv, DeliverySystem::SysDvbt)?;
         self.init_done = true;
         Ok(())
     }
 
     fn get_info(&self) -> Result<TunerInfo> {
-        Ok(self.info)
+        // Harmonic mode moves the range the PLL covers up by the harmonic
+        let mut info = self.info;
+        let harmonic = self.harmonic as u32;
+        info.caps.min_freq = info.caps.min_freq.saturating_mul(harmonic);
+        info.caps.max_freq = info.caps.max_freq.saturating_mul(harmonic);
+        Ok(info)
     }
 
     fn get_gains(&self) -> Result<Vec<i32>> {
-        Ok(GAINS.to_vec())
+        Ok(self.info.caps.gains.to_vec())
     }
 
     fn read_gain(&self, handle: &Device) -> Result<i32> {
+        // R3 holds the LNA gain in the low nibble and the mixer gain in the
+        // high nibble, as set by the tuner AGC
         let mut data: [u8; 4] = [0; 4];
         self.read_reg(handle, 0x00, &mut data, 4)?;
         let gain = ((data[3] & 0x0f) << 1) + ((data[3] & 0xf0) >> 4);
@@ -370,8 +405,8 @@ impl Tuner for R820T {
                 self.write_reg_mask(handle, 0x05, 0, 0x10)?;
                 // Mixer
                 self.write_reg_mask(handle, 0x07, 0x10, 0x10)?;
-                // Set fixed VGA gain for now (26.5 dB)
-                self.write_reg_mask(handle, 0x0c, 0x0b, 0x9f)?;
+                // VGA gain from set_if_gain, or a fixed 26.5 dB
+                self.write_reg_mask(handle, 0x0c, self.vga.unwrap_or(0x0b), 0x9f)?;
             }
             TunerGain::Manual(gain) => {
                 let mut data: [u8; 4] = [0; 4];
@@ -414,24 +449,66 @@ impl Tuner for R820T {
                 // Mixer
                 self.write_reg_mask(handle, 0x07, 0x10, 0x10)?;
 
-                // Set fixed VGA gain for now (26.5dB)
-                self.write_reg_mask(handle, 0x0c, 0x0b, 0x9f)?;
+                // VGA gain from set_if_gain, or a fixed 26.5 dB
+                self.write_reg_mask(handle, 0x0c, self.vga.unwrap_or(0x0b), 0x9f)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_if_gain(&mut self, handle: &Device, _stage: u8, gain: i32) -> Result<()> {
+        // The VGA is the only IF stage. Pick the code closest to `gain`.
+        let mut step_gain = R82XX_VGA_BASE_GAIN;
+        let (mut code, mut best) = (0, i32::MAX);
+        for (i, step) in R82XX_VGA_GAIN_STEPS.iter().enumerate() {
+            step_gain += step;
+            if (step_gain - gain).abs() < best {
+                (code, best) = (i as u8, (step_gain - gain).abs());
             }
         }
+        self.write_reg_mask(handle, 0x0c, code, 0x9f)?;
+        self.vga = Some(code);
         Ok(())
     }
 
     fn set_freq(&mut self, handle: &Device, freq: u32) -> Result<()> {
         info!("set_freq - freq: {}", freq);
-        let lo_freq = freq + self.int_freq;
+        let lo_freq = freq
+            .checked_add(self.if_freq())
+            .ok_or_else(|| RtlsdrErr(format!("Can't tune to {} Hz", freq)))?;
         info!("set_freq - lo_freq: {}", lo_freq);
         self.set_mux(handle, lo_freq)?;
-        self.set_pll(handle, lo_freq)?;
+        // In harmonic mode the PLL runs at a fraction of the LO and the mixer
+        // uses its nth harmonic
+        self.set_pll(handle, lo_freq / self.harmonic as u32)?;
+        self.freq = freq;
 
         // TODO: Some extra stuff for the 828D tuner when we support that
         Ok(())
     }
 
+    fn get_pll_locked(&self) -> Result<bool> {
+        Ok(self.has_lock)
+    }
+
+    fn recalibrate_filter(&mut self, handle: &Device, retries: u8) -> Result<u8> {
+        self.calibrate_filter(handle, retries)
+    }
+
+    fn get_lo_freq(&self) -> Result<f64> {
+        Ok(self.pll_freq * self.harmonic as f64)
+    }
+
+    fn set_harmonic(&mut self, harmonic: u8) -> Result<()> {
+        self.harmonic = harmonic;
+        Ok(())
+    }
+
+    fn set_dither(&mut self, dither: bool) -> Result<()> {
+        self.dither = dither;
+        Ok(())
+    }
+
     fn set_bandwidth(&mut self, handle: &Device, bw_in: u32, _rate: u32) -> Result<()> {
         let mut bw: i32 = bw_in as i32;
         const FILT_HP_BW1: i32 = 350_000;
@@ -495,12 +572,30 @@ impl Tuner for R820T {
         Ok(())
     }
 
+    fn set_tracking_filter(&mut self, handle: &Device, filter: TrackingFilter) -> Result<()> {
+        self.tracking_filter = filter;
+        // Apply immediately if already tuned, otherwise on the next set_freq
+        if self.freq != 0 {
+            self.set_mux(handle, self.freq + self.if_freq())?;
+        }
+        Ok(())
+    }
+
+    fn set_notch_filter(&mut self, handle: &Device, notch: NotchFilter) -> Result<()> {
+        self.notch = notch;
+        if self.freq != 0 {
+            self.set_mux(handle, self.freq + self.if_freq())?;
+        }
+        Ok(())
+    }
+
     fn get_if_freq(&self) -> Result<u32> {
-        Ok(self.int_freq)
+        Ok(self.if_freq())
     }
 
-    fn get_xtal_freq(&self) -> Result<u32> {
-        Ok(self.xtal)
+    fn set_if_freq(&mut self, freq: u32) -> Result<()> {
+        self.if_override = freq;
+        Ok(())
     }
 
     fn set_xtal_freq(&mut self, freq: u32) -> Result<()> {
@@ -531,6 +626,15 @@ impl Tuner for R820T {
 impl R820T {
     // Tuning logic
 
+    /// IF in use: the user override if set, otherwise the one chosen by the bandwidth config
+    fn if_freq(&self) -> u32 {
+        if self.if_override != 0 {
+            self.if_override
+        } else {
+            self.int_freq
+        }
+    }
+
     fn set_mux(&mut self, handle: &Device, freq: u32) -> Result<()> {
         // Get the proper frequency range
         let freq_mhz = freq / 1_000_000;
@@ -548,20 +652,33 @@ impl R820T {
             r
         };
 
-        // Open Drain
-        self.write_reg_mask(handle, 0x17, range.open_d, 0x08)?;
+        // Open Drain (notch filter)
+        let open_d = match self.notch {
+            NotchFilter::Auto => range.open_d,
+            NotchFilter::On => 0x08,
+            NotchFilter::Off => 0x00,
+        };
+        self.write_reg_mask(handle, 0x17, open_d, 0x08)?;
 
-        // RF_MUX, Polymux
-        self.write_reg_mask(handle, 0x1a, range.rf_mux_ploy, 0xc3)?;
+        // RF_MUX, Polymux. R26[7:6] = 01 bypasses the tracking filter
+        let rf_mux_ploy = match self.tracking_filter {
+            TrackingFilter::Bypass => (range.rf_mux_ploy & 0x3f) | 0x40,
+            _ => range.rf_mux_ploy,
+        };
+        self.write_reg_mask(handle, 0x1a, rf_mux_ploy, 0xc3)?;
 
         // TF Band
-        self.write_regs(handle, 0x1b, &[range.tf_c])?;
+        let tf_c = match self.tracking_filter {
+            TrackingFilter::Manual(tf_c) => tf_c,
+            _ => range.tf_c,
+        };
+        self.write_regs(handle, 0x1b, &[tf_c])?;
 
         // XTAL CAP & Drive
         let val = match self.xtal_cap_sel {
             XtalCapValue::XtalLowCap30p | XtalCapValue::XtalLowCap20p => range.xtal_cap20p | 0x08,
             XtalCapValue::XtalLowCap10p => range.xtal_cap10p | 0x08,
-            XtalCapValue::XtalHighCap0p => range.xtal_cap0p | 0x00,
+            XtalCapValue::XtalHighCap0p => range.xtal_cap0p,
             XtalCapValue::XtalLowCap0p => range.xtal_cap0p | 0x08,
         };
         self.write_reg_mask(handle, 0x10, val, 0x0b)?;
@@ -589,9 +706,6 @@ impl R820T {
         #[cfg(not(feature = "rtl_sdr_blog"))]
         self.write_reg_mask(handle, 0x12, 0x80, 0xe0)?;
 
-        // Test turning tracking filter off
-        // self.write_reg_mask(handle, 0x1a, 0x40, 0xc0);
-
         // Calculate divider
         let vco_min: u32 = 1770000;
         let vco_max: u32 = vco_min * 2;
@@ -601,12 +715,12 @@ impl R820T {
             if ((freq_khz * mix_div as u32) >= vco_min) && ((freq_khz * mix_div as u32) < vco_max) {
                 let mut div_buf = mix_div;
                 while div_buf > 2 {
-                    div_buf = div_buf >> 1;
+                    div_buf >>= 1;
                     div_num += 1;
                 }
                 break;
             }
-            mix_div = mix_div << 1;
+            mix_div <<= 1;
         }
 
         let mut data: [u8; 5] = [0; 5];
@@ -615,9 +729,11 @@ impl R820T {
         let vco_power_ref = 2;
         let vco_fine_tune = (data[4] & 0x30) >> 4;
         if vco_fine_tune > vco_power_ref {
-            div_num = div_num - 1;
+            div_num = div_num
+                .checked_sub(1)
+                .ok_or_else(|| RtlsdrErr(format!("No PLL divider for {} Hz", freq)))?;
         } else if vco_fine_tune < vco_power_ref {
-            div_num = div_num + 1;
+            div_num += 1;
         }
         self.write_reg_mask(handle, 0x10, div_num << 5, 0xe0)?;
 
@@ -648,28 +764,34 @@ impl R820T {
         );
         self.write_regs(handle, 0x14, &[ni.overflowing_add(si << 6).0])?;
 
-        // pw_sdm
+        // pw_sdm (bit 3) and SDM dither disable (bit 4)
+        let mut val = 0;
         if vco_fra == 0 {
-            self.write_reg_mask(handle, 0x12, 0x08, 0x08)?;
-        } else {
-            self.write_reg_mask(handle, 0x12, 0x00, 0x08)?;
+            val |= 0x08;
+        }
+        if !self.dither {
+            val |= 0x10;
         }
+        self.write_reg_mask(handle, 0x12, val, 0x18)?;
 
         // SDM Calculator
         let mut sdm = 0;
         let mut n_sdm = 2;
         while vco_fra > 1 {
             if vco_fra > (2 * pll_ref_khz / n_sdm) {
-                sdm = sdm + 32768 / (n_sdm / 2);
-                vco_fra = vco_fra - 2 * pll_ref_khz / n_sdm;
+                sdm += 32768 / (n_sdm / 2);
+                vco_fra -= 2 * pll_ref_khz / n_sdm;
                 if n_sdm >= 0x8000 {
                     break;
                 }
             }
-            n_sdm = n_sdm << 1;
+            n_sdm <<= 1;
         }
         self.write_regs(handle, 0x16, &[(sdm >> 8) as u8])?;
         self.write_regs(handle, 0x15, &[(sdm & 0xff) as u8])?;
+        // VCO = 2 * ref * (nint + sdm / 2^16), divided down by mix_div
+        self.pll_freq =
+            2.0 * pll_ref as f64 * (nint as f64 + sdm as f64 / 65536.0) / mix_div as f64;
         for i in 0..2 {
             // Check if PLL has locked
             self.read_reg(handle, 0x00, &mut data, 3)?;
@@ -706,7 +828,7 @@ impl R820T {
         let mixer_top;
         let lna_top;
         let cp_cur;
-        let mut div_buf_cur;
+        let div_buf_cur;
         let lna_vth_l;
         let mixer_vth_l;
         let air_cable1_in;
@@ -791,16 +913,17 @@ impl R820T {
         self.write_reg_mask(handle, 0x11, cp_cur, 0x38)?;
 
         // RTLSDRBLOG. Improve L-band performance by setting PLL drop out to 2.0v
-        #[cfg(feature = "rtl_sdr_blog")]
-        {
-            div_buf_cur = 0xa0;
-        }
+        let div_buf_cur = if cfg!(feature = "rtl_sdr_blog") {
+            0xa0
+        } else {
+            div_buf_cur
+        };
 
         self.write_reg_mask(handle, 0x17, div_buf_cur, 0x30)?;
         self.write_reg_mask(handle, 0x0a, filter_cur, 0x60)?;
 
         // Set LNA
-        if !matches!(tuner_type, TunerType::TunerAnalogTv) {
+        if !matches!(tuner_type, TunerType::AnalogTv) {
             // LNA TOP: lowest
             self.write_reg_mask(handle, 0x1d, 0, 0x38)?;
             // 0: normal mode
@@ -847,11 +970,8 @@ impl R820T {
     fn set_tv_standard(&mut self, handle: &Device, _bw: u32, tuner_type: TunerType) -> Result<()> {
         /* BW < 6 MHz */
         let if_khz = 3570;
-        let filt_cal_lo = 56000; /* 52000->56000 */
         let filt_gain = 0x10; /* +3db, 6mhz on */
         let img_r = 0x00; /* image negative */
-        let filt_q = 0x10; /* r10[4]:low q(1'b1) */
-        let hp_cor = 0x6b; /* 1.7m disable, +2cap, 1.0mhz */
         let ext_enable = 0x60; /* r30[6]=1 ext enable; r30[5]:1 ext at lna max-1 */
         let loop_through = 0x01; /* r5[7], lt off */
         let lt_att = 0x00; /* r31[7], lt att enable */
@@ -868,47 +988,17 @@ impl R820T {
         self.write_reg_mask(handle, 0x13, VER_NUM, 0x3f)?;
 
         // for LT Gain test
-        if !matches!(tuner_type, TunerType::TunerAnalogTv) {
+        if !matches!(tuner_type, TunerType::AnalogTv) {
             self.write_reg_mask(handle, 0x1d, 0x00, 0x38)?;
         }
         self.int_freq = if_khz * 1000;
 
         /* Check if standard changed. If so, filter calibration is needed */
         /* Since we call this function only once in rtlsdr, force calibration */
-        let need_calibration = true;
-        if need_calibration {
-            for _ in 0..2 {
-                // Set filt_cap
-                self.write_reg_mask(handle, 0x0b, hp_cor, 0x60)?;
-                // set cali clk = on
-                self.write_reg_mask(handle, 0x0f, 0x04, 0x04)?;
-                // X'tal cap 0pF for PLL
-                self.write_reg_mask(handle, 0x10, 0x00, 0x03)?;
-
-                self.set_pll(handle, filt_cal_lo * 1000)?;
-
-                // Start trigger
-                self.write_reg_mask(handle, 0x0b, 0x10, 0x10)?;
-                // Stop trigger
-                self.write_reg_mask(handle, 0x0b, 0x00, 0x04)?;
-
-                // Check if calibration worked
-                let mut data: [u8; 5] = [0; 5];
-                self.read_reg(handle, 0x00, &mut data, 5)?;
-                self.fil_cal_code = data[4] & 0x0f;
-                if self.fil_cal_code & self.fil_cal_code != 0x0f {
-                    break;
-                }
-                // Narrowest
-                if self.fil_cal_code == 0x0f {
-                    self.fil_cal_code = 0;
-                }
-            }
-        }
-        self.write_reg_mask(handle, 0x0a, filt_q | self.fil_cal_code, 0x1f)?;
+        self.calibrate_filter(handle, FILT_CAL_RETRIES)?;
 
         // Set BW, Filter_gain, and HP corner
-        self.write_reg_mask(handle, 0x0b, hp_cor, 0xef)?;
+        self.write_reg_mask(handle, 0x0b, HP_COR, 0xef)?;
 
         // Set Img_R
         self.write_reg_mask(handle, 0x07, img_r, 0x80)?;
@@ -935,13 +1025,60 @@ impl R820T {
         Ok(())
     }
 
+    /// Run the IF filter calibration, retrying until the tuner reports a usable
+    /// code. Falls back to the narrowest filter if every attempt fails.
+    fn calibrate_filter(&mut self, handle: &Device, retries: u8) -> Result<u8> {
+        // The calibration borrows the PLL, so keep the state of the last tune
+        let (has_lock, pll_freq) = (self.has_lock, self.pll_freq);
+        let mut calibrated = false;
+        for _ in 0..retries.max(1) {
+            // Set filt_cap
+            self.write_reg_mask(handle, 0x0b, HP_COR, 0x60)?;
+            // set cali clk = on
+            self.write_reg_mask(handle, 0x0f, 0x04, 0x04)?;
+            // X'tal cap 0pF for PLL
+            self.write_reg_mask(handle, 0x10, 0x00, 0x03)?;
+
+            self.set_pll(handle, FILT_CAL_LO * 1000)?;
+
+            // Start trigger
+            self.write_reg_mask(handle, 0x0b, 0x10, 0x10)?;
+            // Stop trigger
+            self.write_reg_mask(handle, 0x0b, 0x00, 0x10)?;
+            // set cali clk = off
+            self.write_reg_mask(handle, 0x0f, 0x00, 0x04)?;
+
+            // Check if calibration worked
+            let mut data: [u8; 5] = [0; 5];
+            self.read_reg(handle, 0x00, &mut data, 5)?;
+            self.fil_cal_code = data[4] & 0x0f;
+            if self.fil_cal_code != 0 && self.fil_cal_code != 0x0f {
+                calibrated = true;
+                break;
+            }
+        }
+        self.has_lock = has_lock;
+        self.pll_freq = pll_freq;
+        if !calibrated {
+            warn!(
+                "[R82xx] Filter calibration failed (code {:#x}), using narrowest filter",
+                self.fil_cal_code
+            );
+        }
+        // Narrowest
+        if self.fil_cal_code == 0x0f {
+            self.fil_cal_code = 0;
+        }
+        self.write_reg_mask(handle, 0x0a, FILT_Q | self.fil_cal_code, 0x1f)?;
+        info!("[R82xx] Filter calibration code: {:#x}", self.fil_cal_code);
+        Ok(self.fil_cal_code)
+    }
+
     fn _xtal_check(&mut self, handle: &Device) -> Result<u8> {
         let mut data: [u8; 3] = [0; 3];
 
         // Initialize register cache
-        for i in RW_REG_START..NUM_REGS {
-            self.regs[i] = REG_INIT[i];
-        }
+        self.regs.copy_from_slice(&REG_INIT);
 
         // cap 30pF & Drive Low
         self.write_reg_mask(handle, 0x10, 0x0b, 0x0b)?;
@@ -961,39 +1098,36 @@ impl R820T {
             }
 
             let val = data[2] & 0x3f;
-            if (self.xtal == 16_000_000 && (val > 29 || val < 23)) || val != 0x3f {
+            if (self.xtal == 16_000_000 && !(23..=29).contains(&val)) || val != 0x3f {
                 return Ok(*cap_val);
             }
         }
-        Err(RtlsdrErr(format!(
-            "Unable to find good xtal capacitor value!"
-        )))
+        Err(RtlsdrErr(
+            "Unable to find good xtal capacitor value!".to_string(),
+        ))
     }
 
     /// Write register with bit-masked data
     fn write_reg_mask(&mut self, handle: &Device, reg: usize, val: u8, bit_mask: u8) -> Result<()> {
-        let rc = self.read_cache_reg(reg);
+        let rc = self.read_cache_reg(reg)?;
         // Compute the desired register value: (rc & !mask) gets the unmasked bits and leaves the masked as 0,
         // and (val & mask) gets just the masked bits we want to set. Or together to get the desired register.
         let applied: u8 = (rc & !bit_mask) | (val & bit_mask);
-        Ok(self.write_regs(handle, reg, &[applied])?)
+        self.write_regs(handle, reg, &[applied])
     }
 
-    /// Read register data from local cache
-    /// # Panics
-    /// * If `reg` is less than `RW_REG_START`
-    /// * If `reg` is greater than `NUM_REGS`
-    fn read_cache_reg(&self, reg: usize) -> u8 {
-        assert!(reg >= RW_REG_START); // is assert the best thing to use here?
-        let index = reg - RW_REG_START;
-        assert!(index < NUM_CACHE_REGS); // is assert the best thing to use here?
-        self.regs[index]
+    /// Read register data from local cache. Fails if `reg` isn't a cached
+    /// register.
+    fn read_cache_reg(&self, reg: usize) -> Result<u8> {
+        reg.checked_sub(RW_REG_START)
+            .and_then(|index| self.regs.get(index).copied())
+            .ok_or_else(|| RtlsdrErr(format!("Register {:#04x} isn't cached", reg)))
     }
 
     /// Write data to device registers (r82xx_write)
     fn write_regs(&mut self, handle: &Device, reg: usize, val: &[u8]) -> Result<()> {
         // Store write in local cache
-        self.reg_cache_store(reg, val);
+        self.reg_cache_store(reg, val)?;
 
         // Use I2C to write to device in chunks of MAX_I2C_MSG_LEN
         let mut len = val.len();
@@ -1013,7 +1147,7 @@ impl R820T {
             val_index += size;
             reg_index += size;
             len -= size;
-            if len <= 0 {
+            if len == 0 {
                 break;
             }
         }
@@ -1022,23 +1156,37 @@ impl R820T {
 
     // (r82xx_read)
     fn read_reg(&self, handle: &Device, reg: usize, buf: &mut [u8], len: u8) -> Result<()> {
-        assert!(buf.len() >= len as usize);
+        if buf.len() < len as usize {
+            return Err(RtlsdrErr(format!(
+                "Read of {} registers into a {} byte buffer",
+                len,
+                buf.len()
+            )));
+        }
         handle.i2c_write(R820T_I2C_ADDR, &[reg as u8])?;
         handle.i2c_read(R820T_I2C_ADDR, buf, len)?;
         // Need to reverse each byte...for some reason?
-        for i in 0..buf.len() {
-            buf[i] = bit_reverse(buf[i]);
+        for b in buf.iter_mut() {
+            *b = bit_reverse(*b);
         }
         Ok(())
     }
 
-    /// Cache register values locally.
-    /// Will panic if reg < RW_REG_START or (reg + len) > NUM_CACHE_REGS + 1
-    fn reg_cache_store(&mut self, mut reg: usize, val: &[u8]) {
-        assert!(reg >= RW_REG_START);
-        reg = reg - RW_REG_START;
-        assert!(reg + val.len() <= NUM_CACHE_REGS);
-        self.regs[reg..reg + val.len()].copy_from_slice(val);
+    /// Cache register values locally. Fails if any of the registers isn't
+    /// cached.
+    fn reg_cache_store(&mut self, reg: usize, val: &[u8]) -> Result<()> {
+        let cached = reg
+            .checked_sub(RW_REG_START)
+            .and_then(|index| self.regs.get_mut(index..index + val.len()))
+            .ok_or_else(|| {
+                RtlsdrErr(format!(
+                    "Registers {:#04x} to {:#04x} aren't cached",
+                    reg,
+                    reg + val.len()
+                ))
+            })?;
+        cached.copy_from_slice(val);
+        Ok(())
     }
 }
 
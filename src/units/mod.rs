@@ -0,0 +1,132 @@
+//! Units for the tuning API, so a frequency in kHz or a rate passed where a
+//! frequency belongs is caught by the compiler instead of silently
+//! mistuning. `RtlSdr`'s setters take anything that converts into them,
+//! and plain integers convert as Hz, S/s and ppm.
+//!
+//! ```no_run
+//! use rtlsdr_rs::units::{Hertz, Ppm, SampleRate};
+//! use rtlsdr_rs::RtlSdr;
+//!
+//! let sdr = RtlSdr::open(0).unwrap();
+//! sdr.set_center_freq(Hertz::mhz(94.9)).unwrap();
+//! sdr.set_sample_rate(SampleRate::msps(2.048)).unwrap();
+//! sdr.set_freq_correction(Ppm(-3)).unwrap();
+//! ```
+
+use std::fmt;
+
+#[cfg(test)]
+mod units_test;
+
+/// A frequency in Hz
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hertz(pub u32);
+
+impl Hertz {
+    pub fn hz(hz: u32) -> Hertz {
+        Hertz(hz)
+    }
+
+    /// Rounded to the nearest Hz
+    pub fn khz(khz: f64) -> Hertz {
+        Hertz((khz * 1e3).round() as u32)
+    }
+
+    /// Rounded to the nearest Hz
+    pub fn mhz(mhz: f64) -> Hertz {
+        Hertz((mhz * 1e6).round() as u32)
+    }
+
+    pub fn as_mhz(self) -> f64 {
+        self.0 as f64 / 1e6
+    }
+}
+
+impl From<u32> for Hertz {
+    fn from(hz: u32) -> Hertz {
+        Hertz(hz)
+    }
+}
+
+impl From<Hertz> for u32 {
+    fn from(hz: Hertz) -> u32 {
+        hz.0
+    }
+}
+
+impl fmt::Display for Hertz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            hz if hz >= 1_000_000 => write!(f, "{} MHz", hz as f64 / 1e6),
+            hz if hz >= 1_000 => write!(f, "{} kHz", hz as f64 / 1e3),
+            hz => write!(f, "{} Hz", hz),
+        }
+    }
+}
+
+/// A sample rate in complex samples per second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleRate(pub u32);
+
+impl SampleRate {
+    pub fn sps(sps: u32) -> SampleRate {
+        SampleRate(sps)
+    }
+
+    /// Rounded to the nearest sample per second
+    pub fn ksps(ksps: f64) -> SampleRate {
+        SampleRate((ksps * 1e3).round() as u32)
+    }
+
+    /// Rounded to the nearest sample per second
+    pub fn msps(msps: f64) -> SampleRate {
+        SampleRate((msps * 1e6).round() as u32)
+    }
+}
+
+impl From<u32> for SampleRate {
+    fn from(sps: u32) -> SampleRate {
+        SampleRate(sps)
+    }
+}
+
+impl From<SampleRate> for u32 {
+    fn from(rate: SampleRate) -> u32 {
+        rate.0
+    }
+}
+
+impl fmt::Display for SampleRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            sps if sps >= 1_000_000 => write!(f, "{} MS/s", sps as f64 / 1e6),
+            sps if sps >= 1_000 => write!(f, "{} kS/s", sps as f64 / 1e3),
+            sps => write!(f, "{} S/s", sps),
+        }
+    }
+}
+
+/// A frequency error in parts per million
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ppm(pub i32);
+
+impl From<i32> for Ppm {
+    fn from(ppm: i32) -> Ppm {
+        Ppm(ppm)
+    }
+}
+
+impl From<Ppm> for i32 {
+    fn from(ppm: Ppm) -> i32 {
+        ppm.0
+    }
+}
+
+impl fmt::Display for Ppm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ppm", self.0)
+    }
+}
@@ -0,0 +1,22 @@
+use super::{Hertz, Ppm, SampleRate};
+
+#[test]
+fn test_constructors() {
+    assert_eq!(Hertz(94_900_000), Hertz::mhz(94.9));
+    assert_eq!(Hertz(7_074_000), Hertz::khz(7074.0));
+    assert_eq!(Hertz(1090), Hertz::hz(1090));
+    assert_eq!(Hertz(144_800_000), 144_800_000.into());
+    assert_eq!(144.8, Hertz::mhz(144.8).as_mhz());
+    assert_eq!(SampleRate(2_048_000), SampleRate::msps(2.048));
+    assert_eq!(SampleRate(250_000), SampleRate::ksps(250.0));
+    assert_eq!(Ppm(-3), (-3).into());
+}
+
+#[test]
+fn test_display() {
+    assert_eq!("94.9 MHz", Hertz::mhz(94.9).to_string());
+    assert_eq!("25 kHz", Hertz::khz(25.0).to_string());
+    assert_eq!("50 Hz", Hertz(50).to_string());
+    assert_eq!("2.4 MS/s", SampleRate::msps(2.4).to_string());
+    assert_eq!("-3 ppm", Ppm(-3).to_string());
+}